@@ -0,0 +1,573 @@
+//! An HTTP/JSON front end over the library for machines without a display, e.g. a NAS. Built
+//! with `--features server`; the README lists the endpoints.
+
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use manga_cleaner::{
+    analyze, apply_cover_decision, decide_series_cover, ensure_cover_jpg, execute, plan_to_json,
+    rename_warning, resolve_series_dir, with_cancel, Analysis, AnalyzeOptions, Config, CoverCache,
+    Error, ExecuteSummary, LogEvent, DEFAULT_RENAME_WARN_RATIO,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+#[derive(Debug, Parser)]
+#[command(name = "manga_cleaner_server")]
+#[command(about = "Plan and process manga series folders over an HTTP/JSON API.")]
+struct Args {
+    #[arg(
+        long,
+        value_name = "ADDR",
+        default_value = "127.0.0.1:8080",
+        help = "Address to listen on. There is no authentication, so only listen beyond localhost on a trusted network."
+    )]
+    listen: String,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Read options from this JSON file instead of ~/.config/manga_cleaner/config.json."
+    )]
+    config: Option<PathBuf>,
+}
+
+/// The body of every POST endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeriesRequest {
+    series_dir: String,
+    /// Overrides the config file's `batch_size`.
+    #[serde(default)]
+    batch_size: Option<usize>,
+}
+
+/// A failed request: the status to answer with and the `error` text of the JSON body.
+#[derive(Debug)]
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        let status = match err {
+            Error::PathResolution(_) => 404,
+            Error::NoVolumes(_) | Error::CoverNotFound(_) => 422,
+            Error::Network(_) => 502,
+            _ => 500,
+        };
+        Self::new(status, format!("{err:#}"))
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        Error::from(err).into()
+    }
+}
+
+/// What the request threads share.
+struct State {
+    config: Config,
+    /// Remote cover lookup cache, used in place of the user cache dir `Config::analyze_options`
+    /// picks. Lookups with search filters stay uncached either way.
+    cover_cache: Option<CoverCache>,
+    /// Series folders being processed. A second run on one is refused rather than
+    /// interleaving its moves and journal with the first.
+    processing: Mutex<HashSet<PathBuf>>,
+}
+
+impl State {
+    /// Marks `series_dir` as being processed until the guard is dropped, or answers 409.
+    fn claim(&self, series_dir: &Path) -> Result<ProcessingGuard<'_>, ApiError> {
+        let mut processing = self
+            .processing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !processing.insert(series_dir.to_path_buf()) {
+            return Err(ApiError::new(
+                409,
+                format!("{} is already being processed", series_dir.display()),
+            ));
+        }
+        Ok(ProcessingGuard {
+            state: self,
+            series_dir: series_dir.to_path_buf(),
+        })
+    }
+}
+
+struct ProcessingGuard<'a> {
+    state: &'a State,
+    series_dir: PathBuf,
+}
+
+impl Drop for ProcessingGuard<'_> {
+    fn drop(&mut self) {
+        self.state
+            .processing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.series_dir);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endpoint {
+    Health,
+    Plan,
+    Cover,
+    Process,
+}
+
+/// The endpoint for a request line, or the 404/405 to answer with. Query strings are ignored.
+fn route(method: &Method, url: &str) -> Result<Endpoint, ApiError> {
+    let path = url.split('?').next().unwrap_or_default();
+    let (endpoint, allowed) = match path {
+        "/api/health" => (Endpoint::Health, Method::Get),
+        "/api/plan" => (Endpoint::Plan, Method::Post),
+        "/api/cover" => (Endpoint::Cover, Method::Post),
+        "/api/process" => (Endpoint::Process, Method::Post),
+        _ => return Err(ApiError::new(404, format!("no such endpoint: {path}"))),
+    };
+    if *method != allowed {
+        return Err(ApiError::new(405, "method not allowed"));
+    }
+    Ok(endpoint)
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let config = match &args.config {
+        Some(path) => Config::load(path),
+        None => Config::load_default(),
+    }?;
+    config.apply_network_settings();
+
+    let server = Server::http(&args.listen)
+        .map_err(|err| anyhow!("failed to listen on {}: {err}", args.listen))?;
+    println!("[SERVE] Listening on http://{}", args.listen);
+    serve(
+        &server,
+        &Arc::new(State {
+            config,
+            cover_cache: CoverCache::default_location(),
+            processing: Mutex::new(HashSet::new()),
+        }),
+    );
+    Ok(())
+}
+
+/// Answers every request on a thread of its own until the server stops.
+fn serve(server: &Server, state: &Arc<State>) {
+    for request in server.incoming_requests() {
+        let state = Arc::clone(state);
+        thread::spawn(move || handle(request, &state));
+    }
+}
+
+fn handle(mut request: Request, state: &State) {
+    let result = match route(request.method(), request.url()) {
+        Ok(Endpoint::Health) => Ok(json!({
+            "ok": true,
+            "version": env!("CARGO_PKG_VERSION"),
+        })),
+        Ok(Endpoint::Plan) => read_body(&mut request).and_then(|body| plan_series(&body, state)),
+        Ok(Endpoint::Cover) => read_body(&mut request).and_then(|body| cover_series(&body, state)),
+        Ok(Endpoint::Process) => {
+            let claimed = read_body(&mut request).and_then(|body| {
+                let series_dir = resolve_series_dir(&body.series_dir)?;
+                Ok((state.claim(&series_dir)?, body))
+            });
+            match claimed {
+                Ok((_processing, body)) => {
+                    stream_process(request, &body, state);
+                    return;
+                }
+                Err(err) => Err(err),
+            }
+        }
+        Err(err) => Err(err),
+    };
+    let (status, value) = match result {
+        Ok(value) => (200, value),
+        Err(err) => (err.status, json!({ "error": err.message })),
+    };
+    let _ = request.respond(json_response(status, &value));
+}
+
+fn read_body(request: &mut Request) -> Result<SeriesRequest, ApiError> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|err| ApiError::new(400, format!("failed to read the request body: {err}")))?;
+    parse_body(&body)
+}
+
+fn parse_body(body: &str) -> Result<SeriesRequest, ApiError> {
+    serde_json::from_str(body)
+        .map_err(|err| ApiError::new(400, format!("invalid request body: {err}")))
+}
+
+fn json_response(status: u16, value: &Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes("Content-Type", "application/json").ok();
+    let response = Response::from_string(value.to_string()).with_status_code(status);
+    match content_type {
+        Some(header) => response.with_header(header),
+        None => response,
+    }
+}
+
+/// The config's plan and cover options, with the server's cover cache and the request's
+/// batch size.
+fn analyze_options(state: &State, body: &SeriesRequest) -> Result<AnalyzeOptions, ApiError> {
+    let mut options = state.config.analyze_options()?;
+    if options.cover.cache.is_some() {
+        options.cover.cache = state.cover_cache.clone();
+    }
+    if let Some(batch_size) = body.batch_size {
+        options.plan.batch_size = batch_size;
+    }
+    options
+        .plan
+        .validate()
+        .map_err(|err| ApiError::new(400, format!("{err:#}")))?;
+    Ok(options)
+}
+
+fn analyze_request(
+    body: &SeriesRequest,
+    state: &State,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<Analysis, ApiError> {
+    let series_dir = resolve_series_dir(&body.series_dir)?;
+    let options = analyze_options(state, body)?;
+    Ok(analyze(&series_dir, &options, log)?)
+}
+
+/// `POST /api/plan`: the dry-run plan as `plan_to_json` gives it, plus the cover step, the
+/// counts, any rename warning, and the log lines of the analysis.
+fn plan_series(body: &SeriesRequest, state: &State) -> Result<Value, ApiError> {
+    let mut events = Vec::new();
+    let analysis = analyze_request(body, state, &mut |event| events.push(event.to_json()))?;
+    let mut value = plan_to_json(
+        &analysis.series_dir,
+        &analysis.plan,
+        analysis.cover.planned_path(),
+    );
+    value["cover_action"] = json!(analysis.cover.describe());
+    value["volume_count"] = json!(analysis.volume_count);
+    value["rename_count"] = json!(analysis.rename_count);
+    value["duplicate_count"] = json!(analysis.duplicate_count);
+    let ratio = state
+        .config
+        .rename_warn_ratio
+        .unwrap_or(DEFAULT_RENAME_WARN_RATIO);
+    value["rename_warning"] = json!(rename_warning(&analysis.plan, ratio));
+    value["log"] = Value::Array(events);
+    Ok(value)
+}
+
+/// `POST /api/cover`: where the series cover would come from, without writing it.
+fn cover_series(body: &SeriesRequest, state: &State) -> Result<Value, ApiError> {
+    let series_dir = resolve_series_dir(&body.series_dir)?;
+    let options = analyze_options(state, body)?;
+    let title = file_name(&series_dir);
+    let mut events = Vec::new();
+    let decision = decide_series_cover(&series_dir, &title, &options.cover, &mut |event| {
+        events.push(event.to_json())
+    })?;
+    Ok(json!({
+        "action": decision.describe(),
+        "source": decision.source_label(),
+        "path": decision.planned_path().map(|path| path.display().to_string()),
+        "log": events,
+    }))
+}
+
+/// `POST /api/process`: plans the series again and processes it, answering with server-sent
+/// events. Every `LogEvent` is a `log` event holding `LogEvent::to_json`; the stream ends with
+/// one `done` event holding the summary, or `error`. A client that hangs up cancels the run
+/// at the next move or cover download chunk.
+fn stream_process(request: Request, body: &SeriesRequest, state: &State) {
+    let mut writer = request.into_writer();
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    if writer.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    let cancel = Arc::new(AtomicBool::new(false));
+    let result = {
+        let mut log = |event: LogEvent| {
+            if write_event(&mut writer, "log", &event.to_json()).is_err() {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        };
+        with_cancel(Arc::clone(&cancel), || {
+            process_request(body, state, &cancel, &mut log)
+        })
+    };
+    let _ = match result {
+        Ok((summary, report)) => write_event(&mut writer, "done", &summary_json(&summary, &report)),
+        Err(err) => write_event(&mut writer, "error", &json!({ "error": err.message })),
+    };
+}
+
+fn process_request(
+    body: &SeriesRequest,
+    state: &State,
+    cancel: &AtomicBool,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<(ExecuteSummary, String), ApiError> {
+    let config = &state.config;
+    let analysis = analyze_request(body, state, log)?;
+    let series_cover = match apply_cover_decision(&analysis.cover, log)? {
+        Some(selected) if config.keep_cover_format.unwrap_or(false) => Some(selected),
+        Some(selected) => Some(ensure_cover_jpg(&analysis.series_dir, &selected)?),
+        None => None,
+    };
+    let options = config.execute_options(&analysis.series_dir, &analysis.cover);
+    let summary = execute(
+        &analysis.plan,
+        series_cover.as_deref(),
+        &options,
+        cancel,
+        log,
+    )?;
+    let report = summary.report(&analysis.plan).to_string();
+    Ok((summary, report))
+}
+
+fn summary_json(summary: &ExecuteSummary, report: &str) -> Value {
+    let paths = |paths: &[PathBuf]| {
+        paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+    };
+    json!({
+        "report": report,
+        "cancelled": summary.cancelled,
+        "batches_completed": summary.batches_completed,
+        "total_batches": summary.total_batches,
+        "moved": summary.moved.len(),
+        "failed": summary
+            .failed
+            .iter()
+            .map(|failed| json!({
+                "from": failed.src.display().to_string(),
+                "to": failed.dst.display().to_string(),
+                "error": failed.error,
+            }))
+            .collect::<Vec<_>>(),
+        "affected_dirs": paths(&summary.affected_dirs()),
+    })
+}
+
+fn write_event(writer: &mut dyn Write, name: &str, data: &Value) -> std::io::Result<()> {
+    write!(writer, "event: {name}\ndata: {data}\n\n")?;
+    writer.flush()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use manga_cleaner::COVER_CACHE_FILE_NAME;
+
+    use super::*;
+
+    #[test]
+    fn requests_are_routed_by_path_and_method() {
+        assert_eq!(
+            route(&Method::Get, "/api/health?verbose=1").map_err(|err| err.status),
+            Ok(Endpoint::Health)
+        );
+        assert_eq!(
+            route(&Method::Post, "/api/process").map_err(|err| err.status),
+            Ok(Endpoint::Process)
+        );
+        assert_eq!(
+            route(&Method::Get, "/api/plan").map_err(|err| err.status),
+            Err(405)
+        );
+        assert_eq!(
+            route(&Method::Post, "/api/health").map_err(|err| err.status),
+            Err(405)
+        );
+        assert_eq!(
+            route(&Method::Get, "/api/nope").map_err(|err| err.status),
+            Err(404)
+        );
+        assert_eq!(
+            route(&Method::Post, "/").map_err(|err| err.status),
+            Err(404)
+        );
+    }
+
+    #[test]
+    fn library_errors_map_to_http_statuses() {
+        let status = |err: Error| ApiError::from(err).status;
+        assert_eq!(status(Error::PathResolution(anyhow!("missing"))), 404);
+        assert_eq!(status(Error::NoVolumes(anyhow!("empty"))), 422);
+        assert_eq!(status(Error::CoverNotFound(anyhow!("no cover"))), 422);
+        assert_eq!(status(Error::Network(anyhow!("offline"))), 502);
+        assert_eq!(status(Error::Io(anyhow!("disk full"))), 500);
+        assert_eq!(status(Error::Other(anyhow!("bad option"))), 500);
+    }
+
+    #[test]
+    fn request_bodies_are_validated() {
+        let body = parse_body(r#"{"series_dir": "/manga/Title", "batch_size": 5}"#)
+            .map_err(|err| err.message)
+            .expect("valid body");
+        assert_eq!(body.series_dir, "/manga/Title");
+        assert_eq!(body.batch_size, Some(5));
+
+        for invalid in [
+            r#"{"series_dir": "/manga/Title", "batchsize": 5}"#,
+            r#"{"batch_size": 5}"#,
+            r#"{"series_dir": 3}"#,
+            "not json",
+        ] {
+            let err = parse_body(invalid).expect_err(invalid);
+            assert_eq!(err.status, 400, "{invalid}");
+        }
+        let err = parse_body(r#"{"series_dir": "a", "dry_run": true}"#).expect_err("unknown");
+        assert!(err.message.contains("unknown field"), "{}", err.message);
+    }
+
+    /// Sends one request to the server at `addr` and returns the status code and body.
+    fn post(addr: std::net::SocketAddr, path: &str, body: &str) -> (u16, String) {
+        use std::io::Read;
+
+        let mut stream = std::net::TcpStream::connect(addr).expect("connect");
+        write!(
+            stream,
+            "POST {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .expect("send request");
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("read response");
+        let (head, body) = response.split_once("\r\n\r\n").expect("response head");
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .expect("status code");
+        (status, body.to_string())
+    }
+
+    #[test]
+    fn a_series_is_planned_and_processed_over_http() {
+        let dir = std::env::temp_dir().join(format!("manga_cleaner_server_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let series = dir.join("Title");
+        std::fs::create_dir_all(&series).expect("create series dir");
+        for name in ["Title v1.cbz", "Title v2.cbz", "Title v3.cbz"] {
+            std::fs::write(series.join(name), name).expect("write volume");
+        }
+
+        let server = Server::http("127.0.0.1:0").expect("listen");
+        let addr = server.server_addr().to_ip().expect("ip address");
+        // With no cover providers and no readable archives the batches get no cover. Offline
+        // and with a cache of its own, nothing goes online or touches the user's cache.
+        let config = Config {
+            cover_providers: Some(Vec::new()),
+            offline: Some(true),
+            ..Config::default()
+        };
+        config.apply_network_settings();
+        let cache = dir.join("cache").join(COVER_CACHE_FILE_NAME);
+        let state = Arc::new(State {
+            config,
+            cover_cache: Some(CoverCache::new(cache.clone())),
+            processing: Mutex::new(HashSet::new()),
+        });
+        thread::spawn(move || serve(&server, &state));
+        let body = json!({ "series_dir": series, "batch_size": 2 }).to_string();
+
+        let (status, plan) = post(addr, "/api/plan", &body);
+        assert_eq!(status, 200, "{plan}");
+        let plan: Value = serde_json::from_str(&plan).expect("plan json");
+        assert_eq!(plan["volume_count"], 3);
+        assert_eq!(plan["batches"].as_array().map(Vec::len), Some(2), "{plan}");
+        assert!(
+            series.join("Title v1.cbz").is_file(),
+            "planning moved nothing"
+        );
+
+        let (status, stream) = post(addr, "/api/process", &body);
+        assert_eq!(status, 200, "{stream}");
+        let events: Vec<(&str, Value)> = stream
+            .split("\n\n")
+            .filter(|event| !event.trim().is_empty())
+            .map(|event| {
+                let name = event.lines().find_map(|line| line.strip_prefix("event: "));
+                let data = event.lines().find_map(|line| line.strip_prefix("data: "));
+                (
+                    name.expect("event name"),
+                    serde_json::from_str(data.expect("event data")).expect("event json"),
+                )
+            })
+            .collect();
+        assert!(events.iter().any(|(name, _)| *name == "log"), "{stream}");
+        let (name, summary) = events.last().expect("final event");
+        assert_eq!(*name, "done", "{stream}");
+        assert_eq!(summary["moved"], 3);
+        assert_eq!(summary["batches_completed"], 2);
+        assert_eq!(summary["cancelled"], false);
+        assert!(dir.join("Title 1").join("Title v001.cbz").is_file());
+        assert!(dir.join("Title 2").join("Title v003.cbz").is_file());
+        assert!(!cache.exists(), "no remote lookup was cached");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn a_series_is_processed_by_one_request_at_a_time() {
+        let state = State {
+            config: Config::default(),
+            cover_cache: None,
+            processing: Mutex::new(HashSet::new()),
+        };
+        let series = Path::new("/manga/Title");
+        let first = state
+            .claim(series)
+            .map_err(|err| err.message)
+            .expect("claim");
+        assert_eq!(
+            state.claim(series).map(|_| ()).map_err(|err| err.status),
+            Err(409)
+        );
+        assert!(state.claim(Path::new("/manga/Other")).is_ok());
+        drop(first);
+        assert!(state.claim(series).is_ok());
+    }
+}
@@ -1,18 +1,65 @@
-use std::process;
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
-use anyhow::{bail, Result};
-use clap::Parser;
+use anyhow::{anyhow, Result};
+use clap::{
+    parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+    ValueHint,
+};
+use clap_complete::Shell;
 use manga_cleaner::{
-    build_plan, ensure_cover_jpg, ensure_series_cover, execute, format_plan, open_image,
-    prompt_confirm, resolve_series_dir,
+    already_organized, apply_cover_decision, build_plan, cover_providers, cover_providers_named,
+    cover_refresh_plan, decide_series_cover, download_cover_thumbnail, ensure_cover_jpg,
+    ensure_series_cover, execute, format_plan, journal_path, library_series_dirs,
+    mangadex_cover_candidates, open_image, parse_hex_color, plan_to_json, plan_unbatch,
+    preview_clean, preview_series_cover, prompt_choice, prompt_confirm, rename_warning,
+    resolve_series_dir, rollback, scan_volumes_ignoring, set_http_trace, unbatch, AnalyzeOptions,
+    Config, ContentRating, CoverDecision, CoverId, CoverMode, CoverOptions, CoverRatio, CoverSize,
+    CoverStyle, CoverTextColor, Error, ExecuteOptions, ExistingCover, ExtensionSet, FolderTemplate,
+    IgnorePattern, LogEvent, LogLevel, Phase, PhaseTimings, PlanOptions, ReadingDirection,
+    SanitizeNames, SeriesCoverOptions, SpreadHandling, TitlePosition, DEFAULT_RENAME_WARN_RATIO,
+    DEFAULT_REQUEST_RATE, FILES_PER_FOLDER, JPEG_QUALITY, MANGADEX_MAX_SCORE,
 };
 
 #[derive(Debug, Parser)]
 #[command(name = "process_manga_rs")]
 #[command(about = "Clean and batch manga files with numbered covers (Rust port).")]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(help = "Path to the series folder")]
-    series_dir: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(
+        required_unless_present = "bulk",
+        conflicts_with = "bulk",
+        value_hint = ValueHint::DirPath,
+        help = "Path to the series folder"
+    )]
+    series_dir: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PARENT_DIR",
+        value_hint = ValueHint::DirPath,
+        help = "Process every subfolder of PARENT_DIR as a series, keep going when one fails, and report the results at the end."
+    )]
+    bulk: Option<String>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "FILE",
+        help = "Read default options from this JSON file instead of ~/.config/manga_cleaner/config.json."
+    )]
+    config: Option<PathBuf>,
 
     #[arg(
         long,
@@ -35,82 +82,1525 @@ struct Args {
 
     #[arg(long, help = "Print full plan and exit without changing files.")]
     dry_run: bool,
+
+    #[arg(
+        long,
+        help = "Undo the last processing run using the journal in the series folder, then exit."
+    )]
+    rollback: bool,
+
+    #[arg(
+        long,
+        help = "Move the volumes in the series' batch folders back into the series folder and delete the batch folders, then exit. Works without a journal; combine with --dry-run to preview."
+    )]
+    unbatch: bool,
+
+    #[arg(
+        long,
+        help = "Print the plan as JSON instead of text; log lines go to stderr."
+    )]
+    json: bool,
+
+    #[arg(
+        global = true,
+        short,
+        long,
+        conflicts_with = "verbose",
+        help = "Only print warnings and errors, leaving out the plan, moves, and summary. Prompts still show."
+    )]
+    quiet: bool,
+
+    #[arg(
+        global = true,
+        short,
+        long,
+        help = "Also print every HTTP request with its status and time (to stderr)."
+    )]
+    verbose: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = ProgressFormat::Text,
+        help = "How log and progress lines are written: text, or ndjson for one JSON object per event on stderr."
+    )]
+    progress: ProgressFormat,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        default_value_t = FILES_PER_FOLDER,
+        help = "Number of volumes per batch folder."
+    )]
+    batch_size: usize,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Also normalize chapter-only files (c/ch/chapter tokens) and order by chapter."
+    )]
+    chapters: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "FILE",
+        help = "JSON file overriding the filename cleaning patterns."
+    )]
+    clean_rules: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "RATIO",
+        value_parser = parse_rename_warn_ratio,
+        help = "Warn before processing when more than this share of the volumes would get a different title, 0 to 1 [default: 0.8]."
+    )]
+    rename_warn_ratio: Option<f32>,
+
+    #[arg(
+        long,
+        help = "List each volume's cleaned name under the current cleaning rules, then exit without planning anything."
+    )]
+    preview_clean: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Move byte-identical duplicate volumes too instead of leaving them in place."
+    )]
+    keep_duplicates: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Also pick up volumes from subfolders of the series folder."
+    )]
+    recursive: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "TEMPLATE",
+        help = "Name batch folders from a template with {series}, {batch}, {start}, and {end}, e.g. \"{series} Vol {start:03}-{end:03}\" (default \"{series} {batch}\")."
+    )]
+    folder_template: Option<FolderTemplate>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "MODE",
+        help = "Make cleaned file names safe for Windows and exFAT (illegal characters, trailing dots, names like CON): cbz (default), all, or off."
+    )]
+    sanitize_names: Option<SanitizeNames>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Write file extensions in lowercase (\"v01.CBZ\" becomes \"v001.cbz\"), leaving the rest of the name as cleaned."
+    )]
+    lowercase_ext: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "GLOB",
+        help = "Leave volumes whose file name matches GLOB (e.g. \"*Artbook*\", case-insensitive) out of the plan. Repeat for more patterns."
+    )]
+    ignore: Vec<IgnorePattern>,
+
+    #[arg(
+        global = true,
+        long = "volume-ext",
+        value_name = "EXTS",
+        help = "Treat files with these extensions as volumes instead of cbz,cbr,cb7,cbt,zip (comma-separated, e.g. cbz,cbr)."
+    )]
+    volume_exts: Option<ExtensionSet>,
+
+    #[arg(
+        global = true,
+        long = "image-ext",
+        value_name = "EXTS",
+        help = "Accept images with these extensions as a series cover instead of jpg,jpeg,png,webp,bmp,gif (comma-separated)."
+    )]
+    image_exts: Option<ExtensionSet>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "DIR",
+        help = "Folder of <Series Name>.jpg covers to check before extracting or searching online."
+    )]
+    cover_dir: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        help = "Extract the series cover from the Nth volume (1-based)."
+    )]
+    extract_volume: usize,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        help = "Use the Nth image (1-based, natural order) of that volume as the cover."
+    )]
+    extract_page: usize,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Re-extract the cover from the archive even if the folder already has one."
+    )]
+    force_extract: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Keep an extracted PNG or JPEG cover as it is instead of re-encoding it to cover.jpg."
+    )]
+    keep_cover_format: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "MODE",
+        help = "When the extracted cover page is a double-page spread: keep (default), skip to the next single page, crop (the half read first), crop-left, or crop-right."
+    )]
+    spread: Option<SpreadHandling>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "RATIO",
+        value_parser = parse_spread_ratio,
+        help = "Width-to-height ratio above which a page counts as a spread [default: 1.0]."
+    )]
+    spread_ratio: Option<f32>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "DIR",
+        help = "Reading direction of the series, ltr (default) or rtl; decides which half --spread crop keeps."
+    )]
+    reading_direction: Option<ReadingDirection>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "COLOR",
+        default_value = "#000000",
+        help = "Batch number color on generated covers: hex (#RRGGBB) or \"auto\"."
+    )]
+    cover_text_color: CoverTextColor,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Draw a contrasting outline around batch numbers on covers."
+    )]
+    cover_outline: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "W:H",
+        help = "Pad batch covers to this aspect ratio (e.g. 2:3) before numbering."
+    )]
+    cover_ratio: Option<CoverRatio>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "COLOR",
+        default_value = "#000000",
+        value_parser = parse_hex_color,
+        help = "Hex color (#RRGGBB) for the padding added by --cover-ratio."
+    )]
+    cover_pad_color: [u8; 3],
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "PX",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Resize batch covers to this width, keeping the aspect ratio."
+    )]
+    cover_width: Option<u32>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Also print the series title along the edge of each batch cover."
+    )]
+    cover_title: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "TEXT",
+        help = "Print this text on batch covers instead of the folder name (implies --cover-title)."
+    )]
+    cover_title_text: Option<String>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "EDGE",
+        default_value = "bottom",
+        help = "Where to put the cover title: top or bottom."
+    )]
+    cover_title_position: TitlePosition,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "MODE",
+        help = "What to do with a batch's existing cover.jpg: archive it to cover_old_*.jpg (default), overwrite it, or skip the batch."
+    )]
+    existing_cover: Option<ExistingCover>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        help = "Remove all but the N newest cover_old_*.jpg in each batch folder after its cover is rendered [default N: 1]."
+    )]
+    prune_covers: Option<usize>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "PATH",
+        help = "Font file for batch cover text (default: $MANGA_CLEANER_FONT, then system fonts)."
+    )]
+    font: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "Q",
+        default_value_t = JPEG_QUALITY,
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        help = "JPEG quality (1-100) for generated batch covers."
+    )]
+    jpeg_quality: u8,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Skip cover extraction, lookup, and batch covers entirely; only move files."
+    )]
+    no_cover: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "TITLE",
+        help = "Search remote cover providers for this title instead of the folder name."
+    )]
+    title: Option<String>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Skip the remote cover lookup cache and always query providers."
+    )]
+    no_cache: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Never touch the network; use only covers found in the series folder."
+    )]
+    offline: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_REQUEST_RATE,
+        value_parser = parse_request_rate,
+        help = "Send at most N requests per second to each cover provider (0 for no limit)."
+    )]
+    request_rate: f64,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Give up on each cover lookup or download after SECS seconds (default 20 for lookups, 30 for downloads)."
+    )]
+    timeout: Option<u64>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "UA",
+        help = "User-Agent header to send to cover providers."
+    )]
+    user_agent: Option<String>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "URL",
+        help = "Referer header to send with cover downloads (default https://mangadex.org/; empty sends none)."
+    )]
+    referer: Option<String>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "URL",
+        help = "Send every cover request through this HTTP(S) proxy, e.g. http://proxy.example:8080."
+    )]
+    proxy: Option<String>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Ignore the HTTPS_PROXY, HTTP_PROXY and ALL_PROXY environment variables."
+    )]
+    no_env_proxy: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "PROVIDER:ID",
+        help = "Fetch the remote cover for this series ID (mangadex:<uuid> or anilist:<id>) instead of searching by title."
+    )]
+    cover_id: Option<CoverId>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "List MangaDex's best matches for the series with thumbnails and ask which cover to use."
+    )]
+    pick_cover: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "SIZE",
+        default_value = "original",
+        help = "MangaDex cover size to download: original, 512, or 256."
+    )]
+    cover_size: CoverSize,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        help = "Prefer the MangaDex cover for volume N (falls back to volume 1)."
+    )]
+    cover_volume: u32,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Pick the highest-resolution MangaDex cover for the volume instead of the earliest."
+    )]
+    cover_highest_res: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=6),
+        help = "Skip MangaDex when its best match scores below N (6 exact title, 5 exact alternative title, 2 title contains the search, 1 alternative title does, 0 accepts anything)."
+    )]
+    cover_min_score: u8,
+
+    #[arg(
+        global = true,
+        long = "cover-rating",
+        value_name = "RATING",
+        value_delimiter = ',',
+        help = "Only search MangaDex series with these content ratings (comma-separated: safe, suggestive, erotica, pornographic)."
+    )]
+    cover_ratings: Vec<ContentRating>,
+
+    #[arg(
+        global = true,
+        long = "cover-language",
+        value_name = "LANG",
+        value_delimiter = ',',
+        help = "Only search MangaDex series translated into one of these languages (comma-separated codes, e.g. en,pt-br)."
+    )]
+    cover_languages: Vec<String>,
+
+    #[arg(
+        global = true,
+        long = "cover-original-language",
+        value_name = "LANG",
+        value_delimiter = ',',
+        help = "Only search MangaDex series first published in one of these languages (comma-separated codes, e.g. ja,ko)."
+    )]
+    cover_original_languages: Vec<String>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Verify cross-device copies byte-for-byte before deleting the source (slower)."
+    )]
+    verify: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Copy volumes into the batch folders and leave the originals where they are."
+    )]
+    copy: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Add new volumes after the existing batch folders: fill the last one up, then continue the numbering."
+    )]
+    incremental: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "When the series is already organized into batch folders, render their covers again without moving anything."
+    )]
+    rerender_covers: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        help = "Render up to N batch covers in parallel; file moves stay sequential."
+    )]
+    jobs: usize,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Write a series.json with the title, volumes, and cover source into each batch folder."
+    )]
+    write_metadata: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Write a checksums.sha256 of the volumes into each batch folder (sha256sum -c format)."
+    )]
+    checksums: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Leave volumes that can't be moved (locked or read-only) in place, finish the rest, and list them at the end."
+    )]
+    continue_on_error: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(1..=9),
+        help = "Size batch numbers as if they had N digits so every cover's number is the same size."
+    )]
+    cover_number_width: Option<u8>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Zero-pad batch numbers (to --cover-number-width, or the digits of the last batch)."
+    )]
+    cover_number_pad: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Open every archive while planning and flag unreadable ones in the plan."
+    )]
+    validate: bool,
+}
+
+/// How `process_series` writes its `LogEvent`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ProgressFormat {
+    /// The tagged text lines, on stdout (stderr with --json).
+    Text,
+    /// `LogEvent::to_json`, one object per line on stderr, for wrappers that drive the CLI.
+    Ndjson,
+}
+
+/// The modes of the top-level flags as subcommands, each with only the flags that apply to it.
+/// Every other option is global, so it can go before or after the subcommand.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the plan without changing files (the same as --dry-run).
+    Plan {
+        #[arg(value_hint = ValueHint::DirPath, help = "Path to the series folder")]
+        series_dir: String,
+        #[arg(
+            long,
+            help = "Print the plan as JSON instead of text; log lines go to stderr."
+        )]
+        json: bool,
+        #[arg(
+            long,
+            conflicts_with = "json",
+            help = "List each volume's cleaned name under the current cleaning rules instead of planning."
+        )]
+        preview_clean: bool,
+    },
+    /// Plan, confirm, and process the series.
+    Process {
+        #[arg(value_hint = ValueHint::DirPath, help = "Path to the series folder")]
+        series_dir: String,
+        #[arg(
+            short = 'y',
+            long,
+            help = "Execute all planned actions without confirmation."
+        )]
+        yes: bool,
+    },
+    /// Check the series cover without processing anything.
+    Cover {
+        #[command(subcommand)]
+        action: CoverCommand,
+    },
+    /// Move the volumes in the batch folders back into the series folder (the same as --unbatch).
+    Unbatch {
+        #[arg(value_hint = ValueHint::DirPath, help = "Path to the series folder")]
+        series_dir: String,
+        #[arg(
+            long,
+            conflicts_with = "yes",
+            help = "List the moves without making them."
+        )]
+        dry_run: bool,
+        #[arg(
+            short = 'y',
+            long,
+            help = "Move the volumes back without confirmation."
+        )]
+        yes: bool,
+    },
+    /// Undo the last processing run using the series' journal (the same as --rollback).
+    Rollback {
+        #[arg(value_hint = ValueHint::DirPath, help = "Path to the series folder")]
+        series_dir: String,
+    },
+    /// Process every subfolder of PARENT_DIR as a series (the same as --bulk).
+    Bulk {
+        #[arg(
+            value_name = "PARENT_DIR",
+            value_hint = ValueHint::DirPath,
+            help = "Folder holding the series folders"
+        )]
+        parent_dir: String,
+        #[arg(
+            long,
+            conflicts_with = "yes",
+            help = "Print each plan without changing files."
+        )]
+        dry_run: bool,
+        #[arg(
+            short = 'y',
+            long,
+            help = "Execute all planned actions without confirmation."
+        )]
+        yes: bool,
+    },
+    /// Print a completion script for the shell to stdout.
+    Completions {
+        #[arg(value_name = "SHELL", help = "bash, zsh, fish, elvish, or powershell")]
+        shell: Shell,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CoverCommand {
+    /// Resolve the cover and open it (the same as --show-cover).
+    Show {
+        #[arg(value_hint = ValueHint::DirPath, help = "Path to the series folder")]
+        series_dir: String,
+    },
+    /// Resolve the cover, ensure cover.jpg exists, and print its path (the same as
+    /// --print-cover-path).
+    Path {
+        #[arg(value_hint = ValueHint::DirPath, help = "Path to the series folder")]
+        series_dir: String,
+    },
+}
+
+/// Turns a subcommand into the top-level flags it stands for, so both spellings take the same
+/// path through `run`.
+fn apply_command(args: &mut Args) -> Result<()> {
+    let Some(command) = args.command.take() else {
+        return Ok(());
+    };
+    if args.series_dir.is_some()
+        || args.bulk.is_some()
+        || args.show_cover
+        || args.print_cover_path
+        || args.yes
+        || args.dry_run
+        || args.rollback
+        || args.unbatch
+        || args.json
+        || args.preview_clean
+    {
+        return Err(usage("a subcommand takes the series folder and its mode flags after its name; --bulk, --show-cover, --print-cover-path, --yes, --dry-run, --rollback, --unbatch, --json, and --preview-clean only apply without one"));
+    }
+    match command {
+        Command::Plan {
+            series_dir,
+            json,
+            preview_clean,
+        } => {
+            args.series_dir = Some(series_dir);
+            args.dry_run = true;
+            args.json = json;
+            args.preview_clean = preview_clean;
+        }
+        Command::Process { series_dir, yes } => {
+            args.series_dir = Some(series_dir);
+            args.yes = yes;
+        }
+        Command::Cover {
+            action: CoverCommand::Show { series_dir },
+        } => {
+            args.series_dir = Some(series_dir);
+            args.show_cover = true;
+        }
+        Command::Cover {
+            action: CoverCommand::Path { series_dir },
+        } => {
+            args.series_dir = Some(series_dir);
+            args.print_cover_path = true;
+        }
+        Command::Unbatch {
+            series_dir,
+            dry_run,
+            yes,
+        } => {
+            args.series_dir = Some(series_dir);
+            args.unbatch = true;
+            args.dry_run = dry_run;
+            args.yes = yes;
+        }
+        Command::Rollback { series_dir } => {
+            args.series_dir = Some(series_dir);
+            args.rollback = true;
+        }
+        Command::Bulk {
+            parent_dir,
+            dry_run,
+            yes,
+        } => {
+            args.bulk = Some(parent_dir);
+            args.dry_run = dry_run;
+            args.yes = yes;
+        }
+        // Printed by `run` before the config is loaded.
+        Command::Completions { .. } => {}
+    }
+    Ok(())
+}
+
+/// Writes the options passed on the command line into `config`, where they win over the
+/// file's values. Flags with a default fill in the keys the file leaves out, so the CLI keeps
+/// its own defaults.
+fn apply_args(config: &mut Config, args: &Args, matches: &ArgMatches) {
+    fn list<T: Clone>(values: &[T]) -> Option<Vec<T>> {
+        (!values.is_empty()).then(|| values.to_vec())
+    }
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if given("batch_size") || config.batch_size.is_none() {
+        config.batch_size = Some(args.batch_size);
+    }
+    if given("jobs") || config.jobs.is_none() {
+        config.jobs = Some(args.jobs);
+    }
+    if given("request_rate") || config.request_rate.is_none() {
+        config.request_rate = Some(args.request_rate);
+    }
+    if given("jpeg_quality") || config.jpeg_quality.is_none() {
+        config.jpeg_quality = Some(args.jpeg_quality);
+    }
+    if given("cover_text_color") || config.cover_text_color.is_none() {
+        config.cover_text_color = Some(args.cover_text_color);
+    }
+    config.timeout = args.timeout.or(config.timeout);
+    config.user_agent = args.user_agent.clone().or(config.user_agent.take());
+    config.referer = args.referer.clone().or(config.referer.take());
+    config.proxy = args.proxy.clone().or(config.proxy.take());
+    config.clean_rules = args.clean_rules.clone().or(config.clean_rules.take());
+    config.rename_warn_ratio = args.rename_warn_ratio.or(config.rename_warn_ratio);
+    config.font = args.font.clone().or(config.font.take());
+    config.folder_template = args
+        .folder_template
+        .clone()
+        .or(config.folder_template.take());
+    config.sanitize_names = args.sanitize_names.or(config.sanitize_names);
+    config.spread = args.spread.or(config.spread);
+    config.spread_ratio = args.spread_ratio.or(config.spread_ratio);
+    config.reading_direction = args.reading_direction.or(config.reading_direction);
+    config.cover_dir = args.cover_dir.clone().or(config.cover_dir.take());
+    config.volume_extensions = args.volume_exts.clone().or(config.volume_extensions.take());
+    config.image_extensions = args.image_exts.clone().or(config.image_extensions.take());
+    config.existing_cover = args.existing_cover.or(config.existing_cover);
+    config.prune_covers = args.prune_covers.or(config.prune_covers);
+    config.ignore = list(&args.ignore).or(config.ignore.take());
+    config.cover_content_ratings =
+        list(&args.cover_ratings).or(config.cover_content_ratings.take());
+    config.cover_languages = list(&args.cover_languages).or(config.cover_languages.take());
+    config.cover_original_languages =
+        list(&args.cover_original_languages).or(config.cover_original_languages.take());
+    for (flag, key) in [
+        (args.chapters, &mut config.chapters),
+        (args.recursive, &mut config.recursive),
+        (args.keep_duplicates, &mut config.keep_duplicates),
+        (args.offline, &mut config.offline),
+        (args.cover_outline, &mut config.cover_outline),
+        (args.keep_cover_format, &mut config.keep_cover_format),
+        (args.write_metadata, &mut config.write_metadata),
+        (args.checksums, &mut config.checksums),
+        (args.continue_on_error, &mut config.continue_on_error),
+        (args.validate, &mut config.validate_archives),
+        (args.copy, &mut config.copy),
+        (args.lowercase_ext, &mut config.lowercase_extensions),
+        (args.incremental, &mut config.incremental),
+    ] {
+        if flag {
+            *key = Some(true);
+        }
+    }
+    if args.no_env_proxy {
+        config.env_proxy = Some(false);
+    }
+}
+
+fn parse_request_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate >= 0.0 => Ok(rate),
+        _ => Err("expected zero or a positive number".to_string()),
+    }
+}
+
+fn parse_rename_warn_ratio(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err("expected a number from 0 to 1".to_string()),
+    }
+}
+
+fn parse_spread_ratio(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(ratio) if ratio.is_finite() && ratio >= 1.0 => Ok(ratio),
+        _ => Err("expected a number of at least 1".to_string()),
+    }
 }
 
 fn run() -> Result<i32> {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches)?;
+    NDJSON.store(args.progress == ProgressFormat::Ndjson, Ordering::Relaxed);
+    if let Some(Command::Completions { shell }) = args.command {
+        clap_complete::generate(
+            shell,
+            &mut Args::command(),
+            "process_manga_rs",
+            &mut std::io::stdout(),
+        );
+        return Ok(0);
+    }
+    apply_command(&mut args)?;
+    let mut config = match &args.config {
+        Some(path) => Config::load(path),
+        None => Config::load_default(),
+    }
+    .map_err(|err| anyhow::Error::new(UsageError(err)))?;
+    apply_args(&mut config, &args, &matches);
 
     if args.show_cover && (args.print_cover_path || args.yes || args.dry_run) {
-        bail!("--show-cover cannot be combined with --print-cover-path, --yes, or --dry-run");
+        return Err(usage(
+            "--show-cover cannot be combined with --print-cover-path, --yes, or --dry-run",
+        ));
     }
     if args.print_cover_path && (args.show_cover || args.yes || args.dry_run) {
-        bail!("--print-cover-path cannot be combined with --show-cover, --yes, or --dry-run");
+        return Err(usage(
+            "--print-cover-path cannot be combined with --show-cover, --yes, or --dry-run",
+        ));
+    }
+    if args.rollback && (args.show_cover || args.print_cover_path || args.yes || args.dry_run) {
+        return Err(usage("--rollback cannot be combined with --show-cover, --print-cover-path, --yes, or --dry-run"));
+    }
+    if args.unbatch
+        && (args.show_cover || args.print_cover_path || args.rollback || args.pick_cover)
+    {
+        return Err(usage("--unbatch cannot be combined with --show-cover, --print-cover-path, --rollback, or --pick-cover"));
     }
 
-    let series_dir = resolve_series_dir(&args.series_dir)?;
+    if args.preview_clean
+        && (args.show_cover
+            || args.print_cover_path
+            || args.rollback
+            || args.unbatch
+            || args.pick_cover
+            || args.json)
+    {
+        return Err(usage("--preview-clean cannot be combined with --show-cover, --print-cover-path, --rollback, --unbatch, --pick-cover, or --json"));
+    }
+
+    if args.no_cover && (args.show_cover || args.print_cover_path) {
+        return Err(usage(
+            "--no-cover cannot be combined with --show-cover or --print-cover-path",
+        ));
+    }
+    if args.pick_cover
+        && (args.show_cover
+            || args.print_cover_path
+            || args.rollback
+            || args.no_cover
+            || args.cover_id.is_some()
+            || config.offline == Some(true)
+            || args.json)
+    {
+        return Err(usage("--pick-cover cannot be combined with --show-cover, --print-cover-path, --rollback, --no-cover, --cover-id, --offline, or --json"));
+    }
+    // The confirmation prompts are written to stdout, where they would land in the JSON.
+    if args.json
+        && !(args.yes || args.dry_run || args.rollback || args.show_cover || args.print_cover_path)
+    {
+        return Err(usage("--json needs --yes or --dry-run"));
+    }
+    if args.bulk.is_some()
+        && (args.show_cover
+            || args.print_cover_path
+            || args.rollback
+            || args.unbatch
+            || args.pick_cover
+            || args.json
+            || args.title.is_some()
+            || args.cover_id.is_some()
+            || args.cover_title_text.is_some())
+    {
+        return Err(usage("--bulk cannot be combined with --show-cover, --print-cover-path, --rollback, --unbatch, --pick-cover, --json, --title, --cover-id, or --cover-title-text"));
+    }
+
+    config.apply_network_settings();
+
+    let AnalyzeOptions {
+        plan: plan_options,
+        cover: mut cover_options,
+    } = config.analyze_options()?;
+    plan_options
+        .validate()
+        .map_err(|err| anyhow::Error::new(UsageError(err)))?;
+
+    if args.extract_volume == 0 || args.extract_page == 0 {
+        return Err(usage(
+            "--extract-volume and --extract-page are 1-based and must be at least 1",
+        ));
+    }
+    if config.jobs == Some(0) {
+        return Err(usage("--jobs must be at least 1"));
+    }
+    cover_options = SeriesCoverOptions {
+        volume_index: args.extract_volume - 1,
+        page_index: args.extract_page - 1,
+        mode: if args.no_cover {
+            CoverMode::Skip
+        } else {
+            CoverMode::Resolve
+        },
+        title_override: args.title.clone(),
+        // Asking for a specific volume or page only makes sense with a fresh extraction.
+        force_extract: args.force_extract || args.extract_volume != 1 || args.extract_page != 1,
+        ..cover_options
+    };
+    let remote_options = CoverOptions {
+        size: args.cover_size,
+        volume: args.cover_volume,
+        highest_resolution: args.cover_highest_res,
+        min_score: args.cover_min_score,
+        ..config.cover_search_options()
+    };
+    if remote_options != config.cover_search_options() {
+        cover_options.providers = match &config.cover_providers {
+            Some(names) => cover_providers_named(names, &remote_options)?,
+            None => cover_providers(&remote_options),
+        };
+    }
+    if remote_options != CoverOptions::default() || args.no_cache {
+        // Cached URLs were chosen with the default options.
+        cover_options.cache = None;
+    }
+    if let Some(cover_id) = args.cover_id.clone() {
+        // The cache is keyed by title, so it could hold a different series' cover.
+        cover_options.providers = vec![Arc::new(cover_id)];
+        cover_options.cache = None;
+    }
+
+    if log_level(&args) == LogLevel::Verbose {
+        set_http_trace(Some(match args.progress {
+            ProgressFormat::Text => print_http_trace,
+            ProgressFormat::Ndjson => print_http_trace_json,
+        }));
+    }
+
+    match &args.bulk {
+        Some(parent) => run_bulk(
+            &args,
+            &config,
+            parent,
+            &plan_options,
+            &cover_options,
+            &remote_options,
+        ),
+        None => process_series(
+            &args,
+            &config,
+            args.series_dir.as_deref().unwrap_or_default(),
+            &plan_options,
+            &cover_options,
+            &remote_options,
+        ),
+    }
+}
+
+fn log_level(args: &Args) -> LogLevel {
+    if args.quiet {
+        LogLevel::Quiet
+    } else if args.verbose {
+        LogLevel::Verbose
+    } else {
+        LogLevel::Normal
+    }
+}
+
+/// Runs the whole pipeline (or the requested cover, rollback or unbatch step) on one series.
+fn process_series(
+    args: &Args,
+    config: &Config,
+    series_arg: &str,
+    plan_options: &PlanOptions,
+    cover_options: &SeriesCoverOptions,
+    remote_options: &CoverOptions,
+) -> Result<i32> {
+    let series_dir = resolve_series_dir(series_arg)?;
     let series_title = series_dir
         .file_name()
         .map(|n| n.to_string_lossy().into_owned())
         .unwrap_or_else(|| series_dir.display().to_string());
 
-    let mut log = |line: String| println!("{line}");
+    let json = args.json;
+    let ndjson = args.progress == ProgressFormat::Ndjson;
+    let level = log_level(args);
+    // Timings are summed for the breakdown at the end whatever the level.
+    let timings = RefCell::new(PhaseTimings::default());
+    let mut log = |event: LogEvent| {
+        timings.borrow_mut().record(&event);
+        if event.level() > level {
+            return;
+        }
+        if ndjson {
+            eprintln!("{}", event.to_json());
+        } else if json || event.level() == LogLevel::Quiet {
+            // Warnings and failures go to stderr so they aren't lost among routine output.
+            eprintln!("{event}");
+        } else {
+            println!("{event}");
+        }
+    };
+
+    if args.rollback {
+        let journal = journal_path(&series_dir);
+        if !journal.is_file() {
+            return Err(Error::PathResolution(anyhow!(
+                "No journal found at {}",
+                journal.display()
+            ))
+            .into());
+        }
+        rollback(&journal, &mut log)?;
+        return Ok(0);
+    }
+
+    if args.preview_clean {
+        let (volumes, _) = scan_volumes_ignoring(
+            &series_dir,
+            plan_options.recursive,
+            &plan_options.volume_exts,
+            &plan_options.ignore,
+        )?;
+        if volumes.is_empty() {
+            print_stderr(
+                "warn",
+                "[PREVIEW]",
+                &format!("No volume files found in {}", series_dir.display()),
+            );
+            return Ok(EXIT_NO_VOLUMES);
+        }
+        let names: Vec<String> = volumes.iter().map(|path| file_name(path)).collect();
+        let pairs = preview_clean(&names, &plan_options.clean_rules, true);
+        let mut changed = 0;
+        for (src, cleaned) in &pairs {
+            if src == cleaned {
+                println!("  [SAME]   {src}");
+            } else {
+                changed += 1;
+                println!("  [RENAME] {src} -> {cleaned}");
+            }
+        }
+        println!("[PREVIEW] {changed} of {} names would change.", pairs.len());
+        return Ok(0);
+    }
+
+    if args.unbatch {
+        let moves = plan_unbatch(&series_dir, plan_options)?;
+        if moves.is_empty() {
+            print_stderr(
+                "warn",
+                "[UNBATCH]",
+                &format!(
+                    "No batch folders with volumes found next to {}",
+                    series_dir.display()
+                ),
+            );
+            return Ok(EXIT_NO_VOLUMES);
+        }
+        log(LogEvent::Info(format!(
+            "[UNBATCH] Moving {} volumes back into {}",
+            moves.len(),
+            series_dir.display()
+        )));
+        for planned in &moves {
+            let folder = planned
+                .src
+                .parent()
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let source = planned
+                .src
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            log(LogEvent::Info(format!(
+                "  [MOVE] {folder}/{source} -> {}",
+                planned.dst_name
+            )));
+        }
+        if args.dry_run {
+            log(LogEvent::Info(
+                "[DRY-RUN] Plan printed only. No changes were made.".to_string(),
+            ));
+            return Ok(0);
+        }
+        if !args.yes && !prompt_confirm("\nMove them back and delete the batch folders? [y/N]: ")? {
+            println!("[SKIP] Aborted by user.");
+            return Ok(0);
+        }
+        let moved = unbatch(&series_dir, plan_options, &mut log)?;
+        log(LogEvent::Info(format!(
+            "[COMPLETE] {} volumes are back in {}",
+            moved.len(),
+            series_dir.display()
+        )));
+        return Ok(0);
+    }
 
     if args.show_cover {
-        let Some(series_cover) = ensure_series_cover(&series_dir, &series_title, &mut log)? else {
-            eprintln!("[COVER-CHECK] No cover found from local files or remote providers.");
-            return Ok(1);
+        // Opening the cover is a preview; extracted or downloaded covers go to a temp file.
+        let cover = decide_series_cover(&series_dir, &series_title, cover_options, &mut log)?;
+        let Some(preview) = preview_series_cover(&cover, &mut log)? else {
+            print_stderr("warn", "[COVER-CHECK]", NO_COVER_FOUND);
+            return Ok(EXIT_NO_COVER);
         };
 
-        let cover_jpg = ensure_cover_jpg(&series_dir, &series_cover)?;
-        println!("[COVER-CHECK] Opening: {}", cover_jpg.display());
-        open_image(&cover_jpg)?;
+        println!("[COVER-CHECK] Opening: {}", preview.display());
+        open_image(&preview)?;
         return Ok(0);
     }
 
     if args.print_cover_path {
-        let Some(series_cover) = ensure_series_cover(&series_dir, &series_title, &mut log)? else {
-            eprintln!("[COVER-CHECK] No cover found from local files or remote providers.");
-            return Ok(1);
+        let Some(series_cover) =
+            ensure_series_cover(&series_dir, &series_title, cover_options, &mut log)?
+        else {
+            print_stderr("warn", "[COVER-CHECK]", NO_COVER_FOUND);
+            return Ok(EXIT_NO_COVER);
         };
 
-        let cover_jpg = ensure_cover_jpg(&series_dir, &series_cover)?;
-        println!("{}", cover_jpg.display());
+        let cover_path = if cover_options.keep_cover_format {
+            series_cover
+        } else {
+            ensure_cover_jpg(&series_dir, &series_cover)?
+        };
+        println!("{}", cover_path.display());
         return Ok(0);
     }
 
-    let series_cover = ensure_series_cover(&series_dir, &series_title, &mut log)?;
+    // A series whose volumes all sit in batch folders from an earlier run has nothing to move.
+    let organized = already_organized(&series_dir, plan_options)?;
+    if let Some(batches) = &organized {
+        log(LogEvent::Info(format!(
+            "[PLAN] {series_title} is already organized into {} batch folders; nothing to move.",
+            batches.len()
+        )));
+        for batch in batches {
+            log(LogEvent::Info(format!(
+                "  [DIR] {} ({} volumes)",
+                batch.batch_dir.display(),
+                batch.volumes.len()
+            )));
+        }
+        if !args.rerender_covers {
+            log(LogEvent::Info(
+                "[PLAN] Pass --rerender-covers to render their covers again.".to_string(),
+            ));
+            return Ok(0);
+        }
+    }
 
-    let plan = build_plan(&series_dir, series_cover.as_deref())?;
-    print!(
-        "{}",
-        format_plan(&series_dir, &plan, series_cover.as_deref())
-    );
+    // Decide on the cover without writing it, so a dry run leaves the folder untouched.
+    let picked = if args.pick_cover {
+        let search_title = args.title.as_deref().unwrap_or(&series_title);
+        pick_cover(&series_dir, search_title, remote_options, &mut log)?
+    } else {
+        None
+    };
+    let cover = match picked {
+        Some(cover) => cover,
+        None => decide_series_cover(&series_dir, &series_title, cover_options, &mut log)?,
+    };
+    let planned_cover = cover.planned_path();
 
-    if args.dry_run {
-        println!("[DRY-RUN] Plan printed only. No changes were made.");
-        return Ok(0);
+    let plan = if let Some(batches) = &organized {
+        let Some(planned_cover) = planned_cover else {
+            print_stderr("warn", "[COVER-CHECK]", NO_COVER_FOUND);
+            return Ok(EXIT_NO_COVER);
+        };
+        log(LogEvent::Info(format!(
+            "[PLAN] Series cover source: {}",
+            planned_cover.display()
+        )));
+        cover_refresh_plan(batches, Some(planned_cover), &plan_options.clean_rules)
+    } else {
+        let started = Instant::now();
+        let plan = build_plan(&series_dir, planned_cover, plan_options)?;
+        log(LogEvent::Timing {
+            phase: Phase::Planning,
+            elapsed: started.elapsed(),
+        });
+        plan
+    };
+    // For an organized series the batch list above is the whole plan.
+    if organized.is_none() {
+        if json {
+            let mut value = plan_to_json(&series_dir, &plan, planned_cover);
+            value["cover_action"] = serde_json::json!(cover.describe());
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        } else if level > LogLevel::Quiet {
+            print!(
+                "{}",
+                format_plan(&series_dir, &plan, planned_cover, plan_options)
+            );
+        }
+        log(LogEvent::Info(format!(
+            "[PLAN] Cover step: {}",
+            cover.describe()
+        )));
+        let ratio = config
+            .rename_warn_ratio
+            .unwrap_or(DEFAULT_RENAME_WARN_RATIO);
+        if let Some(warning) = rename_warning(&plan, ratio) {
+            log(LogEvent::Warn(warning));
+        }
     }
 
-    if args.yes {
-        execute(&plan, series_cover.as_deref(), &mut log)?;
+    if args.dry_run {
+        log(LogEvent::Info(
+            "[DRY-RUN] Plan printed only. No changes were made.".to_string(),
+        ));
         return Ok(0);
     }
 
-    if !prompt_confirm("\nProceed and execute everything now? [y/N]: ")? {
+    let configured = config.execute_options(&series_dir, &cover);
+    let execute_options = ExecuteOptions {
+        // A cover refresh keeps the journal of the run that made the batches, so that run can
+        // still be rolled back.
+        journal: organized.is_none().then(|| journal_path(&series_dir)),
+        cover_style: CoverStyle {
+            ratio: args.cover_ratio,
+            pad_color: args.cover_pad_color,
+            width: args.cover_width,
+            title: match args.cover_title_text.clone() {
+                Some(text) => Some(text),
+                None => args.cover_title.then(|| series_title.clone()),
+            },
+            title_position: args.cover_title_position,
+            number_width: args.cover_number_width.map(usize::from),
+            pad_number: args.cover_number_pad,
+            ..configured.cover_style
+        },
+        verify_copies: args.verify,
+        ..configured
+    };
+
+    if !args.yes && !prompt_confirm("\nProceed and execute everything now? [y/N]: ")? {
         println!("[SKIP] Aborted by user.");
         return Ok(0);
     }
 
-    execute(&plan, series_cover.as_deref(), &mut log)?;
+    let series_cover = apply_cover_decision(&cover, &mut log)?;
+    let summary = execute(
+        &plan,
+        series_cover.as_deref(),
+        &execute_options,
+        &AtomicBool::new(false),
+        &mut log,
+    )?;
+    log(LogEvent::Info(format!(
+        "[SUMMARY] {}",
+        summary.report(&plan)
+    )));
+    for dir in summary.affected_dirs() {
+        log(LogEvent::Info(format!("  [CHANGED] {}", dir.display())));
+    }
+    for path in &summary.covers_pruned {
+        log(LogEvent::Info(format!("  [PRUNED] {}", path.display())));
+    }
+    let breakdown = timings.borrow().to_string();
+    if !breakdown.is_empty() {
+        log(LogEvent::Info(format!("[TIME] {breakdown}")));
+    }
+    if !summary.failed.is_empty() {
+        let message = format!(
+            "{} volumes could not be moved and were left in place:",
+            summary.failed.len()
+        );
+        if NDJSON.load(Ordering::Relaxed) {
+            let failed: Vec<_> = summary
+                .failed
+                .iter()
+                .map(|failed| {
+                    serde_json::json!({
+                        "from": failed.src.display().to_string(),
+                        "error": failed.error,
+                    })
+                })
+                .collect();
+            eprintln!(
+                "{}",
+                serde_json::json!({ "type": "error", "message": message, "failed": failed })
+            );
+        } else {
+            eprintln!("[FAILED] {message}");
+            for failed in &summary.failed {
+                eprintln!("  {}: {}", failed.src.display(), failed.error);
+            }
+        }
+        return Ok(EXIT_FAILURE);
+    }
     Ok(0)
 }
 
+/// Runs `process_series` on every subfolder of `parent`, in natural order. A series that fails
+/// is reported and the rest still run; the rate limit and cover cache are shared by all of them.
+fn run_bulk(
+    args: &Args,
+    config: &Config,
+    parent: &str,
+    plan_options: &PlanOptions,
+    cover_options: &SeriesCoverOptions,
+    remote_options: &CoverOptions,
+) -> Result<i32> {
+    let parent_dir = resolve_series_dir(parent)?;
+    let series_dirs = library_series_dirs(&parent_dir, plan_options)?;
+    if series_dirs.is_empty() {
+        return Err(Error::NoVolumes(anyhow!(
+            "No series folders found in {}",
+            parent_dir.display()
+        ))
+        .into());
+    }
+    let total = series_dirs.len();
+    let mut processed = 0;
+    let mut skipped = Vec::new();
+    let mut failed = Vec::new();
+    for (i, series_dir) in series_dirs.iter().enumerate() {
+        let name = file_name(series_dir);
+        println!("\n[BULK] ({}/{total}) {name}", i + 1);
+        let result = process_series(
+            args,
+            config,
+            &series_dir.to_string_lossy(),
+            plan_options,
+            cover_options,
+            remote_options,
+        );
+        match result {
+            Ok(0) => processed += 1,
+            Ok(EXIT_NO_COVER) => failed.push((name, "no cover found".to_string())),
+            Ok(_) => failed.push((name, "some volumes could not be moved".to_string())),
+            Err(err) => {
+                let message = err.to_string();
+                if exit_code(err) == EXIT_NO_VOLUMES {
+                    skipped.push(name);
+                } else {
+                    print_stderr("error", "[ERROR]", &message);
+                    failed.push((name, message));
+                }
+            }
+        }
+    }
+
+    println!(
+        "\n[BULK] {processed} of {total} series processed, {} skipped (no volumes), {} failed",
+        skipped.len(),
+        failed.len()
+    );
+    for name in &skipped {
+        println!("  [SKIP] {name}");
+    }
+    for (name, reason) in &failed {
+        print_stderr("error", "  [FAILED]", &format!("{name}: {reason}"));
+    }
+    Ok(if failed.is_empty() { 0 } else { EXIT_FAILURE })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Requests are made on provider threads too, so they go straight to stderr instead of
+/// through `log`.
+fn print_http_trace(event: LogEvent) {
+    eprintln!("{event}");
+}
+
+fn print_http_trace_json(event: LogEvent) {
+    eprintln!("{}", event.to_json());
+}
+
+/// Set for `--progress ndjson`, which `main` needs too when `run` fails.
+static NDJSON: AtomicBool = AtomicBool::new(false);
+
+/// Writes a line that doesn't go through `log` to stderr: `tag` and `message` as text, or with
+/// `--progress ndjson` an object of type `kind` holding `message`, so every line stays JSON.
+fn print_stderr(kind: &str, tag: &str, message: &str) {
+    if NDJSON.load(Ordering::Relaxed) {
+        eprintln!(
+            "{}",
+            serde_json::json!({ "type": kind, "message": message })
+        );
+    } else {
+        eprintln!("{tag} {message}");
+    }
+}
+
+const PICK_COVER_CANDIDATES: usize = 5;
+
+const NO_COVER_FOUND: &str = "No cover found from local files or remote providers.";
+
+/// Lists MangaDex's best matches for `title` and asks which cover to download. `None` (no
+/// matches, or the user skipped) falls back to the usual cover resolution.
+fn pick_cover(
+    series_dir: &Path,
+    title: &str,
+    options: &CoverOptions,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<Option<CoverDecision>> {
+    let mut candidates = mangadex_cover_candidates(title, options, PICK_COVER_CANDIDATES)?;
+    if candidates.is_empty() {
+        log(LogEvent::Warn(format!(
+            "MangaDex has no covers matching {title:?}; resolving the cover as usual."
+        )));
+        return Ok(None);
+    }
+
+    println!("[COVER-PICK] MangaDex matches for {title:?}:");
+    for (i, candidate) in candidates.iter().enumerate() {
+        println!(
+            "  {}. {}  (score {}/{MANGADEX_MAX_SCORE}, source={})",
+            i + 1,
+            candidate.title,
+            candidate.result.match_score.unwrap_or_default(),
+            candidate.result.source
+        );
+        match download_cover_thumbnail(candidate, &format!("pick {}", i + 1)) {
+            Ok(path) => println!("     thumbnail: {}", path.display()),
+            Err(err) => println!("     thumbnail unavailable: {err:#}"),
+        }
+    }
+
+    let prompt = format!("Use which cover? [1-{}, Enter to skip]: ", candidates.len());
+    let Some(choice) = prompt_choice(&prompt, candidates.len())? else {
+        return Ok(None);
+    };
+    Ok(Some(CoverDecision::Download {
+        result: candidates.swap_remove(choice).result,
+        fallbacks: Vec::new(),
+        output: series_dir.join("cover.jpg"),
+    }))
+}
+
+/// Exit statuses; the README lists them, and scripts rely on them staying put.
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_PATH: i32 = 3;
+const EXIT_NO_VOLUMES: i32 = 4;
+const EXIT_NETWORK: i32 = 5;
+const EXIT_NO_COVER: i32 = 6;
+const EXIT_ARCHIVE: i32 = 7;
+const EXIT_FONT: i32 = 8;
+
+/// Conflicting flags or an invalid config file; exits with `EXIT_USAGE`.
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+struct UsageError(anyhow::Error);
+
+fn usage(message: &str) -> anyhow::Error {
+    UsageError(anyhow!("{message}")).into()
+}
+
+fn exit_code(err: anyhow::Error) -> i32 {
+    if err.chain().any(|cause| cause.is::<UsageError>()) {
+        return EXIT_USAGE;
+    }
+    match Error::from(err) {
+        Error::PathResolution(_) | Error::Io(_) => EXIT_PATH,
+        Error::NoVolumes(_) => EXIT_NO_VOLUMES,
+        Error::Network(_) => EXIT_NETWORK,
+        Error::CoverNotFound(_) => EXIT_NO_COVER,
+        Error::Archive(_) => EXIT_ARCHIVE,
+        Error::Font(_) => EXIT_FONT,
+        Error::Other(_) => EXIT_FAILURE,
+    }
+}
+
 fn main() {
     match run() {
         Ok(code) => process::exit(code),
         Err(err) => {
-            eprintln!("[ERROR] {err}");
-            process::exit(2);
+            print_stderr("error", "[ERROR]", &err.to_string());
+            process::exit(exit_code(err));
         }
     }
 }
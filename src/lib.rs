@@ -1,26 +1,59 @@
+//! Library behind the `manga_cleaner_native` app and the `process_manga_rs` CLI.
+//!
+//! Read-only (never write to the series folder): `scan_volumes`, `choose_series_cover`,
+//! `decide_series_cover`, `build_plan`, `analyze`, `format_plan`, `plan_to_json`,
+//! `plan_unbatch`, and `preview_series_cover`, which only writes to the system temp directory. Remote lookups
+//! may still update the cover cache.
+//!
+//! Effectful: `apply_cover_decision`, `ensure_series_cover`, `ensure_cover_jpg` (archives an
+//! existing `cover.jpg`), `write_volume_cover`, `execute`, `rollback`, and `unbatch`.
+//!
+//! These entry points return `Error`, which says what kind of failure it was; the helpers
+//! around them return `anyhow::Error`, which converts into it.
+
+#[cfg(feature = "network")]
+use std::sync::mpsc;
 use std::{
-    cmp::Reverse,
-    collections::HashSet,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ffi::OsString,
     fs,
     io::{self, Read, Write},
     path::{Component, Path, PathBuf},
     process::Command,
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use ab_glyph::{FontArc, PxScale};
 use anyhow::{anyhow, bail, Context, Result};
-use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageReader, Rgba, RgbaImage};
+use filetime::FileTime;
+use image::{
+    codecs::{gif::GifDecoder, jpeg::JpegEncoder, webp::WebPDecoder},
+    AnimationDecoder, DynamicImage, ImageFormat, ImageReader, Rgb, RgbImage, Rgba, RgbaImage,
+};
 use imageproc::drawing::{draw_text_mut, text_size};
 use natord::compare_ignore_case;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use reqwest::blocking::Client;
+#[cfg(feature = "network")]
+use reqwest::{
+    blocking::{Client, Response},
+    header::{CONTENT_TYPE, REFERER, RETRY_AFTER},
+    Proxy, StatusCode,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sevenz_rust::{Password, SevenZReader};
+use sha2::{Digest, Sha256};
 use zip::ZipArchive;
 
 pub const FILES_PER_FOLDER: usize = 20;
-pub const VOLUME_EXTS: &[&str] = &[".cbz", ".cbr", ".cb7", ".zip"];
+pub const VOLUME_EXTS: &[&str] = &[".cbz", ".cbr", ".cb7", ".cbt", ".zip"];
 pub const IMAGE_EXTS: &[&str] = &[".jpg", ".jpeg", ".png", ".webp", ".bmp", ".gif"];
 
 pub const COVER_CANDIDATES: &[&str] = &[
@@ -32,28 +65,201 @@ pub const COVER_CANDIDATES: &[&str] = &[
     "cover_old.jpg",
 ];
 
+pub const JOURNAL_FILE_NAME: &str = ".manga_cleaner_journal.json";
+
+/// Per-batch metadata written by `execute` when `ExecuteOptions::metadata` is set.
+pub const METADATA_FILE_NAME: &str = "series.json";
+
+/// Per-batch SHA-256 manifest written by `execute` when `ExecuteOptions::checksums` is set, in
+/// the format `sha256sum -c` reads.
+pub const CHECKSUMS_FILE_NAME: &str = "checksums.sha256";
+
 pub const USER_AGENT: &str = "manga-toolkit-rust/1.0 (+https://example.invalid)";
+/// Sent with cover downloads unless `HttpSettings::referer` says otherwise; MangaDex's image
+/// servers expect it.
+pub const DEFAULT_REFERER: &str = "https://mangadex.org/";
+
+pub const HTTP_MAX_ATTEMPTS: u32 = 3;
+#[cfg(feature = "network")]
+const HTTP_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+#[cfg(feature = "network")]
+const HTTP_RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Requests per second allowed to each host; MangaDex asks clients to stay around 5.
+pub const DEFAULT_REQUEST_RATE: f64 = 5.0;
+
+/// The failure classes the entry points (`resolve_series_dir`, `analyze`, `build_plan`,
+/// `execute`, ...) return, so callers can react to them. Each wraps the whole `anyhow`
+/// chain: `{err:#}` prints every cause as before.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The folder path is empty, doesn't exist, or isn't a folder.
+    #[error(transparent)]
+    PathResolution(anyhow::Error),
+    /// The series folder has no volumes to plan.
+    #[error(transparent)]
+    NoVolumes(anyhow::Error),
+    /// A volume archive can't be opened or read.
+    #[error(transparent)]
+    Archive(anyhow::Error),
+    /// A request failed, or network access is off.
+    #[error(transparent)]
+    Network(anyhow::Error),
+    /// No usable font for the batch covers.
+    #[error(transparent)]
+    Font(anyhow::Error),
+    /// No cover from local files or remote providers where one was needed.
+    #[error(transparent)]
+    CoverNotFound(anyhow::Error),
+    /// Reading or writing a file failed.
+    #[error(transparent)]
+    Io(anyhow::Error),
+    /// Everything else: invalid options, cancellation, undecodable images, ...
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl Error {
+    /// The constructor of this error's class, to wrap another error the same way.
+    fn class(&self) -> fn(anyhow::Error) -> Self {
+        match self {
+            Self::PathResolution(_) => Self::PathResolution,
+            Self::NoVolumes(_) => Self::NoVolumes,
+            Self::Archive(_) => Self::Archive,
+            Self::Network(_) => Self::Network,
+            Self::Font(_) => Self::Font,
+            Self::CoverNotFound(_) => Self::CoverNotFound,
+            Self::Io(_) => Self::Io,
+            Self::Other(_) => Self::Other,
+        }
+    }
+}
+
+/// Classifies by the outermost typed `Error` in the chain, then by the kind of the underlying
+/// cause (I/O, archive, HTTP, font), so context added on the way up is kept.
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        // Taking the error out is only lossless when no context was added on top of it.
+        let err = if err.chain().next().is_some_and(|top| top.is::<Error>()) {
+            match err.downcast::<Error>() {
+                Ok(typed) => return typed,
+                Err(err) => err,
+            }
+        } else {
+            err
+        };
+        let tagged = err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<Error>())
+            .map(Error::class);
+        let class = tagged.or_else(|| {
+            err.chain().find_map(|cause| {
+                if cause.is::<io::Error>() {
+                    return Some(Self::Io as fn(anyhow::Error) -> Self);
+                }
+                if cause.is::<zip::result::ZipError>()
+                    || cause.is::<sevenz_rust::Error>()
+                    || cause.is::<unrar::error::UnrarError>()
+                {
+                    return Some(Self::Archive);
+                }
+                #[cfg(feature = "network")]
+                if cause.is::<reqwest::Error>() {
+                    return Some(Self::Network);
+                }
+                if cause.is::<ab_glyph::InvalidFont>() {
+                    return Some(Self::Font);
+                }
+                None
+            })
+        });
+        class.unwrap_or(Self::Other)(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err.into())
+    }
+}
 
 static PARENS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s*\([^)]*\)").expect("valid regex"));
+static BRACKET_TAG_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\s*(?:\[([^\]]*)\]|\{([^}]*)\})").expect("valid regex"));
 static MULTI_SPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s{2,}").expect("valid regex"));
 static V_UNDERSCORE_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(v\s*\d+)(?:_\d+)+").expect("valid regex"));
-static VOLUME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bv\s*0*(\d+)").expect("valid regex"));
+static VOLUME_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\bv\s*0*(\d+)(?:\.(\d+))?").expect("valid regex"));
+static COVER_OLD_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^cover_old(?:_\d+)?\.jpg$").expect("valid regex"));
 static NON_ALNUM_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[^a-z0-9]+").expect("valid regex"));
-static INT_VOLUME_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^\s*0*(\d+)(?:\.0+)?\s*$").expect("valid regex"));
+static UUID_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$")
+        .expect("valid regex")
+});
+static CHAPTER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:chapter|ch|c)\.?\s*0*(\d+)(?:\.(\d+))?\b").expect("valid regex")
+});
+static MANGADEX_VOLUME_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*0*(\d+)(?:\.(\d+))?\s*$").expect("valid regex"));
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+static RATE_LIMITER: Lazy<Mutex<RateLimiter>> = Lazy::new(|| {
+    Mutex::new(RateLimiter {
+        rate: DEFAULT_REQUEST_RATE,
+        buckets: HashMap::new(),
+    })
+});
+static HTTP_SETTINGS: Lazy<Mutex<HttpSettings>> = Lazy::new(|| Mutex::new(HttpSettings::default()));
+static HTTP_TRACE: Mutex<Option<fn(LogEvent)>> = Mutex::new(None);
+/// Remote hits waiting on their download, by URL. See `settle_cached_cover`.
+static PENDING_COVERS: Lazy<Mutex<HashMap<String, PendingCover>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
 #[derive(Debug, Clone)]
 pub struct CoverResult {
     pub source: String,
     pub url: String,
+    /// Worth surfacing to the user, e.g. when a preferred variant was unavailable.
+    pub note: Option<String>,
+    /// How well the chosen MangaDex, AniList or Kitsu series matched the searched title; see
+    /// `score_titles`. `None` for pinned IDs, cached results and local covers.
+    pub match_score: Option<u8>,
 }
 
 #[derive(Debug, Clone)]
 pub struct VolumeCoverResult {
+    /// Zero-based position of `volume_file` among the series' volumes.
+    pub volume_index: usize,
     pub volume_file: PathBuf,
     pub image_entry: String,
     pub output_file: PathBuf,
+    /// Write a JPEG or PNG entry's bytes as they are instead of re-encoding them; see
+    /// `SeriesCoverOptions::keep_cover_format`.
+    pub keep_format: bool,
+    /// Half of a double-page spread to keep; see `SpreadHandling::Crop`.
+    pub crop: Option<PageHalf>,
+}
+
+/// What `build_plan` found on disk at a move's cleaned destination name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DestinationState {
+    #[default]
+    Free,
+    /// A byte-identical file is already there (typically from a previous run). The move
+    /// replaces it instead of adding a numbered copy, so re-runs are idempotent.
+    Identical,
+    /// A different file already uses `taken_name`, so the move gets a numbered name.
+    Deduplicated { taken_name: String },
+}
+
+impl DestinationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DestinationState::Free => "free",
+            DestinationState::Identical => "identical",
+            DestinationState::Deduplicated { .. } => "deduplicated",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +267,17 @@ pub struct FileMove {
     pub src: PathBuf,
     pub dst: PathBuf,
     pub dst_name: String,
+    pub destination: DestinationState,
+    /// Why the archive could not be read, when `PlanOptions::validate_archives` found a problem.
+    pub problem: Option<String>,
+}
+
+/// A volume left in place because it is byte-identical to one that is being moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateVolume {
+    pub path: PathBuf,
+    /// The copy that is moved instead.
+    pub original: PathBuf,
 }
 
 #[derive(Debug, Clone)]
@@ -69,6 +286,252 @@ pub struct BatchPlan {
     pub batch_dir: PathBuf,
     pub moves: Vec<FileMove>,
     pub will_make_cover: bool,
+    /// 1-based position of this batch's first volume across the whole series.
+    pub volume_start: usize,
+    /// Duplicates of this batch's volumes that stay where they are.
+    pub duplicates: Vec<DuplicateVolume>,
+    /// Copy the volumes into the batch folder and leave the originals in place.
+    pub copy: bool,
+    /// Volumes an earlier run already put in the folder; see `PlanOptions::incremental`.
+    pub existing_volumes: usize,
+    /// Volumes skipped by `PlanOptions::ignore`, which stay where they are. Only the first
+    /// batch lists them.
+    pub ignored: Vec<PathBuf>,
+    /// The rules the plan cleaned names with, which read the volume numbers in `series.json`.
+    pub clean_rules: CleanRules,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanOptions {
+    pub batch_size: usize,
+    /// Also recognize chapter-only names ("Title Chapter 45" -> "Title c045") and order files by
+    /// their cleaned names. Volume tokens still win when both appear.
+    pub chapters: bool,
+    pub clean_rules: CleanRules,
+    /// Move byte-identical copies of a volume too, instead of leaving them out of the plan.
+    pub keep_duplicates: bool,
+    /// Also collect volumes from subfolders of the series folder.
+    pub recursive: bool,
+    /// Open every archive while planning and record the ones that fail `check_archive`.
+    pub validate_archives: bool,
+    /// How batch folders are named.
+    pub folder_template: FolderTemplate,
+    /// Plan copies instead of moves; see `BatchPlan::copy`.
+    pub copy: bool,
+    /// Add the volumes after the batch folders an earlier run made: fill up the last folder
+    /// first, then continue the batch numbers from there.
+    pub incremental: bool,
+    /// Which cleaned names are made safe for Windows and exFAT; see `sanitize_file_name`.
+    pub sanitize_names: SanitizeNames,
+    /// Write extensions in lowercase (".CBZ" becomes ".cbz"); the stem keeps its case.
+    pub lowercase_extensions: bool,
+    /// Leave volumes whose file name matches one of these out of the plan.
+    pub ignore: Vec<IgnorePattern>,
+    /// Which files count as volumes.
+    pub volume_exts: ExtensionSet,
+}
+
+pub const DEFAULT_FOLDER_TEMPLATE: &str = "{series} {batch}";
+
+/// A batch folder name pattern such as `{series} Vol {start}-{end}`. Placeholders are
+/// `{series}`, `{batch}`, and `{start}`/`{end}`, the first and last volume numbers in the
+/// batch (their positions in the series when a file has no volume number). Numbers accept a
+/// zero-padded width, e.g. `{batch:02}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderTemplate(String);
+
+/// The widest zero padding a folder template placeholder may ask for.
+const MAX_TEMPLATE_WIDTH: usize = 10;
+
+enum TemplatePart {
+    Text(String),
+    Field { name: String, width: usize },
+}
+
+impl FolderTemplate {
+    fn parts(template: &str) -> Result<Vec<TemplatePart>> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(open) = rest.find(['{', '}']) {
+            if rest[open..].starts_with('}') {
+                bail!("unmatched `}}` in folder template {template:?}");
+            }
+            let close = rest[open..]
+                .find('}')
+                .map(|i| open + i)
+                .ok_or_else(|| anyhow!("unclosed `{{` in folder template {template:?}"))?;
+            parts.push(TemplatePart::Text(rest[..open].to_string()));
+            let field = &rest[open + 1..close];
+            let (name, width) = match field.split_once(':') {
+                Some((name, spec)) => {
+                    let width = spec.parse().map_err(|_| {
+                        anyhow!("invalid width {spec:?} for {{{name}}} in folder template")
+                    })?;
+                    if width > MAX_TEMPLATE_WIDTH {
+                        bail!(
+                            "width {width} for {{{name}}} in folder template is too large (at most {MAX_TEMPLATE_WIDTH})"
+                        );
+                    }
+                    (name, width)
+                }
+                None => (field, 0),
+            };
+            match name {
+                "batch" | "start" | "end" => {}
+                "series" if width == 0 => {}
+                "series" => bail!("{{series}} does not take a width"),
+                _ => bail!(
+                    "unknown placeholder {{{name}}} in folder template (use series, batch, start, or end)"
+                ),
+            }
+            parts.push(TemplatePart::Field {
+                name: name.to_string(),
+                width,
+            });
+            rest = &rest[close + 1..];
+        }
+        parts.push(TemplatePart::Text(rest.to_string()));
+        Ok(parts)
+    }
+
+    /// Renders the folder name, rejecting names that are empty, would leave the series' parent
+    /// folder, or are the series folder's own name.
+    fn render(
+        &self,
+        series: &str,
+        batch: usize,
+        start: &VolumeNumber,
+        end: &VolumeNumber,
+    ) -> Result<String> {
+        let number = |value: &VolumeNumber, width: usize| {
+            let whole = format!("{:0width$}", value.whole);
+            if value.fraction.is_empty() {
+                whole
+            } else {
+                format!("{whole}.{}", value.fraction)
+            }
+        };
+        let mut name = String::new();
+        for part in Self::parts(&self.0)? {
+            match part {
+                TemplatePart::Text(text) => name.push_str(&text),
+                TemplatePart::Field { name: field, width } => match field.as_str() {
+                    "series" => name.push_str(series),
+                    "batch" => name.push_str(&format!("{batch:0width$}")),
+                    "start" => name.push_str(&number(start, width)),
+                    _ => name.push_str(&number(end, width)),
+                },
+            }
+        }
+
+        let name = name.trim().to_string();
+        if name.is_empty() || name == "." || name == ".." {
+            bail!("folder template {:?} produced an empty folder name", self.0);
+        }
+        if name.contains(['/', '\\', '\0']) {
+            bail!(
+                "folder template {:?} produced {name:?}, which contains a path separator",
+                self.0
+            );
+        }
+        if name.to_lowercase() == series.trim().to_lowercase() {
+            bail!(
+                "folder template {:?} names a batch folder after the series folder itself",
+                self.0
+            );
+        }
+        Ok(name)
+    }
+}
+
+impl FolderTemplate {
+    /// A pattern for the folder names this template renders for `series`, capturing the
+    /// first `{batch}` as `batch`.
+    fn matcher(&self, series: &str) -> Result<Regex> {
+        let parts = Self::parts(&self.0)?;
+        let last = parts.len() - 1;
+        let mut pattern = String::from("^");
+        let mut has_batch = false;
+        for (i, part) in parts.iter().enumerate() {
+            match part {
+                TemplatePart::Text(text) => {
+                    // `render` trims the whole name.
+                    let mut text = text.as_str();
+                    if i == 0 {
+                        text = text.trim_start();
+                    }
+                    if i == last {
+                        text = text.trim_end();
+                    }
+                    pattern.push_str(&regex::escape(text));
+                }
+                TemplatePart::Field { name, .. } => match name.as_str() {
+                    "series" => pattern.push_str(&regex::escape(series)),
+                    "batch" if !has_batch => {
+                        has_batch = true;
+                        pattern.push_str(r"(?P<batch>\d+)");
+                    }
+                    "batch" => pattern.push_str(r"\d+"),
+                    _ => pattern.push_str(r"\d+(?:\.\d+)?"),
+                },
+            }
+        }
+        pattern.push('$');
+        Ok(Regex::new(&pattern)?)
+    }
+}
+
+impl Default for FolderTemplate {
+    fn default() -> Self {
+        Self(DEFAULT_FOLDER_TEMPLATE.to_string())
+    }
+}
+
+impl std::str::FromStr for FolderTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let template = Self(value.to_string());
+        // A sample render catches separators in the literal text up front.
+        let sample = VolumeNumber::whole(1);
+        template.render("Series", 1, &sample, &sample)?;
+        Ok(template)
+    }
+}
+
+impl std::fmt::Display for FolderTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Default for PlanOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: FILES_PER_FOLDER,
+            chapters: false,
+            clean_rules: CleanRules::default(),
+            keep_duplicates: false,
+            recursive: false,
+            validate_archives: false,
+            folder_template: FolderTemplate::default(),
+            copy: false,
+            incremental: false,
+            sanitize_names: SanitizeNames::default(),
+            lowercase_extensions: false,
+            ignore: Vec::new(),
+            volume_exts: ExtensionSet::volumes(),
+        }
+    }
+}
+
+impl PlanOptions {
+    pub fn validate(&self) -> Result<()> {
+        if self.batch_size == 0 {
+            bail!("Batch size must be at least 1.");
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -102,16 +565,481 @@ pub struct ActionOutput {
     pub cover_path: Option<PathBuf>,
 }
 
+/// Progress and diagnostics reported by the long-running operations. `Display` renders the
+/// plain-text lines the CLI prints; `message` is the same text without the `[TAG]` prefix, and
+/// `to_json` gives the fields as one JSON object for other programs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEvent {
+    CoverExtracted {
+        output: PathBuf,
+        volume_index: usize,
+        volume_file: PathBuf,
+        entry: String,
+    },
+    CoverDownloaded {
+        output: PathBuf,
+        source: String,
+    },
+    /// Bytes of the series cover received so far. `total` is `None` when the server sent no
+    /// `Content-Length`, so only the running count is known.
+    CoverDownloadProgress {
+        received: u64,
+        total: Option<u64>,
+    },
+    /// The series cover was copied from the shared cover directory.
+    CoverCopied {
+        output: PathBuf,
+        source: PathBuf,
+    },
+    CoverNote(String),
+    BatchStarted {
+        batch_index: usize,
+        batch_dir: PathBuf,
+    },
+    /// A file landed in its batch folder. `index` counts moves across the whole plan.
+    Move {
+        index: usize,
+        total: usize,
+        batch: usize,
+        from: PathBuf,
+        to: PathBuf,
+        /// The original was copied and left in place.
+        copied: bool,
+    },
+    /// A move failed and the run carried on; see `ExecuteOptions::continue_on_error`.
+    MoveFailed {
+        from: PathBuf,
+        to: PathBuf,
+        error: String,
+    },
+    CoverRendered {
+        batch: usize,
+    },
+    /// The batch's `cover.jpg` was left as it was; see `ExistingCover`.
+    CoverKept {
+        batch: usize,
+    },
+    Cancelled {
+        batches_completed: usize,
+        total_batches: usize,
+    },
+    Complete,
+    RollbackStarted {
+        entries: usize,
+        journal: PathBuf,
+    },
+    /// Rollback put a file back where it came from.
+    Restored {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    RollbackComplete,
+    /// One HTTP attempt, reported to the hook given to `set_http_trace`. `status` is `None`
+    /// when no response arrived.
+    Http {
+        url: String,
+        attempt: u32,
+        status: Option<u16>,
+        elapsed: Duration,
+    },
+    /// How long one phase of the work took; see `PhaseTimings`.
+    Timing {
+        phase: Phase,
+        elapsed: Duration,
+    },
+    Warn(String),
+    /// Untagged text such as plan lines.
+    Info(String),
+}
+
+/// The parts of a run that `LogEvent::Timing` reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Listing and reading volume archives for the series cover.
+    ArchiveRead,
+    /// Querying the remote cover providers.
+    RemoteLookup,
+    CoverDownload,
+    /// Writing the series cover from its volume or the shared cover folder.
+    CoverWrite,
+    Planning,
+    Moves,
+    /// Decoding the series cover for the batch covers.
+    Decode,
+    /// Finding the font size for the batch numbers.
+    FontFit,
+    Draw,
+    Encode,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::ArchiveRead => "archive reads",
+            Phase::RemoteLookup => "remote lookup",
+            Phase::CoverDownload => "cover download",
+            Phase::CoverWrite => "cover write",
+            Phase::Planning => "planning",
+            Phase::Moves => "moves",
+            Phase::Decode => "decoding",
+            Phase::FontFit => "font fitting",
+            Phase::Draw => "drawing",
+            Phase::Encode => "encoding",
+        }
+    }
+}
+
+/// Time per `Phase`, summed over a run in the order the phases first came up. `Display`
+/// gives a one-line breakdown such as `remote lookup 1.2 s, moves 310 ms`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhaseTimings(Vec<(Phase, Duration)>);
+
+impl PhaseTimings {
+    pub fn add(&mut self, phase: Phase, elapsed: Duration) {
+        match self.0.iter_mut().find(|(seen, _)| *seen == phase) {
+            Some((_, total)) => *total += elapsed,
+            None => self.0.push((phase, elapsed)),
+        }
+    }
+
+    /// Adds the time from a `LogEvent::Timing`; other events are ignored.
+    pub fn record(&mut self, event: &LogEvent) {
+        if let LogEvent::Timing { phase, elapsed } = event {
+            self.add(*phase, *elapsed);
+        }
+    }
+
+    pub fn get(&self, phase: Phase) -> Option<Duration> {
+        self.0
+            .iter()
+            .find(|(seen, _)| *seen == phase)
+            .map(|(_, total)| *total)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn merge(&mut self, other: &PhaseTimings) {
+        for (phase, elapsed) in &other.0 {
+            self.add(*phase, *elapsed);
+        }
+    }
+
+    fn log(&self, log: &mut dyn FnMut(LogEvent)) {
+        for (phase, elapsed) in &self.0 {
+            log(LogEvent::Timing {
+                phase: *phase,
+                elapsed: *elapsed,
+            });
+        }
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    if elapsed < Duration::from_secs(1) {
+        format!("{} ms", elapsed.as_millis())
+    } else {
+        format!("{:.1} s", elapsed.as_secs_f64())
+    }
+}
+
+impl std::fmt::Display for PhaseTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|(phase, elapsed)| format!("{} {}", phase.label(), format_elapsed(*elapsed)))
+            .collect();
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// How much of the log a front end shows, from least to most; see `LogEvent::level`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// Warnings and failures only.
+    Quiet,
+    #[default]
+    Normal,
+    /// Also HTTP requests and phase timings.
+    Verbose,
+}
+
+impl LogEvent {
+    pub fn tag(&self) -> Option<&'static str> {
+        match self {
+            LogEvent::CoverExtracted { .. }
+            | LogEvent::CoverDownloaded { .. }
+            | LogEvent::CoverDownloadProgress { .. }
+            | LogEvent::CoverCopied { .. }
+            | LogEvent::CoverNote(_)
+            | LogEvent::CoverRendered { .. }
+            | LogEvent::CoverKept { .. } => Some("[COVER]"),
+            LogEvent::BatchStarted { .. } => Some("[DO]"),
+            LogEvent::Move { copied: true, .. } => Some("[COPY]"),
+            LogEvent::Move { .. } | LogEvent::Restored { .. } => Some("[MOVE]"),
+            LogEvent::MoveFailed { .. } => Some("[FAILED]"),
+            LogEvent::Cancelled { .. } => Some("[CANCELLED]"),
+            LogEvent::Complete | LogEvent::RollbackComplete => Some("[COMPLETE]"),
+            LogEvent::RollbackStarted { .. } => Some("[ROLLBACK]"),
+            LogEvent::Http { .. } => Some("[HTTP]"),
+            LogEvent::Timing { .. } => Some("[TIME]"),
+            LogEvent::Warn(_) => Some("[WARN]"),
+            LogEvent::Info(_) => None,
+        }
+    }
+
+    /// The lowest `LogLevel` that shows this event.
+    pub fn level(&self) -> LogLevel {
+        match self {
+            LogEvent::Warn(_) | LogEvent::MoveFailed { .. } | LogEvent::Cancelled { .. } => {
+                LogLevel::Quiet
+            }
+            LogEvent::Http { .. } | LogEvent::Timing { .. } => LogLevel::Verbose,
+            _ => LogLevel::Normal,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            LogEvent::CoverExtracted {
+                output,
+                volume_index,
+                volume_file,
+                entry,
+            } => {
+                let volume_label = if *volume_index == 0 {
+                    "first volume".to_string()
+                } else {
+                    format!("volume {}", volume_index + 1)
+                };
+                format!(
+                    "Extracted series cover from {volume_label}: {} (source={}:{entry})",
+                    output.display(),
+                    file_name_text(volume_file)
+                )
+            }
+            LogEvent::CoverDownloaded { output, source } => format!(
+                "Downloaded series cover: {} (source={source})",
+                output.display()
+            ),
+            LogEvent::CoverDownloadProgress {
+                received,
+                total: Some(total),
+            } => format!(
+                "Downloading series cover: {} of {} KB ({}%)",
+                received / 1024,
+                total / 1024,
+                received.saturating_mul(100) / (*total).max(1)
+            ),
+            LogEvent::CoverDownloadProgress {
+                received,
+                total: None,
+            } => format!(
+                "Downloading series cover: {} KB so far (size unknown)",
+                received / 1024
+            ),
+            LogEvent::CoverCopied { output, source } => format!(
+                "Copied series cover: {} (source={})",
+                output.display(),
+                source.display()
+            ),
+            LogEvent::CoverNote(note) => note.clone(),
+            LogEvent::BatchStarted {
+                batch_index,
+                batch_dir,
+            } => format!("Batch {batch_index}: {}", file_name_text(batch_dir)),
+            LogEvent::Move {
+                index,
+                total,
+                from,
+                to,
+                ..
+            } => format!(
+                "({index}/{total}) {} -> {}",
+                file_name_text(from),
+                file_name_text(to)
+            ),
+            LogEvent::MoveFailed { from, to, error } => format!(
+                "{} -> {}: {error} (left in place)",
+                file_name_text(from),
+                file_name_text(to)
+            ),
+            LogEvent::CoverRendered { batch } => {
+                format!("Rendered cover.jpg (batch number {batch})")
+            }
+            LogEvent::CoverKept { batch } => {
+                format!("Kept the existing cover.jpg (batch number {batch})")
+            }
+            LogEvent::Cancelled {
+                batches_completed,
+                total_batches,
+            } => format!("Stopped after {batches_completed} of {total_batches} batches."),
+            LogEvent::Complete => "Done.".to_string(),
+            LogEvent::RollbackStarted { entries, journal } => format!(
+                "Reverting {entries} recorded changes from {}",
+                journal.display()
+            ),
+            LogEvent::Restored { from, to } => {
+                format!("{} -> {}", file_name_text(from), to.display())
+            }
+            LogEvent::RollbackComplete => "Rollback finished.".to_string(),
+            LogEvent::Http {
+                url,
+                attempt,
+                status,
+                elapsed,
+            } => {
+                let outcome = status.map_or("failed".to_string(), |status| status.to_string());
+                format!(
+                    "GET {url} -> {outcome} in {} ms (attempt {attempt})",
+                    elapsed.as_millis()
+                )
+            }
+            LogEvent::Timing { phase, elapsed } => {
+                format!("{} took {}", phase.label(), format_elapsed(*elapsed))
+            }
+            LogEvent::Warn(message) | LogEvent::Info(message) => message.clone(),
+        }
+    }
+
+    /// The event as a flat JSON object: `type` names the variant in snake case, the variant's
+    /// fields follow (paths as text, durations as `elapsed_ms`), and `message` holds the same
+    /// text as `message()`.
+    pub fn to_json(&self) -> Value {
+        let path = |path: &Path| path.display().to_string();
+        let mut value = match self {
+            LogEvent::CoverExtracted {
+                output,
+                volume_index,
+                volume_file,
+                entry,
+            } => json!({
+                "type": "cover_extracted",
+                "output": path(output),
+                "volume_index": volume_index,
+                "volume_file": path(volume_file),
+                "entry": entry,
+            }),
+            LogEvent::CoverDownloaded { output, source } => json!({
+                "type": "cover_downloaded",
+                "output": path(output),
+                "source": source,
+            }),
+            LogEvent::CoverDownloadProgress { received, total } => json!({
+                "type": "cover_download_progress",
+                "received": received,
+                "total": total,
+            }),
+            LogEvent::CoverCopied { output, source } => json!({
+                "type": "cover_copied",
+                "output": path(output),
+                "source": path(source),
+            }),
+            LogEvent::CoverNote(_) => json!({ "type": "cover_note" }),
+            LogEvent::BatchStarted {
+                batch_index,
+                batch_dir,
+            } => json!({
+                "type": "batch_started",
+                "batch_index": batch_index,
+                "batch_dir": path(batch_dir),
+            }),
+            LogEvent::Move {
+                index,
+                total,
+                batch,
+                from,
+                to,
+                copied,
+            } => json!({
+                "type": "move",
+                "index": index,
+                "total": total,
+                "batch": batch,
+                "from": path(from),
+                "to": path(to),
+                "copied": copied,
+            }),
+            LogEvent::MoveFailed { from, to, error } => json!({
+                "type": "move_failed",
+                "from": path(from),
+                "to": path(to),
+                "error": error,
+            }),
+            LogEvent::CoverRendered { batch } => {
+                json!({ "type": "cover_rendered", "batch": batch })
+            }
+            LogEvent::CoverKept { batch } => json!({ "type": "cover_kept", "batch": batch }),
+            LogEvent::Cancelled {
+                batches_completed,
+                total_batches,
+            } => json!({
+                "type": "cancelled",
+                "batches_completed": batches_completed,
+                "total_batches": total_batches,
+            }),
+            LogEvent::Complete => json!({ "type": "complete" }),
+            LogEvent::RollbackStarted { entries, journal } => json!({
+                "type": "rollback_started",
+                "entries": entries,
+                "journal": path(journal),
+            }),
+            LogEvent::Restored { from, to } => json!({
+                "type": "restored",
+                "from": path(from),
+                "to": path(to),
+            }),
+            LogEvent::RollbackComplete => json!({ "type": "rollback_complete" }),
+            LogEvent::Http {
+                url,
+                attempt,
+                status,
+                elapsed,
+            } => json!({
+                "type": "http",
+                "url": url,
+                "attempt": attempt,
+                "status": status,
+                "elapsed_ms": elapsed.as_millis() as u64,
+            }),
+            LogEvent::Timing { phase, elapsed } => json!({
+                "type": "timing",
+                "phase": phase.label(),
+                "elapsed_ms": elapsed.as_millis() as u64,
+            }),
+            LogEvent::Warn(_) => json!({ "type": "warn" }),
+            LogEvent::Info(_) => json!({ "type": "info" }),
+        };
+        value["message"] = json!(self.message());
+        value
+    }
+}
+
+impl std::fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rule = "-".repeat(98);
+        match (self, self.tag()) {
+            (LogEvent::BatchStarted { .. }, Some(tag)) => {
+                write!(f, "\n{rule}\n{tag} {}\n{rule}", self.message())
+            }
+            (_, Some(tag)) => write!(f, "{tag} {}", self.message()),
+            (_, None) => f.write_str(&self.message()),
+        }
+    }
+}
+
 pub fn is_hidden_or_macos_junk(name: &str) -> bool {
     name.starts_with('.') || name.starts_with("._")
 }
 
-fn has_known_ext(path: &Path, exts: &[&str]) -> bool {
+fn has_known_ext<S: AsRef<str>>(path: &Path, exts: &[S]) -> bool {
     let lower = path
         .file_name()
         .map(|n| n.to_string_lossy().to_ascii_lowercase())
         .unwrap_or_default();
-    exts.iter().any(|ext| lower.ends_with(ext))
+    exts.iter().any(|ext| lower.ends_with(ext.as_ref()))
 }
 
 fn file_name_text(path: &Path) -> String {
@@ -133,12 +1061,45 @@ pub fn ensure_dir(path: &Path) -> Result<()> {
         .with_context(|| format!("failed to create directory: {}", path.display()))
 }
 
-pub fn unique_path(dest_dir: &Path, filename: &str) -> PathBuf {
-    let candidate = dest_dir.join(filename);
-    if !candidate.exists() {
-        return candidate;
+/// Writes `path` through a `.tmp` sibling that is renamed into place once `write` succeeds, so
+/// readers never see a half-written file. On failure the temp file is removed and whatever was
+/// at `path` before is left untouched.
+fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut io::BufWriter<fs::File>) -> Result<()>,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        ensure_dir(parent)?;
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+
+    let written = fs::File::create(&tmp)
+        .with_context(|| format!("failed to create file: {}", tmp.display()))
+        .and_then(|file| {
+            let mut out = io::BufWriter::new(file);
+            write(&mut out)?;
+            out.into_inner()
+                .map_err(io::IntoInnerError::into_error)
+                .and_then(|file| file.sync_all())
+                .with_context(|| format!("failed to flush {}", tmp.display()))
+        })
+        .and_then(|()| {
+            fs::rename(&tmp, path)
+                .with_context(|| format!("failed to move {} into place", path.display()))
+        });
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
     }
+    written
+}
 
+/// `filename`, then `stem (2).ext`, `stem (3).ext`, ... in `dest_dir`.
+fn numbered_candidates<'a>(
+    dest_dir: &'a Path,
+    filename: &'a str,
+) -> impl Iterator<Item = (String, PathBuf)> + 'a {
     let src = Path::new(filename);
     let stem = src
         .file_stem()
@@ -148,1263 +1109,10486 @@ pub fn unique_path(dest_dir: &Path, filename: &str) -> PathBuf {
         .extension()
         .map(|e| format!(".{}", e.to_string_lossy()))
         .unwrap_or_default();
+    std::iter::once(filename.to_string())
+        .chain((2..).map(move |idx| format!("{stem} ({idx}){ext}")))
+        .map(move |name| {
+            let path = dest_dir.join(&name);
+            (name, path)
+        })
+}
 
-    let mut idx = 2;
-    loop {
-        let candidate = dest_dir.join(format!("{stem} ({idx}){ext}"));
-        if !candidate.exists() {
-            return candidate;
-        }
-        idx += 1;
-    }
+/// The first of `numbered_candidates` with nothing at it, dangling symlinks included. Another
+/// process can still take the name before it is used; `execute` claims destinations with
+/// `claim_path` instead of trusting this check.
+pub fn unique_path(dest_dir: &Path, filename: &str) -> PathBuf {
+    numbered_candidates(dest_dir, filename)
+        .map(|(_, path)| path)
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .unwrap_or_else(|| dest_dir.join(filename))
 }
 
+/// `unique_path`, also skipping (and then adding) names in `reserved`, so one plan doesn't hand
+/// the same free name to two volumes.
 pub fn unique_path_reserved(
     dest_dir: &Path,
     filename: &str,
     reserved: &mut HashSet<String>,
 ) -> PathBuf {
-    let candidate = dest_dir.join(filename);
-    if !candidate.exists() && !reserved.contains(filename) {
-        reserved.insert(filename.to_string());
-        return candidate;
-    }
-
-    let src = Path::new(filename);
-    let stem = src
-        .file_stem()
-        .map(|s| s.to_string_lossy().into_owned())
-        .unwrap_or_else(|| filename.to_string());
-    let ext = src
-        .extension()
-        .map(|e| format!(".{}", e.to_string_lossy()))
-        .unwrap_or_default();
+    let (name, path) = numbered_candidates(dest_dir, filename)
+        .find(|(name, path)| !reserved.contains(name) && fs::symlink_metadata(path).is_err())
+        .unwrap_or_else(|| (filename.to_string(), dest_dir.join(filename)));
+    reserved.insert(name);
+    path
+}
 
-    let mut idx = 2;
-    loop {
-        let name = format!("{stem} ({idx}){ext}");
-        let candidate = dest_dir.join(&name);
-        if !candidate.exists() && !reserved.contains(&name) {
-            reserved.insert(name);
-            return candidate;
-        }
-        idx += 1;
-    }
+/// Creates `path` as an empty file, failing with `AlreadyExists` when anything is there. A
+/// move or copy that claims its destination first and then lands on the claim can't replace a
+/// file another run put there in the meantime.
+fn claim_path(path: &Path) -> io::Result<()> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map(drop)
 }
 
+/// Any entry counts as taken, including a dangling symlink: copying onto one would write
+/// through it into whatever it points at.
 pub fn unique_cover_old_path(dest_dir: &Path) -> PathBuf {
+    let taken = |path: &Path| fs::symlink_metadata(path).is_ok();
     let first = dest_dir.join("cover_old.jpg");
-    if !first.exists() {
+    if !taken(&first) {
         return first;
     }
 
     let mut idx = 2;
     loop {
         let candidate = dest_dir.join(format!("cover_old_{idx}.jpg"));
-        if !candidate.exists() {
+        if !taken(&candidate) {
             return candidate;
         }
         idx += 1;
     }
 }
 
+/// `$HOME`, or `%USERPROFILE%` where `HOME` is unset (as it usually is on Windows).
+fn home_dir() -> Option<PathBuf> {
+    home_dir_from(|var| std::env::var_os(var))
+}
+
+fn home_dir_from(var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    ["HOME", "USERPROFILE"]
+        .into_iter()
+        .find_map(|name| var(name).filter(|value| !value.is_empty()))
+        .map(PathBuf::from)
+}
+
+/// Expands a leading `~` or `~/` (also `~\` on Windows) to the home directory. Other users'
+/// homes (`~user`) are not looked up and come back unchanged; `resolve_series_dir` rejects them.
 pub fn expand_tilde(path: &str) -> PathBuf {
-    if path == "~" || path.starts_with("~/") {
-        if let Ok(home) = std::env::var("HOME") {
-            let suffix = path.strip_prefix("~/").unwrap_or("");
-            return PathBuf::from(home).join(suffix);
-        }
+    expand_tilde_in(path, home_dir())
+}
+
+fn expand_tilde_in(path: &str, home: Option<PathBuf>) -> PathBuf {
+    let suffix = if path == "~" {
+        Some("")
+    } else {
+        path.strip_prefix("~/")
+            .or_else(|| cfg!(windows).then(|| path.strip_prefix("~\\")).flatten())
+    };
+    match (suffix, home) {
+        (Some(suffix), Some(home)) => home.join(suffix),
+        _ => PathBuf::from(path),
     }
-    PathBuf::from(path)
 }
 
-pub fn resolve_series_dir(path: &str) -> Result<PathBuf> {
+pub fn resolve_series_dir(path: &str) -> Result<PathBuf, Error> {
     let trimmed = path.trim();
     if trimmed.is_empty() {
-        bail!("Folder path is empty.");
+        return Err(Error::PathResolution(anyhow!("Folder path is empty.")));
+    }
+    if let Some(user) = trimmed
+        .strip_prefix('~')
+        .and_then(|rest| rest.split(['/', '\\']).next())
+        .filter(|user| !user.is_empty())
+    {
+        return Err(Error::PathResolution(anyhow!(
+            "~{user} paths are not supported; use the full path to that folder instead"
+        )));
     }
 
     let resolved = expand_tilde(trimmed)
         .canonicalize()
-        .with_context(|| format!("failed to resolve path: {trimmed}"))?;
+        .with_context(|| format!("failed to resolve path: {trimmed}"))
+        .map_err(Error::PathResolution)?;
 
     if !resolved.is_dir() {
-        bail!("Not a valid folder: {}", resolved.display());
+        return Err(Error::PathResolution(anyhow!(
+            "Not a valid folder: {}",
+            resolved.display()
+        )));
     }
 
     Ok(resolved)
 }
 
-pub fn clean_volume_filename(src_name: &str, pad_to_3: bool) -> String {
-    let p = Path::new(src_name);
-    let stem_raw = p
-        .file_stem()
-        .map(|s| s.to_string_lossy().into_owned())
-        .unwrap_or_else(|| src_name.to_string());
-    let ext = p
-        .extension()
-        .map(|e| format!(".{}", e.to_string_lossy()))
-        .unwrap_or_default();
+/// Words that mark a `[...]`/`{...}` span as a release tag rather than part of the title.
+const GROUP_TAG_KEYWORDS: &[&str] = &[
+    "scan",
+    "scans",
+    "scanlation",
+    "scanlations",
+    "digital",
+    "group",
+    "team",
+    "subs",
+    "fansub",
+    "raw",
+    "raws",
+    "hq",
+    "tpb",
+    "c2c",
+    "repack",
+    "webrip",
+];
 
-    let mut stem = PARENS_RE.replace_all(&stem_raw, "").into_owned();
-    stem = MULTI_SPACE_RE.replace_all(stem.trim(), " ").into_owned();
-    stem = V_UNDERSCORE_RE.replace_all(&stem, "$1").into_owned();
+fn looks_like_group_tag(content: &str) -> bool {
+    content
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .map(str::to_ascii_lowercase)
+        .any(|word| {
+            GROUP_TAG_KEYWORDS.contains(&word.as_str())
+                || word.ends_with("scans")
+                || word.ends_with("subs")
+        })
+}
 
-    if let Some(caps) = VOLUME_RE.captures(&stem) {
-        if let Some(vol_match) = caps.get(1) {
-            if let Ok(vol_num) = vol_match.as_str().parse::<u32>() {
-                let whole = caps.get(0).map(|m| m.start()).unwrap_or(0);
-                let mut title = stem[..whole].trim().to_string();
-                title = MULTI_SPACE_RE.replace_all(title.trim(), " ").into_owned();
+/// Drops bracketed and braced spans that come after the volume token or read like a group or
+/// release tag. Anything else (e.g. "[Oshi no Ko] v01") may be the title and is kept.
+fn strip_group_tags(stem: &str, volume_re: &Regex) -> String {
+    let volume_end = volume_re.find(stem).map(|m| m.end());
+    BRACKET_TAG_RE
+        .replace_all(stem, |caps: &regex::Captures| {
+            let Some(span) = caps.get(0) else {
+                return String::new();
+            };
+            let content = caps
+                .get(1)
+                .or_else(|| caps.get(2))
+                .map_or("", |m| m.as_str());
+            let after_volume = volume_end.is_some_and(|end| span.start() >= end);
+            if after_volume || looks_like_group_tag(content) {
+                String::new()
+            } else {
+                span.as_str().to_string()
+            }
+        })
+        .into_owned()
+}
 
-                let vpart = if pad_to_3 {
-                    format!("v{vol_num:03}")
-                } else {
-                    format!("v{vol_num}")
-                };
+/// A volume number such as 1 or 1.5. `fraction` holds the digits after the point with
+/// trailing zeros trimmed, so "01.50" and "1.5" compare equal and "2.0" is just 2. With the
+/// zeros trimmed, comparing the fraction digits as text orders them numerically (1 < 1.25 < 1.5).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct VolumeNumber {
+    whole: u32,
+    fraction: String,
+}
 
-                if title.is_empty() {
-                    return format!("{vpart}{ext}");
-                }
-                return format!("{title} {vpart}{ext}");
-            }
+impl VolumeNumber {
+    fn whole(whole: u32) -> Self {
+        Self {
+            whole,
+            fraction: String::new(),
         }
     }
 
-    format!("{stem}{ext}")
-}
+    fn parse(whole: &str, fraction: Option<&str>) -> Option<Self> {
+        Some(Self {
+            whole: whole.parse().ok()?,
+            fraction: fraction.unwrap_or("").trim_end_matches('0').to_string(),
+        })
+    }
 
-pub fn scan_volumes(series_dir: &Path) -> Result<Vec<PathBuf>> {
-    let mut volumes = Vec::new();
-    for entry in fs::read_dir(series_dir)
-        .with_context(|| format!("failed to read directory: {}", series_dir.display()))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-        if !path.is_file() {
-            continue;
-        }
-        let name = file_name_text(&path);
-        if is_hidden_or_macos_junk(&name) {
-            continue;
-        }
-        if has_known_ext(&path, VOLUME_EXTS) {
-            volumes.push(path);
+    /// A JSON number: an integer for whole volumes, otherwise a float such as 1.5.
+    fn to_json(&self) -> Value {
+        if self.fraction.is_empty() {
+            json!(self.whole)
+        } else {
+            self.format(false)
+                .parse::<f64>()
+                .map_or(Value::Null, |number| json!(number))
         }
     }
-    natural_sort_paths(&mut volumes);
-    Ok(volumes)
-}
-
-fn chunk_paths(paths: &[PathBuf], size: usize) -> Vec<Vec<PathBuf>> {
-    if paths.is_empty() {
-        return Vec::new();
-    }
 
-    let mut chunks = Vec::new();
-    let mut index = 0;
-    while index < paths.len() {
-        let end = usize::min(index + size, paths.len());
-        chunks.push(paths[index..end].to_vec());
-        index = end;
+    fn format(&self, pad_to_3: bool) -> String {
+        let whole = if pad_to_3 {
+            format!("{:03}", self.whole)
+        } else {
+            self.whole.to_string()
+        };
+        if self.fraction.is_empty() {
+            whole
+        } else {
+            format!("{whole}.{}", self.fraction)
+        }
     }
-    chunks
 }
 
-pub fn build_plan(series_dir: &Path, series_cover: Option<&Path>) -> Result<Vec<BatchPlan>> {
-    let volumes = scan_volumes(series_dir)?;
-    if volumes.is_empty() {
-        bail!("No volume files found in: {}", series_dir.display());
-    }
+#[derive(Debug, Deserialize)]
+struct CleanRulesFile {
+    remove: Option<Vec<String>>,
+    replace: Option<Vec<CleanReplacement>>,
+    volume: Option<String>,
+    strip_group_tags: Option<bool>,
+}
 
-    let groups = chunk_paths(&volumes, FILES_PER_FOLDER);
-    let parent = series_dir
-        .parent()
-        .ok_or_else(|| anyhow!("Series folder has no parent: {}", series_dir.display()))?;
+#[derive(Debug, Deserialize)]
+struct CleanReplacement {
+    pattern: String,
+    with: String,
+}
 
-    let mut plan = Vec::new();
-    let series_name = file_name_text(series_dir);
-    for (idx, group) in groups.iter().enumerate() {
-        let batch_index = idx + 1;
-        let batch_dir = parent.join(format!("{series_name} {batch_index}"));
-        let mut moves = Vec::new();
-        let mut reserved = HashSet::new();
+/// Patterns `clean_volume_filename_with` applies to a file stem, in order: `remove` spans,
+/// bracketed group tags, `replace` rewrites, then the `volume` token (capture 1 is the number,
+/// optional capture 2 the decimal part).
+#[derive(Debug, Clone)]
+pub struct CleanRules {
+    pub remove: Vec<Regex>,
+    pub strip_group_tags: bool,
+    pub replace: Vec<(Regex, String)>,
+    pub volume: Regex,
+}
 
-        for src in group {
-            let src_name = file_name_text(src);
-            let cleaned = clean_volume_filename(&src_name, true);
-            let dst = unique_path_reserved(&batch_dir, &cleaned, &mut reserved);
-            let dst_name = file_name_text(&dst);
-            moves.push(FileMove {
-                src: src.clone(),
-                dst,
-                dst_name,
-            });
+impl Default for CleanRules {
+    fn default() -> Self {
+        Self {
+            remove: vec![PARENS_RE.clone()],
+            strip_group_tags: true,
+            replace: vec![(V_UNDERSCORE_RE.clone(), "$1".to_string())],
+            volume: VOLUME_RE.clone(),
         }
+    }
+}
 
-        plan.push(BatchPlan {
-            batch_index,
-            batch_dir,
-            moves,
-            will_make_cover: series_cover.is_some(),
-        });
+impl CleanRules {
+    /// Reads a JSON rules file. Omitted fields keep their defaults, e.g.
+    /// `{"remove": ["\\s*\\([^)]*\\)"], "volume": "(?i)\\bvol\\.?\\s*0*(\\d+)"}`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("failed to read clean rules: {}", path.display()))?;
+        let file: CleanRulesFile = serde_json::from_slice(&bytes)
+            .with_context(|| format!("failed to parse clean rules: {}", path.display()))?;
+        Self::from_file(file).with_context(|| format!("invalid clean rules: {}", path.display()))
     }
 
-    Ok(plan)
+    fn from_file(file: CleanRulesFile) -> Result<Self> {
+        let compile = |field: String, pattern: &str| {
+            Regex::new(pattern).with_context(|| format!("{field} is not a valid regex"))
+        };
+        let mut rules = Self::default();
+        if let Some(remove) = file.remove {
+            rules.remove = remove
+                .iter()
+                .enumerate()
+                .map(|(i, pattern)| compile(format!("remove[{i}]"), pattern))
+                .collect::<Result<_>>()?;
+        }
+        if let Some(replace) = file.replace {
+            rules.replace = replace
+                .into_iter()
+                .enumerate()
+                .map(|(i, r)| Ok((compile(format!("replace[{i}]"), &r.pattern)?, r.with)))
+                .collect::<Result<_>>()?;
+        }
+        if let Some(volume) = file.volume {
+            let volume = compile("volume".to_string(), &volume)?;
+            if volume.captures_len() < 2 {
+                bail!("volume regex needs a capture group for the volume number");
+            }
+            rules.volume = volume;
+        }
+        if let Some(strip) = file.strip_group_tags {
+            rules.strip_group_tags = strip;
+        }
+        Ok(rules)
+    }
 }
 
-pub fn format_plan(series_dir: &Path, plan: &[BatchPlan], series_cover: Option<&Path>) -> String {
-    let mut out = String::new();
-    let vols_count: usize = plan.iter().map(|b| b.moves.len()).sum();
-    let series_name = file_name_text(series_dir);
-
-    out.push('\n');
-    out.push_str(&"=".repeat(98));
-    out.push('\n');
-    out.push_str("[PLAN] Manga toolkit (Rust)\n");
-    out.push_str(&format!("[PLAN] Series folder: {}\n", series_dir.display()));
-    out.push_str(&format!("[PLAN] Volumes found: {vols_count}\n"));
-    out.push_str(&format!("[PLAN] Batch size: {FILES_PER_FOLDER}\n"));
+pub fn clean_volume_filename(src_name: &str, pad_to_3: bool) -> String {
+    clean_volume_filename_with(src_name, pad_to_3, &CleanRules::default())
+}
 
-    if let Some(cover) = series_cover {
-        out.push_str(&format!(
-            "[PLAN] Series cover source: {}\n",
-            cover.display()
-        ));
-        out.push_str("[PLAN] Each batch will have:\n");
-        out.push_str("       - cover_old.jpg (copied once from series cover, preserved)\n");
-        out.push_str("       - cover.jpg (rendered with batch number DEAD-CENTER)\n");
-        out.push_str("       - any existing cover.jpg archived to cover_old_*.jpg\n");
-    } else {
-        out.push_str("[PLAN] Covers: skipped (no cover image found/downloaded)\n");
+/// The file stem after the `remove`, group-tag and `replace` rules, ready for volume matching.
+fn prepared_stem(src_name: &str, rules: &CleanRules) -> String {
+    let mut stem = Path::new(src_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| src_name.to_string());
+    for pattern in &rules.remove {
+        stem = pattern.replace_all(&stem, "").into_owned();
+    }
+    if rules.strip_group_tags {
+        stem = strip_group_tags(&stem, &rules.volume);
+    }
+    stem = MULTI_SPACE_RE.replace_all(stem.trim(), " ").into_owned();
+    for (pattern, with) in &rules.replace {
+        stem = pattern.replace_all(&stem, with.as_str()).into_owned();
     }
+    stem
+}
 
-    out.push_str(&"=".repeat(98));
-    out.push('\n');
+/// The volume number `clean_volume_filename_with` would write for `src_name`, if any.
+fn volume_number_with(src_name: &str, rules: &CleanRules) -> Option<VolumeNumber> {
+    let stem = prepared_stem(src_name, rules);
+    let caps = rules.volume.captures(&stem)?;
+    VolumeNumber::parse(caps.get(1)?.as_str(), caps.get(2).map(|m| m.as_str()))
+}
 
-    for batch in plan {
-        let start_idx = (batch.batch_index - 1) * FILES_PER_FOLDER + 1;
-        let end_idx = start_idx + batch.moves.len() - 1;
+/// Orders volumes by the volume number they will be renamed to, so "v1", "v01" and "v1_2" sit
+/// together and "v10" comes after "v9". The sort is stable, so ties keep the natural name
+/// order from `scan_volumes_with`; files without a number go last.
+fn sort_by_volume_number(volumes: &mut [PathBuf], rules: &CleanRules) {
+    volumes.sort_by_cached_key(|path| {
+        let number = volume_number_with(&file_name_text(path), rules);
+        (number.is_none(), number)
+    });
+}
 
-        out.push('\n');
-        out.push_str(&format!(
-            "{} {}  (volumes {}-{})\n",
-            series_name, batch.batch_index, start_idx, end_idx
-        ));
-        out.push_str(&format!("  [DIR] {}\n", batch.batch_dir.display()));
-        if series_cover.is_some() {
-            out.push_str(&format!(
-                "  [COVER] cover_old.jpg + cover.jpg (number {})\n",
-                batch.batch_index
-            ));
-        }
+/// Pairs each of `names` with what `rules` would rename it to, so rules can be tried out
+/// without planning a series folder.
+pub fn preview_clean(
+    names: &[String],
+    rules: &CleanRules,
+    pad_to_3: bool,
+) -> Vec<(String, String)> {
+    names
+        .iter()
+        .map(|name| {
+            let cleaned = clean_volume_filename_with(name, pad_to_3, rules);
+            (name.clone(), cleaned)
+        })
+        .collect()
+}
 
-        for (i, mv) in batch.moves.iter().enumerate() {
-            let n = start_idx + i;
-            if file_name_text(&mv.src) == mv.dst_name {
-                out.push_str(&format!("  {n:>4}. {}\n", file_name_text(&mv.src)));
-            } else {
-                out.push_str(&format!(
-                    "  {n:>4}. {}  (rename: {} -> {})\n",
-                    file_name_text(&mv.src),
-                    file_name_text(&mv.src),
-                    mv.dst_name
-                ));
+pub fn clean_volume_filename_with(src_name: &str, pad_to_3: bool, rules: &CleanRules) -> String {
+    let ext = Path::new(src_name)
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let stem = prepared_stem(src_name, rules);
+
+    if let Some(caps) = rules.volume.captures(&stem) {
+        if let Some(vol_match) = caps.get(1) {
+            let fraction = caps.get(2).map(|m| m.as_str());
+            if let Some(vol_num) = VolumeNumber::parse(vol_match.as_str(), fraction) {
+                let whole = caps.get(0).map(|m| m.start()).unwrap_or(0);
+                let mut title = stem[..whole].trim().to_string();
+                title = MULTI_SPACE_RE.replace_all(title.trim(), " ").into_owned();
+
+                let vpart = format!("v{}", vol_num.format(pad_to_3));
+
+                if title.is_empty() {
+                    return format!("{vpart}{ext}");
+                }
+                return format!("{title} {vpart}{ext}");
             }
         }
     }
 
-    out.push('\n');
-    out.push_str(&"=".repeat(98));
-    out.push('\n');
+    format!("{stem}{ext}")
+}
 
-    out
+/// `name` with everything after its last dot in lowercase.
+fn lowercase_extension(name: &str) -> String {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{}{}", &name[..dot], name[dot..].to_lowercase()),
+        _ => name.to_string(),
+    }
 }
 
-fn move_file(src: &Path, dst: &Path) -> Result<()> {
-    if let Some(parent) = dst.parent() {
-        ensure_dir(parent)?;
+/// Which planned file names `build_plan` passes through `sanitize_file_name`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SanitizeNames {
+    /// Only `.cbz` files, the ones most often copied onto readers and network shares.
+    #[default]
+    Cbz,
+    All,
+    Off,
+}
+
+impl SanitizeNames {
+    fn applies_to(self, name: &str) -> bool {
+        match self {
+            SanitizeNames::Cbz => has_known_ext(Path::new(name), &[".cbz"]),
+            SanitizeNames::All => true,
+            SanitizeNames::Off => false,
+        }
     }
+}
 
-    match fs::rename(src, dst) {
-        Ok(_) => Ok(()),
-        Err(err) => {
-            if err.raw_os_error() == Some(libc::EXDEV) {
-                fs::copy(src, dst).with_context(|| {
-                    format!(
-                        "cross-device copy failed from {} to {}",
-                        src.display(),
-                        dst.display()
-                    )
-                })?;
-                fs::remove_file(src)
-                    .with_context(|| format!("failed to remove source file: {}", src.display()))?;
-                Ok(())
+impl std::str::FromStr for SanitizeNames {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "cbz" => Ok(SanitizeNames::Cbz),
+            "all" => Ok(SanitizeNames::All),
+            "off" => Ok(SanitizeNames::Off),
+            other => bail!("invalid name sanitizing {other:?}: expected cbz, all, or off"),
+        }
+    }
+}
+
+const WINDOWS_ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Makes a file name legal on Windows and exFAT: `<>:"/\|?*` and control characters become
+/// `_`, trailing dots and spaces are trimmed from the stem and the whole name, and device names
+/// such as `CON` or `lpt1.cbz` get a `_` appended to the part before the first dot.
+pub fn sanitize_file_name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|ch| {
+            if ch.is_control() || WINDOWS_ILLEGAL_CHARS.contains(&ch) {
+                '_'
             } else {
-                Err(err).with_context(|| {
-                    format!(
-                        "failed to move file from {} to {}",
-                        src.display(),
-                        dst.display()
-                    )
-                })
+                ch
             }
-        }
+        })
+        .collect();
+    let (stem, ext) = match replaced.rfind('.') {
+        Some(dot) if dot > 0 => replaced.split_at(dot),
+        _ => (replaced.as_str(), ""),
+    };
+    let mut name = format!("{}{ext}", stem.trim_end_matches(['.', ' ']));
+    name.truncate(name.trim_end_matches(['.', ' ']).len());
+    if name.is_empty() || name.starts_with('.') {
+        name.insert(0, '_');
+    }
+
+    let base_len = name.find('.').unwrap_or(name.len());
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| name[..base_len].eq_ignore_ascii_case(reserved))
+    {
+        name.insert(base_len, '_');
     }
+    name
 }
 
-fn http_client(timeout_secs: u64) -> Result<Client> {
-    Client::builder()
-        .user_agent(USER_AGENT)
-        .timeout(Duration::from_secs(timeout_secs))
-        .build()
-        .context("failed to initialize HTTP client")
+pub fn scan_volumes(series_dir: &Path) -> Result<Vec<PathBuf>> {
+    scan_volumes_with(series_dir, false)
 }
 
-fn http_get_json(url: &str, params: &[(&str, String)], timeout_secs: u64) -> Result<Value> {
-    let client = http_client(timeout_secs)?;
-    let mut req = client.get(url);
-    if !params.is_empty() {
-        req = req.query(params);
+/// The file extensions a scan recognizes, lowercase with their leading dot. Defaults to
+/// `VOLUME_EXTS` or `IMAGE_EXTS`; parses from a comma-separated list such as `cbz,cbr,cbt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionSet(Vec<String>);
+
+impl ExtensionSet {
+    /// Accepts `cbz`, `.CBZ` and the like; duplicates are dropped. The set can't be empty.
+    pub fn new<S: AsRef<str>>(exts: &[S]) -> Result<Self> {
+        let mut normalized: Vec<String> = Vec::new();
+        for ext in exts {
+            let ext = ext.as_ref().trim();
+            let bare = ext.strip_prefix('.').unwrap_or(ext);
+            if bare.is_empty() {
+                bail!("empty extension");
+            }
+            if bare.contains(['/', '\\']) {
+                bail!("invalid extension {ext:?}: expected a name such as cbz");
+            }
+            let dotted = format!(".{}", bare.to_ascii_lowercase());
+            if !normalized.contains(&dotted) {
+                normalized.push(dotted);
+            }
+        }
+        if normalized.is_empty() {
+            bail!("expected at least one extension");
+        }
+        Ok(Self(normalized))
     }
 
-    let resp = req
-        .send()
-        .with_context(|| format!("request failed: {url}"))?
-        .error_for_status()
-        .with_context(|| format!("request returned error status: {url}"))?;
+    pub fn volumes() -> Self {
+        Self(VOLUME_EXTS.iter().map(|ext| ext.to_string()).collect())
+    }
 
-    resp.json().context("failed to decode JSON response")
+    pub fn images() -> Self {
+        Self(IMAGE_EXTS.iter().map(|ext| ext.to_string()).collect())
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        has_known_ext(path, &self.0)
+    }
 }
 
-fn http_post_json(url: &str, payload: &Value, timeout_secs: u64) -> Result<Value> {
-    let client = http_client(timeout_secs)?;
-    let resp = client
-        .post(url)
-        .header("Content-Type", "application/json")
-        .json(payload)
-        .send()
-        .with_context(|| format!("request failed: {url}"))?
-        .error_for_status()
-        .with_context(|| format!("request returned error status: {url}"))?;
+impl std::str::FromStr for ExtensionSet {
+    type Err = anyhow::Error;
 
-    resp.json().context("failed to decode JSON response")
+    fn from_str(value: &str) -> Result<Self> {
+        Self::new(&value.split(',').collect::<Vec<_>>())
+    }
 }
 
-fn download_file(url: &str, out_path: &Path, timeout_secs: u64) -> Result<()> {
-    if let Some(parent) = out_path.parent() {
-        ensure_dir(parent)?;
+impl std::fmt::Display for ExtensionSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.join(","))
     }
+}
 
-    let client = http_client(timeout_secs)?;
-    let mut resp = client
-        .get(url)
-        .header("Referer", "https://mangadex.org/")
-        .send()
-        .with_context(|| format!("request failed: {url}"))?
-        .error_for_status()
-        .with_context(|| format!("request returned error status: {url}"))?;
-
-    let mut out = fs::File::create(out_path)
-        .with_context(|| format!("failed to create output file: {}", out_path.display()))?;
-    io::copy(&mut resp, &mut out).with_context(|| {
-        format!(
-            "failed while writing downloaded data to {}",
-            out_path.display()
-        )
-    })?;
-    Ok(())
+/// A file name glob such as `*Artbook*` or `* Omake.zip`: `*` matches any run of characters
+/// and `?` a single one. Matching ignores case.
+#[derive(Debug, Clone)]
+pub struct IgnorePattern {
+    glob: String,
+    regex: Regex,
 }
 
-fn best_title(attrs: &Value) -> String {
-    let Some(title_obj) = attrs.get("title").and_then(Value::as_object) else {
-        return String::new();
-    };
+impl IgnorePattern {
+    pub fn matches(&self, file_name: &str) -> bool {
+        self.regex.is_match(file_name)
+    }
+}
 
-    if let Some(en) = title_obj.get("en").and_then(Value::as_str) {
-        return en.to_string();
+impl PartialEq for IgnorePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.glob == other.glob
     }
+}
 
-    title_obj
-        .values()
-        .find_map(Value::as_str)
-        .unwrap_or_default()
-        .to_string()
+impl Eq for IgnorePattern {}
+
+impl std::str::FromStr for IgnorePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(glob: &str) -> Result<Self> {
+        if glob.trim().is_empty() {
+            bail!("ignore pattern is empty");
+        }
+        if glob.contains(['/', '\\']) {
+            bail!("invalid ignore pattern {glob:?}: patterns match file names, not paths");
+        }
+        let mut pattern = String::from("(?i)^");
+        for ch in glob.chars() {
+            match ch {
+                '*' => pattern.push_str(".*"),
+                '?' => pattern.push('.'),
+                other => pattern.push_str(&regex::escape(&other.to_string())),
+            }
+        }
+        pattern.push('$');
+        let regex =
+            Regex::new(&pattern).with_context(|| format!("invalid ignore pattern {glob:?}"))?;
+        Ok(Self {
+            glob: glob.to_string(),
+            regex,
+        })
+    }
 }
 
-fn normalize_title(input: &str) -> String {
-    let lower = input.to_ascii_lowercase();
-    NON_ALNUM_RE.replace_all(&lower, "").into_owned()
+impl std::fmt::Display for IgnorePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.glob)
+    }
 }
 
-fn parse_int_volume(vol: &Value) -> Option<u32> {
-    let s = vol.as_str()?;
-    let caps = INT_VOLUME_RE.captures(s)?;
-    caps.get(1)?.as_str().parse::<u32>().ok()
+/// `scan_volumes_matching`, split into the volumes to use and the ones matching `ignore`.
+pub fn scan_volumes_ignoring(
+    series_dir: &Path,
+    recursive: bool,
+    exts: &ExtensionSet,
+    ignore: &[IgnorePattern],
+) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    Ok(scan_volumes_matching(series_dir, recursive, exts)?
+        .into_iter()
+        .partition(|path| {
+            let name = file_name_text(path);
+            !ignore.iter().any(|pattern| pattern.matches(&name))
+        }))
 }
 
-fn score_mangadex_item(item: &Value, title_l: &str, title_n: &str) -> i32 {
-    let attrs = item.get("attributes").unwrap_or(&Value::Null);
-    let main = best_title(attrs).trim().to_ascii_lowercase();
-    let main_n = normalize_title(&main);
+/// Like `scan_volumes`, optionally descending into subfolders (hidden ones and `__MACOSX` are
+/// skipped, symlinked folders are not followed). Volumes sort by file name across the whole
+/// tree, not by their folder.
+pub fn scan_volumes_with(series_dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    scan_volumes_matching(series_dir, recursive, &ExtensionSet::volumes())
+}
 
-    let mut alt_values = Vec::new();
-    let mut alt_norms = Vec::new();
-
-    if let Some(alts) = attrs.get("altTitles").and_then(Value::as_array) {
-        for alt in alts {
-            if let Some(obj) = alt.as_object() {
-                for value in obj.values() {
-                    if let Some(text) = value.as_str() {
-                        let lowered = text.trim().to_ascii_lowercase();
-                        alt_norms.push(normalize_title(&lowered));
-                        alt_values.push(lowered);
-                    }
+/// `scan_volumes_with`, collecting files with one of `exts` instead of `VOLUME_EXTS`.
+pub fn scan_volumes_matching(
+    series_dir: &Path,
+    recursive: bool,
+    exts: &ExtensionSet,
+) -> Result<Vec<PathBuf>> {
+    let mut volumes = Vec::new();
+    let mut pending = vec![series_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("failed to read directory: {}", dir.display()))?
+        {
+            let entry = entry?;
+            let path = entry.path();
+            let name = file_name_text(&path);
+            if is_hidden_or_macos_junk(&name) {
+                continue;
+            }
+            if recursive && entry.file_type()?.is_dir() {
+                if name != "__MACOSX" {
+                    pending.push(path);
                 }
+                continue;
+            }
+            if path.is_file() && exts.matches(&path) {
+                volumes.push(path);
             }
         }
     }
+    // Compare stems first so "v01.5.cbz" lands after "v01.cbz" rather than before it.
+    volumes.sort_by(|a, b| {
+        let stem = |p: &Path| {
+            p.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        compare_ignore_case(&stem(a), &stem(b))
+            .then_with(|| compare_ignore_case(&file_name_text(a), &file_name_text(b)))
+            .then_with(|| compare_ignore_case(&a.to_string_lossy(), &b.to_string_lossy()))
+    });
+    Ok(volumes)
+}
 
-    if main_n == title_n {
-        return 6;
+fn chunk_paths(paths: &[PathBuf], size: usize) -> Vec<Vec<PathBuf>> {
+    if paths.is_empty() {
+        return Vec::new();
     }
-    if alt_norms.iter().any(|v| v == title_n) {
-        return 5;
+
+    let mut chunks = Vec::new();
+    let mut index = 0;
+    while index < paths.len() {
+        let end = usize::min(index + size, paths.len());
+        chunks.push(paths[index..end].to_vec());
+        index = end;
     }
-    if main == title_l {
-        return 4;
+    chunks
+}
+
+fn chapter_number(stem: &str) -> Option<(usize, VolumeNumber)> {
+    let caps = CHAPTER_RE.captures(stem)?;
+    let number = VolumeNumber::parse(caps.get(1)?.as_str(), caps.get(2).map(|m| m.as_str()))?;
+    Some((caps.get(0)?.start(), number))
+}
+
+/// Like `clean_volume_filename`, but names without a volume token that carry a `c`/`ch`/
+/// `chapter` token are normalized to "Title c045".
+pub fn clean_chapter_filename(src_name: &str, pad_to_3: bool, rules: &CleanRules) -> String {
+    let volume_cleaned = clean_volume_filename_with(src_name, pad_to_3, rules);
+    let p = Path::new(&volume_cleaned);
+    let stem = p
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| volume_cleaned.clone());
+    if rules.volume.is_match(&stem) {
+        return volume_cleaned;
+    }
+    let Some((start, number)) = chapter_number(&stem) else {
+        return volume_cleaned;
+    };
+    let ext = p
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let title = stem[..start].trim();
+    let cpart = format!("c{}", number.format(pad_to_3));
+    if title.is_empty() {
+        format!("{cpart}{ext}")
+    } else {
+        format!("{title} {cpart}{ext}")
+    }
+}
+
+pub fn build_plan(
+    series_dir: &Path,
+    series_cover: Option<&Path>,
+    options: &PlanOptions,
+) -> Result<Vec<BatchPlan>, Error> {
+    options.validate()?;
+
+    let (mut volumes, mut ignored) = scan_volumes_ignoring(
+        series_dir,
+        options.recursive,
+        &options.volume_exts,
+        &options.ignore,
+    )?;
+    if volumes.is_empty() {
+        if let Some(batches) = already_organized(series_dir, options)? {
+            return Err(Error::NoVolumes(anyhow!(
+                "{} is already organized: no loose volumes, {} batch folders next to it",
+                series_dir.display(),
+                batches.len()
+            )));
+        }
+        if !ignored.is_empty() {
+            return Err(Error::NoVolumes(anyhow!(
+                "No volume files found in: {} ({} matched the ignore patterns)",
+                series_dir.display(),
+                ignored.len()
+            )));
+        }
+        return Err(Error::NoVolumes(anyhow!(
+            "No volume files found in: {}",
+            series_dir.display()
+        )));
+    }
+    let clean = |src: &Path| {
+        let src_name = file_name_text(src);
+        let cleaned = if options.chapters {
+            clean_chapter_filename(&src_name, true, &options.clean_rules)
+        } else {
+            clean_volume_filename_with(&src_name, true, &options.clean_rules)
+        };
+        if options.lowercase_extensions {
+            lowercase_extension(&cleaned)
+        } else {
+            cleaned
+        }
+    };
+    if options.chapters {
+        // Raw names mix "c046" and "Chapter 45"; order by what they will be called instead.
+        let mut keyed: Vec<(String, PathBuf)> =
+            volumes.into_iter().map(|src| (clean(&src), src)).collect();
+        keyed.sort_by(|a, b| compare_ignore_case(&a.0, &b.0));
+        volumes = keyed.into_iter().map(|(_, src)| src).collect();
+    } else {
+        sort_by_volume_number(&mut volumes, &options.clean_rules);
+    }
+    let mut duplicates = if options.keep_duplicates {
+        Vec::new()
+    } else {
+        let (unique, duplicates) = split_duplicate_volumes(volumes)?;
+        volumes = unique;
+        duplicates
+    };
+
+    // Incremental runs continue after the batch folders already next to the series.
+    let mut first_batch = 1;
+    let mut first_volume = 1;
+    let mut top_up = None;
+    if options.incremental {
+        let existing = find_existing_batches(series_dir, options)?;
+        if let Some(last) = existing.last() {
+            first_volume += existing.iter().map(|b| b.volumes.len()).sum::<usize>();
+            if last.volumes.len() < options.batch_size {
+                first_batch = last.batch_index;
+                top_up = Some(last.clone());
+            } else {
+                first_batch = last.batch_index + 1;
+            }
+        }
+    }
+    let mut groups = Vec::new();
+    let mut rest = volumes.as_slice();
+    if let Some(last) = &top_up {
+        let room = options.batch_size - last.volumes.len();
+        let (head, tail) = rest.split_at(room.min(rest.len()));
+        groups.push(head.to_vec());
+        rest = tail;
+    }
+    groups.extend(chunk_paths(rest, options.batch_size));
+    let parent = series_dir
+        .parent()
+        .ok_or_else(|| anyhow!("Series folder has no parent: {}", series_dir.display()))?;
+
+    let mut plan = Vec::new();
+    let mut folder_names = HashSet::new();
+    let series_name = file_name_text(series_dir);
+    let mut volume_start = first_volume;
+    for (idx, group) in groups.iter().enumerate() {
+        let batch_index = first_batch + idx;
+        let topped_up = top_up.as_ref().filter(|_| idx == 0);
+        let number_at = |offset: usize, path: Option<&PathBuf>| {
+            path.and_then(|p| volume_number_with(&file_name_text(p), &options.clean_rules))
+                .unwrap_or_else(|| VolumeNumber::whole((volume_start + offset) as u32))
+        };
+        // A topped-up folder keeps its name, even if the template would now name it differently.
+        let folder_name = match topped_up {
+            Some(last) => file_name_text(&last.batch_dir),
+            None => options.folder_template.render(
+                &series_name,
+                batch_index,
+                &number_at(0, group.first()),
+                &number_at(group.len().saturating_sub(1), group.last()),
+            )?,
+        };
+        if !folder_names.insert(folder_name.clone()) {
+            return Err(anyhow!(
+                "folder template {:?} gives more than one batch the folder name {folder_name:?}",
+                options.folder_template.to_string()
+            )
+            .into());
+        }
+        let batch_dir = parent.join(folder_name);
+        let mut moves = Vec::new();
+        let mut reserved = HashSet::new();
+
+        for src in group {
+            let mut cleaned = clean(src);
+            if options.sanitize_names.applies_to(&cleaned) {
+                cleaned = sanitize_file_name(&cleaned);
+            }
+            let wanted = batch_dir.join(&cleaned);
+            let existing_on_disk = wanted.is_file() && !reserved.contains(&cleaned);
+            let (dst, destination) =
+                if existing_on_disk && files_are_identical(src, &wanted).unwrap_or(false) {
+                    reserved.insert(cleaned);
+                    (wanted, DestinationState::Identical)
+                } else {
+                    let dst = unique_path_reserved(&batch_dir, &cleaned, &mut reserved);
+                    let destination = if existing_on_disk && dst != wanted {
+                        DestinationState::Deduplicated {
+                            taken_name: cleaned,
+                        }
+                    } else {
+                        DestinationState::Free
+                    };
+                    (dst, destination)
+                };
+            let dst_name = file_name_text(&dst);
+            let problem = if options.validate_archives {
+                check_archive(src).err().map(|err| format!("{err:#}"))
+            } else {
+                None
+            };
+            moves.push(FileMove {
+                src: src.clone(),
+                dst,
+                dst_name,
+                destination,
+                problem,
+            });
+        }
+
+        let (batch_duplicates, rest) = duplicates
+            .into_iter()
+            .partition(|dup| group.contains(&dup.original));
+        duplicates = rest;
+
+        plan.push(BatchPlan {
+            batch_index,
+            batch_dir,
+            moves,
+            will_make_cover: series_cover.is_some(),
+            volume_start,
+            duplicates: batch_duplicates,
+            copy: options.copy,
+            existing_volumes: topped_up.map_or(0, |last| last.volumes.len()),
+            ignored: std::mem::take(&mut ignored),
+            clean_rules: options.clean_rules.clone(),
+        });
+        volume_start += group.len();
+    }
+
+    Ok(plan)
+}
+
+/// A batch folder from an earlier run, found next to the series folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExistingBatch {
+    pub batch_index: usize,
+    pub batch_dir: PathBuf,
+    /// The volume files in the folder, in `scan_volumes` order.
+    pub volumes: Vec<PathBuf>,
+}
+
+/// Finds the sibling folders named by `options.folder_template` for this series that hold
+/// volumes, in batch order. Templates without `{batch}` are numbered by folder name.
+pub fn find_existing_batches(
+    series_dir: &Path,
+    options: &PlanOptions,
+) -> Result<Vec<ExistingBatch>> {
+    let Some(parent) = series_dir.parent() else {
+        return Ok(Vec::new());
+    };
+    let matcher = options
+        .folder_template
+        .matcher(&file_name_text(series_dir))?;
+
+    let mut found = Vec::new();
+    for entry in fs::read_dir(parent)
+        .with_context(|| format!("failed to read directory: {}", parent.display()))?
+    {
+        let path = entry?.path();
+        if !path.is_dir() || path == series_dir {
+            continue;
+        }
+        let name = file_name_text(&path);
+        let Some(captures) = matcher.captures(&name) else {
+            continue;
+        };
+        let batch_index = captures
+            .name("batch")
+            .and_then(|number| number.as_str().parse::<usize>().ok());
+        let volumes = scan_volumes_matching(&path, false, &options.volume_exts)?;
+        if !volumes.is_empty() {
+            found.push((batch_index, name, path, volumes));
+        }
+    }
+    found.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| compare_ignore_case(&a.1, &b.1)));
+
+    Ok(found
+        .into_iter()
+        .enumerate()
+        .map(
+            |(idx, (batch_index, _, batch_dir, volumes))| ExistingBatch {
+                batch_index: batch_index.unwrap_or(idx + 1),
+                batch_dir,
+                volumes,
+            },
+        )
+        .collect())
+}
+
+/// The existing batches when the series folder has no loose volumes left but an earlier run
+/// put them in batch folders, so there is nothing to move.
+pub fn already_organized(
+    series_dir: &Path,
+    options: &PlanOptions,
+) -> Result<Option<Vec<ExistingBatch>>> {
+    if !scan_volumes_ignoring(
+        series_dir,
+        options.recursive,
+        &options.volume_exts,
+        &options.ignore,
+    )?
+    .0
+    .is_empty()
+    {
+        return Ok(None);
+    }
+    let batches = find_existing_batches(series_dir, options)?;
+    Ok((!batches.is_empty()).then_some(batches))
+}
+
+/// Whether `dir`, a sibling of `series_dir`, shows signs of being one of its batch folders
+/// from an earlier run: one of the series' journals created it, it holds a `cover_old.jpg`, or
+/// its `series.json` names the series. A sequel such as `Title 2` next to `Title` shows none.
+fn made_for_series(series_dir: &Path, dir: &Path) -> bool {
+    let series = file_name_text(series_dir);
+    if dir.join("cover_old.jpg").is_file() {
+        return true;
+    }
+    let metadata = fs::read(dir.join(METADATA_FILE_NAME))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+    if metadata.is_some_and(|value| value["series"].as_str() == Some(series.as_str())) {
+        return true;
+    }
+    // Compared by name: the journal may hold the folder under a differently spelled parent.
+    let name = file_name_text(dir);
+    let journal = journal_path(series_dir);
+    journal_archives(&journal)
+        .into_iter()
+        .chain([journal])
+        .flat_map(|journal| Journal::load(&journal).unwrap_or_default())
+        .any(|entry| {
+            matches!(entry, JournalEntry::CreateDir { path } if file_name_text(&path) == name)
+        })
+}
+
+/// The subfolders of `parent` to process as series in a library run, in natural order. Hidden
+/// folders are left out, and so are the batch folders an earlier run made for a sibling series:
+/// folders named by `options.folder_template` for a sibling that show the signs
+/// `made_for_series` looks for.
+pub fn library_series_dirs(parent: &Path, options: &PlanOptions) -> Result<Vec<PathBuf>> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(parent)
+        .with_context(|| format!("failed to read directory: {}", parent.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir() && !file_name_text(path).starts_with('.'))
+        .collect();
+
+    let mut batch_dirs = HashSet::new();
+    for series in &dirs {
+        let matcher = options.folder_template.matcher(&file_name_text(series))?;
+        for dir in &dirs {
+            if dir != series
+                && matcher.is_match(&file_name_text(dir))
+                && made_for_series(series, dir)
+            {
+                batch_dirs.insert(dir.clone());
+            }
+        }
+    }
+    dirs.retain(|dir| !batch_dirs.contains(dir));
+    natural_sort_paths(&mut dirs);
+    Ok(dirs)
+}
+
+/// A plan that moves nothing and renders `series_cover` into each existing batch folder.
+pub fn cover_refresh_plan(
+    batches: &[ExistingBatch],
+    series_cover: Option<&Path>,
+    clean_rules: &CleanRules,
+) -> Vec<BatchPlan> {
+    let mut volume_start = 1;
+    batches
+        .iter()
+        .map(|batch| {
+            let plan = BatchPlan {
+                batch_index: batch.batch_index,
+                batch_dir: batch.batch_dir.clone(),
+                moves: Vec::new(),
+                will_make_cover: series_cover.is_some(),
+                volume_start,
+                duplicates: Vec::new(),
+                copy: false,
+                existing_volumes: batch.volumes.len(),
+                ignored: Vec::new(),
+                clean_rules: clean_rules.clone(),
+            };
+            volume_start += batch.volumes.len();
+            plan
+        })
+        .collect()
+}
+
+/// Splits `volumes` into the ones to move and byte-identical copies of earlier ones. Only
+/// files of equal size are compared, so distinct volumes cost a metadata lookup each.
+fn split_duplicate_volumes(volumes: Vec<PathBuf>) -> Result<(Vec<PathBuf>, Vec<DuplicateVolume>)> {
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut unique: Vec<PathBuf> = Vec::new();
+    let mut duplicates = Vec::new();
+
+    for path in volumes {
+        let len = fs::metadata(&path)
+            .with_context(|| format!("failed to read metadata: {}", path.display()))?
+            .len();
+        let same_size = by_size.entry(len).or_default();
+        let mut original = None;
+        for &idx in same_size.iter() {
+            if files_have_same_contents(&unique[idx], &path)? {
+                original = Some(unique[idx].clone());
+                break;
+            }
+        }
+        match original {
+            Some(original) => duplicates.push(DuplicateVolume { path, original }),
+            None => {
+                same_size.push(unique.len());
+                unique.push(path);
+            }
+        }
+    }
+
+    Ok((unique, duplicates))
+}
+
+/// "chapters 45-64" when the batch's first and last files are chapter-named.
+fn chapter_range(batch: &BatchPlan) -> Option<String> {
+    let number = |mv: &FileMove| {
+        let stem = Path::new(&mv.dst_name)
+            .file_stem()?
+            .to_string_lossy()
+            .into_owned();
+        if VOLUME_RE.is_match(&stem) {
+            return None;
+        }
+        chapter_number(&stem).map(|(_, number)| number.format(false))
+    };
+    let first = number(batch.moves.first()?)?;
+    let last = number(batch.moves.last()?)?;
+    Some(format!("chapters {first}-{last}"))
+}
+
+pub fn format_plan(
+    series_dir: &Path,
+    plan: &[BatchPlan],
+    series_cover: Option<&Path>,
+    options: &PlanOptions,
+) -> String {
+    let mut out = String::new();
+    let vols_count: usize = plan.iter().map(|b| b.moves.len()).sum();
+
+    out.push('\n');
+    out.push_str(&"=".repeat(98));
+    out.push('\n');
+    out.push_str("[PLAN] Manga toolkit (Rust)\n");
+    out.push_str(&format!("[PLAN] Series folder: {}\n", series_dir.display()));
+    out.push_str(&format!("[PLAN] Volumes found: {vols_count}\n"));
+    let duplicate_count: usize = plan.iter().map(|b| b.duplicates.len()).sum();
+    if duplicate_count > 0 {
+        out.push_str(&format!(
+            "[PLAN] Duplicates skipped: {duplicate_count} (byte-identical to another volume)\n"
+        ));
+    }
+    let ignored: Vec<&PathBuf> = plan.iter().flat_map(|b| &b.ignored).collect();
+    if !ignored.is_empty() {
+        out.push_str(&format!(
+            "[PLAN] Ignored: {} (matched an ignore pattern; left in place)\n",
+            ignored.len()
+        ));
+        for path in ignored {
+            out.push_str(&format!("       - {}\n", file_name_text(path)));
+        }
+    }
+    let broken_count = plan
+        .iter()
+        .flat_map(|b| b.moves.iter())
+        .filter(|mv| mv.problem.is_some())
+        .count();
+    if broken_count > 0 {
+        out.push_str(&format!(
+            "[PLAN] Unreadable archives: {broken_count} (marked [BROKEN] below; they are still {})\n",
+            if plan.iter().any(|batch| batch.copy) { "copied" } else { "moved" }
+        ));
+    }
+    out.push_str(&format!("[PLAN] Batch size: {}\n", options.batch_size));
+    if let Some(first) = plan.first().filter(|_| options.incremental) {
+        out.push_str(&format!(
+            "[PLAN] Incremental: continuing after {} volumes already in batch folders\n",
+            first.volume_start - 1
+        ));
+    }
+    if plan.iter().any(|batch| batch.copy) {
+        out.push_str(
+            "[PLAN] Mode: copy (volumes are copied into the batches; originals stay in place)\n",
+        );
+    }
+
+    if let Some(cover) = series_cover {
+        out.push_str(&format!(
+            "[PLAN] Series cover source: {}\n",
+            cover.display()
+        ));
+        out.push_str("[PLAN] Each batch will have:\n");
+        out.push_str("       - cover_old.jpg (copied once from series cover, preserved)\n");
+        out.push_str("       - cover.jpg (rendered with batch number DEAD-CENTER)\n");
+        out.push_str("       - any existing cover.jpg archived to cover_old_*.jpg\n");
+    } else {
+        out.push_str("[PLAN] Covers: skipped (no cover image found/downloaded)\n");
+    }
+
+    out.push_str(&"=".repeat(98));
+    out.push('\n');
+
+    for batch in plan {
+        let start_idx = batch.volume_start;
+        let end_idx = start_idx + batch.moves.len() - 1;
+
+        let range = options
+            .chapters
+            .then(|| chapter_range(batch))
+            .flatten()
+            .unwrap_or_else(|| format!("volumes {start_idx}-{end_idx}"));
+
+        out.push('\n');
+        out.push_str(&format!(
+            "{}  ({range})\n",
+            file_name_text(&batch.batch_dir)
+        ));
+        out.push_str(&format!("  [DIR] {}\n", batch.batch_dir.display()));
+        if batch.existing_volumes > 0 {
+            out.push_str(&format!(
+                "  [KEEP] {} volumes already in the folder; the new ones are added after them\n",
+                batch.existing_volumes
+            ));
+        }
+        if series_cover.is_some() {
+            out.push_str(&format!(
+                "  [COVER] cover_old.jpg + cover.jpg (number {})\n",
+                batch.batch_index
+            ));
+        }
+
+        let verb = if batch.copy { "copy" } else { "move" };
+        for dup in &batch.duplicates {
+            out.push_str(&format!(
+                "  [SKIP] {}  (identical to {}; left in place, use --keep-duplicates to {verb} it)\n",
+                file_name_text(&dup.path),
+                file_name_text(&dup.original)
+            ));
+        }
+
+        for (i, mv) in batch.moves.iter().enumerate() {
+            let n = start_idx + i;
+            if let DestinationState::Deduplicated { taken_name } = &mv.destination {
+                out.push_str(&format!(
+                    "  {n:>4}. {}  ({taken_name} already exists; will deduplicate to {})\n",
+                    file_name_text(&mv.src),
+                    mv.dst_name
+                ));
+            } else if mv.destination == DestinationState::Identical && batch.copy {
+                out.push_str(&format!(
+                    "  {n:>4}. {}  (identical {} already exists; nothing to copy)\n",
+                    file_name_text(&mv.src),
+                    mv.dst_name
+                ));
+            } else if mv.destination == DestinationState::Identical {
+                out.push_str(&format!(
+                    "  {n:>4}. {}  (identical {} already exists; will remove the duplicate)\n",
+                    file_name_text(&mv.src),
+                    mv.dst_name
+                ));
+            } else if file_name_text(&mv.src) == mv.dst_name {
+                out.push_str(&format!("  {n:>4}. {}\n", file_name_text(&mv.src)));
+            } else {
+                out.push_str(&format!(
+                    "  {n:>4}. {}  (rename: {} -> {})\n",
+                    file_name_text(&mv.src),
+                    file_name_text(&mv.src),
+                    mv.dst_name
+                ));
+            }
+            if let Some(problem) = &mv.problem {
+                out.push_str(&format!("        [BROKEN] {problem}\n"));
+            }
+        }
+    }
+
+    out.push('\n');
+    out.push_str(&"=".repeat(98));
+    out.push('\n');
+
+    out
+}
+
+pub fn plan_to_json(series_dir: &Path, plan: &[BatchPlan], series_cover: Option<&Path>) -> Value {
+    let volume_count: usize = plan.iter().map(|b| b.moves.len()).sum();
+    let batches: Vec<Value> = plan
+        .iter()
+        .map(|batch| {
+            let moves: Vec<Value> = batch
+                .moves
+                .iter()
+                .map(|mv| {
+                    json!({
+                        "src": mv.src.display().to_string(),
+                        "dst": mv.dst.display().to_string(),
+                        "dst_name": mv.dst_name,
+                        "rename": file_name_text(&mv.src) != mv.dst_name,
+                        "cross_device": is_cross_device(&mv.src, &mv.dst),
+                        "destination": mv.destination.as_str(),
+                        "problem": mv.problem,
+                    })
+                })
+                .collect();
+            let duplicates: Vec<Value> = batch
+                .duplicates
+                .iter()
+                .map(|dup| {
+                    json!({
+                        "path": dup.path.display().to_string(),
+                        "original": dup.original.display().to_string(),
+                    })
+                })
+                .collect();
+
+            json!({
+                "batch_index": batch.batch_index,
+                "batch_dir": batch.batch_dir.display().to_string(),
+                "volume_start": batch.volume_start,
+                "will_make_cover": batch.will_make_cover,
+                "copy": batch.copy,
+                "existing_volumes": batch.existing_volumes,
+                "moves": moves,
+                "duplicates": duplicates,
+            })
+        })
+        .collect();
+
+    json!({
+        "series_dir": series_dir.display().to_string(),
+        "series_cover": series_cover.map(|p| p.display().to_string()),
+        "volume_count": volume_count,
+        "batches": batches,
+    })
+}
+
+/// Returns the closest ancestor of `path` (including itself) that exists on disk.
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|p| p.exists())
+}
+
+#[cfg(unix)]
+fn is_cross_device(src: &Path, dst: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = |path: &Path| {
+        existing_ancestor(path)
+            .and_then(|p| fs::metadata(p).ok())
+            .map(|m| m.dev())
+    };
+    match (device(src), device(dst)) {
+        (Some(a), Some(b)) => a != b,
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_cross_device(src: &Path, dst: &Path) -> bool {
+    let prefix = |path: &Path| {
+        existing_ancestor(path)
+            .and_then(|p| p.canonicalize().ok())
+            .and_then(|p| match p.components().next() {
+                Some(Component::Prefix(prefix)) => Some(prefix.as_os_str().to_os_string()),
+                _ => None,
+            })
+    };
+    prefix(src) != prefix(dst)
+}
+
+fn read_chunk(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn files_are_identical(a: &Path, b: &Path) -> Result<bool> {
+    let len = |path: &Path| {
+        fs::metadata(path)
+            .map(|m| m.len())
+            .with_context(|| format!("failed to read metadata: {}", path.display()))
+    };
+    Ok(len(a)? == len(b)? && files_have_same_contents(a, b)?)
+}
+
+fn files_have_same_contents(a: &Path, b: &Path) -> Result<bool> {
+    let open = |path: &Path| {
+        fs::File::open(path)
+            .map(io::BufReader::new)
+            .with_context(|| format!("failed to open for verification: {}", path.display()))
+    };
+    let (mut a_reader, mut b_reader) = (open(a)?, open(b)?);
+    let mut a_buf = vec![0u8; 64 * 1024];
+    let mut b_buf = vec![0u8; 64 * 1024];
+    loop {
+        let a_len = read_chunk(&mut a_reader, &mut a_buf)
+            .with_context(|| format!("failed to read {}", a.display()))?;
+        let b_len = read_chunk(&mut b_reader, &mut b_buf)
+            .with_context(|| format!("failed to read {}", b.display()))?;
+        if a_buf[..a_len] != b_buf[..b_len] {
+            return Ok(false);
+        }
+        if a_len == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// The `EXDEV` fallback of `move_file`. The source is only removed once `copy_verified`
+/// succeeds.
+fn copy_across_devices(src: &Path, dst: &Path, verify_contents: bool) -> Result<()> {
+    copy_verified(src, dst, verify_contents)?;
+    fs::remove_file(src)
+        .with_context(|| format!("failed to remove source file: {}", src.display()))?;
+    Ok(())
+}
+
+/// `fs::copy` does not reliably keep timestamps, so the source's access and modification times
+/// are restored on the copy. The copy is removed again unless it has the source's size and,
+/// with `verify_contents`, the same bytes.
+fn copy_verified(src: &Path, dst: &Path, verify_contents: bool) -> Result<()> {
+    let metadata =
+        fs::metadata(src).with_context(|| format!("failed to read metadata: {}", src.display()))?;
+    fs::copy(src, dst)
+        .with_context(|| format!("copy failed from {} to {}", src.display(), dst.display()))?;
+    filetime::set_file_times(
+        dst,
+        FileTime::from_last_access_time(&metadata),
+        FileTime::from_last_modification_time(&metadata),
+    )
+    .with_context(|| format!("failed to restore timestamps on {}", dst.display()))?;
+
+    let copied_len = fs::metadata(dst)
+        .with_context(|| format!("failed to read metadata: {}", dst.display()))?
+        .len();
+    let mismatch = if copied_len != metadata.len() {
+        Some(format!(
+            "size mismatch ({copied_len} of {} bytes)",
+            metadata.len()
+        ))
+    } else if verify_contents && !files_have_same_contents(src, dst)? {
+        Some("contents differ".to_string())
+    } else {
+        None
+    };
+    if let Some(reason) = mismatch {
+        // The source is intact; drop the bad copy so a re-run starts clean.
+        let _ = fs::remove_file(dst);
+        bail!(
+            "copy of {} to {} failed verification: {reason}; source left in place",
+            src.display(),
+            dst.display()
+        );
+    }
+    Ok(())
+}
+
+/// Like `move_file`, but fails with an `AlreadyExists` `io::Error` instead of replacing a file
+/// at `dst`; the claim is removed again if the move fails.
+fn move_file_new(src: &Path, dst: &Path, verify_contents: bool) -> Result<()> {
+    if let Some(parent) = dst.parent() {
+        ensure_dir(parent)?;
+    }
+    claim_path(dst).with_context(|| format!("failed to claim {}", dst.display()))?;
+    let moved = move_file(src, dst, verify_contents);
+    if moved.is_err() {
+        let _ = fs::remove_file(dst);
+    }
+    moved
+}
+
+/// Copies `src` to `dst` for copy-mode runs, leaving the source in place. Like
+/// `move_file_new`, it fails with `AlreadyExists` rather than replace a file at `dst`.
+fn copy_file_new(src: &Path, dst: &Path, verify_contents: bool) -> Result<()> {
+    if let Some(parent) = dst.parent() {
+        ensure_dir(parent)?;
+    }
+    claim_path(dst).with_context(|| format!("failed to claim {}", dst.display()))?;
+    let copied = copy_verified(src, dst, verify_contents);
+    if copied.is_err() {
+        let _ = fs::remove_file(dst);
+    }
+    copied
+}
+
+fn is_already_exists(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|err| err.kind() == io::ErrorKind::AlreadyExists)
+}
+
+/// Moves (or copies) a planned volume without replacing anything at its destination. When
+/// another run took the name after planning, the volume goes to the next free `stem (N).ext`
+/// instead. Returns where it landed.
+fn place_volume(mv: &FileMove, copy: bool, verify_contents: bool) -> Result<PathBuf> {
+    let dest_dir = mv.dst.parent().unwrap_or_else(|| Path::new(""));
+    for (_, candidate) in numbered_candidates(dest_dir, &mv.dst_name) {
+        let placed = if copy {
+            copy_file_new(&mv.src, &candidate, verify_contents)
+        } else {
+            move_file_new(&mv.src, &candidate, verify_contents)
+        };
+        match placed {
+            Err(err) if is_already_exists(&err) => continue,
+            placed => return placed.map(|()| candidate),
+        }
+    }
+    bail!("no free name for {} in {}", mv.dst_name, dest_dir.display())
+}
+
+fn move_file(src: &Path, dst: &Path, verify_contents: bool) -> Result<()> {
+    if let Some(parent) = dst.parent() {
+        ensure_dir(parent)?;
+    }
+
+    match fs::rename(src, dst) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            if err.raw_os_error() == Some(libc::EXDEV) {
+                copy_across_devices(src, dst, verify_contents)
+            } else {
+                Err(err).with_context(|| {
+                    format!(
+                        "failed to move file from {} to {}",
+                        src.display(),
+                        dst.display()
+                    )
+                })
+            }
+        }
+    }
+}
+
+/// Turns network access off (or back on) for the whole process. While offline,
+/// `find_remote_cover` returns immediately and no HTTP client can be built, so
+/// covers come only from local archives and `COVER_CANDIDATES`.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+thread_local! {
+    static CANCEL: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with its HTTP requests abortable through `cancel`: once it is set, cover lookups
+/// fail before their next request and downloads stop at the next chunk, removing the partial
+/// file. A request already waiting on the server still runs into its timeout.
+pub fn with_cancel<T>(cancel: Arc<AtomicBool>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Arc<AtomicBool>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CANCEL.with(|slot| *slot.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(CANCEL.with(|slot| slot.replace(Some(cancel))));
+    f()
+}
+
+#[cfg(feature = "network")]
+fn current_cancel() -> Option<Arc<AtomicBool>> {
+    CANCEL.with(|slot| slot.borrow().clone())
+}
+
+#[cfg(feature = "network")]
+fn check_cancelled() -> Result<()> {
+    if current_cancel().is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+        bail!("cancelled");
+    }
+    Ok(())
+}
+
+/// Caps HTTP requests per host for the whole process, so bulk runs stay under provider rate
+/// limits. `0` turns the limit off.
+pub fn set_request_rate(per_second: f64) {
+    if let Ok(mut limiter) = RATE_LIMITER.lock() {
+        limiter.rate = per_second.max(0.0);
+        limiter.buckets.clear();
+    }
+}
+
+/// How every HTTP request in the process is sent; see `set_http_settings`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpSettings {
+    /// Replaces the built-in timeouts (20 seconds for lookups, 30 for downloads).
+    pub timeout: Option<Duration>,
+    pub user_agent: String,
+    /// Sent with cover downloads. `None` sends no `Referer`.
+    pub referer: Option<String>,
+    /// An HTTP(S) proxy for every request, e.g. `http://proxy.example:8080`.
+    pub proxy: Option<String>,
+    /// Honor `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` when `proxy` is unset.
+    pub env_proxy: bool,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            user_agent: USER_AGENT.to_string(),
+            referer: Some(DEFAULT_REFERER.to_string()),
+            proxy: None,
+            env_proxy: true,
+        }
+    }
+}
+
+impl HttpSettings {
+    /// The defaults with whichever overrides are given. An empty `referer` sends none.
+    pub fn with_overrides(
+        timeout_secs: Option<u64>,
+        user_agent: Option<&str>,
+        referer: Option<&str>,
+    ) -> Self {
+        let defaults = Self::default();
+        Self {
+            timeout: timeout_secs.map(Duration::from_secs),
+            user_agent: user_agent.map_or(defaults.user_agent, str::to_string),
+            referer: match referer {
+                Some("") => None,
+                Some(referer) => Some(referer.to_string()),
+                None => defaults.referer,
+            },
+            ..defaults
+        }
+    }
+}
+
+/// Sets the timeout, `User-Agent` and `Referer` used by every later request in the process.
+pub fn set_http_settings(settings: HttpSettings) {
+    if let Ok(mut current) = HTTP_SETTINGS.lock() {
+        *current = settings;
+    }
+}
+
+#[cfg(feature = "network")]
+fn http_settings() -> HttpSettings {
+    HTTP_SETTINGS
+        .lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_default()
+}
+
+/// Sends a `LogEvent::Http` for every later request attempt in the process to `hook`, from
+/// whichever thread makes it. `None` stops the reports.
+pub fn set_http_trace(hook: Option<fn(LogEvent)>) {
+    if let Ok(mut current) = HTTP_TRACE.lock() {
+        *current = hook;
+    }
+}
+
+#[cfg(feature = "network")]
+fn trace_http(url: &str, attempt: u32, status: Option<StatusCode>, started: Instant) {
+    let hook = HTTP_TRACE.lock().ok().and_then(|hook| *hook);
+    if let Some(hook) = hook {
+        hook(LogEvent::Http {
+            url: url.to_string(),
+            attempt,
+            status: status.map(|status| status.as_u16()),
+            elapsed: started.elapsed(),
+        });
+    }
+}
+
+/// A token bucket holding up to one second's worth of requests.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[cfg(feature = "network")]
+impl TokenBucket {
+    /// Takes a token and returns how long to wait before sending. The balance may go negative,
+    /// which queues concurrent callers behind each other.
+    fn reserve(&mut self, now: Instant, rate: f64) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate.max(1.0)) - 1.0;
+        self.updated = now;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RateLimiter {
+    rate: f64,
+    buckets: HashMap<String, TokenBucket>,
+}
+
+/// Blocks until the limiter allows another request to `url`'s host.
+#[cfg(feature = "network")]
+fn throttle(url: &str) {
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default();
+    let wait = match RATE_LIMITER.lock() {
+        Ok(mut limiter) if limiter.rate > 0.0 => {
+            let rate = limiter.rate;
+            let now = Instant::now();
+            limiter
+                .buckets
+                .entry(host)
+                .or_insert(TokenBucket {
+                    tokens: rate.max(1.0),
+                    updated: now,
+                })
+                .reserve(now, rate)
+        }
+        _ => Duration::ZERO,
+    };
+    if !wait.is_zero() {
+        thread::sleep(wait);
+    }
+}
+
+/// A client for one lookup or download; `timeout_secs` applies unless `HttpSettings::timeout`
+/// overrides it.
+#[cfg(feature = "network")]
+fn http_client(timeout_secs: u64) -> Result<Client> {
+    if is_offline() {
+        return Err(Error::Network(anyhow!("network access is disabled (offline mode)")).into());
+    }
+    let settings = http_settings();
+    let mut builder = Client::builder().user_agent(settings.user_agent).timeout(
+        settings
+            .timeout
+            .unwrap_or(Duration::from_secs(timeout_secs)),
+    );
+    if !settings.env_proxy {
+        builder = builder.no_proxy();
+    }
+    if let Some(proxy) = &settings.proxy {
+        let proxy = Proxy::all(proxy).with_context(|| format!("invalid proxy URL {proxy:?}"))?;
+        builder = builder.proxy(proxy);
+    }
+    builder.build().context("failed to initialize HTTP client")
+}
+
+/// The proxy requests to `url` go through, if any, for naming it in errors. Environment
+/// proxies are reported by variable; `NO_PROXY` exceptions aren't checked.
+#[cfg(feature = "network")]
+fn active_proxy(settings: &HttpSettings, url: &str) -> Option<String> {
+    if let Some(proxy) = &settings.proxy {
+        return Some(proxy.clone());
+    }
+    if !settings.env_proxy {
+        return None;
+    }
+    let vars: &[&str] = if url.starts_with("https:") {
+        &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+    } else {
+        &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+    };
+    vars.iter().find_map(|var| {
+        std::env::var(var)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| format!("{value} (from {var})"))
+    })
+}
+
+/// Wraps a failed request, calling out a proxy that couldn't be reached so it doesn't read
+/// like the provider being down.
+#[cfg(feature = "network")]
+fn request_failure(err: reqwest::Error, url: &str, proxy: Option<&str>) -> anyhow::Error {
+    match proxy {
+        Some(proxy) if err.is_connect() => {
+            anyhow!(err).context(format!("could not connect through proxy {proxy}: {url}"))
+        }
+        _ => anyhow!(err).context(format!("request failed: {url}")),
+    }
+}
+
+/// A GET for an image, with the configured `Referer`.
+#[cfg(feature = "network")]
+fn image_request(client: &Client, url: &str) -> reqwest::blocking::RequestBuilder {
+    let request = client.get(url);
+    match http_settings().referer {
+        Some(referer) => request.header(REFERER, referer),
+        None => request,
+    }
+}
+
+#[cfg(feature = "network")]
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Parses a `Retry-After` header given in seconds. HTTP-date values are ignored.
+#[cfg(feature = "network")]
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+/// Delay before retry number `attempt` (1-based): the server's `Retry-After` when given,
+/// otherwise exponential backoff from `HTTP_RETRY_BASE_DELAY`. Both are capped.
+#[cfg(feature = "network")]
+fn retry_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    let backoff = HTTP_RETRY_BASE_DELAY.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    retry_after.unwrap_or(backoff).min(HTTP_RETRY_MAX_DELAY)
+}
+
+/// Sends the request built by `send`, retrying timeouts, connection failures, 429 and 5xx
+/// responses up to `HTTP_MAX_ATTEMPTS` times. Only the final failure is returned. Every attempt
+/// waits its turn with the per-host rate limiter.
+#[cfg(feature = "network")]
+fn with_retries(
+    url: &str,
+    mut send: impl FnMut() -> reqwest::Result<Response>,
+) -> Result<Response> {
+    let mut attempt = 1;
+    loop {
+        let retries_left = attempt < HTTP_MAX_ATTEMPTS;
+        throttle(url);
+        check_cancelled()?;
+        let started = Instant::now();
+        let sent = send();
+        trace_http(
+            url,
+            attempt,
+            sent.as_ref().ok().map(Response::status),
+            started,
+        );
+        match sent {
+            Ok(resp) if retries_left && is_retryable_status(resp.status()) => {
+                let retry_after = resp
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after);
+                thread::sleep(retry_delay(attempt, retry_after));
+            }
+            Ok(resp) => {
+                return resp
+                    .error_for_status()
+                    .with_context(|| format!("request returned error status: {url}"));
+            }
+            Err(err) if retries_left && (err.is_timeout() || err.is_connect()) => {
+                thread::sleep(retry_delay(attempt, None));
+            }
+            Err(err) => {
+                let proxy = active_proxy(&http_settings(), url);
+                return Err(request_failure(err, url, proxy.as_deref()));
+            }
+        }
+        attempt += 1;
+    }
+}
+
+#[cfg(feature = "network")]
+fn http_get_json(url: &str, params: &[(&str, String)], timeout_secs: u64) -> Result<Value> {
+    let client = http_client(timeout_secs)?;
+    let resp = with_retries(url, || {
+        let mut req = client.get(url);
+        if !params.is_empty() {
+            req = req.query(params);
+        }
+        req.send()
+    })?;
+
+    resp.json().context("failed to decode JSON response")
+}
+
+#[cfg(feature = "network")]
+fn http_post_json(url: &str, payload: &Value, timeout_secs: u64) -> Result<Value> {
+    let client = http_client(timeout_secs)?;
+    let resp = with_retries(url, || {
+        client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .json(payload)
+            .send()
+    })?;
+
+    resp.json().context("failed to decode JSON response")
+}
+
+/// Whether a `Content-Type` could be an image. Some CDNs label images as generic binary data,
+/// so only clearly non-image types (an HTML error page, say) are rejected.
+#[cfg(feature = "network")]
+fn is_image_content_type(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime.starts_with("image/")
+        || mime == "application/octet-stream"
+        || mime == "binary/octet-stream"
+}
+
+/// Streams `url` to `out_path`, calling `progress` with the bytes received so far and the
+/// `Content-Length`, if any, after every chunk. The file is only put in place once the bytes
+/// decode as an image, so an error page served with a 200 never becomes a cover.
+#[cfg(feature = "network")]
+fn download_file(
+    url: &str,
+    out_path: &Path,
+    timeout_secs: u64,
+    progress: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<()> {
+    if let Some(parent) = out_path.parent() {
+        ensure_dir(parent)?;
+    }
+
+    let client = http_client(timeout_secs)?;
+    let mut resp = with_retries(url, || image_request(&client, url).send())?;
+    if let Some(content_type) = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    {
+        if !is_image_content_type(content_type) {
+            bail!("{url} returned {content_type} instead of an image");
+        }
+    }
+
+    let total = resp.content_length();
+    write_atomically(out_path, |out| {
+        let mut buf = [0u8; 16 * 1024];
+        let mut body = Vec::new();
+        let mut received = 0u64;
+        loop {
+            check_cancelled()?;
+            let read = resp
+                .read(&mut buf)
+                .with_context(|| format!("failed while downloading {url}"))?;
+            if read == 0 {
+                decode_cover_image(&body)
+                    .with_context(|| format!("{url} did not return a readable image"))?;
+                return Ok(());
+            }
+            body.extend_from_slice(&buf[..read]);
+            out.write_all(&buf[..read]).with_context(|| {
+                format!(
+                    "failed while writing downloaded data to {}",
+                    out_path.display()
+                )
+            })?;
+            received += read as u64;
+            progress(received, total);
+        }
+    })
+}
+
+/// Thins per-chunk download progress to one report per tenth of the file (or per
+/// `DOWNLOAD_PROGRESS_STEP` bytes when the size is unknown), plus the last chunk.
+#[derive(Debug, Default)]
+struct ProgressThrottle {
+    next: u64,
+}
+
+const DOWNLOAD_PROGRESS_STEP: u64 = 256 * 1024;
+
+impl ProgressThrottle {
+    fn due(&mut self, received: u64, total: Option<u64>) -> bool {
+        let finished = total == Some(received);
+        if received < self.next && !finished {
+            return false;
+        }
+        let step = total.map_or(DOWNLOAD_PROGRESS_STEP, |total| (total / 10).max(1));
+        self.next = if finished {
+            u64::MAX
+        } else {
+            (received / step + 1) * step
+        };
+        true
+    }
+}
+
+// Without the `network` feature every request fails, so the providers report an error instead
+// of a cover and only local covers are used.
+#[cfg(not(feature = "network"))]
+fn http_get_json(url: &str, _params: &[(&str, String)], _timeout_secs: u64) -> Result<Value> {
+    bail!("cannot fetch {url}: built without the `network` feature")
+}
+
+#[cfg(not(feature = "network"))]
+fn http_post_json(url: &str, _payload: &Value, _timeout_secs: u64) -> Result<Value> {
+    bail!("cannot fetch {url}: built without the `network` feature")
+}
+
+#[cfg(not(feature = "network"))]
+fn download_file(
+    url: &str,
+    _out_path: &Path,
+    _timeout_secs: u64,
+    _progress: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<()> {
+    bail!("cannot download {url}: built without the `network` feature")
+}
+
+fn best_title(attrs: &Value) -> String {
+    let Some(title_obj) = attrs.get("title").and_then(Value::as_object) else {
+        return String::new();
+    };
+
+    if let Some(en) = title_obj.get("en").and_then(Value::as_str) {
+        return en.to_string();
+    }
+
+    title_obj
+        .values()
+        .find_map(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn normalize_title(input: &str) -> String {
+    let lower = input.to_ascii_lowercase();
+    NON_ALNUM_RE.replace_all(&lower, "").into_owned()
+}
+
+fn parse_mangadex_volume(vol: &Value) -> Option<VolumeNumber> {
+    let s = vol.as_str()?;
+    let caps = MANGADEX_VOLUME_RE.captures(s)?;
+    VolumeNumber::parse(caps.get(1)?.as_str(), caps.get(2).map(|m| m.as_str()))
+}
+
+/// The best `score_titles` score.
+pub const MANGADEX_MAX_SCORE: u8 = 6;
+
+/// Scores how well a search result's titles match the search: 6 when the main title is equal
+/// after normalization, 5 for an equal alternative title, 4 and 3 for the same (exact,
+/// case-insensitive) without normalization, 2 when the main title contains the search, 1 when
+/// an alternative title does, and 0 otherwise.
+fn score_titles(main: &str, alts: &[String], title_l: &str, title_n: &str) -> u8 {
+    let main = main.trim().to_ascii_lowercase();
+    let main_n = normalize_title(&main);
+    let alt_values: Vec<String> = alts
+        .iter()
+        .map(|alt| alt.trim().to_ascii_lowercase())
+        .collect();
+
+    if main_n == title_n {
+        return 6;
+    }
+    if alt_values.iter().any(|v| normalize_title(v) == title_n) {
+        return 5;
+    }
+    if main == title_l {
+        return 4;
+    }
+    if alt_values.iter().any(|v| v == title_l) {
+        return 3;
+    }
+    if main.contains(title_l) {
+        return 2;
+    }
+    if alt_values.iter().any(|v| v.contains(title_l)) {
+        return 1;
+    }
+    0
+}
+
+/// The titles of a MangaDex search result.
+fn mangadex_item_titles(item: &Value) -> TitledHit<'_> {
+    let attrs = item.get("attributes").unwrap_or(&Value::Null);
+    let alts = attrs
+        .get("altTitles")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+        .flat_map(|alt| alt.values().filter_map(Value::as_str).map(str::to_string))
+        .collect();
+    TitledHit {
+        item,
+        main: best_title(attrs),
+        alts,
+        id: item.get("id").map(id_text).unwrap_or_default(),
+    }
+}
+
+fn mangadex_score_label(score: u8) -> &'static str {
+    match score {
+        5.. => "exact title",
+        3 | 4 => "exact alternative title",
+        2 => "title contains the search",
+        1 => "alternative title contains the search",
+        0 => "no title match",
+    }
+}
+
+/// A provider's search result with the titles it is scored on.
+struct TitledHit<'a> {
+    item: &'a Value,
+    main: String,
+    alts: Vec<String>,
+    id: String,
+}
+
+/// String and numeric IDs alike (AniList and Kitsu use numbers or numeric strings).
+fn id_text(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Search results scoring at least `min_score`, best first; see `score_titles`. A provider's
+/// own order can change between requests, so ties go to the main title closest in length to
+/// the search, then to the lowest id, which keeps the pick the same run to run.
+fn rank_by_title<'a>(hits: Vec<TitledHit<'a>>, title: &str, min_score: u8) -> Vec<(&'a Value, u8)> {
+    let title_l = title.trim().to_ascii_lowercase();
+    let title_n = normalize_title(&title_l);
+    let mut ranked: Vec<(u8, usize, TitledHit<'a>)> = hits
+        .into_iter()
+        .map(|hit| {
+            let main_n = normalize_title(&hit.main.trim().to_ascii_lowercase());
+            (
+                score_titles(&hit.main, &hit.alts, &title_l, &title_n),
+                main_n.len().abs_diff(title_n.len()),
+                hit,
+            )
+        })
+        .filter(|(score, _, _)| *score >= min_score)
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(a.1.cmp(&b.1))
+            .then_with(|| a.2.id.cmp(&b.2.id))
+    });
+    ranked
+        .into_iter()
+        .map(|(score, _, hit)| (hit.item, score))
+        .collect()
+}
+
+fn rank_mangadex_items<'a>(items: &'a [Value], title: &str, min_score: u8) -> Vec<(&'a Value, u8)> {
+    rank_by_title(
+        items.iter().map(mangadex_item_titles).collect(),
+        title,
+        min_score,
+    )
+}
+
+/// Query for a MangaDex title search, with the filters in `options`.
+fn mangadex_search_params(
+    title: &str,
+    limit: usize,
+    options: &CoverOptions,
+) -> Vec<(&'static str, String)> {
+    let mut params = vec![
+        ("title", title.to_string()),
+        ("limit", limit.clamp(5, 100).to_string()),
+    ];
+    params.extend(
+        options
+            .content_ratings
+            .iter()
+            .map(|rating| ("contentRating[]", rating.as_str().to_string())),
+    );
+    params.extend(options.languages.iter().map(|lang| {
+        (
+            "availableTranslatedLanguage[]",
+            lang.trim().to_ascii_lowercase(),
+        )
+    }));
+    params.extend(
+        options
+            .original_languages
+            .iter()
+            .map(|lang| ("originalLanguage[]", lang.trim().to_ascii_lowercase())),
+    );
+    params
+}
+
+/// Searches MangaDex for `title`, returning at least the first five results.
+fn search_mangadex(title: &str, limit: usize, options: &CoverOptions) -> Result<Vec<Value>> {
+    let data = http_get_json(
+        "https://api.mangadex.org/manga",
+        &mangadex_search_params(title, limit, options),
+        20,
+    )?;
+    Ok(data
+        .get("data")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default())
+}
+
+pub fn fetch_cover_mangadex(title: &str, options: &CoverOptions) -> Result<Option<CoverResult>> {
+    let items = search_mangadex(title, 5, options)?;
+    let Some(&(item, score)) = rank_mangadex_items(&items, title, options.min_score).first() else {
+        return Ok(None);
+    };
+    let Some(manga_id) = item.get("id").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+
+    Ok(
+        mangadex_cover_for_manga(manga_id, options)?.map(|result| CoverResult {
+            match_score: Some(score),
+            ..result
+        }),
+    )
+}
+
+/// A MangaDex series that matched a title search, with the cover `CoverOptions` selects for it.
+#[derive(Debug, Clone)]
+pub struct CoverCandidate {
+    pub title: String,
+    pub result: CoverResult,
+    /// A 256px rendition of the same cover, for showing the choice.
+    pub thumbnail_url: String,
+}
+
+/// Like `fetch_cover_mangadex`, but returns up to `limit` of the best matches instead of only
+/// the first, skipping series without a cover. Costs one request per candidate.
+pub fn mangadex_cover_candidates(
+    title: &str,
+    options: &CoverOptions,
+    limit: usize,
+) -> Result<Vec<CoverCandidate>> {
+    let items = search_mangadex(title, limit, options)?;
+    let mut candidates = Vec::new();
+    for (item, score) in rank_mangadex_items(&items, title, options.min_score) {
+        if candidates.len() == limit {
+            break;
+        }
+        let Some(manga_id) = item.get("id").and_then(Value::as_str) else {
+            continue;
+        };
+        let Some(result) = mangadex_cover_for_manga(manga_id, options)? else {
+            continue;
+        };
+        // Cover URLs are the file URL plus the size suffix.
+        let file_url = result.url.trim_end_matches(options.size.url_suffix());
+        candidates.push(CoverCandidate {
+            title: best_title(item.get("attributes").unwrap_or(&Value::Null)),
+            thumbnail_url: format!("{file_url}{}", CoverSize::Px256.url_suffix()),
+            result: CoverResult {
+                match_score: Some(score),
+                ..result
+            },
+        });
+    }
+    Ok(candidates)
+}
+
+/// Downloads the candidate's thumbnail to the system temp directory as `{name}.jpg`.
+pub fn download_cover_thumbnail(candidate: &CoverCandidate, name: &str) -> Result<PathBuf> {
+    let output = std::env::temp_dir()
+        .join("manga_cleaner_preview")
+        .join(format!("{name}.jpg"));
+    download_file(&candidate.thumbnail_url, &output, 30, &mut |_, _| {})?;
+    Ok(output)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoverSize {
+    #[default]
+    Original,
+    Px512,
+    Px256,
+}
+
+impl CoverSize {
+    fn url_suffix(self) -> &'static str {
+        match self {
+            CoverSize::Original => "",
+            CoverSize::Px512 => ".512.jpg",
+            CoverSize::Px256 => ".256.jpg",
+        }
+    }
+}
+
+impl std::str::FromStr for CoverSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "original" | "best" => Ok(CoverSize::Original),
+            "512" => Ok(CoverSize::Px512),
+            "256" => Ok(CoverSize::Px256),
+            other => bail!("invalid cover size {other:?}: expected original, 512, or 256"),
+        }
+    }
+}
+
+/// A MangaDex content rating, for filtering searches with `CoverOptions::content_ratings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentRating {
+    Safe,
+    Suggestive,
+    Erotica,
+    Pornographic,
+}
+
+impl ContentRating {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentRating::Safe => "safe",
+            ContentRating::Suggestive => "suggestive",
+            ContentRating::Erotica => "erotica",
+            ContentRating::Pornographic => "pornographic",
+        }
+    }
+}
+
+impl std::str::FromStr for ContentRating {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "safe" => Ok(ContentRating::Safe),
+            "suggestive" => Ok(ContentRating::Suggestive),
+            "erotica" => Ok(ContentRating::Erotica),
+            "pornographic" => Ok(ContentRating::Pornographic),
+            other => bail!(
+                "invalid content rating {other:?}: expected safe, suggestive, erotica, or pornographic"
+            ),
+        }
+    }
+}
+
+/// Which MangaDex cover to pick for a series.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverOptions {
+    pub size: CoverSize,
+    /// Volume whose cover is preferred. Falls back to volume 1 when it has no cover.
+    pub volume: u32,
+    /// Among that volume's covers (one per locale, typically), pick the largest image instead
+    /// of the earliest upload. Costs one download per candidate.
+    pub highest_resolution: bool,
+    /// Skip MangaDex when its best search result scores below this rather than take a poor
+    /// match; see `score_titles`. 0 accepts any result.
+    pub min_score: u8,
+    /// Only search series with these content ratings. Empty leaves MangaDex's own default.
+    pub content_ratings: Vec<ContentRating>,
+    /// Only search series translated into one of these languages (codes such as `en`, `pt-br`).
+    pub languages: Vec<String>,
+    /// Only search series first published in one of these languages (such as `ja`, `ko`).
+    pub original_languages: Vec<String>,
+}
+
+impl Default for CoverOptions {
+    fn default() -> Self {
+        Self {
+            size: CoverSize::Original,
+            volume: 1,
+            highest_resolution: false,
+            min_score: 0,
+            content_ratings: Vec::new(),
+            languages: Vec::new(),
+            original_languages: Vec::new(),
+        }
+    }
+}
+
+struct MangaDexCover {
+    volume: Option<VolumeNumber>,
+    file_name: String,
+}
+
+#[cfg(feature = "network")]
+fn image_pixel_count(url: &str) -> Result<u64> {
+    let client = http_client(30)?;
+    let resp = with_retries(url, || image_request(&client, url).send())?;
+    let bytes = resp.bytes().context("failed to read image data")?;
+    let (width, height) = ImageReader::new(io::Cursor::new(bytes))
+        .with_guessed_format()
+        .context("failed to detect image format")?
+        .into_dimensions()
+        .context("failed to read image dimensions")?;
+    Ok(u64::from(width) * u64::from(height))
+}
+
+#[cfg(not(feature = "network"))]
+fn image_pixel_count(url: &str) -> Result<u64> {
+    bail!("cannot download {url}: built without the `network` feature")
+}
+
+fn mangadex_cover_for_manga(manga_id: &str, options: &CoverOptions) -> Result<Option<CoverResult>> {
+    let covers_resp = http_get_json(
+        "https://api.mangadex.org/cover",
+        &[
+            ("manga[]", manga_id.to_string()),
+            ("limit", "100".to_string()),
+            ("order[createdAt]", "asc".to_string()),
+        ],
+        20,
+    );
+    // A failed cover listing means "no cover" rather than a hard error, as before.
+    let covers: Vec<MangaDexCover> = covers_resp
+        .ok()
+        .as_ref()
+        .and_then(|resp| resp.get("data"))
+        .and_then(Value::as_array)
+        .map(|covers| {
+            covers
+                .iter()
+                .filter_map(|cover| {
+                    let attrs = cover.get("attributes")?;
+                    Some(MangaDexCover {
+                        volume: parse_mangadex_volume(attrs.get("volume").unwrap_or(&Value::Null)),
+                        file_name: attrs.get("fileName")?.as_str()?.to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let base_url = |cover: &MangaDexCover| {
+        format!(
+            "https://uploads.mangadex.org/covers/{manga_id}/{}",
+            cover.file_name
+        )
+    };
+
+    let mut note = None;
+    let mut candidates: Vec<&MangaDexCover> = covers
+        .iter()
+        .filter(|cover| cover.volume == Some(VolumeNumber::whole(options.volume)))
+        .collect();
+    if candidates.is_empty() && options.volume != 1 {
+        note = Some(format!(
+            "MangaDex has no volume {} cover; falling back to volume 1",
+            options.volume
+        ));
+        candidates = covers
+            .iter()
+            .filter(|cover| cover.volume == Some(VolumeNumber::whole(1)))
+            .collect();
+    }
+
+    let chosen = if options.highest_resolution && candidates.len() > 1 {
+        candidates
+            .iter()
+            .copied()
+            .filter_map(|cover| {
+                image_pixel_count(&base_url(cover))
+                    .ok()
+                    .map(|pixels| (pixels, cover))
+            })
+            // Ties keep the earliest upload.
+            .fold(
+                None,
+                |best: Option<(u64, &MangaDexCover)>, (pixels, cover)| match best {
+                    Some((best_pixels, _)) if best_pixels >= pixels => best,
+                    _ => Some((pixels, cover)),
+                },
+            )
+            .map(|(_, cover)| cover)
+            .or_else(|| candidates.first().copied())
+    } else {
+        candidates.first().copied()
+    };
+
+    let Some(chosen) = chosen else {
+        return Ok(None);
+    };
+
+    Ok(Some(CoverResult {
+        source: "mangadex".to_string(),
+        url: format!("{}{}", base_url(chosen), options.size.url_suffix()),
+        note,
+        match_score: None,
+    }))
+}
+
+pub fn fetch_cover_anilist(title: &str) -> Result<Option<CoverResult>> {
+    let endpoint = "https://graphql.anilist.co";
+    let query = r#"
+    query ($search: String) {
+      Page(perPage: 10) {
+        media(search: $search, type: MANGA) {
+          id
+          title { english romaji native }
+          synonyms
+          coverImage { extraLarge large }
+        }
+      }
+    }
+    "#;
+
+    let payload = json!({
+        "query": query,
+        "variables": {
+            "search": title,
+        }
+    });
+
+    let resp = http_post_json(endpoint, &payload, 20)?;
+    Ok(anilist_best_cover(&resp, title))
+}
+
+/// The AniList search result whose titles best match `title`; see `rank_by_title`.
+fn anilist_best_cover(resp: &Value, title: &str) -> Option<CoverResult> {
+    let media = resp.pointer("/data/Page/media").and_then(Value::as_array)?;
+    let hits = media
+        .iter()
+        .map(|item| {
+            let titles = item.get("title").unwrap_or(&Value::Null);
+            let main = ["english", "romaji", "native"]
+                .iter()
+                .find_map(|key| titles.get(key).and_then(Value::as_str))
+                .unwrap_or_default()
+                .to_string();
+            let alts = titles
+                .as_object()
+                .into_iter()
+                .flat_map(|titles| titles.values())
+                .chain(
+                    item.get("synonyms")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten(),
+                )
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect();
+            TitledHit {
+                item,
+                main,
+                alts,
+                id: item.get("id").map(id_text).unwrap_or_default(),
+            }
+        })
+        .collect();
+    rank_by_title(hits, title, 0)
+        .into_iter()
+        .find_map(|(item, score)| {
+            let mut result = anilist_media_cover(item)?;
+            result.match_score = Some(score);
+            Some(result)
+        })
+}
+
+fn anilist_cover_from_response(resp: &Value) -> Result<Option<CoverResult>> {
+    let media = resp.pointer("/data/Media").unwrap_or(&Value::Null);
+    if media.is_null() {
+        return Ok(None);
+    }
+    Ok(anilist_media_cover(media))
+}
+
+fn anilist_media_cover(media: &Value) -> Option<CoverResult> {
+    let url = media
+        .pointer("/coverImage/extraLarge")
+        .and_then(Value::as_str)
+        .or_else(|| media.pointer("/coverImage/large").and_then(Value::as_str))?;
+
+    Some(CoverResult {
+        source: "anilist".to_string(),
+        url: url.to_string(),
+        note: None,
+        match_score: None,
+    })
+}
+
+pub fn fetch_cover_kitsu(title: &str) -> Result<Option<CoverResult>> {
+    let base = "https://kitsu.io/api/edge";
+    let data = http_get_json(
+        &format!("{base}/manga"),
+        &[
+            ("filter[text]", title.to_string()),
+            ("page[limit]", "5".to_string()),
+        ],
+        20,
+    )?;
+
+    Ok(kitsu_best_cover(&data, title))
+}
+
+/// The Kitsu search result whose titles best match `title`; see `rank_by_title`.
+fn kitsu_best_cover(data: &Value, title: &str) -> Option<CoverResult> {
+    let items = data.get("data").and_then(Value::as_array)?;
+    let hits = items
+        .iter()
+        .map(|item| {
+            let attrs = item.get("attributes").unwrap_or(&Value::Null);
+            let alts = attrs
+                .get("titles")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flat_map(|titles| titles.values())
+                .chain(
+                    attrs
+                        .get("abbreviatedTitles")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten(),
+                )
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect();
+            TitledHit {
+                item,
+                main: attrs
+                    .get("canonicalTitle")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                alts,
+                id: item.get("id").map(id_text).unwrap_or_default(),
+            }
+        })
+        .collect();
+    rank_by_title(hits, title, 0)
+        .into_iter()
+        .find_map(|(item, score)| {
+            let url = ["original", "large", "small", "tiny"]
+                .iter()
+                .find_map(|size| {
+                    item.pointer(&format!("/attributes/coverImage/{size}"))
+                        .and_then(Value::as_str)
+                })?;
+            Some(CoverResult {
+                source: "kitsu".to_string(),
+                url: url.to_string(),
+                note: None,
+                match_score: Some(score),
+            })
+        })
+}
+
+fn validate_cover_id(provider: &str, id: &str) -> Result<()> {
+    match provider {
+        "mangadex" if UUID_RE.is_match(id) => Ok(()),
+        "mangadex" => bail!("invalid MangaDex ID {id:?}: expected a UUID"),
+        "anilist" if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) => Ok(()),
+        "anilist" => bail!("invalid AniList ID {id:?}: expected a numeric ID"),
+        other => bail!("unknown cover ID provider {other:?}: expected mangadex or anilist"),
+    }
+}
+
+/// Fetches the cover for a specific series ID, skipping title search and scoring entirely.
+/// `provider` is `mangadex` (UUID) or `anilist` (numeric ID).
+pub fn fetch_cover_by_id(provider: &str, id: &str) -> Result<Option<CoverResult>> {
+    let provider = provider.trim().to_ascii_lowercase();
+    let id = id.trim();
+    validate_cover_id(&provider, id)?;
+
+    if provider == "mangadex" {
+        // Fail loudly on an unknown series rather than returning an empty cover list.
+        http_get_json(&format!("https://api.mangadex.org/manga/{id}"), &[], 20)
+            .with_context(|| format!("MangaDex has no manga with ID {id}"))?;
+        return mangadex_cover_for_manga(id, &CoverOptions::default());
+    }
+
+    let query = r#"
+    query ($id: Int) {
+      Media(id: $id, type: MANGA) {
+        id
+        coverImage { extraLarge large }
+      }
+    }
+    "#;
+    let numeric_id: u64 = id
+        .parse()
+        .with_context(|| format!("invalid AniList ID {id:?}"))?;
+    let payload = json!({
+        "query": query,
+        "variables": {
+            "id": numeric_id,
+        }
+    });
+    let resp = http_post_json("https://graphql.anilist.co", &payload, 20)
+        .with_context(|| format!("AniList has no manga with ID {id}"))?;
+    anilist_cover_from_response(&resp)
+}
+
+/// A pinned remote series, written `mangadex:<uuid>` or `anilist:<id>`. As a `CoverProvider`
+/// it ignores the title and fetches by ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverId {
+    pub provider: String,
+    pub id: String,
+}
+
+impl std::str::FromStr for CoverId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((provider, id)) = s.split_once(':') else {
+            bail!("invalid cover ID {s:?}: expected mangadex:<uuid> or anilist:<id>");
+        };
+        let provider = provider.trim().to_ascii_lowercase();
+        let id = id.trim().to_string();
+        validate_cover_id(&provider, &id)?;
+        Ok(Self { provider, id })
+    }
+}
+
+impl CoverProvider for CoverId {
+    fn name(&self) -> &str {
+        &self.provider
+    }
+
+    fn fetch(&self, _title: &str) -> Result<Option<CoverResult>> {
+        fetch_cover_by_id(&self.provider, &self.id)
+    }
+}
+
+/// A remote source of series cover art. Implement this to register a custom source (for
+/// example a self-hosted library server) alongside or instead of the built-in ones.
+pub trait CoverProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn fetch(&self, title: &str) -> Result<Option<CoverResult>>;
+}
+
+impl std::fmt::Debug for dyn CoverProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MangaDexProvider {
+    pub options: CoverOptions,
+}
+
+impl CoverProvider for MangaDexProvider {
+    fn name(&self) -> &str {
+        "mangadex"
+    }
+
+    fn fetch(&self, title: &str) -> Result<Option<CoverResult>> {
+        fetch_cover_mangadex(title, &self.options)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AniListProvider;
+
+impl CoverProvider for AniListProvider {
+    fn name(&self) -> &str {
+        "anilist"
+    }
+
+    fn fetch(&self, title: &str) -> Result<Option<CoverResult>> {
+        fetch_cover_anilist(title)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KitsuProvider;
+
+impl CoverProvider for KitsuProvider {
+    fn name(&self) -> &str {
+        "kitsu"
+    }
+
+    fn fetch(&self, title: &str) -> Result<Option<CoverResult>> {
+        fetch_cover_kitsu(title)
+    }
+}
+
+/// The built-in providers in priority order: MangaDex, AniList, Kitsu.
+pub fn default_cover_providers() -> Vec<Arc<dyn CoverProvider>> {
+    cover_providers(&CoverOptions::default())
+}
+
+/// The built-in providers, with MangaDex selecting covers according to `options`.
+pub fn cover_providers(options: &CoverOptions) -> Vec<Arc<dyn CoverProvider>> {
+    vec![
+        Arc::new(MangaDexProvider {
+            options: options.clone(),
+        }),
+        Arc::new(AniListProvider),
+        Arc::new(KitsuProvider),
+    ]
+}
+
+/// Built-in providers by name (`mangadex`, `anilist`, `kitsu`), in the given priority order.
+pub fn cover_providers_named(
+    names: &[String],
+    options: &CoverOptions,
+) -> Result<Vec<Arc<dyn CoverProvider>>> {
+    names
+        .iter()
+        .map(|name| -> Result<Arc<dyn CoverProvider>> {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "mangadex" => Ok(Arc::new(MangaDexProvider {
+                    options: options.clone(),
+                })),
+                "anilist" => Ok(Arc::new(AniListProvider)),
+                "kitsu" => Ok(Arc::new(KitsuProvider)),
+                _ => {
+                    bail!("unknown cover provider {name:?} (expected mangadex, anilist, or kitsu)")
+                }
+            }
+        })
+        .collect()
+}
+
+pub const COVER_CACHE_FILE_NAME: &str = "remote_covers.json";
+pub const COVER_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The platform's per-user config directory: `%APPDATA%` on Windows, otherwise
+/// `$XDG_CONFIG_HOME` or `~/.config` (macOS included, where command-line tools usually look).
+fn user_config_dir() -> Option<PathBuf> {
+    let non_empty = |var: &str| std::env::var_os(var).filter(|value| !value.is_empty());
+    if cfg!(target_os = "windows") {
+        return non_empty("APPDATA").map(PathBuf::from);
+    }
+    non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+}
+
+/// The platform's per-user cache directory: `%LOCALAPPDATA%` on Windows, `~/Library/Caches` on
+/// macOS, otherwise `$XDG_CACHE_HOME` or `~/.cache`.
+fn user_cache_dir() -> Option<PathBuf> {
+    let non_empty = |var: &str| std::env::var_os(var).filter(|value| !value.is_empty());
+    if cfg!(target_os = "windows") {
+        return non_empty("LOCALAPPDATA").map(PathBuf::from);
+    }
+    let home = home_dir();
+    if cfg!(target_os = "macos") {
+        return home.map(|home| home.join("Library").join("Caches"));
+    }
+    non_empty("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join(".cache")))
+}
+
+/// A remote hit's cache and title, and whether the hit came from that cache.
+#[derive(Debug)]
+struct PendingCover {
+    cache: CoverCache,
+    title: String,
+    cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedCover {
+    source: String,
+    url: String,
+    fetched_at: u64,
+}
+
+/// On-disk cache of resolved remote cover URLs, keyed by normalized series title.
+#[derive(Debug, Clone)]
+pub struct CoverCache {
+    pub path: PathBuf,
+    pub ttl: Duration,
+    /// Prefix for keys, so differently-derived lookups of the same title don't share entries.
+    pub namespace: Option<String>,
+}
+
+impl CoverCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ttl: COVER_CACHE_TTL,
+            namespace: None,
+        }
+    }
+
+    /// `<user cache dir>/manga_cleaner/remote_covers.json`, when a cache dir can be determined.
+    pub fn default_location() -> Option<Self> {
+        user_cache_dir().map(|dir| Self::new(dir.join("manga_cleaner").join(COVER_CACHE_FILE_NAME)))
+    }
+
+    fn key(&self, title: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{namespace}:{}", normalize_title(title)),
+            None => normalize_title(title),
+        }
+    }
+
+    fn load(&self) -> HashMap<String, CachedCover> {
+        fs::read(&self.path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns a fresh cached cover for `title`. Missing, stale, or corrupt entries yield `None`.
+    pub fn get(&self, title: &str) -> Option<CoverResult> {
+        let entry = self.load().remove(&self.key(title))?;
+        let age = unix_now().saturating_sub(entry.fetched_at);
+        if age > self.ttl.as_secs() {
+            return None;
+        }
+        Some(CoverResult {
+            source: entry.source,
+            url: entry.url,
+            note: None,
+            match_score: None,
+        })
+    }
+
+    pub fn put(&self, title: &str, cover: &CoverResult) -> Result<()> {
+        let entry = CachedCover {
+            source: cover.source.clone(),
+            url: cover.url.clone(),
+            fetched_at: unix_now(),
+        };
+        self.update(|entries| {
+            entries.insert(self.key(title), entry);
+            true
+        })
+    }
+
+    /// Drops the entry for `title`, e.g. when its URL no longer gives an image.
+    pub fn remove(&self, title: &str) -> Result<()> {
+        self.update(|entries| entries.remove(&self.key(title)).is_some())
+    }
+
+    /// Applies `change` to the entries on disk and writes them back when it returns `true`.
+    /// Bulk runs, the server and the native app share the file, so the read and the write
+    /// happen under `CoverCacheLock` and go through a temp file of this writer's own.
+    fn update(&self, change: impl FnOnce(&mut HashMap<String, CachedCover>) -> bool) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create cache dir: {}", parent.display()))?;
+        }
+        let _lock = CoverCacheLock::acquire(&self.path)?;
+        let mut entries = self.load();
+        if !change(&mut entries) {
+            return Ok(());
+        }
+        let data = serde_json::to_vec_pretty(&entries).context("failed to encode cover cache")?;
+        let tmp = unique_temp_path(&self.path);
+        let written = fs::write(&tmp, data)
+            .with_context(|| format!("failed to write cover cache: {}", tmp.display()))
+            .and_then(|()| {
+                fs::rename(&tmp, &self.path).with_context(|| {
+                    format!("failed to write cover cache: {}", self.path.display())
+                })
+            });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        written
+    }
+}
+
+/// A lock file next to the cover cache, held while one writer reads and rewrites it. A lock
+/// left behind by a crashed process is taken over once it is `STALE` old.
+struct CoverCacheLock {
+    path: PathBuf,
+}
+
+impl CoverCacheLock {
+    const STALE: Duration = Duration::from_secs(30);
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn acquire(cache: &Path) -> Result<Self> {
+        let mut name = cache.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        let path = cache.with_file_name(name);
+        let started = Instant::now();
+        loop {
+            match claim_path(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to lock {}", cache.display()));
+                }
+            }
+            let stale = fs::metadata(&path)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > Self::STALE);
+            if stale {
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if started.elapsed() > Self::WAIT {
+                bail!("timed out waiting for the lock on {}", cache.display());
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl Drop for CoverCacheLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A `.tmp` sibling of `path` that no other process or thread writes: the name carries the
+/// process id and a per-process counter.
+fn unique_temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Queries every provider concurrently and returns the hit from the earliest provider in
+/// `providers`, without waiting on slower providers further down the list. When nothing is found, the second element aggregates each provider's error.
+/// A fresh entry in `cache` short-circuits the network; new hits are written back to it.
+pub fn find_remote_cover(
+    title: &str,
+    providers: &[Arc<dyn CoverProvider>],
+    cache: Option<&CoverCache>,
+) -> (Option<CoverResult>, Option<String>) {
+    let (covers, err) = find_remote_covers(title, providers, cache);
+    (covers.into_iter().next(), err)
+}
+
+/// Like `find_remote_cover`, but returns every hit in `providers` order so a failed download
+/// can fall back to the next one: the best hit, and any from later providers that had
+/// answered by the time it was decided. A cached hit comes back alone.
+#[cfg(feature = "network")]
+pub fn find_remote_covers(
+    title: &str,
+    providers: &[Arc<dyn CoverProvider>],
+    cache: Option<&CoverCache>,
+) -> (Vec<CoverResult>, Option<String>) {
+    if is_offline() {
+        // Even a cached hit would need a download.
+        return (Vec::new(), Some("offline".to_string()));
+    }
+    if let Some(cache) = cache {
+        if let Some(cover) = cache.get(title) {
+            await_download(cache, title, &cover, true);
+            return (vec![cover], None);
+        }
+    }
+
+    // Each provider answers on its own thread. Once every provider ahead of the first hit has
+    // answered, nothing slower can beat it, so the lookup returns and leaves the rest running.
+    let cancel = current_cancel();
+    let (tx, rx) = mpsc::channel();
+    for (index, provider) in providers.iter().enumerate() {
+        let provider = Arc::clone(provider);
+        let cancel = cancel.clone();
+        let title = title.to_string();
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let result = match cancel {
+                Some(cancel) => with_cancel(cancel, || provider.fetch(&title)),
+                None => provider.fetch(&title),
+            };
+            let _ = tx.send((index, result));
+        });
+    }
+    drop(tx);
+
+    let mut results: Vec<Option<Result<Option<CoverResult>>>> =
+        providers.iter().map(|_| None).collect();
+    loop {
+        let deciding = results
+            .iter()
+            .find(|result| !matches!(result, Some(Ok(None) | Err(_))));
+        if !matches!(deciding, Some(None)) {
+            break;
+        }
+        match rx.recv() {
+            Ok((index, result)) => results[index] = Some(result),
+            // Every thread is gone; the ones that never answered panicked.
+            Err(_) => {
+                for result in results.iter_mut().filter(|result| result.is_none()) {
+                    *result = Some(Err(anyhow!("provider lookup panicked")));
+                }
+            }
+        }
+    }
+
+    let mut covers = Vec::new();
+    let mut errors = Vec::new();
+    for (provider, result) in providers.iter().zip(results) {
+        match result {
+            Some(Ok(Some(cover))) => covers.push(cover),
+            Some(Ok(None)) | None => {}
+            Some(Err(err)) => errors.push(format!("{}: {err:#}", provider.name())),
+        }
+    }
+
+    if !covers.is_empty() {
+        if let Some(cache) = cache {
+            for cover in &covers {
+                await_download(cache, title, cover, false);
+            }
+        }
+        (covers, None)
+    } else if errors.is_empty() {
+        (covers, None)
+    } else {
+        (covers, Some(errors.join("; ")))
+    }
+}
+
+/// Holds back caching `cover` until `apply_cover_decision` has downloaded it, so a URL that
+/// gives no image is never cached; a hit that came from the cache is dropped from it instead.
+#[cfg(feature = "network")]
+fn await_download(cache: &CoverCache, title: &str, cover: &CoverResult, cached: bool) {
+    if let Ok(mut pending) = PENDING_COVERS.lock() {
+        let pending_cover = PendingCover {
+            cache: cache.clone(),
+            title: title.to_string(),
+            cached,
+        };
+        pending.insert(cover.url.clone(), pending_cover);
+    }
+}
+
+/// Caches a downloaded remote hit, or drops a cached one whose download failed. A cache write
+/// failure only costs a refetch next run.
+fn settle_cached_cover(cover: &CoverResult, downloaded: bool) {
+    let Some(PendingCover {
+        cache,
+        title,
+        cached,
+    }) = PENDING_COVERS
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.remove(&cover.url))
+    else {
+        return;
+    };
+    let _ = match (downloaded, cached) {
+        (true, false) => cache.put(&title, cover),
+        (false, true) => cache.remove(&title),
+        _ => Ok(()),
+    };
+}
+
+/// Without the `network` feature there is nothing to query; the caller falls back to local covers.
+#[cfg(not(feature = "network"))]
+pub fn find_remote_covers(
+    _title: &str,
+    _providers: &[Arc<dyn CoverProvider>],
+    _cache: Option<&CoverCache>,
+) -> (Vec<CoverResult>, Option<String>) {
+    (
+        Vec::new(),
+        Some("built without the `network` feature".to_string()),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Rar,
+    SevenZip,
+    Tar,
+}
+
+impl ArchiveKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if has_known_ext(path, &[".cbz", ".zip"]) {
+            Some(ArchiveKind::Zip)
+        } else if has_known_ext(path, &[".cbr"]) {
+            Some(ArchiveKind::Rar)
+        } else if has_known_ext(path, &[".cb7"]) {
+            Some(ArchiveKind::SevenZip)
+        } else if has_known_ext(path, &[".cbt"]) {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+fn archive_entry_is_image(entry_name: &str) -> bool {
+    let lower = entry_name.to_ascii_lowercase();
+    if !IMAGE_EXTS.iter().any(|ext| lower.ends_with(ext)) {
+        return false;
+    }
+
+    for component in Path::new(entry_name).components() {
+        if let Component::Normal(part) = component {
+            let part_str = part.to_string_lossy();
+            if part_str == "__MACOSX" || is_hidden_or_macos_junk(&part_str) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn archive_kind(volume_file: &Path) -> Result<ArchiveKind> {
+    ArchiveKind::from_path(volume_file).ok_or_else(|| {
+        Error::Archive(anyhow!(
+            "unsupported archive format: {}",
+            file_name_text(volume_file)
+        ))
+        .into()
+    })
+}
+
+fn archive_entry_names(volume_file: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    match archive_kind(volume_file)? {
+        ArchiveKind::Zip => {
+            let file = fs::File::open(volume_file)
+                .with_context(|| format!("failed to open archive: {}", volume_file.display()))?;
+            let mut archive = ZipArchive::new(file)
+                .with_context(|| format!("failed to read archive: {}", volume_file.display()))?;
+            for idx in 0..archive.len() {
+                let entry = archive.by_index(idx)?;
+                if !entry.is_dir() {
+                    names.push(entry.name().to_string());
+                }
+            }
+        }
+        ArchiveKind::Rar => {
+            let archive = unrar::Archive::new(volume_file)
+                .open_for_listing()
+                .with_context(|| format!("failed to read archive: {}", volume_file.display()))?;
+            for header in archive {
+                let header = header.with_context(|| {
+                    format!("failed to list archive: {}", volume_file.display())
+                })?;
+                if header.is_file() {
+                    names.push(header.filename.to_string_lossy().into_owned());
+                }
+            }
+        }
+        ArchiveKind::SevenZip => {
+            let reader = SevenZReader::open(volume_file, Password::empty())
+                .with_context(|| format!("failed to read archive: {}", volume_file.display()))?;
+            for entry in &reader.archive().files {
+                if !entry.is_directory() && entry.has_stream() {
+                    names.push(entry.name().to_string());
+                }
+            }
+        }
+        ArchiveKind::Tar => {
+            let file = fs::File::open(volume_file)
+                .with_context(|| format!("failed to open archive: {}", volume_file.display()))?;
+            let mut archive = tar::Archive::new(file);
+            let entries = archive
+                .entries()
+                .with_context(|| format!("failed to read archive: {}", volume_file.display()))?;
+            for entry in entries {
+                let entry = entry.with_context(|| {
+                    format!("failed to list archive: {}", volume_file.display())
+                })?;
+                if entry.header().entry_type().is_file() {
+                    names.push(entry.path()?.to_string_lossy().into_owned());
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// The archive format named by the file's magic bytes, regardless of its extension.
+fn sniff_archive_kind(volume_file: &Path) -> Result<Option<ArchiveKind>> {
+    // Tar has no leading magic; "ustar" sits in the first header block instead.
+    let mut magic = Vec::with_capacity(262);
+    let file = fs::File::open(volume_file)
+        .with_context(|| format!("failed to open archive: {}", volume_file.display()))?;
+    file.take(262)
+        .read_to_end(&mut magic)
+        .with_context(|| format!("failed to read archive: {}", volume_file.display()))?;
+    Ok(if magic.starts_with(b"PK") {
+        Some(ArchiveKind::Zip)
+    } else if magic.starts_with(b"Rar!") {
+        Some(ArchiveKind::Rar)
+    } else if magic.starts_with(b"7z\xbc\xaf\x27\x1c") {
+        Some(ArchiveKind::SevenZip)
+    } else if magic.get(257..262) == Some(&b"ustar"[..]) {
+        Some(ArchiveKind::Tar)
+    } else {
+        None
+    })
+}
+
+/// Opens `volume_file` and reads its first image entry, so truncated archives or archives with
+/// the wrong extension are caught before anything is moved.
+pub fn check_archive(volume_file: &Path) -> Result<()> {
+    let expected = archive_kind(volume_file)?;
+    if let Some(actual) = sniff_archive_kind(volume_file)? {
+        if actual != expected {
+            let (label, ext) = match actual {
+                ArchiveKind::Zip => ("ZIP", ".cbz"),
+                ArchiveKind::Rar => ("RAR", ".cbr"),
+                ArchiveKind::SevenZip => ("7z", ".cb7"),
+                ArchiveKind::Tar => ("tar", ".cbt"),
+            };
+            bail!("file is really a {label} archive; rename it to {ext}");
+        }
+    }
+
+    let entries = archive_image_entries(volume_file)?;
+    let Some(first) = entries.first() else {
+        bail!("archive has no image entries");
+    };
+    let bytes = read_archive_entry(volume_file, first)?;
+    image::guess_format(&bytes)
+        .with_context(|| format!("first image entry is not a readable image: {first}"))?;
+    Ok(())
+}
+
+fn archive_image_entries(volume_file: &Path) -> Result<Vec<String>> {
+    let mut entries: Vec<String> = archive_entry_names(volume_file)?
+        .into_iter()
+        .filter(|name| archive_entry_is_image(name))
+        .collect();
+    natural_sort_strings(&mut entries);
+    Ok(entries)
+}
+
+fn read_archive_entry(volume_file: &Path, entry_name: &str) -> Result<Vec<u8>> {
+    match archive_kind(volume_file)? {
+        ArchiveKind::Zip => {
+            let file = fs::File::open(volume_file)
+                .with_context(|| format!("failed to open archive: {}", volume_file.display()))?;
+            let mut archive = ZipArchive::new(file)
+                .with_context(|| format!("failed to read archive: {}", volume_file.display()))?;
+            let mut entry = archive
+                .by_name(entry_name)
+                .with_context(|| format!("missing image entry in archive: {entry_name}"))?;
+
+            let mut bytes = Vec::new();
+            entry
+                .read_to_end(&mut bytes)
+                .context("failed to read image from archive")?;
+            Ok(bytes)
+        }
+        ArchiveKind::Rar => {
+            let mut archive = unrar::Archive::new(volume_file)
+                .open_for_processing()
+                .with_context(|| format!("failed to open archive: {}", volume_file.display()))?;
+            while let Some(header) = archive
+                .read_header()
+                .with_context(|| format!("failed to read archive: {}", volume_file.display()))?
+            {
+                if header.entry().filename.to_string_lossy() == entry_name {
+                    let (bytes, _) = header.read().context("failed to read image from archive")?;
+                    return Ok(bytes);
+                }
+                archive = header.skip().with_context(|| {
+                    format!("failed to read archive: {}", volume_file.display())
+                })?;
+            }
+            bail!("missing image entry in archive: {entry_name}")
+        }
+        ArchiveKind::SevenZip => {
+            let mut reader = SevenZReader::open(volume_file, Password::empty())
+                .with_context(|| format!("failed to open archive: {}", volume_file.display()))?;
+            let mut found: Option<Vec<u8>> = None;
+            reader
+                .for_each_entries(|entry, data| {
+                    if entry.name() == entry_name {
+                        let mut bytes = Vec::new();
+                        data.read_to_end(&mut bytes)?;
+                        found = Some(bytes);
+                        return Ok(false);
+                    }
+                    // Entries share one decoder stream, so skipped entries must still be drained.
+                    io::copy(data, &mut io::sink())?;
+                    Ok(true)
+                })
+                .context("failed to read image from archive")?;
+            found.ok_or_else(|| anyhow!("missing image entry in archive: {entry_name}"))
+        }
+        ArchiveKind::Tar => {
+            let file = fs::File::open(volume_file)
+                .with_context(|| format!("failed to open archive: {}", volume_file.display()))?;
+            let mut archive = tar::Archive::new(file);
+            let entries = archive
+                .entries()
+                .with_context(|| format!("failed to read archive: {}", volume_file.display()))?;
+            for entry in entries {
+                let mut entry = entry.with_context(|| {
+                    format!("failed to read archive: {}", volume_file.display())
+                })?;
+                if entry.path()?.to_string_lossy() == entry_name {
+                    let mut bytes = Vec::new();
+                    entry
+                        .read_to_end(&mut bytes)
+                        .context("failed to read image from archive")?;
+                    return Ok(bytes);
+                }
+            }
+            bail!("missing image entry in archive: {entry_name}")
+        }
+    }
+}
+
+/// Selects the cover from the `volume_index`-th volume (natural order) and its `page_index`-th
+/// image entry. Both indexes are zero-based.
+pub fn find_volume_cover(
+    series_dir: &Path,
+    volume_index: usize,
+    page_index: usize,
+) -> Result<Option<VolumeCoverResult>> {
+    find_volume_cover_with(series_dir, volume_index, page_index, false)
+}
+
+/// `find_volume_cover` over `scan_volumes_with(series_dir, recursive)`.
+pub fn find_volume_cover_with(
+    series_dir: &Path,
+    volume_index: usize,
+    page_index: usize,
+    recursive: bool,
+) -> Result<Option<VolumeCoverResult>> {
+    find_volume_cover_matching(
+        series_dir,
+        volume_index,
+        page_index,
+        recursive,
+        &ExtensionSet::volumes(),
+    )
+}
+
+fn find_volume_cover_matching(
+    series_dir: &Path,
+    volume_index: usize,
+    page_index: usize,
+    recursive: bool,
+    exts: &ExtensionSet,
+) -> Result<Option<VolumeCoverResult>> {
+    let volumes = scan_volumes_matching(series_dir, recursive, exts)?;
+    if volumes.is_empty() {
+        return Ok(None);
+    }
+
+    let volume = volumes.get(volume_index).cloned().ok_or_else(|| {
+        anyhow!(
+            "volume index {volume_index} is out of range ({} volumes available)",
+            volumes.len()
+        )
+    })?;
+
+    let images = archive_image_entries(&volume)?;
+    if images.is_empty() {
+        return Err(Error::Archive(anyhow!(
+            "no image files found in volume archive: {}",
+            file_name_text(&volume)
+        ))
+        .into());
+    }
+
+    let image_count = images.len();
+    let image_entry = images.into_iter().nth(page_index).ok_or_else(|| {
+        anyhow!(
+            "page index {page_index} is out of range ({image_count} images available in {})",
+            file_name_text(&volume)
+        )
+    })?;
+
+    Ok(Some(VolumeCoverResult {
+        volume_index,
+        volume_file: volume,
+        image_entry,
+        output_file: series_dir.join("cover.jpg"),
+        keep_format: false,
+        crop: None,
+    }))
+}
+
+pub fn find_first_volume_cover(series_dir: &Path) -> (Option<VolumeCoverResult>, Option<String>) {
+    match find_volume_cover(series_dir, 0, 0) {
+        Ok(result) => (result, None),
+        Err(err) => (None, Some(err.to_string())),
+    }
+}
+
+/// Quality used for every JPEG written unless `CoverStyle::jpeg_quality` says otherwise.
+pub const JPEG_QUALITY: u8 = 95;
+
+fn save_jpeg(image: &DynamicImage, out_path: &Path) -> Result<()> {
+    save_jpeg_with_quality(image, out_path, JPEG_QUALITY)
+}
+
+fn save_jpeg_with_quality(image: &DynamicImage, out_path: &Path, quality: u8) -> Result<()> {
+    if let Some(parent) = out_path.parent() {
+        ensure_dir(parent)?;
+    }
+
+    let rgb = image.to_rgb8();
+    let rendered = DynamicImage::ImageRgb8(rgb);
+
+    write_atomically(out_path, |out| {
+        let mut encoder = JpegEncoder::new_with_quality(out, quality.clamp(1, 100));
+        encoder
+            .encode_image(&rendered)
+            .with_context(|| format!("failed to encode JPEG: {}", out_path.display()))
+    })
+}
+
+/// Decodes an archive image entry. Animated GIF/WebP entries yield their first
+/// frame, flattened onto white so transparent areas don't turn black in the JPEG.
+fn decode_cover_image(bytes: &[u8]) -> Result<DynamicImage> {
+    let format = image::guess_format(bytes).context("unrecognized image format")?;
+    if !format.reading_enabled() {
+        bail!("{format:?} images are not supported by this build");
+    }
+
+    let first_frame = match format {
+        ImageFormat::Gif => {
+            let decoder = GifDecoder::new(io::Cursor::new(bytes))?;
+            Some(decoder.into_frames().next())
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(io::Cursor::new(bytes))?;
+            decoder
+                .has_animation()
+                .then(|| decoder.into_frames().next())
+        }
+        _ => None,
+    };
+
+    match first_frame {
+        Some(frame) => {
+            let frame = frame.context("animated image has no frames")??;
+            Ok(flatten_onto_white(&frame.into_buffer()))
+        }
+        None => Ok(image::load_from_memory_with_format(bytes, format)?),
+    }
+}
+
+/// Whether a `width` x `height` page is wider than `ratio` times its height.
+fn is_spread(width: u32, height: u32, ratio: f32) -> bool {
+    width as f32 > height as f32 * ratio
+}
+
+fn crop_half(image: &DynamicImage, half: PageHalf) -> DynamicImage {
+    let width = (image.width() / 2).max(1);
+    let x = match half {
+        PageHalf::Left => 0,
+        PageHalf::Right => image.width() - width,
+    };
+    image.crop_imm(x, 0, width, image.height())
+}
+
+/// The first image entry after `cover.image_entry` that isn't a spread, with its format.
+fn next_single_page(
+    cover: &VolumeCoverResult,
+    ratio: f32,
+) -> Result<Option<(String, Option<ImageFormat>)>> {
+    let entries = archive_image_entries(&cover.volume_file)?;
+    let after = entries
+        .iter()
+        .position(|entry| *entry == cover.image_entry)
+        .map_or(entries.len(), |index| index + 1);
+    for entry in &entries[after..] {
+        let bytes = read_archive_entry(&cover.volume_file, entry)?;
+        let Ok((width, height)) = ImageReader::new(io::Cursor::new(&bytes))
+            .with_guessed_format()?
+            .into_dimensions()
+        else {
+            continue;
+        };
+        if !is_spread(width, height, ratio) {
+            return Ok(Some((entry.clone(), image::guess_format(&bytes).ok())));
+        }
+    }
+    Ok(None)
+}
+
+fn flatten_onto_white(image: &RgbaImage) -> DynamicImage {
+    let mut out = RgbImage::new(image.width(), image.height());
+    for (src, dst) in image.pixels().zip(out.pixels_mut()) {
+        let [r, g, b, a] = src.0;
+        let blend =
+            |c: u8| ((u16::from(c) * u16::from(a) + 255 * (255 - u16::from(a))) / 255) as u8;
+        *dst = Rgb([blend(r), blend(g), blend(b)]);
+    }
+    DynamicImage::ImageRgb8(out)
+}
+
+pub fn write_volume_cover(result: &VolumeCoverResult) -> Result<PathBuf, Error> {
+    if let Some(parent) = result.output_file.parent() {
+        ensure_dir(parent)?;
+    }
+
+    let bytes = read_archive_entry(&result.volume_file, &result.image_entry)?;
+    let image = decode_cover_image(&bytes).context("failed to decode image from archive")?;
+    let same_format = match image::guess_format(&bytes) {
+        Ok(ImageFormat::Jpeg) => has_known_ext(&result.output_file, &[".jpg", ".jpeg"]),
+        Ok(ImageFormat::Png) => has_known_ext(&result.output_file, &[".png"]),
+        _ => false,
+    };
+    if let Some(half) = result.crop {
+        save_jpeg(&crop_half(&image, half), &result.output_file)?;
+    } else if result.keep_format && same_format {
+        write_atomically(&result.output_file, |out| {
+            out.write_all(&bytes)
+                .with_context(|| format!("failed to write cover: {}", result.output_file.display()))
+        })?;
+    } else {
+        save_jpeg(&image, &result.output_file)?;
+    }
+    Ok(result.output_file.clone())
+}
+
+pub fn ensure_cover_jpg(series_dir: &Path, selected_cover: &Path) -> Result<PathBuf, Error> {
+    let cover_jpg = series_dir.join("cover.jpg");
+    let selected_resolved = selected_cover
+        .canonicalize()
+        .unwrap_or_else(|_| selected_cover.to_path_buf());
+    let cover_resolved = cover_jpg
+        .canonicalize()
+        .unwrap_or_else(|_| cover_jpg.clone());
+
+    if selected_resolved == cover_resolved {
+        return Ok(cover_jpg);
+    }
+
+    let image = ImageReader::open(selected_cover)
+        .with_context(|| format!("failed to open image: {}", selected_cover.display()))?
+        .decode()
+        .context("failed to decode selected cover image")?;
+
+    save_jpeg(&image, &cover_jpg)?;
+    Ok(cover_jpg)
+}
+
+pub fn open_image(path: &Path) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut cmd = Command::new("open");
+        cmd.arg(path);
+        cmd
+    };
+
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg("start").arg("").arg(path);
+        cmd
+    };
+
+    #[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+    let mut command = {
+        let mut cmd = Command::new("xdg-open");
+        cmd.arg(path);
+        cmd
+    };
+
+    let status = command
+        .status()
+        .with_context(|| format!("failed to launch image viewer for {}", path.display()))?;
+
+    if !status.success() {
+        bail!("image viewer exited with status: {status}");
+    }
+
+    Ok(())
+}
+
+pub fn choose_series_cover(series_dir: &Path) -> Result<Option<PathBuf>> {
+    choose_series_cover_with(series_dir, &ExtensionSet::images())
+}
+
+/// `choose_series_cover`, falling back to the first image with one of `exts` instead of
+/// `IMAGE_EXTS`. The `COVER_CANDIDATES` names always count.
+pub fn choose_series_cover_with(series_dir: &Path, exts: &ExtensionSet) -> Result<Option<PathBuf>> {
+    for name in COVER_CANDIDATES {
+        let candidate = series_dir.join(name);
+        if candidate.is_file() {
+            return Ok(Some(candidate));
+        }
+    }
+
+    let mut images = Vec::new();
+    for entry in fs::read_dir(series_dir)
+        .with_context(|| format!("failed to read directory: {}", series_dir.display()))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let name = file_name_text(&path);
+        if is_hidden_or_macos_junk(&name) {
+            continue;
+        }
+        if exts.matches(&path) {
+            images.push(path);
+        }
+    }
+
+    if images.is_empty() {
+        return Ok(None);
+    }
+
+    natural_sort_paths(&mut images);
+    Ok(images.into_iter().next())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoverMode {
+    /// Extract, reuse, or download a series cover as usual.
+    #[default]
+    Resolve,
+    /// Don't look for a cover at all: no archive reads, no network, no batch covers.
+    Skip,
+}
+
+/// What cover extraction does with a page that is wider than `SeriesCoverOptions::spread_ratio`
+/// times its height, typically a front and back cover scanned as one double-page spread.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpreadHandling {
+    /// Use the spread as it is.
+    #[default]
+    Keep,
+    /// Use the next page of the volume that isn't a spread, or the spread if there is none.
+    Skip,
+    /// Use one half of the spread.
+    Crop(PageHalf),
+    /// Use the half that is read first: the left one, or the right one when the series reads
+    /// right to left (`SeriesCoverOptions::reading_direction`).
+    CropFront,
+}
+
+impl SpreadHandling {
+    fn crop_half(self, direction: ReadingDirection) -> Option<PageHalf> {
+        match self {
+            SpreadHandling::Keep | SpreadHandling::Skip => None,
+            SpreadHandling::Crop(half) => Some(half),
+            SpreadHandling::CropFront => Some(match direction {
+                ReadingDirection::Ltr => PageHalf::Left,
+                ReadingDirection::Rtl => PageHalf::Right,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageHalf {
+    Left,
+    Right,
+}
+
+impl std::str::FromStr for SpreadHandling {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "keep" => Ok(SpreadHandling::Keep),
+            "skip" => Ok(SpreadHandling::Skip),
+            "crop" => Ok(SpreadHandling::CropFront),
+            "crop-left" => Ok(SpreadHandling::Crop(PageHalf::Left)),
+            "crop-right" => Ok(SpreadHandling::Crop(PageHalf::Right)),
+            other => bail!(
+                "invalid spread handling {other:?}: expected keep, skip, crop, crop-left, or crop-right"
+            ),
+        }
+    }
+}
+
+/// Page order of a series. Only spread cropping (`SpreadHandling::CropFront`) depends on it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadingDirection {
+    #[default]
+    Ltr,
+    /// Manga read right to left, whose spreads start with the right-hand page.
+    Rtl,
+}
+
+impl std::str::FromStr for ReadingDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ltr" => Ok(ReadingDirection::Ltr),
+            "rtl" => Ok(ReadingDirection::Rtl),
+            other => bail!("invalid reading direction {other:?}: expected ltr or rtl"),
+        }
+    }
+}
+
+/// Pages wider than they are tall count as spreads unless `SeriesCoverOptions::spread_ratio`
+/// says otherwise.
+pub const DEFAULT_SPREAD_RATIO: f32 = 1.0;
+
+#[derive(Debug)]
+pub struct SeriesCoverOptions {
+    pub mode: CoverMode,
+    /// Zero-based volume (in natural order) to extract the cover from.
+    pub volume_index: usize,
+    /// Zero-based image entry within that volume.
+    pub page_index: usize,
+    /// Remote providers consulted, in priority order, when no local cover exists.
+    pub providers: Vec<Arc<dyn CoverProvider>>,
+    /// Cache for remote lookups. Every run hits the network when `None`.
+    pub cache: Option<CoverCache>,
+    /// Search title for remote lookups instead of the folder name.
+    pub title_override: Option<String>,
+    /// Extract from the archive even when the series folder already has a cover,
+    /// overwriting `cover.jpg`.
+    pub force_extract: bool,
+    /// Look for the volume to extract from in subfolders too; see `scan_volumes_with`.
+    pub recursive: bool,
+    /// Shared folder of `<Series Name>.jpg` covers, checked before extracting or searching
+    /// remotely; see `find_cover_in_dir`.
+    pub cover_dir: Option<PathBuf>,
+    /// Volumes to extract from; see `PlanOptions::volume_exts`.
+    pub volume_exts: ExtensionSet,
+    /// Images in the series folder and `cover_dir` that can be the cover.
+    pub image_exts: ExtensionSet,
+    /// Extract a JPEG or PNG page without re-encoding it: a PNG becomes `cover.png` (unless
+    /// the folder already has a `cover.jpg` or `cover.png`), a JPEG is copied as it is.
+    /// Other formats are still converted to `cover.jpg`, and batch covers are always JPEG.
+    pub keep_cover_format: bool,
+    /// What to do when the extracted page is a double-page spread.
+    pub spread: SpreadHandling,
+    /// Width-to-height ratio above which a page counts as a spread.
+    pub spread_ratio: f32,
+    /// Decides which half `SpreadHandling::CropFront` keeps.
+    pub reading_direction: ReadingDirection,
+}
+
+impl Default for SeriesCoverOptions {
+    fn default() -> Self {
+        Self {
+            mode: CoverMode::Resolve,
+            volume_index: 0,
+            page_index: 0,
+            providers: default_cover_providers(),
+            cache: CoverCache::default_location(),
+            title_override: None,
+            force_extract: false,
+            recursive: false,
+            cover_dir: None,
+            volume_exts: ExtensionSet::volumes(),
+            image_exts: ExtensionSet::images(),
+            keep_cover_format: false,
+            spread: SpreadHandling::Keep,
+            spread_ratio: DEFAULT_SPREAD_RATIO,
+            reading_direction: ReadingDirection::Ltr,
+        }
+    }
+}
+
+/// Where the series cover will come from, decided without writing anything to the series
+/// folder. `apply_cover_decision` performs the extraction or download.
+#[derive(Debug, Clone)]
+pub enum CoverDecision {
+    /// An image that is already in the series folder.
+    Existing(PathBuf),
+    /// Extract an archive page to `VolumeCoverResult::output_file`.
+    Extract(VolumeCoverResult),
+    /// Download a remote cover to `output`, falling back to the other providers' hits in order
+    /// when it fails or isn't an image.
+    Download {
+        result: CoverResult,
+        fallbacks: Vec<CoverResult>,
+        output: PathBuf,
+    },
+    /// Copy an image from the shared cover directory to `output`.
+    Copy { source: PathBuf, output: PathBuf },
+    /// No cover was found (or the cover step is skipped); batches get none.
+    None,
+}
+
+impl CoverDecision {
+    /// The file the series cover will be read from once the decision is applied.
+    pub fn planned_path(&self) -> Option<&Path> {
+        match self {
+            CoverDecision::Existing(path) => Some(path),
+            CoverDecision::Extract(cover) => Some(&cover.output_file),
+            CoverDecision::Download { output, .. } | CoverDecision::Copy { output, .. } => {
+                Some(output)
+            }
+            CoverDecision::None => None,
+        }
+    }
+
+    /// The same decision with its extraction or download written to `output` instead.
+    fn redirected(&self, output: PathBuf) -> CoverDecision {
+        match self {
+            CoverDecision::Extract(cover) => CoverDecision::Extract(VolumeCoverResult {
+                output_file: output,
+                ..cover.clone()
+            }),
+            CoverDecision::Download {
+                result, fallbacks, ..
+            } => CoverDecision::Download {
+                result: result.clone(),
+                fallbacks: fallbacks.clone(),
+                output,
+            },
+            CoverDecision::Copy { source, .. } => CoverDecision::Copy {
+                source: source.clone(),
+                output,
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// What applying the decision will do, e.g. "extract 001.png from Series v01.cbz to
+    /// cover.jpg", for plans and dry runs that stop before anything is written.
+    pub fn describe(&self) -> String {
+        match self {
+            CoverDecision::Existing(path) => format!("use the existing {}", file_name_text(path)),
+            CoverDecision::Extract(cover) => format!(
+                "extract {} from {} to {}",
+                cover.image_entry,
+                file_name_text(&cover.volume_file),
+                file_name_text(&cover.output_file)
+            ),
+            CoverDecision::Download { result, output, .. } => format!(
+                "download the {} cover ({}) to {}",
+                result.source,
+                result.url,
+                file_name_text(output)
+            ),
+            CoverDecision::Copy { source, output } => {
+                format!("copy {} to {}", source.display(), file_name_text(output))
+            }
+            CoverDecision::None => "none; batches get no cover".to_string(),
+        }
+    }
+
+    /// Where the cover comes from, as recorded in batch metadata.
+    pub fn source_label(&self) -> Option<String> {
+        match self {
+            CoverDecision::Existing(path) | CoverDecision::Copy { source: path, .. } => {
+                Some(path.display().to_string())
+            }
+            CoverDecision::Extract(cover) => Some(format!(
+                "{}:{}",
+                file_name_text(&cover.volume_file),
+                cover.image_entry
+            )),
+            CoverDecision::Download { result, .. } => {
+                Some(format!("{} {}", result.source, result.url))
+            }
+            CoverDecision::None => None,
+        }
+    }
+
+    /// Image bytes for previewing the cover before it is written. Remote covers have none
+    /// until they are downloaded.
+    pub fn preview_bytes(&self) -> Result<Option<Vec<u8>>> {
+        match self {
+            CoverDecision::Existing(path) | CoverDecision::Copy { source: path, .. } => {
+                fs::read(path)
+                    .map(Some)
+                    .with_context(|| format!("failed to read cover: {}", path.display()))
+            }
+            CoverDecision::Extract(cover) => {
+                let bytes = read_archive_entry(&cover.volume_file, &cover.image_entry)?;
+                let Some(half) = cover.crop else {
+                    return Ok(Some(bytes));
+                };
+                let image = crop_half(&decode_cover_image(&bytes)?, half);
+                let mut cropped = Vec::new();
+                image
+                    .write_to(&mut io::Cursor::new(&mut cropped), ImageFormat::Png)
+                    .context("failed to encode cover preview")?;
+                Ok(Some(cropped))
+            }
+            CoverDecision::Download { .. } | CoverDecision::None => Ok(None),
+        }
+    }
+}
+
+/// Finds `<title>.<image ext>` in `cover_dir`, comparing names with `normalize_title` so
+/// `Series Name` matches `series-name.jpg`. The first match in natural order wins.
+pub fn find_cover_in_dir(cover_dir: &Path, titles: &[&str]) -> Result<Option<PathBuf>> {
+    find_cover_in_dir_matching(cover_dir, titles, &ExtensionSet::images())
+}
+
+fn find_cover_in_dir_matching(
+    cover_dir: &Path,
+    titles: &[&str],
+    exts: &ExtensionSet,
+) -> Result<Option<PathBuf>> {
+    let wanted: Vec<String> = titles
+        .iter()
+        .map(|title| normalize_title(title))
+        .filter(|title| !title.is_empty())
+        .collect();
+    if wanted.is_empty() {
+        return Ok(None);
+    }
+
+    let mut matches = Vec::new();
+    for entry in fs::read_dir(cover_dir)
+        .with_context(|| format!("failed to read cover directory: {}", cover_dir.display()))?
+    {
+        let path = entry?.path();
+        if !path.is_file() || !exts.matches(&path) {
+            continue;
+        }
+        if is_hidden_or_macos_junk(&file_name_text(&path)) {
+            continue;
+        }
+        let stem = path
+            .file_stem()
+            .map(|stem| normalize_title(&stem.to_string_lossy()))
+            .unwrap_or_default();
+        if wanted.contains(&stem) {
+            matches.push(path);
+        }
+    }
+
+    matches.sort_by(|a, b| compare_ignore_case(&file_name_text(a), &file_name_text(b)));
+    Ok(matches.into_iter().next())
+}
+
+/// Copies `source` to `output`. PNG, WebP and other non-JPEG images are re-encoded so a
+/// `cover.jpg` output really is a JPEG.
+fn copy_cover(source: &Path, output: &Path) -> Result<()> {
+    if let Some(parent) = output.parent() {
+        ensure_dir(parent)?;
+    }
+    let is_jpeg_output = has_known_ext(output, &[".jpg", ".jpeg"]);
+    let bytes = fs::read(source).with_context(|| format!("failed to read {}", source.display()))?;
+    if is_jpeg_output
+        && matches!(image::guess_format(&bytes), Ok(format) if format != ImageFormat::Jpeg)
+    {
+        let image = decode_cover_image(&bytes)
+            .with_context(|| format!("failed to decode cover: {}", source.display()))?;
+        return save_jpeg(&image, output);
+    }
+    write_atomically(output, |out| {
+        out.write_all(&bytes).with_context(|| {
+            format!(
+                "failed to copy {} to {}",
+                source.display(),
+                output.display()
+            )
+        })
+    })
+}
+
+/// The decision for a cover image the user picked by hand. The file must decode; one inside
+/// the series folder is used where it is, anything else is copied in as `cover.jpg`.
+pub fn manual_cover_decision(series_dir: &Path, image: &Path) -> Result<CoverDecision> {
+    let bytes = fs::read(image).with_context(|| format!("failed to read {}", image.display()))?;
+    decode_cover_image(&bytes)
+        .with_context(|| format!("{} is not a readable image", image.display()))?;
+    if image.parent() == Some(series_dir) {
+        return Ok(CoverDecision::Existing(image.to_path_buf()));
+    }
+    Ok(CoverDecision::Copy {
+        source: image.to_path_buf(),
+        output: series_dir.join("cover.jpg"),
+    })
+}
+
+fn shared_cover(
+    options: &SeriesCoverOptions,
+    title: &str,
+    override_title: Option<&str>,
+) -> Result<Option<PathBuf>> {
+    let Some(cover_dir) = &options.cover_dir else {
+        return Ok(None);
+    };
+    let titles: Vec<&str> = [Some(title), override_title]
+        .into_iter()
+        .flatten()
+        .collect();
+    find_cover_in_dir_matching(cover_dir, &titles, &options.image_exts)
+}
+
+/// Picks the series cover the way `ensure_series_cover` does, but without extracting or
+/// downloading it. Remote providers are still queried (and their cache updated).
+pub fn decide_series_cover(
+    series_dir: &Path,
+    title: &str,
+    options: &SeriesCoverOptions,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<CoverDecision, Error> {
+    if options.mode == CoverMode::Skip {
+        log(LogEvent::CoverNote(
+            "Cover step skipped; batches will get no cover.".to_string(),
+        ));
+        return Ok(CoverDecision::None);
+    }
+
+    if !options.force_extract {
+        if let Some(existing) = choose_series_cover_with(series_dir, &options.image_exts)? {
+            return Ok(CoverDecision::Existing(existing));
+        }
+    }
+
+    let override_title = options
+        .title_override
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty());
+
+    if !options.force_extract {
+        if let Some(source) = shared_cover(options, title, override_title)? {
+            return Ok(CoverDecision::Copy {
+                source,
+                output: series_dir.join("cover.jpg"),
+            });
+        }
+    }
+
+    let started = Instant::now();
+    let (first_vol_cover, mut first_vol_err) = match find_volume_cover_matching(
+        series_dir,
+        options.volume_index,
+        options.page_index,
+        options.recursive,
+        &options.volume_exts,
+    ) {
+        Ok(result) => (result, None),
+        Err(err) => (None, Some(err.to_string())),
+    };
+
+    if let Some(cover) = first_vol_cover {
+        // Decode in memory so an unreadable page falls through to the other sources now
+        // rather than failing when the cover is written.
+        let bytes = read_archive_entry(&cover.volume_file, &cover.image_entry);
+        log(LogEvent::Timing {
+            phase: Phase::ArchiveRead,
+            elapsed: started.elapsed(),
+        });
+        let started = Instant::now();
+        let decoded = bytes.and_then(|bytes| {
+            let image = decode_cover_image(&bytes)?;
+            Ok((
+                image.width(),
+                image.height(),
+                image::guess_format(&bytes).ok(),
+            ))
+        });
+        log(LogEvent::Timing {
+            phase: Phase::Decode,
+            elapsed: started.elapsed(),
+        });
+        match decoded {
+            Ok((width, height, mut format)) => {
+                let mut cover = cover;
+                if is_spread(width, height, options.spread_ratio) {
+                    let crop = options.spread.crop_half(options.reading_direction);
+                    if options.spread == SpreadHandling::Skip {
+                        // Best effort: an unreadable later page just keeps the spread.
+                        if let Ok(Some((entry, entry_format))) =
+                            next_single_page(&cover, options.spread_ratio)
+                        {
+                            log(LogEvent::CoverNote(format!(
+                                "{} is a double-page spread; using {entry} instead",
+                                cover.image_entry
+                            )));
+                            cover.image_entry = entry;
+                            format = entry_format;
+                        }
+                    } else if let Some(half) = crop {
+                        log(LogEvent::CoverNote(format!(
+                            "{} is a double-page spread; using its {} half",
+                            cover.image_entry,
+                            match half {
+                                PageHalf::Left => "left",
+                                PageHalf::Right => "right",
+                            }
+                        )));
+                        cover.crop = Some(half);
+                    }
+                }
+                // A cropped page is re-encoded, so only an untouched PNG keeps its format.
+                if options.keep_cover_format && cover.crop.is_none() {
+                    cover.keep_format = true;
+                    let taken = ["cover.jpg", "cover.png"]
+                        .iter()
+                        .any(|name| series_dir.join(name).exists());
+                    if format == Some(ImageFormat::Png) && !taken {
+                        cover.output_file = series_dir.join("cover.png");
+                    }
+                }
+                return Ok(CoverDecision::Extract(cover));
+            }
+            Err(err) => first_vol_err = Some(format!("{err:#}")),
+        }
+    }
+
+    // Without forcing, the folder and cover directory were already checked above.
+    if options.force_extract {
+        if let Some(existing) = choose_series_cover_with(series_dir, &options.image_exts)? {
+            return Ok(CoverDecision::Existing(existing));
+        }
+        if let Some(source) = shared_cover(options, title, override_title)? {
+            return Ok(CoverDecision::Copy {
+                source,
+                output: series_dir.join("cover.jpg"),
+            });
+        }
+    }
+    let started = Instant::now();
+    let (remote_covers, last_err) = match override_title {
+        Some(search_title) => {
+            let cache = options.cache.clone().map(|cache| CoverCache {
+                namespace: Some("title-override".to_string()),
+                ..cache
+            });
+            find_remote_covers(search_title, &options.providers, cache.as_ref())
+        }
+        None => find_remote_covers(title, &options.providers, options.cache.as_ref()),
+    };
+    log(LogEvent::Timing {
+        phase: Phase::RemoteLookup,
+        elapsed: started.elapsed(),
+    });
+    let mut remote_covers = remote_covers.into_iter();
+    if let Some(result) = remote_covers.next() {
+        if let Some(note) = &result.note {
+            log(LogEvent::CoverNote(note.clone()));
+        }
+        if let Some(score) = result.match_score {
+            let source = match result.source.as_str() {
+                "mangadex" => "MangaDex",
+                "anilist" => "AniList",
+                "kitsu" => "Kitsu",
+                other => other,
+            };
+            log(LogEvent::CoverNote(format!(
+                "{source} match score {score}/{MANGADEX_MAX_SCORE} ({})",
+                mangadex_score_label(score)
+            )));
+        }
+        return Ok(CoverDecision::Download {
+            result,
+            fallbacks: remote_covers.collect(),
+            output: series_dir.join("cover.jpg"),
+        });
+    }
+
+    if let Some(err) = first_vol_err {
+        log(LogEvent::Warn(format!(
+            "Failed to extract volume cover. Last error: {err}"
+        )));
+    }
+
+    if let Some(err) = last_err {
+        log(LogEvent::Warn(format!(
+            "Failed to download series cover. Last error: {err}"
+        )));
+    } else {
+        log(LogEvent::Warn(
+            "Failed to download series cover (no results).".to_string(),
+        ));
+    }
+
+    Ok(CoverDecision::None)
+}
+
+/// Writes the cover chosen by `decide_series_cover` and returns its path. A failed extraction
+/// or download is logged and leaves the series without a cover.
+pub fn apply_cover_decision(
+    decision: &CoverDecision,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<Option<PathBuf>, Error> {
+    let started = Instant::now();
+    let applied = apply_cover_decision_untimed(decision, log);
+    let phase = match decision {
+        CoverDecision::Extract(_) | CoverDecision::Copy { .. } => Phase::CoverWrite,
+        CoverDecision::Download { .. } => Phase::CoverDownload,
+        CoverDecision::Existing(_) | CoverDecision::None => return applied,
+    };
+    log(LogEvent::Timing {
+        phase,
+        elapsed: started.elapsed(),
+    });
+    applied
+}
+
+fn apply_cover_decision_untimed(
+    decision: &CoverDecision,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<Option<PathBuf>, Error> {
+    match decision {
+        CoverDecision::Existing(path) => Ok(Some(path.clone())),
+        CoverDecision::Extract(cover) => match write_volume_cover(cover) {
+            Ok(out) => {
+                log(LogEvent::CoverExtracted {
+                    output: out.clone(),
+                    volume_index: cover.volume_index,
+                    volume_file: cover.volume_file.clone(),
+                    entry: cover.image_entry.clone(),
+                });
+                Ok(Some(out))
+            }
+            Err(err) => {
+                log(LogEvent::Warn(format!(
+                    "Failed to extract volume cover. Last error: {err}"
+                )));
+                Ok(None)
+            }
+        },
+        CoverDecision::Download {
+            result,
+            fallbacks,
+            output,
+        } => {
+            for (attempt, result) in std::iter::once(result).chain(fallbacks).enumerate() {
+                if attempt > 0 {
+                    log(LogEvent::CoverNote(format!(
+                        "Trying the {} cover instead.",
+                        result.source
+                    )));
+                }
+                let mut throttle = ProgressThrottle::default();
+                let mut progress = |received, total| {
+                    if throttle.due(received, total) {
+                        log(LogEvent::CoverDownloadProgress { received, total });
+                    }
+                };
+                let downloaded = download_file(&result.url, output, 30, &mut progress);
+                settle_cached_cover(result, downloaded.is_ok());
+                match downloaded {
+                    Ok(()) => {
+                        log(LogEvent::CoverDownloaded {
+                            output: output.clone(),
+                            source: result.source.clone(),
+                        });
+                        return Ok(Some(output.clone()));
+                    }
+                    Err(err) => {
+                        log(LogEvent::Warn(format!(
+                            "Failed to download series cover. Last error: {err:#}"
+                        )));
+                    }
+                }
+            }
+            Ok(None)
+        }
+        CoverDecision::Copy { source, output } => match copy_cover(source, output) {
+            Ok(_) => {
+                log(LogEvent::CoverCopied {
+                    output: output.clone(),
+                    source: source.clone(),
+                });
+                Ok(Some(output.clone()))
+            }
+            Err(err) => {
+                log(LogEvent::Warn(format!(
+                    "Failed to copy series cover. Last error: {err:#}"
+                )));
+                Ok(None)
+            }
+        },
+        CoverDecision::None => Ok(None),
+    }
+}
+
+/// Writes a copy of the decided cover to the system temp directory for display, leaving the
+/// series folder untouched. An existing cover is returned as is.
+pub fn preview_series_cover(
+    decision: &CoverDecision,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<Option<PathBuf>, Error> {
+    let Some(planned) = decision.planned_path() else {
+        return Ok(None);
+    };
+    if let CoverDecision::Existing(path) = decision {
+        return Ok(Some(path.clone()));
+    }
+
+    let series_name = planned.parent().map(file_name_text).unwrap_or_default();
+    let output = std::env::temp_dir()
+        .join("manga_cleaner_preview")
+        .join(format!("{series_name} cover.jpg"));
+    apply_cover_decision(&decision.redirected(output), log)
+}
+
+/// Decides and writes the series cover in one step; see `decide_series_cover`.
+pub fn ensure_series_cover(
+    series_dir: &Path,
+    title: &str,
+    options: &SeriesCoverOptions,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<Option<PathBuf>, Error> {
+    let decision = decide_series_cover(series_dir, title, options, log)?;
+    apply_cover_decision(&decision, log)
+}
+
+/// Environment variable naming a font file for cover rendering.
+pub const FONT_ENV_VAR: &str = "MANGA_CLEANER_FONT";
+
+const FONT_CANDIDATES: &[&str] = &[
+    "/System/Library/Fonts/Supplemental/Arial Black.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Bold.ttf",
+    "/System/Library/Fonts/Supplemental/Impact.ttf",
+    "/System/Library/Fonts/Supplemental/Helvetica Bold.ttf",
+    "/Library/Fonts/Arial Black.ttf",
+    "/Library/Fonts/Arial Bold.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Bold.ttf",
+    "C:\\Windows\\Fonts\\ariblk.ttf",
+    "C:\\Windows\\Fonts\\arialbd.ttf",
+];
+
+/// Loads the cover font: `custom` first, then `$MANGA_CLEANER_FONT`, then the built-in
+/// system candidates.
+fn pick_font(custom: Option<&Path>) -> Result<FontArc> {
+    let env_font = std::env::var_os(FONT_ENV_VAR)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from);
+    let candidates = custom
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(env_font)
+        .chain(FONT_CANDIDATES.iter().map(PathBuf::from));
+    load_first_font(candidates)
+}
+
+fn load_first_font(candidates: impl IntoIterator<Item = PathBuf>) -> Result<FontArc> {
+    let mut tried = Vec::new();
+    for path in candidates {
+        match fs::read(&path) {
+            Ok(bytes) => match FontArc::try_from_vec(bytes) {
+                Ok(font) => return Ok(font),
+                Err(_) => tried.push(format!("{} (not a usable font)", path.display())),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                tried.push(format!("{} (not found)", path.display()))
+            }
+            Err(err) => tried.push(format!("{} ({err})", path.display())),
+        }
+    }
+
+    Err(Error::Font(anyhow!(
+        "unable to find a usable font for cover rendering; pass --font or set {FONT_ENV_VAR}. Tried: {}",
+        tried.join(", ")
+    ))
+    .into())
+}
+
+fn fit_font_size(font: &FontArc, text: &str, w: u32, h: u32, margin_frac: f32) -> u32 {
+    let max_w = ((w as f32) * (1.0 - 2.0 * margin_frac)).max(1.0) as u32;
+    let max_h = ((h as f32) * (1.0 - 2.0 * margin_frac)).max(1.0) as u32;
+
+    let mut lo: u32 = 10;
+    let mut hi: u32 = w.max(h).saturating_mul(5).max(10);
+    let mut best = lo;
+
+    while lo <= hi {
+        let mid = (lo + hi) / 2;
+        let scale = PxScale::from(mid as f32);
+        let (tw, th) = text_size(scale, font, text);
+
+        if tw <= max_w && th <= max_h {
+            best = mid;
+            lo = mid.saturating_add(1);
+        } else {
+            if mid == 0 {
+                break;
+            }
+            hi = mid.saturating_sub(1);
+        }
+    }
+
+    best
+}
+
+/// Bounding box of the non-transparent pixels. Rows are scanned from the top and bottom, then
+/// columns from the left and right within those rows, so each scan stops at the first hit
+/// instead of visiting every pixel.
+fn alpha_bbox(image: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let (w, h) = image.dimensions();
+    let raw = image.as_raw();
+    let stride = w as usize * 4;
+    let opaque = |x: u32, y: u32| raw[y as usize * stride + x as usize * 4 + 3] != 0;
+    let row_has_ink = |y: u32| {
+        raw[y as usize * stride..(y as usize + 1) * stride]
+            .chunks_exact(4)
+            .any(|px| px[3] != 0)
+    };
+
+    let min_y = (0..h).find(|&y| row_has_ink(y))?;
+    let max_y = (min_y..h).rev().find(|&y| row_has_ink(y))?;
+    let column_has_ink = |x: u32| (min_y..=max_y).any(|y| opaque(x, y));
+    let min_x = (0..w).find(|&x| column_has_ink(x))?;
+    let max_x = (min_x..w).rev().find(|&x| column_has_ink(x))?;
+
+    Some((min_x, min_y, max_x, max_y))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverTextColor {
+    Rgb([u8; 3]),
+    /// Black or white, whichever contrasts better with the middle of the cover.
+    Auto,
+}
+
+impl Default for CoverTextColor {
+    fn default() -> Self {
+        CoverTextColor::Rgb([0, 0, 0])
+    }
+}
+
+impl std::str::FromStr for CoverTextColor {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let trimmed = value.trim();
+        if trimmed.eq_ignore_ascii_case("auto") {
+            return Ok(CoverTextColor::Auto);
+        }
+
+        parse_hex_color(trimmed)
+            .map(CoverTextColor::Rgb)
+            .with_context(|| format!("invalid cover text color {value:?} (expected \"auto\" or a hex color like #FFFFFF)"))
+    }
+}
+
+/// Parses `#RRGGBB` (the `#` is optional).
+pub fn parse_hex_color(value: &str) -> Result<[u8; 3]> {
+    let trimmed = value.trim();
+    let hex = trimmed.strip_prefix('#').unwrap_or(trimmed);
+    if hex.len() != 6 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        bail!("invalid hex color {value:?} (expected something like #FFFFFF)");
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+impl std::fmt::Display for CoverTextColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoverTextColor::Rgb([r, g, b]) => write!(f, "#{r:02X}{g:02X}{b:02X}"),
+            CoverTextColor::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Which edge of a batch cover the series title is drawn along.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TitlePosition {
+    Top,
+    #[default]
+    Bottom,
+}
+
+impl std::str::FromStr for TitlePosition {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "top" => Ok(TitlePosition::Top),
+            "bottom" => Ok(TitlePosition::Bottom),
+            _ => bail!("invalid title position {value:?} (expected top or bottom)"),
+        }
+    }
+}
+
+/// What rendering a batch cover does with a `cover.jpg` already in the folder. Whichever is
+/// chosen, a cover identical to the one about to be rendered is left as it is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExistingCover {
+    /// Move it aside to the next free `cover_old_*.jpg`.
+    #[default]
+    Archive,
+    /// Replace it without keeping a copy.
+    Overwrite,
+    /// Keep it and render nothing for that batch.
+    Skip,
+}
+
+impl std::str::FromStr for ExistingCover {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "archive" => Ok(ExistingCover::Archive),
+            "overwrite" => Ok(ExistingCover::Overwrite),
+            "skip" => Ok(ExistingCover::Skip),
+            _ => bail!(
+                "invalid existing cover mode {value:?} (expected archive, overwrite, or skip)"
+            ),
+        }
+    }
+}
+
+/// Width-to-height proportion, parsed from `W:H` (e.g. `2:3`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoverRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::str::FromStr for CoverRatio {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let parsed = value
+            .split_once(':')
+            .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)));
+        match parsed {
+            Some((width, height)) if width > 0 && height > 0 => Ok(CoverRatio { width, height }),
+            _ => bail!("invalid cover ratio {value:?} (expected W:H, e.g. 2:3)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CoverStyle {
+    pub text_color: CoverTextColor,
+    /// Draw a contrasting outline around the batch number.
+    pub outline: bool,
+    /// Pad the cover to this aspect ratio before numbering it.
+    pub ratio: Option<CoverRatio>,
+    /// Fill color for the padding added by `ratio`.
+    pub pad_color: [u8; 3],
+    /// Resize the cover to this width (keeping its aspect ratio) before numbering it.
+    pub width: Option<u32>,
+    /// Text drawn along one edge of each batch cover, usually the series title.
+    pub title: Option<String>,
+    pub title_position: TitlePosition,
+    /// Font file to render with instead of `$MANGA_CLEANER_FONT` or the system fonts.
+    pub font: Option<PathBuf>,
+    /// JPEG quality (1-100) for the rendered `cover.jpg`; `JPEG_QUALITY` when `None`.
+    pub jpeg_quality: Option<u8>,
+    /// Size the batch number as if it had this many digits, so "7" and "250" get the same
+    /// font size. Numbers with more digits are fitted on their own.
+    pub number_width: Option<usize>,
+    /// Zero-pad the batch number to `number_width` digits ("007"). `execute` uses the digit
+    /// count of the last batch when no width is set.
+    pub pad_number: bool,
+}
+
+impl CoverStyle {
+    /// The batch number as drawn on the cover.
+    pub fn number_text(&self, number: usize) -> String {
+        match self.number_width {
+            Some(width) if self.pad_number => format!("{number:0width$}"),
+            _ => number.to_string(),
+        }
+    }
+}
+
+/// Font size for the batch number `text`, fitted to `number_width` digits when that is wider.
+fn batch_number_font_size(
+    image: &DynamicImage,
+    font: &FontArc,
+    text: &str,
+    style: &CoverStyle,
+) -> f32 {
+    let sizing = match style.number_width {
+        Some(width) if width > text.chars().count() => "0".repeat(width),
+        _ => text.to_string(),
+    };
+    let max_size = fit_font_size(font, &sizing, image.width(), image.height(), 0.06);
+    ((max_size as f32) * 0.90).max(10.0)
+}
+
+/// Applies the optional padding and resizing from `style`. Covers pass through untouched when
+/// neither is set, so default output stays identical.
+pub fn normalize_cover(image: DynamicImage, style: &CoverStyle) -> DynamicImage {
+    let mut image = image;
+
+    if let Some(ratio) = style.ratio {
+        let (w, h) = (u64::from(image.width()), u64::from(image.height()));
+        let (rw, rh) = (u64::from(ratio.width), u64::from(ratio.height));
+        let (target_w, target_h) = if w * rh > h * rw {
+            (w, (w * rh).div_ceil(rw))
+        } else {
+            ((h * rw).div_ceil(rh), h)
+        };
+        if (target_w, target_h) != (w, h) {
+            let [r, g, b] = style.pad_color;
+            let mut canvas = RgbImage::from_pixel(target_w as u32, target_h as u32, Rgb([r, g, b]));
+            image::imageops::overlay(
+                &mut canvas,
+                &image.to_rgb8(),
+                ((target_w - w) / 2) as i64,
+                ((target_h - h) / 2) as i64,
+            );
+            image = DynamicImage::ImageRgb8(canvas);
+        }
+    }
+
+    if let Some(width) = style.width.filter(|&width| width > 0) {
+        if width != image.width() {
+            let height = (u64::from(image.height()) * u64::from(width))
+                .div_ceil(u64::from(image.width()))
+                .max(1) as u32;
+            image = image.resize_exact(width, height, image::imageops::FilterType::Lanczos3);
+        }
+    }
+
+    image
+}
+
+/// Average Rec. 601 luma of the central third of the image, in 0..=255.
+fn center_luminance(image: &DynamicImage) -> f32 {
+    let rgb = image.to_rgb8();
+    let (w, h) = rgb.dimensions();
+    let (x0, x1) = (w / 3, (w * 2 / 3).max(w / 3 + 1).min(w));
+    let (y0, y1) = (h / 3, (h * 2 / 3).max(h / 3 + 1).min(h));
+
+    let mut total = 0.0f64;
+    let mut count = 0u64;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let [r, g, b] = rgb.get_pixel(x, y).0;
+            total += 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        return 255.0;
+    }
+    (total / count as f64) as f32
+}
+
+fn resolve_text_color(image: &DynamicImage, color: CoverTextColor) -> [u8; 3] {
+    match color {
+        CoverTextColor::Rgb(rgb) => rgb,
+        CoverTextColor::Auto => {
+            if center_luminance(image) >= 128.0 {
+                [0, 0, 0]
+            } else {
+                [255, 255, 255]
+            }
+        }
+    }
+}
+
+/// Black for light fills, white for dark ones.
+fn contrasting_color(color: [u8; 3]) -> [u8; 3] {
+    let [r, g, b] = color;
+    let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    if luma >= 128.0 {
+        [0, 0, 0]
+    } else {
+        [255, 255, 255]
+    }
+}
+
+/// Stroke width for outlined numbers, proportional to the glyph size.
+fn outline_width(font_size: f32) -> i32 {
+    (font_size * 0.035).round().max(1.0) as i32
+}
+
+/// Draws `text` at (x, y), first stamping the outline in 8 directions when requested.
+#[allow(clippy::too_many_arguments)]
+fn draw_outlined_text(
+    canvas: &mut RgbaImage,
+    fill: Rgba<u8>,
+    outline: Option<(Rgba<u8>, i32)>,
+    x: i32,
+    y: i32,
+    scale: PxScale,
+    font: &FontArc,
+    text: &str,
+) {
+    if let Some((stroke, width)) = outline {
+        for (dx, dy) in [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ] {
+            draw_text_mut(
+                canvas,
+                stroke,
+                x + dx * width,
+                y + dy * width,
+                scale,
+                font,
+                text,
+            );
+        }
+    }
+    draw_text_mut(canvas, fill, x, y, scale, font, text);
+}
+
+/// Draws `text` centered on the image at `font_size`, optionally outlined.
+fn draw_centered_text_at_size(
+    base_image: &DynamicImage,
+    font: &FontArc,
+    text: &str,
+    color: [u8; 3],
+    outline: bool,
+    opacity: u8,
+    font_size: f32,
+) -> DynamicImage {
+    let mut rgba = base_image.to_rgba8();
+    let (w, h) = rgba.dimensions();
+    let px_scale = PxScale::from(font_size);
+    let stroke_width = outline.then(|| outline_width(font_size));
+    let (x, y) = centered_text_origin(
+        (w, h),
+        (w as f32 / 2.0, h as f32 / 2.0),
+        px_scale,
+        stroke_width,
+        font,
+        text,
+    );
+
+    let [r, g, b] = color;
+    let [sr, sg, sb] = contrasting_color(color);
+    draw_outlined_text(
+        &mut rgba,
+        Rgba([r, g, b, opacity]),
+        stroke_width.map(|width| (Rgba([sr, sg, sb, opacity]), width)),
+        x,
+        y,
+        px_scale,
+        font,
+        text,
+    );
+
+    let rgb = DynamicImage::ImageRgba8(rgba).to_rgb8();
+    DynamicImage::ImageRgb8(rgb)
+}
+
+/// Finds the draw origin that puts the rendered bbox center of `text` on `(cx, cy)`.
+///
+/// Probe-and-correct placement on a full-size transparent canvas until the rendered bbox center
+/// lands on the target. This mirrors Pillow's anchor-centered behavior.
+fn centered_text_origin(
+    (w, h): (u32, u32),
+    (cx, cy): (f32, f32),
+    px_scale: PxScale,
+    stroke_width: Option<i32>,
+    font: &FontArc,
+    text: &str,
+) -> (i32, i32) {
+    let mut x = cx.round() as i32;
+    let mut y = cy.round() as i32;
+
+    let mut probe = RgbaImage::new(w, h);
+    for attempt in 0..4 {
+        if attempt > 0 {
+            probe.fill(0);
+        }
+        // The probe includes the stroke so the centered bbox covers the full outlined glyphs.
+        draw_outlined_text(
+            &mut probe,
+            Rgba([0, 0, 0, 255]),
+            stroke_width.map(|width| (Rgba([0, 0, 0, 255]), width)),
+            x,
+            y,
+            px_scale,
+            font,
+            text,
+        );
+
+        let Some((min_x, min_y, max_x, max_y)) = alpha_bbox(&probe) else {
+            break;
+        };
+
+        let bcx = (min_x as f32 + max_x as f32) / 2.0;
+        let bcy = (min_y as f32 + max_y as f32) / 2.0;
+        let dx = (cx - bcx).round() as i32;
+        let dy = (cy - bcy).round() as i32;
+
+        if dx == 0 && dy == 0 {
+            break;
+        }
+        x += dx;
+        y += dy;
+    }
+
+    (x, y)
+}
+
+/// Fraction of the cover height reserved for the title band.
+const TITLE_BAND_FRAC: f32 = 0.12;
+
+/// Draws `title` centered in a band along the top or bottom edge, sized independently of the
+/// batch number so the two never compete for space.
+fn draw_title_text(
+    base_image: &DynamicImage,
+    font: &FontArc,
+    title: &str,
+    position: TitlePosition,
+    color: [u8; 3],
+    outline: bool,
+) -> DynamicImage {
+    let mut rgba = base_image.to_rgba8();
+    let (w, h) = rgba.dimensions();
+    let band_h = ((h as f32) * TITLE_BAND_FRAC).round().max(1.0) as u32;
+
+    let font_size = fit_font_size(font, title, w, band_h, 0.08) as f32;
+    let px_scale = PxScale::from(font_size);
+    let stroke_width = outline.then(|| outline_width(font_size));
+    let cy = match position {
+        TitlePosition::Top => band_h as f32 / 2.0,
+        TitlePosition::Bottom => h as f32 - band_h as f32 / 2.0,
+    };
+    let (x, y) = centered_text_origin(
+        (w, h),
+        (w as f32 / 2.0, cy),
+        px_scale,
+        stroke_width,
+        font,
+        title,
+    );
+
+    let [r, g, b] = color;
+    let [sr, sg, sb] = contrasting_color(color);
+    draw_outlined_text(
+        &mut rgba,
+        Rgba([r, g, b, 255]),
+        stroke_width.map(|width| (Rgba([sr, sg, sb, 255]), width)),
+        x,
+        y,
+        px_scale,
+        font,
+        title,
+    );
+
+    let rgb = DynamicImage::ImageRgba8(rgba).to_rgb8();
+    DynamicImage::ImageRgb8(rgb)
+}
+
+pub fn ensure_cover_old(batch_dir: &Path, series_cover: &Path) -> Result<PathBuf> {
+    let primary = batch_dir.join("cover_old.jpg");
+    if primary.exists() {
+        return Ok(primary);
+    }
+
+    // `copy_cover` converts a PNG series cover, so `cover_old.jpg` really is a JPEG.
+    let target = unique_cover_old_path(batch_dir);
+    copy_cover(series_cover, &target).with_context(|| {
+        format!(
+            "failed to copy series cover from {} to {}",
+            series_cover.display(),
+            target.display()
+        )
+    })?;
+    Ok(target)
+}
+
+/// Moves `cover.jpg` aside to the next free `cover_old*.jpg`. A symlinked cover (e.g. into a
+/// shared cover store) is archived as a copy of its target and only the link is removed, so
+/// the store is never moved or overwritten.
+pub fn archive_existing_cover_jpg(batch_dir: &Path) -> Result<Option<PathBuf>> {
+    let cover = batch_dir.join("cover.jpg");
+    if !cover.exists() {
+        return Ok(None);
+    }
+
+    let destination = unique_cover_old_path(batch_dir);
+    let is_symlink = fs::symlink_metadata(&cover)
+        .map(|meta| meta.file_type().is_symlink())
+        .unwrap_or(false);
+    if is_symlink {
+        fs::copy(&cover, &destination).with_context(|| {
+            format!(
+                "failed to archive symlinked cover {} to {}",
+                cover.display(),
+                destination.display()
+            )
+        })?;
+        fs::remove_file(&cover)
+            .with_context(|| format!("failed to remove cover link: {}", cover.display()))?;
+        return Ok(Some(destination));
+    }
+
+    fs::rename(&cover, &destination).with_context(|| {
+        format!(
+            "failed to archive cover from {} to {}",
+            cover.display(),
+            destination.display()
+        )
+    })?;
+
+    Ok(Some(destination))
+}
+
+/// Removes all but the `keep` most recent `cover_old_*.jpg` files in `batch_dir`, newest by
+/// modification time and then by number, and returns the removed paths. `cover_old.jpg` is
+/// the base every batch cover is drawn on and is never removed. Neither is `spare`, the cover
+/// archived by the current run, which rollback needs to put back; it counts towards `keep`.
+pub fn prune_cover_old(
+    batch_dir: &Path,
+    keep: usize,
+    spare: Option<&Path>,
+) -> Result<Vec<PathBuf>> {
+    let mut archived = Vec::new();
+    let entries = fs::read_dir(batch_dir)
+        .with_context(|| format!("failed to list batch folder: {}", batch_dir.display()))?;
+    for entry in entries {
+        let entry = entry
+            .with_context(|| format!("failed to list batch folder: {}", batch_dir.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(number) = name
+            .strip_prefix("cover_old_")
+            .and_then(|rest| rest.strip_suffix(".jpg"))
+            .and_then(|number| number.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let meta = entry
+            .metadata()
+            .with_context(|| format!("failed to inspect {}", entry.path().display()))?;
+        if meta.is_file() {
+            let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+            archived.push((modified, number, entry.path()));
+        }
+    }
+
+    let before = archived.len();
+    archived.retain(|(_, _, path)| Some(path.as_path()) != spare);
+    let keep = keep.saturating_sub(before - archived.len());
+    archived.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+    let mut removed = Vec::new();
+    for (_, _, path) in archived.into_iter().skip(keep) {
+        fs::remove_file(&path)
+            .with_context(|| format!("failed to remove old cover: {}", path.display()))?;
+        removed.push(path);
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+pub fn write_numbered_cover(
+    batch_dir: &Path,
+    number: usize,
+    series_cover: &Path,
+    style: &CoverStyle,
+) -> Result<()> {
+    let font = pick_font(style.font.as_deref())?;
+    render_batch_cover(
+        batch_dir,
+        number,
+        series_cover,
+        style,
+        ExistingCover::Archive,
+        &font,
+        None,
+    )?;
+    Ok(())
+}
+
+/// The files `render_batch_cover` wrote or renamed.
+struct RenderedCover {
+    cover: PathBuf,
+    /// The `cover.jpg` already there was kept, so nothing was written.
+    kept: bool,
+    /// `cover_old.jpg`, when it had to be copied from the series cover.
+    base: Option<PathBuf>,
+    archived: Option<PathBuf>,
+    timings: PhaseTimings,
+}
+
+fn render_batch_cover(
+    batch_dir: &Path,
+    number: usize,
+    series_cover: &Path,
+    style: &CoverStyle,
+    existing: ExistingCover,
+    font: &FontArc,
+    journal: Option<&Mutex<Journal>>,
+) -> Result<RenderedCover> {
+    ensure_dir(batch_dir)?;
+    let cover_jpg = batch_dir.join("cover.jpg");
+    let mut timings = PhaseTimings::default();
+    if existing == ExistingCover::Skip && cover_jpg.exists() {
+        return Ok(RenderedCover {
+            cover: cover_jpg,
+            kept: true,
+            base: None,
+            archived: None,
+            timings,
+        });
+    }
+    let archive = |journal: Option<&Mutex<Journal>>| -> Result<Option<PathBuf>> {
+        let archived = archive_existing_cover_jpg(batch_dir)?;
+        if let Some(archived) = &archived {
+            record_shared(
+                journal,
+                JournalEntry::Move {
+                    src: batch_dir.join("cover.jpg"),
+                    dst: archived.clone(),
+                },
+            )?;
+        }
+        Ok(archived)
+    };
+    // Without a `cover_old.jpg`, an archived cover becomes the base the number is drawn on.
+    let mut archived = None;
+    if existing == ExistingCover::Archive && !batch_dir.join("cover_old.jpg").exists() {
+        archived = archive(journal)?;
+    }
+
+    let had_cover_old = batch_dir.join("cover_old.jpg").exists();
+    let base_cover = ensure_cover_old(batch_dir, series_cover)?;
+    if !had_cover_old {
+        record_shared(
+            journal,
+            JournalEntry::WriteFile {
+                path: base_cover.clone(),
+            },
+        )?;
+    }
+
+    let started = Instant::now();
+    let image = ImageReader::open(&base_cover)
+        .with_context(|| format!("failed to open base cover image: {}", base_cover.display()))?
+        .decode()
+        .context("failed to decode base cover image")?;
+    timings.add(Phase::Decode, started.elapsed());
+    let image = normalize_cover(image, style);
+
+    let color = resolve_text_color(&image, style.text_color);
+    let text = style.number_text(number);
+    let started = Instant::now();
+    let font_size = batch_number_font_size(&image, font, &text, style);
+    timings.add(Phase::FontFit, started.elapsed());
+    let started = Instant::now();
+    let mut rendered =
+        draw_centered_text_at_size(&image, font, &text, color, style.outline, 255, font_size);
+    if let Some(title) = style
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+    {
+        rendered = draw_title_text(
+            &rendered,
+            font,
+            title,
+            style.title_position,
+            color,
+            style.outline,
+        );
+    }
+    timings.add(Phase::Draw, started.elapsed());
+    let started = Instant::now();
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, style.jpeg_quality.unwrap_or(JPEG_QUALITY))
+        .encode_image(&DynamicImage::ImageRgb8(rendered.to_rgb8()))
+        .with_context(|| format!("failed to encode JPEG: {}", cover_jpg.display()))?;
+    timings.add(Phase::Encode, started.elapsed());
+    // Re-runs render the same cover again; leave it be rather than archiving a copy of it.
+    if fs::read(&cover_jpg).is_ok_and(|current| current == bytes) {
+        return Ok(RenderedCover {
+            cover: cover_jpg,
+            kept: true,
+            base: (!had_cover_old).then_some(base_cover),
+            archived,
+            timings,
+        });
+    }
+    if existing == ExistingCover::Archive && archived.is_none() {
+        archived = archive(journal)?;
+    }
+    write_atomically(&cover_jpg, |out| {
+        out.write_all(&bytes)
+            .with_context(|| format!("failed to write {}", cover_jpg.display()))
+    })?;
+    record_shared(
+        journal,
+        JournalEntry::WriteFile {
+            path: cover_jpg.clone(),
+        },
+    )?;
+    Ok(RenderedCover {
+        cover: cover_jpg,
+        kept: false,
+        base: (!had_cover_old).then_some(base_cover),
+        archived,
+        timings,
+    })
+}
+
+/// Records `entry` in a journal shared between cover-rendering threads.
+fn record_shared(journal: Option<&Mutex<Journal>>, entry: JournalEntry) -> Result<()> {
+    let Some(journal) = journal else {
+        return Ok(());
+    };
+    journal
+        .lock()
+        .map_err(|_| anyhow!("journal lock poisoned"))?
+        .record(entry)
+}
+
+pub fn journal_path(series_dir: &Path) -> PathBuf {
+    series_dir.join(JOURNAL_FILE_NAME)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEntry {
+    CreateDir {
+        path: PathBuf,
+    },
+    Move {
+        src: PathBuf,
+        dst: PathBuf,
+    },
+    /// A copy-mode run created `dst` from `src`; rollback only deletes `dst`.
+    Copy {
+        src: PathBuf,
+        dst: PathBuf,
+    },
+    /// `src` was removed because an identical `kept` was already in place; rollback copies
+    /// `kept` back to `src`.
+    RemovedDuplicate {
+        src: PathBuf,
+        kept: PathBuf,
+    },
+    WriteFile {
+        path: PathBuf,
+    },
+    /// A small text file at `path` was rewritten; rollback writes `previous` back.
+    ReplaceFile {
+        path: PathBuf,
+        previous: String,
+    },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalFile {
+    entries: Vec<JournalEntry>,
+}
+
+/// Append-only record of the filesystem changes made by `execute`. The file is rewritten after
+/// every entry so an interrupted run can still be rolled back.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    contents: JournalFile,
+}
+
+impl Journal {
+    /// Starts an empty journal at `path`. One left by an earlier run is archived first as
+    /// `<stem>.N.json`, one past the highest `N` already there, rather than overwritten;
+    /// rolling this run back puts it back in place, so the earlier run can be rolled back
+    /// after it.
+    pub fn create(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let next = journal_archive_numbers(path)
+                .last()
+                .map_or(1, |(n, _)| n + 1);
+            let archived = journal_archive_path(path, next);
+            fs::rename(path, &archived).with_context(|| {
+                format!(
+                    "failed to archive the earlier journal {} as {}",
+                    path.display(),
+                    archived.display()
+                )
+            })?;
+        }
+        let journal = Self {
+            path: path.to_path_buf(),
+            contents: JournalFile::default(),
+        };
+        journal.flush()?;
+        Ok(journal)
+    }
+
+    pub fn load(path: &Path) -> Result<Vec<JournalEntry>> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("failed to read journal: {}", path.display()))?;
+        let contents: JournalFile = serde_json::from_slice(&bytes)
+            .with_context(|| format!("failed to parse journal: {}", path.display()))?;
+        Ok(contents.entries)
+    }
+
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.contents.entries
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) -> Result<()> {
+        self.contents.entries.push(entry);
+        self.flush()
+    }
+
+    fn flush(&self) -> Result<()> {
+        let data = serde_json::to_vec_pretty(&self.contents).context("failed to encode journal")?;
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, data)
+            .with_context(|| format!("failed to write journal: {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed to write journal: {}", self.path.display()))
+    }
+}
+
+fn journal_archive_path(journal: &Path, n: usize) -> PathBuf {
+    let stem = journal.file_stem().unwrap_or_default().to_string_lossy();
+    journal.with_file_name(format!("{stem}.{n}.json"))
+}
+
+/// Every `<stem>.N.json` next to `journal` with its `N`, in ascending order. Gaps left by
+/// archives deleted by hand are skipped over.
+fn journal_archive_numbers(journal: &Path) -> Vec<(usize, PathBuf)> {
+    let stem = journal.file_stem().unwrap_or_default().to_string_lossy();
+    let prefix = format!("{stem}.");
+    let dir = match journal.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<(usize, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let number = name.strip_prefix(&prefix)?.strip_suffix(".json")?;
+            if !number.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let number = number.parse().ok()?;
+            let path = entry.path();
+            path.is_file().then_some((number, path))
+        })
+        .collect();
+    archives.sort_by_key(|(n, _)| *n);
+    archives
+}
+
+/// The journals `Journal::create` archived next to `journal`, oldest first.
+fn journal_archives(journal: &Path) -> Vec<PathBuf> {
+    journal_archive_numbers(journal)
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExecuteOptions {
+    /// Where to record completed changes for `rollback`. No journal is kept when `None`.
+    pub journal: Option<PathBuf>,
+    pub cover_style: CoverStyle,
+    /// Compare contents byte-for-byte after cross-device copies, not just sizes.
+    pub verify_copies: bool,
+    /// Batch covers rendered at once. Moves stay sequential; 0 and 1 both mean one at a time.
+    pub jobs: usize,
+    /// Write `METADATA_FILE_NAME` into every batch folder. Nothing is written when `None`.
+    pub metadata: Option<BatchMetadata>,
+    /// Hash every volume as it is placed and write `CHECKSUMS_FILE_NAME` into its batch
+    /// folder; see `verify_manifest`.
+    pub checksums: bool,
+    /// Record a volume that can't be moved (say, a locked or read-only file) in
+    /// `ExecuteSummary::failed` and carry on, instead of stopping the run.
+    pub continue_on_error: bool,
+    /// What to do with a `cover.jpg` already in a batch folder.
+    pub existing_cover: ExistingCover,
+    /// Keep only this many `cover_old_*.jpg` in each batch folder once its cover is done; see
+    /// `prune_cover_old`. Nothing is removed when `None`.
+    pub prune_covers: Option<usize>,
+}
+
+/// Series-wide values recorded in each batch folder's metadata file.
+#[derive(Debug, Clone, Default)]
+pub struct BatchMetadata {
+    pub series_title: String,
+    /// See `CoverDecision::source_label`.
+    pub cover_source: Option<String>,
+}
+
+/// Writes the batch's metadata file from the volumes now in its folder, so re-runs rewrite
+/// the same content instead of appending to it.
+fn write_batch_metadata(
+    batch: &BatchPlan,
+    metadata: &BatchMetadata,
+    journal: Option<&Mutex<Journal>>,
+) -> Result<PathBuf> {
+    let volumes: Vec<String> = scan_volumes(&batch.batch_dir)?
+        .iter()
+        .map(|path| file_name_text(path))
+        .collect();
+    // A volume this plan moved is numbered from its original name by the plan's rules, since
+    // those may not read the `vNNN` it was renamed to. Anything else in the folder was named by
+    // an earlier run, or left as it was.
+    let numbers: Vec<VolumeNumber> = volumes
+        .iter()
+        .filter_map(
+            |name| match batch.moves.iter().find(|mv| mv.dst_name == *name) {
+                Some(mv) => volume_number_with(&file_name_text(&mv.src), &batch.clean_rules),
+                None => volume_number_with(name, &batch.clean_rules)
+                    .or_else(|| volume_number_with(name, &CleanRules::default())),
+            },
+        )
+        .collect();
+    let value = json!({
+        "series": metadata.series_title,
+        "batch": batch.batch_index,
+        "volume_count": volumes.len(),
+        "start_volume": numbers.iter().min().map(VolumeNumber::to_json),
+        "end_volume": numbers.iter().max().map(VolumeNumber::to_json),
+        "first_volume": volumes.first(),
+        "last_volume": volumes.last(),
+        "volumes": volumes,
+        "cover_source": metadata.cover_source,
+    });
+
+    let path = batch.batch_dir.join(METADATA_FILE_NAME);
+    let mut text = serde_json::to_string_pretty(&value)?;
+    text.push('\n');
+    let before = path
+        .is_file()
+        .then(|| fs::read_to_string(&path))
+        .transpose();
+    let before = before.with_context(|| format!("failed to read metadata: {}", path.display()))?;
+    write_atomically(&path, |out| {
+        out.write_all(text.as_bytes())
+            .with_context(|| format!("failed to write metadata: {}", path.display()))
+    })?;
+    let entry = match before {
+        Some(previous) => JournalEntry::ReplaceFile {
+            path: path.clone(),
+            previous,
+        },
+        None => JournalEntry::WriteFile { path: path.clone() },
+    };
+    record_shared(journal, entry)?;
+    Ok(path)
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// `(file name, hex digest)` for every line of a checksum manifest.
+fn read_manifest(path: &Path) -> Result<Vec<(String, String)>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read checksums: {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // `sha256sum` separates the digest from the name with two spaces, or " *" in binary mode.
+        let parsed = line
+            .split_once("  ")
+            .or_else(|| line.split_once(" *"))
+            .filter(|(digest, name)| {
+                digest.len() == 64
+                    && digest.chars().all(|c| c.is_ascii_hexdigit())
+                    && !name.is_empty()
+            });
+        let Some((digest, name)) = parsed else {
+            bail!(
+                "invalid line {} in checksums: {}",
+                number + 1,
+                path.display()
+            );
+        };
+        entries.push((name.to_string(), digest.to_ascii_lowercase()));
+    }
+    Ok(entries)
+}
+
+/// Writes the batch's checksum manifest for the volumes now in its folder. `hashed` holds the
+/// digests of the volumes just placed; the rest come from the previous manifest or are
+/// hashed now.
+fn write_checksums(
+    batch_dir: &Path,
+    hashed: &HashMap<String, String>,
+    journal: Option<&Mutex<Journal>>,
+) -> Result<PathBuf> {
+    let path = batch_dir.join(CHECKSUMS_FILE_NAME);
+    let previous: HashMap<String, String> = if path.is_file() {
+        read_manifest(&path)?.into_iter().collect()
+    } else {
+        HashMap::new()
+    };
+    let mut text = String::new();
+    for volume in scan_volumes(batch_dir)? {
+        let name = file_name_text(&volume);
+        let digest = match hashed.get(&name).or_else(|| previous.get(&name)) {
+            Some(digest) => digest.clone(),
+            None => sha256_file(&volume)?,
+        };
+        text.push_str(&format!("{digest}  {name}\n"));
+    }
+    let before = path
+        .is_file()
+        .then(|| fs::read_to_string(&path))
+        .transpose();
+    let before = before.with_context(|| format!("failed to read checksums: {}", path.display()))?;
+    write_atomically(&path, |out| {
+        out.write_all(text.as_bytes())
+            .with_context(|| format!("failed to write checksums: {}", path.display()))
+    })?;
+    let entry = match before {
+        Some(previous) => JournalEntry::ReplaceFile {
+            path: path.clone(),
+            previous,
+        },
+        None => JournalEntry::WriteFile { path: path.clone() },
+    };
+    record_shared(journal, entry)?;
+    Ok(path)
+}
+
+/// Hashes the files listed in `batch_dir`'s `CHECKSUMS_FILE_NAME` again and pairs each with
+/// whether it still matches, in manifest order. A listed file that is gone doesn't match.
+pub fn verify_manifest(batch_dir: &Path) -> Result<Vec<(PathBuf, bool)>> {
+    read_manifest(&batch_dir.join(CHECKSUMS_FILE_NAME))?
+        .into_iter()
+        .map(|(name, digest)| {
+            let path = batch_dir.join(&name);
+            let matches = path.is_file() && sha256_file(&path)? == digest;
+            Ok((path, matches))
+        })
+        .collect()
+}
+
+/// How far `execute` got before returning, and what it changed on the way.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecuteSummary {
+    /// Batches whose moves (and cover, when one applies) all finished.
+    pub batches_completed: usize,
+    pub total_batches: usize,
+    /// The cancel flag stopped the run before every batch finished.
+    pub cancelled: bool,
+    /// `(from, to)` for every volume moved (or copied, in copy mode), in order.
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    /// Batch folders that did not exist before the run.
+    pub created_dirs: Vec<PathBuf>,
+    /// Rendered `cover.jpg`s, plus `cover_old.jpg` bases copied from the series cover.
+    pub covers_written: Vec<PathBuf>,
+    /// Earlier `cover.jpg`s renamed to `cover_old_*.jpg`.
+    pub covers_archived: Vec<PathBuf>,
+    /// `cover_old_*.jpg`s removed for `ExecuteOptions::prune_covers`.
+    pub covers_pruned: Vec<PathBuf>,
+    /// `series.json` files written for `ExecuteOptions::metadata`.
+    pub metadata_written: Vec<PathBuf>,
+    /// `checksums.sha256` files written for `ExecuteOptions::checksums`.
+    pub checksums_written: Vec<PathBuf>,
+    /// Volumes left where they were under `ExecuteOptions::continue_on_error`.
+    pub failed: Vec<FailedMove>,
+}
+
+/// A volume `execute` could not move; the file is still at `src`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedMove {
+    pub src: PathBuf,
+    pub dst: PathBuf,
+    pub error: String,
+}
+
+impl ExecuteSummary {
+    /// Every folder the run added, removed, or rewrote files in, sorted and without
+    /// duplicates; handy for asking a media server to rescan just those.
+    pub fn affected_dirs(&self) -> Vec<PathBuf> {
+        let files = self
+            .moved
+            .iter()
+            .flat_map(|(from, to)| [from, to])
+            .chain(&self.covers_written)
+            .chain(&self.covers_archived)
+            .chain(&self.covers_pruned)
+            .chain(&self.metadata_written)
+            .chain(&self.checksums_written);
+        let mut dirs: Vec<PathBuf> = files
+            .filter_map(|path| path.parent().map(Path::to_path_buf))
+            .chain(self.created_dirs.iter().cloned())
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    /// Counts for an end-of-run summary of this run of `plan`.
+    pub fn report(&self, plan: &[BatchPlan]) -> RunReport {
+        let identical_in_place = plan
+            .iter()
+            .filter(|batch| batch.copy)
+            .flat_map(|batch| &batch.moves)
+            .filter(|mv| mv.destination == DestinationState::Identical)
+            .count();
+        RunReport {
+            batches: self.batches_completed,
+            new_folders: self.created_dirs.len(),
+            placed: self.moved.len(),
+            renamed: self
+                .moved
+                .iter()
+                .filter(|(from, to)| from.file_name() != to.file_name())
+                .count(),
+            copied: plan.iter().any(|batch| batch.copy),
+            covers_written: self.covers_written.len(),
+            covers_archived: self.covers_archived.len(),
+            covers_pruned: self.covers_pruned.len(),
+            skipped: identical_in_place
+                + plan
+                    .iter()
+                    .map(|batch| batch.duplicates.len())
+                    .sum::<usize>(),
+            failed: self.failed.len(),
+        }
+    }
+}
+
+/// What a run changed, in numbers; see `ExecuteSummary::report`. Displays as one line, e.g.
+/// "20 files moved (18 renamed), 2 covers written, 1 cover_old archived across 1 batch".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunReport {
+    pub batches: usize,
+    /// Batch folders the run created.
+    pub new_folders: usize,
+    /// Volumes moved, or copied when `copied` is set.
+    pub placed: usize,
+    /// Placed volumes whose file name changed on the way.
+    pub renamed: usize,
+    pub copied: bool,
+    pub covers_written: usize,
+    pub covers_archived: usize,
+    /// Old `cover_old_*.jpg`s removed; see `ExecuteOptions::prune_covers`.
+    pub covers_pruned: usize,
+    /// Volumes left alone on purpose: duplicates, and copies already in place.
+    pub skipped: usize,
+    /// Volumes that could not be moved; see `ExecuteSummary::failed`.
+    pub failed: usize,
+}
+
+impl std::fmt::Display for RunReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural =
+            |n: usize, one: &str, many: &str| format!("{n} {}", if n == 1 { one } else { many });
+        write!(
+            f,
+            "{} {}",
+            plural(self.placed, "file", "files"),
+            if self.copied { "copied" } else { "moved" }
+        )?;
+        if self.renamed > 0 {
+            write!(f, " ({} renamed)", self.renamed)?;
+        }
+        write!(
+            f,
+            ", {} written, {} cover_old archived across {}",
+            plural(self.covers_written, "cover", "covers"),
+            self.covers_archived,
+            plural(self.batches, "batch", "batches")
+        )?;
+        if self.new_folders > 0 {
+            write!(f, " ({} new)", self.new_folders)?;
+        }
+        if self.covers_pruned > 0 {
+            write!(
+                f,
+                "; {} pruned",
+                plural(self.covers_pruned, "old cover", "old covers")
+            )?;
+        }
+        if self.skipped > 0 {
+            write!(f, "; {} skipped", self.skipped)?;
+        }
+        if self.failed > 0 {
+            write!(f, "; {} failed", self.failed)?;
+        }
+        Ok(())
+    }
+}
+
+fn cancelled_summary(mut summary: ExecuteSummary, log: &mut dyn FnMut(LogEvent)) -> ExecuteSummary {
+    log(LogEvent::Cancelled {
+        batches_completed: summary.batches_completed,
+        total_batches: summary.total_batches,
+    });
+    summary.cancelled = true;
+    summary
+}
+
+/// Applies `plan`. `cancel` is checked before every move and cover render; once it is set the
+/// run stops after the file in flight, leaving the journal consistent with what was done.
+/// A `LogEvent::Move` is reported after every completed move.
+pub fn execute(
+    plan: &[BatchPlan],
+    series_cover: Option<&Path>,
+    options: &ExecuteOptions,
+    cancel: &AtomicBool,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<ExecuteSummary, Error> {
+    let total_batches = plan.len();
+    let total_moves: usize = plan.iter().map(|batch| batch.moves.len()).sum();
+    let mut moves_done = 0;
+    // Loaded once for every batch cover, and before any file moves so a missing font fails
+    // the run up front.
+    let font = series_cover
+        .map(|_| pick_font(options.cover_style.font.as_deref()))
+        .transpose()?;
+    let journal = options
+        .journal
+        .as_deref()
+        .map(Journal::create)
+        .transpose()?
+        .map(Mutex::new);
+    let jobs = options.jobs.max(1);
+    let mut summary = ExecuteSummary {
+        total_batches,
+        ..ExecuteSummary::default()
+    };
+    let mut timings = PhaseTimings::default();
+    let mut cover_style = options.cover_style.clone();
+    if cover_style.pad_number && cover_style.number_width.is_none() {
+        let last = plan
+            .iter()
+            .map(|batch| batch.batch_index)
+            .max()
+            .unwrap_or(1);
+        cover_style.number_width = Some(last.to_string().len());
+    }
+
+    // Batches are handled in groups of `jobs`: each batch's moves run in order, then the
+    // group's covers render in parallel and are reported in batch order.
+    for group in plan.chunks(jobs) {
+        // Whether each batch in the group got through its moves without a `FailedMove`.
+        let mut moved_cleanly = Vec::with_capacity(group.len());
+        for batch in group {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(cancelled_summary(summary, log));
+            }
+            let failed_before = summary.failed.len();
+
+            let created = !batch.batch_dir.exists();
+            ensure_dir(&batch.batch_dir)?;
+            if created {
+                record_shared(
+                    journal.as_ref(),
+                    JournalEntry::CreateDir {
+                        path: batch.batch_dir.clone(),
+                    },
+                )?;
+                summary.created_dirs.push(batch.batch_dir.clone());
+            }
+
+            log(LogEvent::BatchStarted {
+                batch_index: batch.batch_index,
+                batch_dir: batch.batch_dir.clone(),
+            });
+
+            let mut hashed = HashMap::new();
+            for mv in &batch.moves {
+                if cancel.load(Ordering::Relaxed) {
+                    return Ok(cancelled_summary(summary, log));
+                }
+                let started = Instant::now();
+                let mut removed_duplicate = false;
+                // With checksums the source is hashed before it is placed, and copies are
+                // checked byte for byte against it, so a bad copy fails the move instead of
+                // going into the manifest.
+                let digest = if options.checksums {
+                    sha256_file(&mv.src).map(Some)
+                } else {
+                    Ok(None)
+                };
+                let verify = options.verify_copies || options.checksums;
+                let placed = digest.and_then(|digest| {
+                    let placed = match (&mv.destination, batch.copy) {
+                        // An identical file is already in place (checked again, as it may have
+                        // changed since planning), so there is nothing to copy. A move removes the
+                        // source instead of replacing that file, which keeps re-runs idempotent
+                        // and leaves rollback a copy to restore the source from.
+                        (DestinationState::Identical, copy)
+                            if files_are_identical(&mv.src, &mv.dst).unwrap_or(false) =>
+                        {
+                            if copy {
+                                Ok(None)
+                            } else {
+                                removed_duplicate = true;
+                                fs::remove_file(&mv.src)
+                                    .with_context(|| {
+                                        format!("failed to remove {}", mv.src.display())
+                                    })
+                                    .map(|()| Some(mv.dst.clone()))
+                            }
+                        }
+                        (_, copy) => place_volume(mv, copy, verify).map(Some),
+                    };
+                    Ok(placed?.map(|placed| (placed, digest)))
+                });
+                timings.add(Phase::Moves, started.elapsed());
+                moves_done += 1;
+                let mut dst = mv.dst.clone();
+                match placed {
+                    Ok(Some((placed, digest))) => {
+                        dst = placed;
+                        let (src, placed) = (mv.src.clone(), dst.clone());
+                        let entry = if batch.copy {
+                            JournalEntry::Copy { src, dst: placed }
+                        } else if removed_duplicate {
+                            JournalEntry::RemovedDuplicate { src, kept: placed }
+                        } else {
+                            JournalEntry::Move { src, dst: placed }
+                        };
+                        record_shared(journal.as_ref(), entry)?;
+                        if dst != mv.dst {
+                            log(LogEvent::Warn(format!(
+                                "{} was taken by another run; placed {} as {} instead",
+                                mv.dst_name,
+                                file_name_text(&mv.src),
+                                file_name_text(&dst)
+                            )));
+                        }
+                        summary.moved.push((mv.src.clone(), dst.clone()));
+                        if let Some(digest) = digest {
+                            hashed.insert(file_name_text(&dst), digest);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) if options.continue_on_error => {
+                        let error = format!("{err:#}");
+                        log(LogEvent::MoveFailed {
+                            from: mv.src.clone(),
+                            to: mv.dst.clone(),
+                            error: error.clone(),
+                        });
+                        summary.failed.push(FailedMove {
+                            src: mv.src.clone(),
+                            dst: mv.dst.clone(),
+                            error,
+                        });
+                        continue;
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+                log(LogEvent::Move {
+                    index: moves_done,
+                    total: total_moves,
+                    batch: batch.batch_index,
+                    from: mv.src.clone(),
+                    to: dst,
+                    copied: batch.copy,
+                });
+            }
+
+            if let Some(metadata) = &options.metadata {
+                let path = write_batch_metadata(batch, metadata, journal.as_ref())?;
+                summary.metadata_written.push(path);
+            }
+            if options.checksums {
+                let path = write_checksums(&batch.batch_dir, &hashed, journal.as_ref())?;
+                summary.checksums_written.push(path);
+            }
+
+            let clean = summary.failed.len() == failed_before;
+            if clean && series_cover.is_none() {
+                summary.batches_completed += 1;
+            }
+            moved_cleanly.push(clean);
+        }
+
+        if let (Some(cover), Some(font)) = (series_cover, font.as_ref()) {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(cancelled_summary(summary, log));
+            }
+            let render = |batch: &BatchPlan| {
+                render_batch_cover(
+                    &batch.batch_dir,
+                    batch.batch_index,
+                    cover,
+                    &cover_style,
+                    options.existing_cover,
+                    font,
+                    journal.as_ref(),
+                )
+            };
+            let results: Vec<Result<RenderedCover>> = if group.len() == 1 {
+                group.iter().map(render).collect()
+            } else {
+                thread::scope(|scope| {
+                    let handles: Vec<_> = group
+                        .iter()
+                        .map(|batch| scope.spawn(move || render(batch)))
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| {
+                            handle
+                                .join()
+                                .unwrap_or_else(|_| Err(anyhow!("cover rendering panicked")))
+                        })
+                        .collect()
+                })
+            };
+            for ((batch, result), clean) in group.iter().zip(results).zip(moved_cleanly) {
+                let rendered = result?;
+                summary.covers_archived.extend(rendered.archived.clone());
+                summary.covers_written.extend(rendered.base);
+                timings.merge(&rendered.timings);
+                if rendered.kept {
+                    log(LogEvent::CoverKept {
+                        batch: batch.batch_index,
+                    });
+                } else {
+                    summary.covers_written.push(rendered.cover);
+                    log(LogEvent::CoverRendered {
+                        batch: batch.batch_index,
+                    });
+                }
+                if let Some(keep) = options.prune_covers {
+                    let pruned =
+                        prune_cover_old(&batch.batch_dir, keep, rendered.archived.as_deref())?;
+                    if !pruned.is_empty() {
+                        log(LogEvent::CoverNote(format!(
+                            "Removed {} old covers from {}",
+                            pruned.len(),
+                            file_name_text(&batch.batch_dir)
+                        )));
+                    }
+                    summary.covers_pruned.extend(pruned);
+                }
+                if clean {
+                    summary.batches_completed += 1;
+                }
+            }
+        }
+    }
+
+    timings.log(log);
+    log(LogEvent::Complete);
+    Ok(summary)
+}
+
+/// Reverses the changes recorded in `journal`, newest first. Entries that no longer match the
+/// filesystem (for example a file the user already moved back) are skipped with a warning. An
+/// entry that fails to undo doesn't stop the rest: every failure is logged, the failed entries
+/// stay in `journal` so rollback can be retried, and the error lists them all. Once the journal
+/// is done with, the one an earlier run archived (see `Journal::create`) takes its place.
+pub fn rollback(journal: &Path, log: &mut dyn FnMut(LogEvent)) -> Result<(), Error> {
+    let entries = Journal::load(journal)?;
+    log(LogEvent::RollbackStarted {
+        entries: entries.len(),
+        journal: journal.to_path_buf(),
+    });
+
+    let mut failed = Vec::new();
+    for entry in entries.iter().rev() {
+        if let Err(err) = undo_entry(entry, log) {
+            log(LogEvent::Warn(format!("Could not undo: {err:#}")));
+            failed.push((entry.clone(), format!("{err:#}")));
+        }
+    }
+    if !failed.is_empty() {
+        let remaining = Journal {
+            path: journal.to_path_buf(),
+            contents: JournalFile {
+                entries: failed
+                    .iter()
+                    .rev()
+                    .map(|(entry, _)| entry.clone())
+                    .collect(),
+            },
+        };
+        remaining.flush()?;
+        let reasons: Vec<String> = failed.into_iter().map(|(_, reason)| reason).collect();
+        return Err(anyhow!(
+            "rollback could not undo {} changes, kept in {}: {}",
+            reasons.len(),
+            journal.display(),
+            reasons.join("; ")
+        )
+        .into());
+    }
+
+    fs::remove_file(journal)
+        .with_context(|| format!("failed to remove journal: {}", journal.display()))?;
+    if let Some(archived) = journal_archives(journal).pop() {
+        fs::rename(&archived, journal)
+            .with_context(|| format!("failed to restore journal: {}", archived.display()))?;
+    }
+    log(LogEvent::RollbackComplete);
+    Ok(())
+}
+
+fn undo_entry(entry: &JournalEntry, log: &mut dyn FnMut(LogEvent)) -> Result<()> {
+    match entry {
+        JournalEntry::Move { src, dst } => {
+            if src.exists() {
+                log(LogEvent::Warn(format!(
+                    "Skipping {}: original location is already occupied",
+                    src.display()
+                )));
+            } else if !dst.exists() {
+                log(LogEvent::Warn(format!(
+                    "Skipping {}: file is no longer at {}",
+                    src.display(),
+                    dst.display()
+                )));
+            } else {
+                move_file(dst, src, false)?;
+                log(LogEvent::Restored {
+                    from: dst.clone(),
+                    to: src.clone(),
+                });
+            }
+        }
+        JournalEntry::RemovedDuplicate { src, kept } => {
+            if src.exists() {
+                log(LogEvent::Warn(format!(
+                    "Skipping {}: original location is already occupied",
+                    src.display()
+                )));
+            } else if !kept.is_file() {
+                log(LogEvent::Warn(format!(
+                    "Skipping {}: file is no longer at {}",
+                    src.display(),
+                    kept.display()
+                )));
+            } else {
+                copy_file_new(kept, src, false)?;
+                log(LogEvent::Restored {
+                    from: kept.clone(),
+                    to: src.clone(),
+                });
+            }
+        }
+        JournalEntry::Copy { src, dst } => {
+            if !src.exists() {
+                log(LogEvent::Warn(format!(
+                    "Keeping {}: the original {} is gone",
+                    dst.display(),
+                    src.display()
+                )));
+            } else if dst.exists() {
+                fs::remove_file(dst)
+                    .with_context(|| format!("failed to remove copy: {}", dst.display()))?;
+            }
+        }
+        JournalEntry::WriteFile { path } => {
+            if path.exists() {
+                fs::remove_file(path)
+                    .with_context(|| format!("failed to remove file: {}", path.display()))?;
+            }
+        }
+        JournalEntry::ReplaceFile { path, previous } => {
+            write_atomically(path, |out| {
+                out.write_all(previous.as_bytes())
+                    .with_context(|| format!("failed to restore {}", path.display()))
+            })?;
+        }
+        JournalEntry::CreateDir { path } => {
+            if !path.exists() {
+                return Ok(());
+            }
+            if fs::remove_dir(path).is_err() {
+                log(LogEvent::Warn(format!(
+                    "Leaving {} in place: folder is not empty",
+                    path.display()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Where `unbatch` would put each volume: every batch folder's volumes, in batch order, back
+/// in `series_dir`. Names already taken there get a numbered name. Read-only.
+pub fn plan_unbatch(series_dir: &Path, options: &PlanOptions) -> Result<Vec<FileMove>, Error> {
+    let mut reserved = HashSet::new();
+    let mut moves = Vec::new();
+    for batch in find_existing_batches(series_dir, options)? {
+        for src in batch.volumes {
+            let name = file_name_text(&src);
+            let dst = unique_path_reserved(series_dir, &name, &mut reserved);
+            let dst_name = file_name_text(&dst);
+            let destination = if dst_name == name {
+                DestinationState::Free
+            } else {
+                DestinationState::Deduplicated { taken_name: name }
+            };
+            moves.push(FileMove {
+                src,
+                dst,
+                dst_name,
+                destination,
+                problem: None,
+            });
+        }
+    }
+    Ok(moves)
+}
+
+/// Generated files that `unbatch` removes from a batch folder once its volumes are out.
+fn is_generated_batch_file(name: &str) -> bool {
+    name == "cover.jpg" || name == METADATA_FILE_NAME || COVER_OLD_RE.is_match(name)
+}
+
+/// Undoes the batching without a journal: moves every volume from the batch folders back into
+/// `series_dir` (see `plan_unbatch`), then deletes the folders' generated covers and metadata
+/// and the folders themselves. A folder holding anything else is left in place with a warning.
+pub fn unbatch(
+    series_dir: &Path,
+    options: &PlanOptions,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<Vec<FileMove>, Error> {
+    let batch_dirs: Vec<PathBuf> = find_existing_batches(series_dir, options)?
+        .into_iter()
+        .map(|batch| batch.batch_dir)
+        .collect();
+    let moves = plan_unbatch(series_dir, options)?;
+    for planned in &moves {
+        move_file_new(&planned.src, &planned.dst, false)?;
+        log(LogEvent::Restored {
+            from: planned.src.clone(),
+            to: planned.dst.clone(),
+        });
+    }
+
+    for batch_dir in &batch_dirs {
+        for entry in fs::read_dir(batch_dir)
+            .with_context(|| format!("failed to read directory: {}", batch_dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_file() && is_generated_batch_file(&file_name_text(&path)) {
+                fs::remove_file(&path)
+                    .with_context(|| format!("failed to remove file: {}", path.display()))?;
+            }
+        }
+        if fs::remove_dir(batch_dir).is_err() {
+            log(LogEvent::Warn(format!(
+                "Leaving {} in place: folder is not empty",
+                batch_dir.display()
+            )));
+        }
+    }
+    Ok(moves)
+}
+
+/// Everything `analyze` worked out about a series folder.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub series_dir: PathBuf,
+    pub cover: CoverDecision,
+    pub plan: Vec<BatchPlan>,
+    pub volume_count: usize,
+    pub rename_count: usize,
+    pub duplicate_count: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct AnalyzeOptions {
+    pub plan: PlanOptions,
+    pub cover: SeriesCoverOptions,
+}
+
+/// Decides the series cover and builds the plan without writing to the series folder. Use
+/// `apply_cover_decision` on `Analysis::cover` before `execute` to create the cover file.
+pub fn analyze(
+    series_dir: &Path,
+    options: &AnalyzeOptions,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<Analysis, Error> {
+    if !series_dir.is_dir() {
+        return Err(Error::PathResolution(anyhow!(
+            "Not a directory: {}",
+            series_dir.display()
+        )));
+    }
+
+    let cover = decide_series_cover(series_dir, &file_name_text(series_dir), &options.cover, log)?;
+    let started = Instant::now();
+    let plan = build_plan(series_dir, cover.planned_path(), &options.plan)?;
+    log(LogEvent::Timing {
+        phase: Phase::Planning,
+        elapsed: started.elapsed(),
+    });
+    let volume_count = plan.iter().map(|batch| batch.moves.len()).sum();
+    let rename_count = rename_count(&plan);
+    let duplicate_count = plan.iter().map(|batch| batch.duplicates.len()).sum();
+
+    Ok(Analysis {
+        series_dir: series_dir.to_path_buf(),
+        cover,
+        plan,
+        volume_count,
+        rename_count,
+        duplicate_count,
+    })
+}
+
+/// Share of volumes above which `rename_warning` flags a plan.
+pub const DEFAULT_RENAME_WARN_RATIO: f32 = 0.8;
+
+/// How many planned volumes get a new file name.
+pub fn rename_count(plan: &[BatchPlan]) -> usize {
+    plan.iter()
+        .flat_map(|batch| batch.moves.iter())
+        .filter(|mv| file_name_text(&mv.src) != mv.dst_name)
+        .count()
+}
+
+/// The lowercase words of a file stem that have no digits in them, i.e. the title and tags
+/// without volume or chapter numbers.
+fn title_words(name: &str) -> HashSet<String> {
+    let stem = Path::new(name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    stem.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !word.chars().any(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// A warning when more than `ratio` of the planned volumes would come out with title words
+/// that weren't in their old name, or with no title left at all. That is more often a
+/// cleaning rule gone wrong than a messy folder; dropping tags and padding numbers, which
+/// renames most volumes, doesn't count. A ratio of 1 never warns.
+pub fn rename_warning(plan: &[BatchPlan], ratio: f32) -> Option<String> {
+    let moves = || plan.iter().flat_map(|batch| batch.moves.iter());
+    let total = moves().count();
+    let retitled = moves()
+        .filter(|mv| {
+            let before = title_words(&file_name_text(&mv.src));
+            let after = title_words(&mv.dst_name);
+            (after.is_empty() && !before.is_empty()) || !after.is_subset(&before)
+        })
+        .count();
+    if total == 0 || retitled as f32 <= ratio * total as f32 {
+        return None;
+    }
+    Some(format!(
+        "{retitled} of {total} volumes ({}%) would get a different title. Check the cleaning rules before processing.",
+        retitled * 100 / total
+    ))
+}
+
+pub fn run_action(
+    action: UiAction,
+    series_dir: &Path,
+    log: &mut dyn FnMut(LogEvent),
+) -> Result<ActionOutput, Error> {
+    if !series_dir.is_dir() {
+        return Err(Error::PathResolution(anyhow!(
+            "Not a directory: {}",
+            series_dir.display()
+        )));
+    }
+
+    match action {
+        UiAction::ShowCover => {
+            let decision = decide_series_cover(
+                series_dir,
+                &file_name_text(series_dir),
+                &SeriesCoverOptions::default(),
+                log,
+            )?;
+            let Some(preview) = preview_series_cover(&decision, log)? else {
+                return Err(Error::CoverNotFound(anyhow!(
+                    "[COVER-CHECK] No cover found from local files or remote providers."
+                )));
+            };
+
+            log(LogEvent::Info(preview.display().to_string()));
+            Ok(ActionOutput {
+                action,
+                cover_path: Some(preview),
+            })
+        }
+        UiAction::Preview => {
+            let options = AnalyzeOptions::default();
+            let analysis = analyze(series_dir, &options, log)?;
+            let plan_text = format_plan(
+                series_dir,
+                &analysis.plan,
+                analysis.cover.planned_path(),
+                &options.plan,
+            );
+            for line in plan_text.lines() {
+                log(LogEvent::Info(line.to_string()));
+            }
+            log(LogEvent::Info(
+                "[DRY-RUN] Plan printed only. No changes were made.".to_string(),
+            ));
+            Ok(ActionOutput {
+                action,
+                cover_path: None,
+            })
+        }
+        UiAction::Process => {
+            let series_cover = ensure_series_cover(
+                series_dir,
+                &file_name_text(series_dir),
+                &SeriesCoverOptions::default(),
+                log,
+            )?;
+            let options = PlanOptions::default();
+            let plan = build_plan(series_dir, series_cover.as_deref(), &options)?;
+            let plan_text = format_plan(series_dir, &plan, series_cover.as_deref(), &options);
+            for line in plan_text.lines() {
+                log(LogEvent::Info(line.to_string()));
+            }
+            let execute_options = ExecuteOptions {
+                journal: Some(journal_path(series_dir)),
+                ..ExecuteOptions::default()
+            };
+            execute(
+                &plan,
+                series_cover.as_deref(),
+                &execute_options,
+                &AtomicBool::new(false),
+                log,
+            )?;
+            Ok(ActionOutput {
+                action,
+                cover_path: None,
+            })
+        }
+    }
+}
+
+pub fn prompt_confirm(prompt: &str) -> Result<bool> {
+    print!("{prompt}");
+    io::stdout().flush().context("failed to flush stdout")?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .context("failed to read user input")?;
+
+    let answer = input.trim().to_ascii_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
+/// Asks for a number from 1 to `count` until one is given, returning it zero-based. An empty
+/// answer (or end of input) returns `None`.
+pub fn prompt_choice(prompt: &str, count: usize) -> Result<Option<usize>> {
+    loop {
+        print!("{prompt}");
+        io::stdout().flush().context("failed to flush stdout")?;
+
+        let mut input = String::new();
+        let read = io::stdin()
+            .read_line(&mut input)
+            .context("failed to read user input")?;
+        let answer = input.trim();
+        if read == 0 || answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => return Ok(Some(n - 1)),
+            _ => println!("Enter a number from 1 to {count}, or nothing to skip."),
+        }
+    }
+}
+
+pub const CONFIG_FILE_NAME: &str = "config.json";
+
+/// Defaults for the CLI and the native app, read from a JSON file. Every key is optional;
+/// command-line flags win over values from the file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub batch_size: Option<usize>,
+    pub chapters: Option<bool>,
+    pub recursive: Option<bool>,
+    pub keep_duplicates: Option<bool>,
+    pub clean_rules: Option<PathBuf>,
+    pub rename_warn_ratio: Option<f32>,
+    pub cover_dir: Option<PathBuf>,
+    /// Remote providers to query, in priority order (`mangadex`, `anilist`, `kitsu`).
+    pub cover_providers: Option<Vec<String>>,
+    pub offline: Option<bool>,
+    pub font: Option<PathBuf>,
+    pub jpeg_quality: Option<u8>,
+    pub keep_cover_format: Option<bool>,
+    #[serde(deserialize_with = "deserialize_parsed")]
+    pub spread: Option<SpreadHandling>,
+    pub spread_ratio: Option<f32>,
+    #[serde(deserialize_with = "deserialize_parsed")]
+    pub reading_direction: Option<ReadingDirection>,
+    #[serde(deserialize_with = "deserialize_parsed")]
+    pub cover_text_color: Option<CoverTextColor>,
+    pub cover_outline: Option<bool>,
+    #[serde(deserialize_with = "deserialize_parsed")]
+    pub existing_cover: Option<ExistingCover>,
+    pub prune_covers: Option<usize>,
+    pub jobs: Option<usize>,
+    pub write_metadata: Option<bool>,
+    pub checksums: Option<bool>,
+    pub validate_archives: Option<bool>,
+    pub copy: Option<bool>,
+    pub incremental: Option<bool>,
+    pub continue_on_error: Option<bool>,
+    #[serde(deserialize_with = "deserialize_parsed")]
+    pub folder_template: Option<FolderTemplate>,
+    #[serde(deserialize_with = "deserialize_parsed")]
+    pub sanitize_names: Option<SanitizeNames>,
+    pub lowercase_extensions: Option<bool>,
+    /// File name globs to leave out of the plan; see `IgnorePattern`.
+    #[serde(deserialize_with = "deserialize_parsed_list")]
+    pub ignore: Option<Vec<IgnorePattern>>,
+    /// Extension lists replacing `VOLUME_EXTS` and `IMAGE_EXTS`; see `ExtensionSet`.
+    #[serde(deserialize_with = "deserialize_extensions")]
+    pub volume_extensions: Option<ExtensionSet>,
+    #[serde(deserialize_with = "deserialize_extensions")]
+    pub image_extensions: Option<ExtensionSet>,
+    /// Requests per second per host (`0` for no limit).
+    pub request_rate: Option<f64>,
+    /// HTTP settings; see `HttpSettings::with_overrides`. `timeout` is in seconds.
+    pub timeout: Option<u64>,
+    pub user_agent: Option<String>,
+    pub referer: Option<String>,
+    pub proxy: Option<String>,
+    /// `false` ignores `HTTPS_PROXY` and the other proxy environment variables.
+    pub env_proxy: Option<bool>,
+    /// MangaDex search filters; see `CoverOptions`.
+    #[serde(deserialize_with = "deserialize_parsed_list")]
+    pub cover_content_ratings: Option<Vec<ContentRating>>,
+    pub cover_languages: Option<Vec<String>>,
+    pub cover_original_languages: Option<Vec<String>>,
+}
+
+fn deserialize_parsed<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| value.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn deserialize_parsed_list<'de, D, T>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    Option::<Vec<String>>::deserialize(deserializer)?
+        .map(|values| {
+            values
+                .iter()
+                .map(|value| value.parse().map_err(serde::de::Error::custom))
+                .collect()
+        })
+        .transpose()
+}
+
+fn deserialize_extensions<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<ExtensionSet>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<Vec<String>>::deserialize(deserializer)?
+        .map(|exts| ExtensionSet::new(&exts).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+impl Config {
+    /// `manga_cleaner/config.json` under the user config directory (`$XDG_CONFIG_HOME` or
+    /// `~/.config`, `%APPDATA%` on Windows).
+    pub fn default_path() -> Option<PathBuf> {
+        user_config_dir().map(|dir| dir.join("manga_cleaner").join(CONFIG_FILE_NAME))
+    }
+
+    /// Loads the file at `default_path`, or the defaults when there is none.
+    pub fn load_default() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) if path.is_file() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Reads a config file. Errors name the offending key.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes =
+            fs::read(path).with_context(|| format!("failed to read config: {}", path.display()))?;
+        let value: Value = serde_json::from_slice(&bytes)
+            .with_context(|| format!("failed to parse config: {}", path.display()))?;
+        // Flatten the cause into the message so the key shows up however the error is printed.
+        Self::from_value(value).map_err(|err| anyhow!("invalid config {}: {err:#}", path.display()))
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        let Value::Object(fields) = value else {
+            bail!("expected a JSON object of settings");
+        };
+        // Check keys one at a time so the error says which one is wrong.
+        for (key, field) in &fields {
+            let single = Value::Object([(key.clone(), field.clone())].into_iter().collect());
+            if let Err(err) = serde_json::from_value::<Config>(single) {
+                bail!("config key `{key}`: {err}");
+            }
+        }
+        let config: Config = serde_json::from_value(Value::Object(fields))?;
+        if config.batch_size == Some(0) {
+            bail!("config key `batch_size`: must be at least 1");
+        }
+        if config.jobs == Some(0) {
+            bail!("config key `jobs`: must be at least 1");
+        }
+        if config.timeout == Some(0) {
+            bail!("config key `timeout`: must be at least 1");
+        }
+        if matches!(config.request_rate, Some(rate) if !rate.is_finite() || rate < 0.0) {
+            bail!("config key `request_rate`: must be zero or a positive number");
+        }
+        if matches!(config.rename_warn_ratio, Some(ratio) if !(0.0..=1.0).contains(&ratio)) {
+            bail!("config key `rename_warn_ratio`: must be a number from 0 to 1");
+        }
+        if matches!(config.spread_ratio, Some(ratio) if !ratio.is_finite() || ratio < 1.0) {
+            bail!("config key `spread_ratio`: must be a number of at least 1");
+        }
+        if matches!(config.jpeg_quality, Some(q) if !(1..=100).contains(&q)) {
+            bail!("config key `jpeg_quality`: must be between 1 and 100");
+        }
+        if let Some(names) = &config.cover_providers {
+            cover_providers_named(names, &CoverOptions::default())
+                .context("config key `cover_providers`")?;
+        }
+        Ok(config)
+    }
+
+    /// Applies the offline, rate-limit and HTTP settings to the whole process.
+    pub fn apply_network_settings(&self) {
+        set_offline(self.offline.unwrap_or(false));
+        set_request_rate(self.request_rate.unwrap_or(DEFAULT_REQUEST_RATE));
+        set_http_settings(HttpSettings {
+            proxy: self.proxy.clone(),
+            env_proxy: self.env_proxy.unwrap_or(true),
+            ..HttpSettings::with_overrides(
+                self.timeout,
+                self.user_agent.as_deref(),
+                self.referer.as_deref(),
+            )
+        });
+    }
+
+    /// The MangaDex search filters the config asks for.
+    pub fn cover_search_options(&self) -> CoverOptions {
+        CoverOptions {
+            content_ratings: self.cover_content_ratings.clone().unwrap_or_default(),
+            languages: self.cover_languages.clone().unwrap_or_default(),
+            original_languages: self.cover_original_languages.clone().unwrap_or_default(),
+            ..CoverOptions::default()
+        }
+    }
+
+    /// The plan and cover options the config asks for, as the native app and the server run
+    /// with. Loads `clean_rules` and builds the cover providers, but leaves
+    /// `PlanOptions::validate` to the caller, which may still change the batch size.
+    pub fn analyze_options(&self) -> Result<AnalyzeOptions> {
+        let plan = PlanOptions {
+            batch_size: self.batch_size.unwrap_or(FILES_PER_FOLDER),
+            chapters: self.chapters.unwrap_or(false),
+            clean_rules: match &self.clean_rules {
+                Some(path) => CleanRules::load(path)?,
+                None => CleanRules::default(),
+            },
+            keep_duplicates: self.keep_duplicates.unwrap_or(false),
+            recursive: self.recursive.unwrap_or(false),
+            validate_archives: self.validate_archives.unwrap_or(false),
+            folder_template: self.folder_template.clone().unwrap_or_default(),
+            copy: self.copy.unwrap_or(false),
+            incremental: self.incremental.unwrap_or(false),
+            sanitize_names: self.sanitize_names.unwrap_or_default(),
+            lowercase_extensions: self.lowercase_extensions.unwrap_or(false),
+            ignore: self.ignore.clone().unwrap_or_default(),
+            volume_exts: self
+                .volume_extensions
+                .clone()
+                .unwrap_or_else(ExtensionSet::volumes),
+        };
+
+        let search = self.cover_search_options();
+        let providers = match &self.cover_providers {
+            Some(names) => cover_providers_named(names, &search)?,
+            None => cover_providers(&search),
+        };
+        let cover = SeriesCoverOptions {
+            providers,
+            recursive: plan.recursive,
+            cover_dir: self.cover_dir.clone(),
+            volume_exts: plan.volume_exts.clone(),
+            image_exts: self
+                .image_extensions
+                .clone()
+                .unwrap_or_else(ExtensionSet::images),
+            keep_cover_format: self.keep_cover_format.unwrap_or(false),
+            spread: self.spread.unwrap_or_default(),
+            spread_ratio: self.spread_ratio.unwrap_or(DEFAULT_SPREAD_RATIO),
+            reading_direction: self.reading_direction.unwrap_or_default(),
+            // Cached URLs were chosen without search filters.
+            cache: if search == CoverOptions::default() {
+                CoverCache::default_location()
+            } else {
+                None
+            },
+            ..SeriesCoverOptions::default()
+        };
+        Ok(AnalyzeOptions { plan, cover })
+    }
+
+    /// The execute options the config asks for when processing `series_dir` with the cover
+    /// decided for it. Every run is journaled.
+    pub fn execute_options(&self, series_dir: &Path, cover: &CoverDecision) -> ExecuteOptions {
+        ExecuteOptions {
+            journal: Some(journal_path(series_dir)),
+            cover_style: CoverStyle {
+                text_color: self.cover_text_color.unwrap_or_default(),
+                outline: self.cover_outline.unwrap_or(false),
+                font: self.font.clone(),
+                jpeg_quality: self.jpeg_quality,
+                ..CoverStyle::default()
+            },
+            jobs: self
+                .jobs
+                .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get())),
+            metadata: self.write_metadata.unwrap_or(false).then(|| BatchMetadata {
+                series_title: file_name_text(series_dir),
+                cover_source: cover.source_label(),
+            }),
+            checksums: self.checksums.unwrap_or(false),
+            continue_on_error: self.continue_on_error.unwrap_or(false),
+            existing_cover: self.existing_cover.unwrap_or_default(),
+            prune_covers: self.prune_covers,
+            ..ExecuteOptions::default()
+        }
+    }
+}
+
+pub const SESSION_FILE_NAME: &str = "session.json";
+
+/// What the native app remembers between launches: the last folder it opened and the values of
+/// its controls. Unlike `Config`, the app writes this file itself.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub series_dir: Option<PathBuf>,
+    /// `series_dir` was opened as a library of series rather than a single series.
+    pub library: bool,
+    pub batch_size: Option<usize>,
+    /// A `CoverTextColor` in its `FromStr` form.
+    pub cover_text_color: Option<String>,
+}
+
+impl Session {
+    /// `manga_cleaner/session.json`, next to `Config::default_path`.
+    pub fn default_path() -> Option<PathBuf> {
+        user_config_dir().map(|dir| dir.join("manga_cleaner").join(SESSION_FILE_NAME))
+    }
+
+    /// Reads a session file; a missing file is an empty session.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+        serde_json::from_slice(&bytes)
+            .with_context(|| format!("failed to parse session: {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomically(path, |out| {
+            serde_json::to_writer_pretty(out, self)
+                .with_context(|| format!("failed to write session: {}", path.display()))
+        })
+    }
+
+    /// Deletes the session file, so the next launch starts blank.
+    pub fn forget(path: &Path) -> Result<()> {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("failed to delete {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgb, RgbImage};
+
+    fn bbox_for_mask(mask: impl Iterator<Item = (u32, u32)>) -> Option<(u32, u32, u32, u32)> {
+        let mut found = false;
+        let mut min_x = u32::MAX;
+        let mut min_y = u32::MAX;
+        let mut max_x = 0;
+        let mut max_y = 0;
+
+        for (x, y) in mask {
+            found = true;
+            if x < min_x {
+                min_x = x;
+            }
+            if y < min_y {
+                min_y = y;
+            }
+            if x > max_x {
+                max_x = x;
+            }
+            if y > max_y {
+                max_y = y;
+            }
+        }
+
+        if !found {
+            return None;
+        }
+        Some((min_x, min_y, max_x, max_y))
+    }
+
+    fn center_of_bbox(b: (u32, u32, u32, u32)) -> (f32, f32) {
+        let (x0, y0, x1, y1) = b;
+        ((x0 + x1) as f32 / 2.0, (y0 + y1) as f32 / 2.0)
+    }
+
+    /// Numbers `base` the way `execute` does, through `render_batch_cover`, and returns the
+    /// `cover_old.jpg` it drew on alongside the rendered `cover.jpg`.
+    fn render_numbered_cover(
+        name: &str,
+        base: &DynamicImage,
+        number: usize,
+        style: &CoverStyle,
+    ) -> (DynamicImage, DynamicImage) {
+        let dir = scratch_dir(name);
+        let series_cover = dir.join("series.jpg");
+        save_jpeg_with_quality(base, &series_cover, 100).expect("save series cover");
+        let batch = dir.join("Series 1");
+        let font = pick_font(None).expect("cover font");
+        let style = CoverStyle {
+            jpeg_quality: Some(100),
+            ..style.clone()
+        };
+        let rendered = render_batch_cover(
+            &batch,
+            number,
+            &series_cover,
+            &style,
+            ExistingCover::Archive,
+            &font,
+            None,
+        )
+        .expect("render cover");
+        let decode = |path: &Path| {
+            ImageReader::open(path)
+                .expect("open cover")
+                .decode()
+                .expect("decode cover")
+        };
+        let images = (
+            decode(&batch.join("cover_old.jpg")),
+            decode(&rendered.cover),
+        );
+        let _ = fs::remove_dir_all(&dir);
+        images
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("manga_cleaner_test_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create scratch dir");
+        dir
+    }
+
+    fn png_bytes(w: u32, h: u32) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(w, h, Rgb([200, 30, 30])));
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .expect("encode png");
+        bytes
+    }
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let file = fs::File::create(path).expect("create zip");
+        let mut writer = zip::ZipWriter::new(file);
+        for (name, data) in entries {
+            writer
+                .start_file(*name, zip::write::FileOptions::default())
+                .expect("start zip entry");
+            writer.write_all(data).expect("write zip entry");
+        }
+        writer.finish().expect("finish zip");
+    }
+
+    #[test]
+    fn build_plan_honors_batch_size() {
+        let dir = scratch_dir("batch_size");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for n in 1..=5 {
+            fs::write(series.join(format!("Series v{n:02}.cbz")), [n]).expect("write volume");
+        }
+
+        let options = PlanOptions {
+            batch_size: 2,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        assert_eq!(plan.len(), 3);
+        assert_eq!(
+            plan.iter().map(|b| b.volume_start).collect::<Vec<_>>(),
+            vec![1, 3, 5]
+        );
+        assert_eq!(plan[2].batch_dir, dir.join("Series 3"));
+
+        let text = format_plan(&series, &plan, None, &options);
+        assert!(text.contains("[PLAN] Batch size: 2"));
+        assert!(text.contains("Series 2  (volumes 3-4)"));
+        assert!(text.contains("Series 3  (volumes 5-5)"));
+
+        let err = build_plan(
+            &series,
+            None,
+            &PlanOptions {
+                batch_size: 0,
+                ..PlanOptions::default()
+            },
+        )
+        .expect_err("zero batch size is rejected");
+        assert!(err.to_string().contains("at least 1"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rollback_restores_journaled_moves() {
+        let dir = scratch_dir("rollback");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        let names = ["Series (CM) v1.cbz", "Series v2.cbz", "Series v3.cbz"];
+        for name in names {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
+        }
+
+        let options = PlanOptions {
+            batch_size: 2,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        let journal = journal_path(&series);
+        let execute_options = ExecuteOptions {
+            journal: Some(journal.clone()),
+            ..ExecuteOptions::default()
+        };
+        let mut progress = Vec::new();
+        let summary = execute(
+            &plan,
+            None,
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |event| {
+                if let LogEvent::Move {
+                    index,
+                    total,
+                    batch,
+                    ..
+                } = event
+                {
+                    progress.push((index, total, batch));
+                }
+            },
+        )
+        .expect("execute plan");
+        assert_eq!(progress, vec![(1, 3, 1), (2, 3, 1), (3, 3, 2)]);
+        assert_eq!(summary.batches_completed, 2);
+        assert!(!summary.cancelled);
+
+        let entries = Journal::load(&journal).expect("load journal");
+        let moves = entries
+            .iter()
+            .filter(|e| matches!(e, JournalEntry::Move { .. }))
+            .count();
+        assert_eq!(moves, 3);
+        assert!(dir.join("Series 1").join("Series v001.cbz").is_file());
+
+        // Simulate the user having already put one file back by hand.
+        fs::rename(
+            dir.join("Series 2").join("Series v003.cbz"),
+            series.join("Series v3.cbz"),
+        )
+        .expect("manual move back");
+
+        let mut events = Vec::new();
+        rollback(&journal, &mut |event| events.push(event)).expect("rollback");
+
+        for name in names {
+            assert!(series.join(name).is_file(), "{name} restored");
+        }
+        assert!(!dir.join("Series 1").exists());
+        assert!(!dir.join("Series 2").exists());
+        assert!(!journal.exists());
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, LogEvent::Warn(m) if m.starts_with("Skipping"))));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn plan_json_lists_moves_and_renames() {
+        let dir = scratch_dir("plan_json");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::write(series.join("Series (CM) v1.cbz"), b"1").expect("write volume");
+        fs::write(series.join("Series v002.cbz"), b"2").expect("write volume");
+
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+        let value = plan_to_json(&series, &plan, None);
+
+        assert_eq!(value["volume_count"], 2);
+        assert!(value["series_cover"].is_null());
+        let batch = &value["batches"][0];
+        assert_eq!(batch["batch_index"], 1);
+        assert_eq!(batch["will_make_cover"], false);
+        assert_eq!(batch["moves"][0]["dst_name"], "Series v001.cbz");
+        assert_eq!(batch["moves"][0]["rename"], true);
+        assert_eq!(batch["moves"][1]["rename"], false);
+        assert_eq!(batch["moves"][1]["cross_device"], false);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn find_volume_cover_selects_volume_and_page() {
+        let dir = scratch_dir("volume_cover");
+        let png = png_bytes(4, 6);
+        write_zip(
+            &dir.join("Series v01.cbz"),
+            &[("a.png", png.as_slice()), ("b.png", &png)],
+        );
+        write_zip(
+            &dir.join("Series v02.cbz"),
+            &[
+                ("p1.png", png.as_slice()),
+                ("p2.png", &png),
+                ("p10.png", &png),
+            ],
+        );
+
+        let cover = find_volume_cover(&dir, 1, 2)
+            .expect("select cover")
+            .expect("cover exists");
+        assert!(cover.volume_file.ends_with("Series v02.cbz"));
+        assert_eq!(cover.image_entry, "p10.png");
+
+        let (first, err) = find_first_volume_cover(&dir);
+        assert!(err.is_none());
+        assert_eq!(first.expect("first cover").image_entry, "a.png");
+
+        let err = find_volume_cover(&dir, 5, 0).expect_err("volume out of range");
+        assert!(err.to_string().contains("2 volumes available"), "{err}");
+        let err = find_volume_cover(&dir, 0, 9).expect_err("page out of range");
+        assert!(err.to_string().contains("2 images available"), "{err}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cover_text_color_parses_and_auto_contrasts() {
+        assert_eq!(
+            "#ff8000".parse::<CoverTextColor>().expect("hex"),
+            CoverTextColor::Rgb([255, 128, 0])
+        );
+        assert_eq!(
+            "FFFFFF".parse::<CoverTextColor>().expect("bare hex"),
+            CoverTextColor::Rgb([255, 255, 255])
+        );
+        assert_eq!(
+            "Auto".parse::<CoverTextColor>().expect("auto"),
+            CoverTextColor::Auto
+        );
+        assert!("#12345".parse::<CoverTextColor>().is_err());
+        assert!("banana".parse::<CoverTextColor>().is_err());
+
+        let dark = DynamicImage::ImageRgb8(RgbImage::from_pixel(30, 30, Rgb([10, 10, 20])));
+        let light = DynamicImage::ImageRgb8(RgbImage::from_pixel(30, 30, Rgb([240, 240, 230])));
+        assert_eq!(
+            resolve_text_color(&dark, CoverTextColor::Auto),
+            [255, 255, 255]
+        );
+        assert_eq!(resolve_text_color(&light, CoverTextColor::Auto), [0, 0, 0]);
+    }
+
+    #[test]
+    fn white_text_is_centered_on_dark_canvas() {
+        let (w, h) = (600, 900);
+        let base = DynamicImage::ImageRgb8(RgbImage::from_pixel(w, h, Rgb([0, 0, 0])));
+        let style = CoverStyle {
+            text_color: CoverTextColor::Rgb([255, 255, 255]),
+            ..CoverStyle::default()
+        };
+        let (_, rendered) = render_numbered_cover("white_text_centered", &base, 7, &style);
+        let rgb = rendered.to_rgb8();
+
+        let bbox = bbox_for_mask(rgb.enumerate_pixels().filter_map(|(x, y, p)| {
+            if p.0[0] > 5 {
+                Some((x, y))
+            } else {
+                None
+            }
+        }))
+        .expect("text pixels should exist");
+
+        let (cx, cy) = center_of_bbox(bbox);
+        assert!((cx - (w as f32 / 2.0)).abs() <= 2.0, "x center {cx}");
+        assert!((cy - (h as f32 / 2.0)).abs() <= 2.0, "y center {cy}");
+    }
+
+    #[test]
+    fn outlined_text_stays_centered_and_adds_stroke() {
+        let (w, h) = (600, 900);
+        let base = DynamicImage::ImageRgb8(RgbImage::from_pixel(w, h, Rgb([128, 128, 128])));
+        let style = CoverStyle {
+            text_color: CoverTextColor::Rgb([255, 255, 255]),
+            ..CoverStyle::default()
+        };
+        let (_, plain) = render_numbered_cover("plain_number", &base, 4, &style);
+        let outlined_style = CoverStyle {
+            outline: true,
+            ..style
+        };
+        let (_, outlined) = render_numbered_cover("outlined_number", &base, 4, &outlined_style);
+
+        let dark_pixels = |image: &DynamicImage| {
+            image
+                .to_rgb8()
+                .enumerate_pixels()
+                .filter(|(_, _, p)| p.0[0] < 60)
+                .map(|(x, y, _)| (x, y))
+                .collect::<Vec<_>>()
+        };
+        assert!(dark_pixels(&plain).is_empty());
+        let stroke = dark_pixels(&outlined);
+        assert!(stroke.len() > 500, "outline too thin: {}", stroke.len());
+
+        let bbox = bbox_for_mask(stroke.into_iter()).expect("outline bbox");
+        let (cx, cy) = center_of_bbox(bbox);
+        assert!((cx - (w as f32 / 2.0)).abs() <= 3.0, "x center {cx}");
+        assert!((cy - (h as f32 / 2.0)).abs() <= 3.0, "y center {cy}");
     }
-    if alt_values.iter().any(|v| v == title_l) {
-        return 3;
+
+    #[test]
+    fn title_text_stays_inside_its_edge_band() {
+        let (w, h) = (600, 900);
+        let base = DynamicImage::ImageRgb8(RgbImage::from_pixel(w, h, Rgb([0, 0, 0])));
+        let font = pick_font(None).expect("cover font");
+        let band_h = (h as f32 * TITLE_BAND_FRAC).round() as u32;
+
+        for position in [TitlePosition::Top, TitlePosition::Bottom] {
+            let rendered =
+                draw_title_text(&base, &font, "One Piece", position, [255, 255, 255], false);
+            let rgb = rendered.to_rgb8();
+            let (_, min_y, _, max_y) = bbox_for_mask(
+                rgb.enumerate_pixels()
+                    .filter_map(|(x, y, p)| (p.0[0] > 5).then_some((x, y))),
+            )
+            .expect("title pixels should exist");
+            match position {
+                TitlePosition::Top => assert!(max_y < band_h, "top title reaches {max_y}"),
+                TitlePosition::Bottom => {
+                    assert!(min_y >= h - band_h, "bottom title starts at {min_y}")
+                }
+            }
+        }
     }
-    if main.contains(title_l) {
-        return 2;
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn retry_delay_backs_off_and_honors_retry_after() {
+        assert_eq!(retry_delay(1, None), Duration::from_millis(500));
+        assert_eq!(retry_delay(2, None), Duration::from_millis(1000));
+        assert_eq!(retry_delay(3, None), Duration::from_millis(2000));
+        assert_eq!(retry_delay(40, None), HTTP_RETRY_MAX_DELAY);
+        assert_eq!(
+            retry_delay(1, parse_retry_after(" 7 ")),
+            Duration::from_secs(7)
+        );
+        assert_eq!(
+            retry_delay(1, parse_retry_after("3600")),
+            HTTP_RETRY_MAX_DELAY
+        );
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+    }
+
+    struct StubProvider {
+        name: &'static str,
+        outcome: std::result::Result<Option<&'static str>, &'static str>,
+    }
+
+    impl CoverProvider for StubProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn fetch(&self, _title: &str) -> Result<Option<CoverResult>> {
+            match self.outcome {
+                Ok(url) => Ok(url.map(|url| CoverResult {
+                    source: self.name.to_string(),
+                    url: url.to_string(),
+                    note: None,
+                    match_score: None,
+                })),
+                Err(msg) => Err(anyhow!(msg)),
+            }
+        }
+    }
+
+    /// A provider that doesn't answer until its sender is dropped, like a lookup hanging on
+    /// a timeout.
+    #[cfg(feature = "network")]
+    struct BlockingProvider {
+        release: Mutex<mpsc::Receiver<()>>,
+    }
+
+    #[cfg(feature = "network")]
+    impl CoverProvider for BlockingProvider {
+        fn name(&self) -> &str {
+            "blocking"
+        }
+
+        fn fetch(&self, _title: &str) -> Result<Option<CoverResult>> {
+            let _ = self.release.lock().map(|release| release.recv());
+            Err(anyhow!("timed out"))
+        }
+    }
+
+    fn stub(
+        name: &'static str,
+        outcome: std::result::Result<Option<&'static str>, &'static str>,
+    ) -> Arc<dyn CoverProvider> {
+        Arc::new(StubProvider { name, outcome })
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn remote_cover_prefers_earliest_provider_with_a_hit() {
+        let providers = vec![
+            stub("first", Ok(None)),
+            stub("second", Ok(Some("https://example.com/second.jpg"))),
+            stub("third", Ok(Some("https://example.com/third.jpg"))),
+        ];
+        let (cover, err) = find_remote_cover("Title", &providers, None);
+        let cover = cover.expect("cover");
+        assert_eq!(cover.source, "second");
+        assert!(err.is_none());
+
+        let providers = vec![
+            stub("a", Err("down")),
+            stub("b", Ok(None)),
+            stub("c", Err("slow")),
+        ];
+        let (cover, err) = find_remote_cover("Title", &providers, None);
+        assert!(cover.is_none());
+        assert_eq!(err.as_deref(), Some("a: down; c: slow"));
+
+        // A hanging lower-priority provider doesn't hold up a hit from a better one.
+        let (release, blocked) = mpsc::channel();
+        let providers = vec![
+            stub("first", Ok(None)),
+            stub("second", Ok(Some("https://example.com/second.jpg"))),
+            Arc::new(BlockingProvider {
+                release: Mutex::new(blocked),
+            }),
+        ];
+        let started = Instant::now();
+        let (cover, err) = find_remote_cover("Title", &providers, None);
+        assert_eq!(cover.expect("cover").source, "second");
+        assert!(err.is_none());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        drop(release);
+    }
+
+    #[test]
+    #[cfg(not(feature = "network"))]
+    fn without_the_network_feature_remote_lookups_report_why() {
+        let providers = vec![stub("first", Ok(Some("https://example.com/first.jpg")))];
+        let (cover, err) = find_remote_cover("Title", &providers, None);
+        assert!(cover.is_none());
+        assert!(err.expect("error").contains("network"));
+
+        let dir = scratch_dir("no_network");
+        let err = download_file(
+            "https://example.com/a.jpg",
+            &dir.join("a.jpg"),
+            5,
+            &mut |_, _| {},
+        )
+        .expect_err("download should fail");
+        assert!(err.to_string().contains("`network` feature"));
+        assert!(!dir.join("a.jpg").exists());
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn cover_cache_serves_fresh_hits_and_ignores_stale_or_corrupt_entries() {
+        let dir = scratch_dir("cover_cache");
+        let cache = CoverCache::new(dir.join("cache").join(COVER_CACHE_FILE_NAME));
+        assert!(cache.get("Some Title").is_none());
+
+        let providers = vec![stub("live", Ok(Some("https://example.com/live.jpg")))];
+        let (cover, _) = find_remote_cover("Some Title!", &providers, Some(&cache));
+        let cover = cover.expect("cover");
+        assert_eq!(cover.source, "live");
+        // Hits are only cached once their download worked.
+        assert!(cache.get("Some Title").is_none());
+        settle_cached_cover(&cover, true);
+
+        let failing = vec![stub("live", Err("offline"))];
+        let (cover, err) = find_remote_cover("some title", &failing, Some(&cache));
+        assert_eq!(
+            cover.expect("cached cover").url,
+            "https://example.com/live.jpg"
+        );
+        assert!(err.is_none());
+
+        let expired = CoverCache {
+            ttl: Duration::ZERO,
+            ..cache.clone()
+        };
+        let stale = format!(
+            r#"{{"sometitle":{{"source":"live","url":"u","fetched_at":{}}}}}"#,
+            unix_now() - 60
+        );
+        fs::write(&cache.path, stale).expect("write stale cache");
+        assert!(expired.get("Some Title").is_none());
+
+        let scoped = CoverCache {
+            namespace: Some("title-override".to_string()),
+            ..cache.clone()
+        };
+        assert!(cache.get("Some Title").is_some());
+        assert!(scoped.get("Some Title").is_none());
+
+        fs::write(&cache.path, b"not json").expect("write corrupt cache");
+        assert!(cache.get("Some Title").is_none());
+        let (cover, _) = find_remote_cover("Some Title", &providers, Some(&cache));
+        settle_cached_cover(&cover.expect("cover"), true);
+        assert!(cache.get("Some Title").is_some());
+
+        // A cached hit whose download fails is dropped, and a failed fresh hit isn't cached.
+        let (cover, _) = find_remote_cover("Some Title", &failing, Some(&cache));
+        settle_cached_cover(&cover.expect("cached cover"), false);
+        assert!(cache.get("Some Title").is_none());
+        let (cover, _) = find_remote_cover("Some Title", &providers, Some(&cache));
+        settle_cached_cover(&cover.expect("cover"), false);
+        assert!(cache.get("Some Title").is_none());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn concurrent_cover_cache_writers_keep_every_entry() {
+        let dir = scratch_dir("cover_cache_concurrent");
+        let cache = CoverCache::new(dir.join("cache").join(COVER_CACHE_FILE_NAME));
+        thread::scope(|scope| {
+            for writer in 0..8 {
+                let cache = &cache;
+                scope.spawn(move || {
+                    for n in 0..5 {
+                        let cover = CoverResult {
+                            source: "stub".to_string(),
+                            url: format!("https://example.com/{writer}/{n}.jpg"),
+                            note: None,
+                            match_score: None,
+                        };
+                        cache
+                            .put(&format!("Title {writer} {n}"), &cover)
+                            .expect("put");
+                    }
+                });
+            }
+        });
+
+        assert_eq!(cache.load().len(), 40);
+        assert_eq!(
+            cache.get("Title 3 4").map(|cover| cover.url),
+            Some("https://example.com/3/4.jpg".to_string())
+        );
+        let leftovers: Vec<_> = fs::read_dir(dir.join("cache"))
+            .expect("read cache dir")
+            .filter_map(|entry| entry.ok().map(|entry| entry.file_name()))
+            .filter(|name| name != COVER_CACHE_FILE_NAME)
+            .collect();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn cover_ids_are_validated_before_any_lookup() {
+        let id: CoverId = "MangaDex:a1c7c817-4e59-43b7-9365-09675a149a6f"
+            .parse()
+            .expect("valid mangadex id");
+        assert_eq!(id.provider, "mangadex");
+        assert_eq!(id.id, "a1c7c817-4e59-43b7-9365-09675a149a6f");
+        let id: CoverId = "anilist: 30013".parse().expect("valid anilist id");
+        assert_eq!(id.id, "30013");
+
+        for bad in [
+            "mangadex:not-a-uuid",
+            "anilist:abc",
+            "anilist:",
+            "kitsu:1",
+            "30013",
+        ] {
+            assert!(bad.parse::<CoverId>().is_err(), "{bad} should be rejected");
+        }
+        assert!(fetch_cover_by_id("mangadex", "123").is_err());
+    }
+
+    #[test]
+    fn cover_size_parses_known_variants() {
+        assert_eq!(
+            "original".parse::<CoverSize>().ok(),
+            Some(CoverSize::Original)
+        );
+        assert_eq!("best".parse::<CoverSize>().ok(), Some(CoverSize::Original));
+        assert_eq!(" 512 ".parse::<CoverSize>().ok(), Some(CoverSize::Px512));
+        assert_eq!("256".parse::<CoverSize>().ok(), Some(CoverSize::Px256));
+        assert!("1024".parse::<CoverSize>().is_err());
+        assert_eq!(CoverSize::Px256.url_suffix(), ".256.jpg");
+        assert_eq!(CoverSize::Original.url_suffix(), "");
+    }
+
+    #[test]
+    fn execute_stops_cleanly_when_cancelled() {
+        let dir = scratch_dir("cancel");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for name in ["Series v1.cbz", "Series v2.cbz", "Series v3.cbz"] {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
+        }
+
+        let options = PlanOptions {
+            batch_size: 1,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        let journal = journal_path(&series);
+        let execute_options = ExecuteOptions {
+            journal: Some(journal.clone()),
+            ..ExecuteOptions::default()
+        };
+        let cancel = AtomicBool::new(false);
+        let summary = execute(&plan, None, &execute_options, &cancel, &mut |event| {
+            if matches!(&event, LogEvent::Move { from, .. } if from.ends_with("Series v2.cbz")) {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        })
+        .expect("execute plan");
+
+        assert!(summary.cancelled);
+        assert_eq!(summary.batches_completed, 2);
+        assert_eq!(summary.total_batches, 3);
+        assert!(series.join("Series v3.cbz").is_file());
+        let moves = Journal::load(&journal)
+            .expect("load journal")
+            .iter()
+            .filter(|e| matches!(e, JournalEntry::Move { .. }))
+            .count();
+        assert_eq!(moves, 2);
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn log_events_render_cli_text() {
+        let event = LogEvent::Move {
+            index: 3,
+            total: 40,
+            batch: 1,
+            from: PathBuf::from("/lib/Series/Series v3.cbz"),
+            to: PathBuf::from("/lib/Series 1/Series v003.cbz"),
+            copied: false,
+        };
+        assert_eq!(
+            event.to_string(),
+            "[MOVE] (3/40) Series v3.cbz -> Series v003.cbz"
+        );
+        assert_eq!(event.message(), "(3/40) Series v3.cbz -> Series v003.cbz");
+
+        let batch = LogEvent::BatchStarted {
+            batch_index: 2,
+            batch_dir: PathBuf::from("/lib/Series 2"),
+        };
+        let rule = "-".repeat(98);
+        assert_eq!(
+            batch.to_string(),
+            format!("\n{rule}\n[DO] Batch 2: Series 2\n{rule}")
+        );
+        assert_eq!(
+            LogEvent::Warn("careful".into()).to_string(),
+            "[WARN] careful"
+        );
+        assert_eq!(LogEvent::Info("plain".into()).to_string(), "plain");
+    }
+
+    #[test]
+    fn cross_device_copy_preserves_timestamps() {
+        let dir = scratch_dir("mtime");
+        let src = dir.join("Series v1.cbz");
+        let dst = dir.join("other").join("Series v001.cbz");
+        fs::write(&src, b"volume").expect("write volume");
+        fs::create_dir_all(dst.parent().expect("parent")).expect("create dst dir");
+        let mtime = FileTime::from_unix_time(1_500_000_000, 0);
+        filetime::set_file_mtime(&src, mtime).expect("set mtime");
+
+        copy_across_devices(&src, &dst, true).expect("copy across devices");
+
+        assert!(!src.exists());
+        let metadata = fs::metadata(&dst).expect("dst metadata");
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn content_verification_detects_differing_files() {
+        let dir = scratch_dir("verify");
+        let a = dir.join("a.cbz");
+        let b = dir.join("b.cbz");
+        let body = vec![7u8; 200 * 1024];
+        fs::write(&a, &body).expect("write a");
+        fs::write(&b, &body).expect("write b");
+        assert!(files_have_same_contents(&a, &b).expect("compare"));
+
+        let mut changed = body.clone();
+        changed[150 * 1024] = 8;
+        fs::write(&b, &changed).expect("rewrite b");
+        assert!(!files_have_same_contents(&a, &b).expect("compare"));
+        fs::write(&b, &body[..1000]).expect("truncate b");
+        assert!(!files_have_same_contents(&a, &b).expect("compare"));
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn plan_flags_existing_destinations() {
+        let dir = scratch_dir("collisions");
+        let series = dir.join("Series");
+        let batch_dir = dir.join("Series 1");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::create_dir_all(&batch_dir).expect("create batch dir");
+        fs::write(series.join("Series v1.cbz"), b"one").expect("write v1");
+        fs::write(series.join("Series v2.cbz"), b"two").expect("write v2");
+        fs::write(series.join("Series v3.cbz"), b"three").expect("write v3");
+        fs::write(batch_dir.join("Series v001.cbz"), b"one").expect("write identical");
+        fs::write(batch_dir.join("Series v002.cbz"), b"other").expect("write different");
+
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+        let moves = &plan[0].moves;
+        assert_eq!(moves[0].destination, DestinationState::Identical);
+        assert_eq!(moves[0].dst_name, "Series v001.cbz");
+        assert_eq!(
+            moves[1].destination,
+            DestinationState::Deduplicated {
+                taken_name: "Series v002.cbz".to_string()
+            }
+        );
+        assert_eq!(moves[1].dst_name, "Series v002 (2).cbz");
+        assert_eq!(moves[2].destination, DestinationState::Free);
+
+        let text = format_plan(&series, &plan, None, &PlanOptions::default());
+        assert!(text.contains("will deduplicate to Series v002 (2).cbz"));
+        assert!(text.contains("identical Series v001.cbz already exists"));
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn decimal_volume_numbers_are_preserved() {
+        assert_eq!(
+            clean_volume_filename("Title v01.5.cbz", true),
+            "Title v001.5.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v3.1.cbz", true),
+            "Title v003.1.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v3.1.cbz", false),
+            "Title v3.1.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v02.50 (Digital).cbz", true),
+            "Title v002.5.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v02.0.cbz", true),
+            "Title v002.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v7.cbz", true),
+            "Title v007.cbz"
+        );
+
+        assert_eq!(
+            parse_mangadex_volume(&json!("1.50")),
+            VolumeNumber::parse("1", Some("5"))
+        );
+        assert_eq!(
+            parse_mangadex_volume(&json!("02.0")),
+            Some(VolumeNumber::whole(2))
+        );
+        assert_eq!(parse_mangadex_volume(&json!("extra")), None);
+
+        let dir = scratch_dir("decimal_volumes");
+        for name in ["Title v02.cbz", "Title v01.5.cbz", "Title v01.cbz"] {
+            fs::write(dir.join(name), b"x").expect("write volume");
+        }
+        let names: Vec<String> = scan_volumes(&dir)
+            .expect("scan volumes")
+            .iter()
+            .map(|p| file_name_text(p))
+            .collect();
+        assert_eq!(names, ["Title v01.cbz", "Title v01.5.cbz", "Title v02.cbz"]);
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn chapter_mode_normalizes_and_orders_chapter_files() {
+        let rules = CleanRules::default();
+        assert_eq!(
+            clean_chapter_filename("Title Chapter 45.cbz", true, &rules),
+            "Title c045.cbz"
+        );
+        assert_eq!(
+            clean_chapter_filename("Title ch.7.5.cbz", true, &rules),
+            "Title c007.5.cbz"
+        );
+        assert_eq!(
+            clean_chapter_filename("Title c045.cbz", true, &rules),
+            "Title c045.cbz"
+        );
+        assert_eq!(
+            clean_chapter_filename("Title v02 c010.cbz", true, &rules),
+            "Title v002.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title Chapter 45.cbz", true),
+            "Title Chapter 45.cbz"
+        );
+
+        let dir = scratch_dir("chapters");
+        let series = dir.join("Title");
+        fs::create_dir_all(&series).expect("create series dir");
+        for name in ["Title c046.cbz", "Title Chapter 45.cbz", "Title ch 47.cbz"] {
+            fs::write(series.join(name), name.as_bytes()).expect("write chapter");
+        }
+        let options = PlanOptions {
+            batch_size: 2,
+            chapters: true,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        let names: Vec<&str> = plan
+            .iter()
+            .flat_map(|b| b.moves.iter().map(|mv| mv.dst_name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            ["Title c045.cbz", "Title c046.cbz", "Title c047.cbz"]
+        );
+        let text = format_plan(&series, &plan, None, &options);
+        assert!(text.contains("Title 1  (chapters 45-46)"));
+        assert!(text.contains("Title 2  (chapters 47-47)"));
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn group_tags_in_brackets_are_stripped_but_titles_are_kept() {
+        assert_eq!(
+            clean_volume_filename("Title v01 [GroupName].cbz", true),
+            "Title v001.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("[HotScans] Title v02.cbz", true),
+            "Title v002.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title {Digital} v03 (CM).cbz", true),
+            "Title v003.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("[Oshi no Ko] v01.cbz", true),
+            "[Oshi no Ko] v001.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title [Extra Story].cbz", true),
+            "Title [Extra Story].cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title Extras [Scanlation Team].cbz", true),
+            "Title Extras.cbz"
+        );
+    }
+
+    #[test]
+    fn clean_rules_load_from_file_and_reject_bad_regexes() {
+        let dir = scratch_dir("clean_rules");
+        let path = dir.join("rules.json");
+        fs::write(
+            &path,
+            r#"{"remove": ["\\s*-\\s*Digital"], "volume": "(?i)\\bvol\\.?\\s*0*(\\d+)"}"#,
+        )
+        .expect("write rules");
+        let rules = CleanRules::load(&path).expect("load rules");
+        assert_eq!(
+            clean_volume_filename_with("Title Vol.4 - Digital.cbz", true, &rules),
+            "Title v004.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename_with("Title (CM) Vol 2.cbz", true, &rules),
+            "Title (CM) v002.cbz"
+        );
+
+        fs::write(&path, r#"{"remove": ["("]}"#).expect("write bad rules");
+        let err = CleanRules::load(&path).expect_err("bad regex is rejected");
+        assert!(format!("{err:#}").contains("remove[0] is not a valid regex"));
+
+        fs::write(&path, r#"{"volume": "v\\d+"}"#).expect("write rules without group");
+        assert!(CleanRules::load(&path).is_err());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn clean_previews_pair_each_name_with_its_cleaned_form() {
+        let names = ["[Group] Title v1 (Digital).cbz", "Title v002.cbz"].map(String::from);
+        assert_eq!(
+            preview_clean(&names, &CleanRules::default(), true),
+            [
+                (names[0].clone(), "Title v001.cbz".to_string()),
+                (names[1].clone(), "Title v002.cbz".to_string()),
+            ]
+        );
+        assert_eq!(
+            preview_clean(&names[..1], &CleanRules::default(), false)[0].1,
+            "Title v1.cbz"
+        );
+    }
+
+    #[test]
+    fn skip_cover_mode_short_circuits_resolution() {
+        let dir = scratch_dir("skip_cover");
+        write_zip(
+            &dir.join("Series v01.cbz"),
+            &[("001.png", &png_bytes(8, 8))],
+        );
+        fs::write(dir.join("cover.jpg"), b"local cover").expect("write local cover");
+
+        let options = SeriesCoverOptions {
+            mode: CoverMode::Skip,
+            providers: vec![stub("network", Err("must not be called"))],
+            ..SeriesCoverOptions::default()
+        };
+        let mut events = Vec::new();
+        let cover = ensure_series_cover(&dir, "Series", &options, &mut |e| events.push(e))
+            .expect("skip cover");
+        assert!(cover.is_none());
+        assert!(matches!(events.as_slice(), [LogEvent::CoverNote(_)]));
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn existing_local_cover_wins_unless_extraction_is_forced() {
+        let dir = scratch_dir("local_cover_first");
+        write_zip(
+            &dir.join("Series v01.cbz"),
+            &[("001.png", &png_bytes(8, 8))],
+        );
+        fs::write(dir.join("cover.jpg"), b"hand-picked").expect("write local cover");
+
+        let options = SeriesCoverOptions {
+            providers: vec![stub("network", Err("must not be called"))],
+            cache: None,
+            ..SeriesCoverOptions::default()
+        };
+        let cover =
+            ensure_series_cover(&dir, "Series", &options, &mut |_| {}).expect("resolve cover");
+        assert_eq!(cover, Some(dir.join("cover.jpg")));
+        assert_eq!(
+            fs::read(dir.join("cover.jpg")).expect("read cover"),
+            b"hand-picked"
+        );
+
+        let forced = SeriesCoverOptions {
+            force_extract: true,
+            ..options
+        };
+        let mut events = Vec::new();
+        ensure_series_cover(&dir, "Series", &forced, &mut |e| {
+            if !matches!(e, LogEvent::Timing { .. }) {
+                events.push(e);
+            }
+        })
+        .expect("force extract");
+        assert!(matches!(
+            events.as_slice(),
+            [LogEvent::CoverExtracted { .. }]
+        ));
+        assert!(image::open(dir.join("cover.jpg")).is_ok());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn kept_cover_format_saves_png_pages_untouched() {
+        let dir = scratch_dir("keep_cover_format");
+        let page = png_bytes(8, 8);
+        write_zip(&dir.join("Series v01.cbz"), &[("001.png", &page)]);
+
+        let options = SeriesCoverOptions {
+            providers: vec![stub("network", Err("must not be called"))],
+            cache: None,
+            keep_cover_format: true,
+            ..SeriesCoverOptions::default()
+        };
+        let cover =
+            ensure_series_cover(&dir, "Series", &options, &mut |_| {}).expect("resolve cover");
+        assert_eq!(cover, Some(dir.join("cover.png")));
+        assert_eq!(fs::read(dir.join("cover.png")).expect("read cover"), page);
+        assert!(!dir.join("cover.jpg").exists());
+
+        // The default still converts to JPEG.
+        fs::remove_file(dir.join("cover.png")).expect("remove kept cover");
+        let options = SeriesCoverOptions {
+            keep_cover_format: false,
+            ..options
+        };
+        let cover =
+            ensure_series_cover(&dir, "Series", &options, &mut |_| {}).expect("resolve cover");
+        assert_eq!(cover, Some(dir.join("cover.jpg")));
+        let bytes = fs::read(dir.join("cover.jpg")).expect("read cover");
+        assert_eq!(image::guess_format(&bytes).ok(), Some(ImageFormat::Jpeg));
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn wide_first_pages_are_skipped_or_cropped() {
+        let dir = scratch_dir("spread_cover");
+        let spread = DynamicImage::ImageRgb8(RgbImage::from_fn(16, 8, |x, _| {
+            if x < 8 {
+                Rgb([200, 30, 30])
+            } else {
+                Rgb([30, 30, 200])
+            }
+        }));
+        let mut spread_bytes = Vec::new();
+        spread
+            .write_to(&mut io::Cursor::new(&mut spread_bytes), ImageFormat::Png)
+            .expect("encode spread");
+        write_zip(
+            &dir.join("Series v01.cbz"),
+            &[("001.png", &spread_bytes), ("002.png", &png_bytes(8, 12))],
+        );
+        let options = SeriesCoverOptions {
+            providers: vec![stub("network", Err("must not be called"))],
+            cache: None,
+            ..SeriesCoverOptions::default()
+        };
+        let entry = |options: &SeriesCoverOptions| match decide_series_cover(
+            &dir,
+            "Series",
+            options,
+            &mut |_| {},
+        )
+        .expect("decide")
+        {
+            CoverDecision::Extract(cover) => (cover.image_entry, cover.crop),
+            other => panic!("expected an extraction, got {other:?}"),
+        };
+
+        assert_eq!(entry(&options), ("001.png".to_string(), None));
+        let skip = SeriesCoverOptions {
+            spread: SpreadHandling::Skip,
+            ..options
+        };
+        assert_eq!(entry(&skip), ("002.png".to_string(), None));
+        // A stricter threshold no longer treats a 2:1 page as a spread.
+        let lenient = SeriesCoverOptions {
+            spread_ratio: 2.5,
+            ..skip
+        };
+        assert_eq!(entry(&lenient), ("001.png".to_string(), None));
+
+        let crop = SeriesCoverOptions {
+            spread: SpreadHandling::Crop(PageHalf::Right),
+            spread_ratio: DEFAULT_SPREAD_RATIO,
+            ..lenient
+        };
+        let cover = ensure_series_cover(&dir, "Series", &crop, &mut |_| {})
+            .expect("resolve cover")
+            .expect("cover");
+        let written = image::open(&cover).expect("open cover").to_rgb8();
+        assert_eq!(written.dimensions(), (8, 8));
+        let Rgb([r, _, b]) = *written.get_pixel(4, 4);
+        assert!(b > 150 && r < 100, "kept the right half");
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn cropping_a_spread_keeps_the_half_read_first() {
+        let dir = scratch_dir("spread_direction");
+        write_zip(
+            &dir.join("Series v01.cbz"),
+            &[("001.png", &png_bytes(16, 8))],
+        );
+        let crop_for = |direction: ReadingDirection| {
+            let options = SeriesCoverOptions {
+                providers: vec![stub("network", Err("must not be called"))],
+                cache: None,
+                spread: SpreadHandling::CropFront,
+                reading_direction: direction,
+                ..SeriesCoverOptions::default()
+            };
+            match decide_series_cover(&dir, "Series", &options, &mut |_| {}).expect("decide") {
+                CoverDecision::Extract(cover) => cover.crop,
+                other => panic!("expected an extraction, got {other:?}"),
+            }
+        };
+
+        assert_eq!(crop_for(ReadingDirection::Ltr), Some(PageHalf::Left));
+        assert_eq!(crop_for(ReadingDirection::Rtl), Some(PageHalf::Right));
+        assert_eq!(
+            "RTL".parse::<ReadingDirection>().expect("parse"),
+            ReadingDirection::Rtl
+        );
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn animated_gif_cover_uses_the_first_frame() {
+        let dir = scratch_dir("animated_gif");
+        let mut gif = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut gif);
+            // Opaque red on the left, transparent on the right; the second frame is blue.
+            let first = RgbaImage::from_fn(8, 8, |x, _| {
+                if x < 4 {
+                    Rgba([255, 0, 0, 255])
+                } else {
+                    Rgba([0, 0, 0, 0])
+                }
+            });
+            let second = RgbaImage::from_pixel(8, 8, Rgba([0, 0, 255, 255]));
+            encoder
+                .encode_frames([image::Frame::new(first), image::Frame::new(second)])
+                .expect("encode gif");
+        }
+        write_zip(&dir.join("Series v01.cbz"), &[("001.gif", &gif)]);
+
+        let cover = find_volume_cover(&dir, 0, 0)
+            .expect("find cover")
+            .expect("gif entry found");
+        let out = write_volume_cover(&cover).expect("extract gif cover");
+        let extracted = image::open(&out).expect("open jpeg").to_rgb8();
+        assert_eq!(extracted.dimensions(), (8, 8));
+        let [r, g, b] = extracted.get_pixel(1, 4).0;
+        assert!(r > 200 && g < 60 && b < 60, "left half is red: {r},{g},{b}");
+        let [r, g, b] = extracted.get_pixel(6, 4).0;
+        assert!(
+            r > 200 && g > 200 && b > 200,
+            "transparency is white: {r},{g},{b}"
+        );
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn normalize_cover_pads_to_ratio_then_resizes() {
+        let base = DynamicImage::ImageRgb8(RgbImage::from_pixel(40, 40, Rgb([200, 30, 30])));
+
+        let untouched = normalize_cover(base.clone(), &CoverStyle::default());
+        assert_eq!(untouched.as_bytes(), base.as_bytes());
+
+        let style = CoverStyle {
+            ratio: Some("2:3".parse().expect("parse ratio")),
+            pad_color: [255, 255, 255],
+            ..CoverStyle::default()
+        };
+        let padded = normalize_cover(base.clone(), &style).to_rgb8();
+        assert_eq!(padded.dimensions(), (40, 60));
+        assert_eq!(padded.get_pixel(20, 2).0, [255, 255, 255]);
+        assert_eq!(padded.get_pixel(20, 30).0, [200, 30, 30]);
+
+        let resized = normalize_cover(
+            base,
+            &CoverStyle {
+                width: Some(20),
+                ..style
+            },
+        );
+        assert_eq!((resized.width(), resized.height()), (20, 30));
+
+        assert!("2x3".parse::<CoverRatio>().is_err());
+        assert!("0:3".parse::<CoverRatio>().is_err());
+    }
+
+    #[test]
+    fn font_loading_skips_bad_candidates_and_lists_them() {
+        let dir = scratch_dir("font_candidates");
+        let not_a_font = dir.join("notes.ttf");
+        fs::write(&not_a_font, b"not a font").expect("write fake font");
+        let missing = dir.join("missing.ttf");
+
+        let err =
+            load_first_font([not_a_font.clone(), missing.clone()]).expect_err("no usable font");
+        let message = err.to_string();
+        assert!(message.contains(&format!("{} (not a usable font)", not_a_font.display())));
+        assert!(message.contains(&format!("{} (not found)", missing.display())));
+
+        let system = FONT_CANDIDATES.iter().map(PathBuf::from);
+        if load_first_font(system.clone()).is_ok() {
+            assert!(load_first_font([missing].into_iter().chain(system)).is_ok());
+        }
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn alpha_bbox_matches_a_full_scan() {
+        let mut image = RgbaImage::new(50, 40);
+        assert_eq!(alpha_bbox(&image), None);
+
+        for (x, y) in [(12, 30), (7, 22), (44, 9), (20, 15)] {
+            image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+        }
+        assert_eq!(alpha_bbox(&image), Some((7, 9, 44, 30)));
+
+        let mut single = RgbaImage::new(5, 5);
+        single.put_pixel(4, 0, Rgba([0, 0, 0, 1]));
+        assert_eq!(alpha_bbox(&single), Some((4, 0, 4, 0)));
+    }
+
+    #[test]
+    fn parallel_cover_rendering_reports_batches_in_order() {
+        let dir = scratch_dir("parallel_covers");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for n in 1..=5 {
+            fs::write(series.join(format!("Series v{n}.cbz")), [n]).expect("write volume");
+        }
+        let cover = series.join("cover.jpg");
+        save_jpeg(
+            &DynamicImage::ImageRgb8(RgbImage::from_pixel(60, 90, Rgb([90, 120, 200]))),
+            &cover,
+        )
+        .expect("write series cover");
+
+        let plan = build_plan(
+            &series,
+            Some(&cover),
+            &PlanOptions {
+                batch_size: 1,
+                ..PlanOptions::default()
+            },
+        )
+        .expect("build plan");
+        let journal = journal_path(&series);
+        let execute_options = ExecuteOptions {
+            journal: Some(journal.clone()),
+            jobs: 3,
+            ..ExecuteOptions::default()
+        };
+        let mut rendered = Vec::new();
+        let summary = execute(
+            &plan,
+            Some(&cover),
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |event| {
+                if let LogEvent::CoverRendered { batch } = event {
+                    rendered.push(batch);
+                }
+            },
+        )
+        .expect("execute plan");
+        assert_eq!(rendered, vec![1, 2, 3, 4, 5]);
+        assert_eq!(summary.batches_completed, 5);
+        for n in 1..=5 {
+            assert!(dir.join(format!("Series {n}")).join("cover.jpg").is_file());
+        }
+
+        rollback(&journal, &mut |_| {}).expect("rollback");
+        for n in 1..=5 {
+            assert!(!dir.join(format!("Series {n}")).exists());
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn identical_volumes_are_left_out_unless_kept() {
+        let dir = scratch_dir("duplicate_volumes");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::write(series.join("Series v01.cbz"), b"volume one").expect("write volume");
+        fs::write(series.join("Series v01 (1).cbz"), b"volume one").expect("write copy");
+        // Same size, different bytes: not a duplicate.
+        fs::write(series.join("Series v02.cbz"), b"volume two").expect("write volume");
+
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+        assert_eq!(plan[0].moves.len(), 2);
+        assert_eq!(
+            plan[0].duplicates,
+            vec![DuplicateVolume {
+                path: series.join("Series v01 (1).cbz"),
+                original: series.join("Series v01.cbz"),
+            }]
+        );
+        let text = format_plan(&series, &plan, None, &PlanOptions::default());
+        assert!(text.contains("[PLAN] Duplicates skipped: 1"));
+        assert!(text.contains("[SKIP] Series v01 (1).cbz  (identical to Series v01.cbz"));
+
+        let kept = build_plan(
+            &series,
+            None,
+            &PlanOptions {
+                keep_duplicates: true,
+                ..PlanOptions::default()
+            },
+        )
+        .expect("build plan keeping duplicates");
+        assert_eq!(kept[0].moves.len(), 3);
+        assert!(kept[0].duplicates.is_empty());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn recursive_scan_collects_nested_volumes_in_name_order() {
+        let dir = scratch_dir("recursive_scan");
+        let series = dir.join("Series");
+        for sub in ["Vol 2", "Vol 10", "__MACOSX", ".hidden", "Vol 1/extras"] {
+            fs::create_dir_all(series.join(sub)).expect("create subfolder");
+        }
+        fs::write(series.join("Vol 10").join("Series v10.cbz"), b"10").expect("write volume");
+        fs::write(series.join("Vol 2").join("Series v2.cbz"), b"2").expect("write volume");
+        fs::write(series.join("Vol 1/extras").join("Series v1.cbz"), b"1").expect("write");
+        fs::write(series.join("__MACOSX").join("Series v3.cbz"), b"junk").expect("write junk");
+        fs::write(series.join(".hidden").join("Series v4.cbz"), b"junk").expect("write junk");
+
+        assert!(scan_volumes(&series).expect("flat scan").is_empty());
+        let names: Vec<String> = scan_volumes_with(&series, true)
+            .expect("recursive scan")
+            .iter()
+            .map(|p| file_name_text(p))
+            .collect();
+        assert_eq!(names, ["Series v1.cbz", "Series v2.cbz", "Series v10.cbz"]);
+
+        let plan = build_plan(
+            &series,
+            None,
+            &PlanOptions {
+                recursive: true,
+                ..PlanOptions::default()
+            },
+        )
+        .expect("build plan");
+        let dst_names: Vec<&str> = plan[0].moves.iter().map(|m| m.dst_name.as_str()).collect();
+        assert_eq!(
+            dst_names,
+            ["Series v001.cbz", "Series v002.cbz", "Series v010.cbz"]
+        );
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn config_file_loads_typed_values_and_names_bad_keys() {
+        let dir = scratch_dir("config_file");
+        let path = dir.join(CONFIG_FILE_NAME);
+        fs::write(
+            &path,
+            r##"{"batch_size": 10, "cover_text_color": "#FFFFFF", "cover_providers": ["anilist", "mangadex"], "offline": true}"##,
+        )
+        .expect("write config");
+        let config = Config::load(&path).expect("load config");
+        assert_eq!(config.batch_size, Some(10));
+        assert_eq!(
+            config.cover_text_color,
+            Some(CoverTextColor::Rgb([255, 255, 255]))
+        );
+        assert_eq!(config.offline, Some(true));
+        assert_eq!(config.jobs, None);
+        let providers = cover_providers_named(
+            config.cover_providers.as_deref().unwrap_or_default(),
+            &CoverOptions::default(),
+        )
+        .expect("known providers");
+        assert_eq!(providers[0].name(), "anilist");
+
+        for (contents, key) in [
+            (r#"{"batch_size": "ten"}"#, "`batch_size`"),
+            (r#"{"cover_text_color": "purple"}"#, "`cover_text_color`"),
+            (r#"{"bach_size": 10}"#, "`bach_size`"),
+            (r#"{"cover_providers": ["bing"]}"#, "`cover_providers`"),
+        ] {
+            fs::write(&path, contents).expect("write bad config");
+            let err = Config::load(&path).expect_err("bad config is rejected");
+            assert!(format!("{err:#}").contains(key), "{key} in {err:#}");
+        }
+
+        fs::remove_dir_all(&dir).expect("cleanup");
     }
-    if alt_values.iter().any(|v| v.contains(title_l)) {
-        return 1;
+
+    #[test]
+    fn analyze_plans_an_extraction_without_writing_it() {
+        let dir = scratch_dir("analyze_pure");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        write_zip(
+            &series.join("Series v01.cbz"),
+            &[("001.png", &png_bytes(8, 8))],
+        );
+
+        let options = AnalyzeOptions {
+            cover: SeriesCoverOptions {
+                providers: vec![stub("network", Err("must not be called"))],
+                cache: None,
+                ..SeriesCoverOptions::default()
+            },
+            ..AnalyzeOptions::default()
+        };
+        let analysis = analyze(&series, &options, &mut |_| {}).expect("analyze");
+        assert!(matches!(analysis.cover, CoverDecision::Extract(_)));
+        assert_eq!(
+            analysis.cover.planned_path(),
+            Some(series.join("cover.jpg").as_path())
+        );
+        assert_eq!(analysis.volume_count, 1);
+        assert!(analysis.plan[0].will_make_cover);
+        assert!(!series.join("cover.jpg").exists());
+        assert!(analysis
+            .cover
+            .preview_bytes()
+            .expect("preview")
+            .is_some_and(|bytes| bytes == png_bytes(8, 8)));
+
+        let mut events = Vec::new();
+        let written = apply_cover_decision(&analysis.cover, &mut |e| {
+            if !matches!(e, LogEvent::Timing { .. }) {
+                events.push(e);
+            }
+        })
+        .expect("apply cover");
+        assert_eq!(written, Some(series.join("cover.jpg")));
+        assert!(matches!(
+            events.as_slice(),
+            [LogEvent::CoverExtracted { .. }]
+        ));
+
+        fs::remove_dir_all(&dir).expect("cleanup");
     }
-    1
-}
 
-pub fn fetch_cover_mangadex(title: &str, size: &str) -> Result<Option<CoverResult>> {
-    let base = "https://api.mangadex.org";
+    #[test]
+    fn cover_preview_is_written_outside_the_series_folder() {
+        let dir = scratch_dir("cover_preview");
+        let series = dir.join(format!("Preview Series {}", std::process::id()));
+        fs::create_dir_all(&series).expect("create series dir");
+        write_zip(
+            &series.join("Series v01.cbz"),
+            &[("001.png", &png_bytes(8, 8))],
+        );
 
-    let data = http_get_json(
-        &format!("{base}/manga"),
-        &[("title", title.to_string()), ("limit", "5".to_string())],
-        20,
-    )?;
+        let options = SeriesCoverOptions {
+            providers: Vec::new(),
+            cache: None,
+            ..SeriesCoverOptions::default()
+        };
+        let decision =
+            decide_series_cover(&series, "Series", &options, &mut |_| {}).expect("decide cover");
+        let preview = preview_series_cover(&decision, &mut |_| {})
+            .expect("preview cover")
+            .expect("preview path");
+
+        assert!(preview.starts_with(std::env::temp_dir()));
+        assert!(preview.is_file());
+        assert!(!series.join("cover.jpg").exists());
+        assert_eq!(fs::read_dir(&series).expect("list series").count(), 1);
+
+        let _ = fs::remove_file(preview);
+        let _ = fs::remove_dir_all(dir);
+    }
 
-    let mut items = data
-        .get("data")
-        .and_then(Value::as_array)
-        .cloned()
-        .unwrap_or_default();
+    #[test]
+    #[cfg(feature = "network")]
+    fn token_bucket_allows_a_burst_then_spaces_requests() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket {
+            tokens: 2.0,
+            updated: start,
+        };
 
-    if items.is_empty() {
-        return Ok(None);
+        assert_eq!(bucket.reserve(start, 2.0), Duration::ZERO);
+        assert_eq!(bucket.reserve(start, 2.0), Duration::ZERO);
+        assert_eq!(bucket.reserve(start, 2.0), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(start, 2.0), Duration::from_secs(1));
+
+        // Idle time refills the bucket, but never past one second's worth.
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.reserve(later, 2.0), Duration::ZERO);
+        assert_eq!(bucket.reserve(later, 2.0), Duration::ZERO);
+        assert_eq!(bucket.reserve(later, 2.0), Duration::from_millis(500));
     }
 
-    let title_l = title.trim().to_ascii_lowercase();
-    let title_n = normalize_title(&title_l);
+    #[test]
+    fn shared_cover_dir_matches_normalized_names_before_extraction() {
+        let dir = scratch_dir("cover_dir");
+        let series = dir.join("Series Name");
+        let covers = dir.join("covers");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::create_dir_all(&covers).expect("create cover dir");
+        write_zip(
+            &series.join("Series Name v01.cbz"),
+            &[("001.png", &png_bytes(8, 8))],
+        );
+        fs::write(covers.join("other-series.jpg"), b"other").expect("write other cover");
+        fs::write(covers.join("series-name.jpg"), b"shared").expect("write shared cover");
+
+        let options = SeriesCoverOptions {
+            providers: vec![stub("network", Err("must not be called"))],
+            cache: None,
+            cover_dir: Some(covers.clone()),
+            ..SeriesCoverOptions::default()
+        };
+        let decision =
+            decide_series_cover(&series, "Series Name", &options, &mut |_| {}).expect("decide");
+        assert!(matches!(
+            &decision,
+            CoverDecision::Copy { source, .. } if *source == covers.join("series-name.jpg")
+        ));
+        assert!(!series.join("cover.jpg").exists());
 
-    items.sort_by_key(|item| Reverse(score_mangadex_item(item, &title_l, &title_n)));
+        let written = apply_cover_decision(&decision, &mut |_| {}).expect("apply cover");
+        assert_eq!(written, Some(series.join("cover.jpg")));
+        assert_eq!(
+            fs::read(series.join("cover.jpg")).expect("read cover"),
+            b"shared"
+        );
 
-    let manga_id = match items
-        .first()
-        .and_then(|item| item.get("id"))
-        .and_then(Value::as_str)
-    {
-        Some(id) => id.to_string(),
-        None => return Ok(None),
-    };
+        let _ = fs::remove_dir_all(dir);
+    }
 
-    let cover_id = match http_get_json(
-        &format!("{base}/cover"),
-        &[
-            ("manga[]", manga_id.clone()),
-            ("limit", "100".to_string()),
-            ("order[createdAt]", "asc".to_string()),
-        ],
-        20,
-    ) {
-        Ok(covers_resp) => {
-            let mut first_volume_cover: Option<String> = None;
-            if let Some(covers) = covers_resp.get("data").and_then(Value::as_array) {
-                for cover in covers {
-                    let attrs = cover.get("attributes").unwrap_or(&Value::Null);
-                    if parse_int_volume(attrs.get("volume").unwrap_or(&Value::Null)) == Some(1) {
-                        first_volume_cover =
-                            cover.get("id").and_then(Value::as_str).map(str::to_string);
-                        if first_volume_cover.is_some() {
-                            break;
-                        }
-                    }
-                }
-            }
-            first_volume_cover
+    #[test]
+    fn batch_metadata_lists_folder_volumes_and_is_rewritten_identically() {
+        let dir = scratch_dir("batch_metadata");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for name in ["Series v1.cbz", "Series v2.cbz", "Series v3.cbz"] {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
         }
-        Err(_) => None,
-    };
 
-    let Some(cover_id) = cover_id else {
-        return Ok(None);
-    };
+        let options = PlanOptions {
+            batch_size: 2,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        let metadata = BatchMetadata {
+            series_title: "Series".to_string(),
+            cover_source: Some("Series v1.cbz:001.png".to_string()),
+        };
+        let execute_options = ExecuteOptions {
+            metadata: Some(metadata.clone()),
+            ..ExecuteOptions::default()
+        };
+        execute(
+            &plan,
+            None,
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("execute plan");
+
+        let path = dir.join("Series 1").join(METADATA_FILE_NAME);
+        let written = fs::read_to_string(&path).expect("read metadata");
+        let value: Value = serde_json::from_str(&written).expect("parse metadata");
+        assert_eq!(value["series"], "Series");
+        assert_eq!(value["batch"], 1);
+        assert_eq!(value["volume_count"], 2);
+        assert_eq!(value["start_volume"], 1);
+        assert_eq!(value["end_volume"], 2);
+        assert_eq!(value["first_volume"], "Series v001.cbz");
+        assert_eq!(value["last_volume"], "Series v002.cbz");
+        assert_eq!(value["cover_source"], "Series v1.cbz:001.png");
+        assert!(dir.join("Series 2").join(METADATA_FILE_NAME).is_file());
+
+        write_batch_metadata(&plan[0], &metadata, None).expect("rewrite metadata");
+        assert_eq!(fs::read_to_string(&path).expect("reread metadata"), written);
+
+        let _ = fs::remove_dir_all(dir);
+    }
 
-    let cover = http_get_json(&format!("{base}/cover/{cover_id}"), &[], 20)?;
-    let file_name = match cover
-        .pointer("/data/attributes/fileName")
-        .and_then(Value::as_str)
-    {
-        Some(name) => name,
-        None => return Ok(None),
-    };
+    #[test]
+    fn batch_metadata_reads_volume_numbers_with_the_plan_rules() {
+        let dir = scratch_dir("batch_metadata_rules");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for name in [
+            "Series Book 1.cbz",
+            "Series Book 2.cbz",
+            "Series Side v9.cbz",
+        ] {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
+        }
+
+        let options = PlanOptions {
+            clean_rules: CleanRules {
+                volume: Regex::new(r"(?i)\bbook\s*0*(\d+)").expect("regex"),
+                ..CleanRules::default()
+            },
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        let execute_options = ExecuteOptions {
+            metadata: Some(BatchMetadata {
+                series_title: "Series".to_string(),
+                cover_source: None,
+            }),
+            ..ExecuteOptions::default()
+        };
+        execute(
+            &plan,
+            None,
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("execute plan");
 
-    let mut url = format!("https://uploads.mangadex.org/covers/{manga_id}/{file_name}");
-    if size == "512" {
-        url.push_str(".512.jpg");
-    } else if size == "256" {
-        url.push_str(".256.jpg");
+        let path = dir.join("Series 1").join(METADATA_FILE_NAME);
+        let value: Value = serde_json::from_str(&fs::read_to_string(&path).expect("read metadata"))
+            .expect("parse metadata");
+        assert_eq!(value["volume_count"], 3);
+        // "Side v9" has no book number under these rules, so it doesn't end the range.
+        assert_eq!(value["start_volume"], 1);
+        assert_eq!(value["end_volume"], 2);
+
+        let _ = fs::remove_dir_all(dir);
     }
 
-    Ok(Some(CoverResult {
-        source: "mangadex".to_string(),
-        url,
-    }))
-}
+    #[test]
+    fn archive_validation_flags_broken_volumes_in_the_plan() {
+        let dir = scratch_dir("validate_archives");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        write_zip(
+            &series.join("Series v01.cbz"),
+            &[("001.png", &png_bytes(4, 4))],
+        );
+        write_zip(
+            &series.join("Series v02.cbz"),
+            &[("001.png", &png_bytes(4, 4)), ("002.png", &png_bytes(5, 5))],
+        );
+        let truncated = fs::read(series.join("Series v02.cbz")).expect("read volume");
+        fs::write(
+            series.join("Series v02.cbz"),
+            &truncated[..truncated.len() / 2],
+        )
+        .expect("truncate volume");
+        fs::write(series.join("Series v03.cbz"), b"Rar!\x1a\x07\x00rest").expect("write rar");
+        write_zip(
+            &series.join("Series v04.cbz"),
+            &[("notes.txt", b"no pages")],
+        );
 
-pub fn fetch_cover_anilist(title: &str) -> Result<Option<CoverResult>> {
-    let endpoint = "https://graphql.anilist.co";
-    let query = r#"
-    query ($search: String) {
-      Media(search: $search, type: MANGA) {
-        id
-        coverImage { extraLarge large }
-      }
+        let options = PlanOptions {
+            validate_archives: true,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        let problems: Vec<Option<&str>> = plan[0]
+            .moves
+            .iter()
+            .map(|mv| mv.problem.as_deref())
+            .collect();
+        assert_eq!(problems[0], None);
+        assert!(problems[1].is_some());
+        assert!(problems[2].is_some_and(|p| p.contains("really a RAR archive")));
+        assert!(problems[3].is_some_and(|p| p.contains("no image entries")));
+
+        let text = format_plan(&series, &plan, None, &options);
+        assert!(text.contains("[PLAN] Unreadable archives: 3"));
+        let broken_lines = text
+            .lines()
+            .filter(|line| line.trim_start().starts_with("[BROKEN]"))
+            .count();
+        assert_eq!(broken_lines, 3);
+
+        let unchecked = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+        assert!(unchecked[0].moves.iter().all(|mv| mv.problem.is_none()));
+
+        let _ = fs::remove_dir_all(dir);
     }
-    "#;
 
-    let payload = json!({
-        "query": query,
-        "variables": {
-            "search": title,
+    #[test]
+    fn volumes_are_planned_in_parsed_volume_order() {
+        let dir = scratch_dir("volume_order");
+        let series = dir.join("Title");
+        fs::create_dir_all(&series).expect("create series dir");
+        let names = [
+            "Title v10.cbz",
+            "Title v1.5.cbz",
+            "Title v01.cbz",
+            "Title Extras.cbz",
+            "Title v2 (Digital).cbz",
+            "Title v1_2.cbz",
+            "Title v1.cbz",
+            "Title v009.cbz",
+            "Title v1.25.cbz",
+        ];
+        for name in names {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
         }
-    });
 
-    let resp = http_post_json(endpoint, &payload, 20)?;
-    let media = resp.pointer("/data/Media").unwrap_or(&Value::Null);
-    if media.is_null() {
-        return Ok(None);
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+        let order: Vec<String> = plan[0]
+            .moves
+            .iter()
+            .map(|mv| file_name_text(&mv.src))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "Title v01.cbz",
+                "Title v1.cbz",
+                "Title v1_2.cbz",
+                "Title v1.25.cbz",
+                "Title v1.5.cbz",
+                "Title v2 (Digital).cbz",
+                "Title v009.cbz",
+                "Title v10.cbz",
+                "Title Extras.cbz",
+            ]
+        );
+
+        let _ = fs::remove_dir_all(dir);
     }
 
-    let url = media
-        .pointer("/coverImage/extraLarge")
-        .and_then(Value::as_str)
-        .or_else(|| media.pointer("/coverImage/large").and_then(Value::as_str));
+    #[test]
+    fn three_digit_volumes_keep_their_numbers_and_order() {
+        assert_eq!(
+            clean_volume_filename("Title v100.cbz", true),
+            "Title v100.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v250.cbz", true),
+            "Title v250.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v0250.cbz", true),
+            "Title v250.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v1000.cbz", true),
+            "Title v1000.cbz"
+        );
+        assert_eq!(
+            clean_volume_filename("Title v99.cbz", true),
+            "Title v099.cbz"
+        );
 
-    let Some(url) = url else {
-        return Ok(None);
-    };
+        let mut volumes: Vec<PathBuf> = ["Title v100.cbz", "Title v99.cbz", "Title v250.cbz"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        sort_by_volume_number(&mut volumes, &CleanRules::default());
+        let names: Vec<String> = volumes.iter().map(|p| file_name_text(p)).collect();
+        assert_eq!(names, ["Title v99.cbz", "Title v100.cbz", "Title v250.cbz"]);
+    }
 
-    Ok(Some(CoverResult {
-        source: "anilist".to_string(),
-        url: url.to_string(),
-    }))
-}
+    #[test]
+    fn fixed_number_width_gives_every_cover_the_same_digit_size() {
+        let (w, h) = (600, 900);
+        let base = DynamicImage::ImageRgb8(RgbImage::from_pixel(w, h, Rgb([255, 255, 255])));
+        let font = pick_font(None).expect("cover font");
+        let size = |number: usize, style: &CoverStyle| {
+            batch_number_font_size(&base, &font, &style.number_text(number), style)
+        };
 
-pub fn fetch_cover_kitsu(title: &str) -> Result<Option<CoverResult>> {
-    let base = "https://kitsu.io/api/edge";
-    let data = http_get_json(
-        &format!("{base}/manga"),
-        &[
-            ("filter[text]", title.to_string()),
-            ("page[limit]", "5".to_string()),
-        ],
-        20,
-    )?;
+        let fixed = CoverStyle {
+            number_width: Some(3),
+            ..CoverStyle::default()
+        };
+        assert_eq!(size(7, &fixed), size(250, &fixed));
+        assert_eq!(size(250, &fixed), size(250, &CoverStyle::default()));
+        assert!(size(7, &CoverStyle::default()) > size(7, &fixed) * 1.5);
+        assert!(size(1000, &fixed) < size(250, &fixed));
+
+        // Three digits at that size still fit inside the cover.
+        let rendered = draw_centered_text_at_size(
+            &base,
+            &font,
+            "250",
+            [0, 0, 0],
+            false,
+            255,
+            size(250, &fixed),
+        );
+        let rgb = rendered.to_rgb8();
+        let (x0, _, x1, _) = bbox_for_mask(
+            rgb.enumerate_pixels()
+                .filter(|(_, _, p)| p.0[0] < 128)
+                .map(|(x, y, _)| (x, y)),
+        )
+        .expect("text pixels should exist");
+        assert!(x0 > 0 && x1 < w - 1, "number spans {x0}..{x1}");
 
-    let items = data
-        .get("data")
-        .and_then(Value::as_array)
-        .cloned()
-        .unwrap_or_default();
+        let padded = CoverStyle {
+            pad_number: true,
+            ..fixed
+        };
+        assert_eq!(padded.number_text(7), "007");
+        assert_eq!(padded.number_text(250), "250");
+        assert_eq!(padded.number_text(1000), "1000");
+        assert_eq!(CoverStyle::default().number_text(7), "7");
+    }
 
-    let Some(first) = items.first() else {
-        return Ok(None);
-    };
+    #[test]
+    fn folder_templates_name_batches_and_reject_bad_names() {
+        let dir = scratch_dir("folder_template");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for name in ["Series v1.cbz", "Series v2.cbz", "Series v3.5.cbz"] {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
+        }
 
-    let url = first
-        .pointer("/attributes/coverImage/original")
-        .and_then(Value::as_str)
-        .or_else(|| {
-            first
-                .pointer("/attributes/coverImage/large")
-                .and_then(Value::as_str)
-        })
-        .or_else(|| {
-            first
-                .pointer("/attributes/coverImage/small")
-                .and_then(Value::as_str)
-        })
-        .or_else(|| {
-            first
-                .pointer("/attributes/coverImage/tiny")
-                .and_then(Value::as_str)
-        });
+        let plan_with = |template: &str| {
+            let options = PlanOptions {
+                batch_size: 2,
+                folder_template: template.parse().expect("parse template"),
+                ..PlanOptions::default()
+            };
+            build_plan(&series, None, &options)
+        };
+        let names = |plan: Vec<BatchPlan>| {
+            plan.iter()
+                .map(|b| file_name_text(&b.batch_dir))
+                .collect::<Vec<_>>()
+        };
 
-    let Some(url) = url else {
-        return Ok(None);
-    };
+        assert_eq!(
+            names(plan_with(DEFAULT_FOLDER_TEMPLATE).expect("plan")),
+            ["Series 1", "Series 2"]
+        );
+        assert_eq!(
+            names(plan_with("{series} Vol {start:02}-{end:02}").expect("plan")),
+            ["Series Vol 01-02", "Series Vol 03.5-03.5"]
+        );
+        assert_eq!(
+            names(plan_with("{series} Part {batch:03}").expect("plan")),
+            ["Series Part 001", "Series Part 002"]
+        );
 
-    Ok(Some(CoverResult {
-        source: "kitsu".to_string(),
-        url: url.to_string(),
-    }))
-}
+        let same_name = plan_with("{series} Part").expect_err("duplicate folder names");
+        assert!(format!("{same_name:#}").contains("more than one batch"));
+        for bad in [
+            "{series}",
+            "{series}/{batch}",
+            "{batch:11}",
+            "{title} {batch}",
+            "{series:02}",
+            "{batch",
+            " ",
+        ] {
+            assert!(bad.parse::<FolderTemplate>().is_err(), "accepted {bad:?}");
+        }
 
-pub fn find_remote_cover(title: &str) -> (Option<CoverResult>, Option<String>) {
-    let mut last_err: Option<String> = None;
+        let options = PlanOptions {
+            batch_size: 2,
+            folder_template: "{series} Part {batch:02}".parse().expect("parse template"),
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("plan");
+        assert!(
+            format_plan(&series, &plan, None, &options).contains("Series Part 01  (volumes 1-2)")
+        );
 
-    match fetch_cover_mangadex(title, "best") {
-        Ok(Some(cover)) => return (Some(cover), None),
-        Ok(None) => {}
-        Err(err) => last_err = Some(err.to_string()),
+        let _ = fs::remove_dir_all(dir);
     }
 
-    match fetch_cover_anilist(title) {
-        Ok(Some(cover)) => return (Some(cover), None),
-        Ok(None) => {}
-        Err(err) => last_err = Some(err.to_string()),
-    }
+    #[test]
+    fn copy_mode_leaves_originals_and_rollback_removes_only_copies() {
+        let dir = scratch_dir("copy_mode");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for name in ["Series v1.cbz", "Series v2.cbz"] {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
+        }
+
+        let options = PlanOptions {
+            copy: true,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        assert!(format_plan(&series, &plan, None, &options).contains("[PLAN] Mode: copy"));
+
+        let journal = journal_path(&series);
+        let execute_options = ExecuteOptions {
+            journal: Some(journal.clone()),
+            ..ExecuteOptions::default()
+        };
+        let mut tags = Vec::new();
+        execute(
+            &plan,
+            None,
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |event| tags.extend(event.tag()),
+        )
+        .expect("execute plan");
+        assert_eq!(tags.iter().filter(|tag| **tag == "[COPY]").count(), 2);
+
+        let batch = dir.join("Series 1");
+        assert!(series.join("Series v1.cbz").is_file());
+        assert_eq!(
+            fs::read(batch.join("Series v001.cbz")).expect("read copy"),
+            b"Series v1.cbz"
+        );
+        let entries = Journal::load(&journal).expect("load journal");
+        assert!(entries
+            .iter()
+            .all(|e| !matches!(e, JournalEntry::Move { .. })));
+
+        rollback(&journal, &mut |_| {}).expect("rollback");
+        assert!(!batch.exists());
+        assert!(series.join("Series v1.cbz").is_file());
+        assert!(series.join("Series v2.cbz").is_file());
 
-    match fetch_cover_kitsu(title) {
-        Ok(Some(cover)) => return (Some(cover), None),
-        Ok(None) => {}
-        Err(err) => last_err = Some(err.to_string()),
+        let _ = fs::remove_dir_all(dir);
     }
 
-    (None, last_err)
-}
+    #[test]
+    fn organized_series_is_reported_and_its_covers_can_be_refreshed() {
+        let dir = scratch_dir("already_organized");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        let cover = series.join("cover.jpg");
+        save_jpeg(
+            &DynamicImage::ImageRgb8(RgbImage::from_pixel(60, 90, Rgb([90, 120, 200]))),
+            &cover,
+        )
+        .expect("write series cover");
+        for (folder, volume) in [
+            ("Series 2", "Series v003.cbz"),
+            ("Series 1", "Series v001.cbz"),
+            ("Series Extras", "Series v099.cbz"),
+            ("Other 1", "Other v001.cbz"),
+        ] {
+            fs::create_dir_all(dir.join(folder)).expect("create batch dir");
+            fs::write(dir.join(folder).join(volume), volume.as_bytes()).expect("write volume");
+        }
+        fs::write(dir.join("Series 2").join("Series v004.cbz"), b"v4").expect("write volume");
+
+        let options = PlanOptions::default();
+        let err = build_plan(&series, Some(&cover), &options).expect_err("nothing to move");
+        assert!(err.to_string().contains("already organized"), "{err}");
+
+        let batches = already_organized(&series, &options)
+            .expect("check series")
+            .expect("series is organized");
+        let found: Vec<(usize, String, usize)> = batches
+            .iter()
+            .map(|b| (b.batch_index, file_name_text(&b.batch_dir), b.volumes.len()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (1, "Series 1".to_string(), 1),
+                (2, "Series 2".to_string(), 2)
+            ]
+        );
 
-fn zip_entry_is_image(entry_name: &str) -> bool {
-    let lower = entry_name.to_ascii_lowercase();
-    if !IMAGE_EXTS.iter().any(|ext| lower.ends_with(ext)) {
-        return false;
+        let plan = cover_refresh_plan(&batches, Some(&cover), &CleanRules::default());
+        assert_eq!(plan[1].volume_start, 2);
+        execute(
+            &plan,
+            Some(&cover),
+            &ExecuteOptions::default(),
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("refresh covers");
+        for folder in ["Series 1", "Series 2"] {
+            assert!(dir.join(folder).join("cover.jpg").is_file());
+        }
+        assert!(dir.join("Series 2").join("Series v004.cbz").is_file());
+        assert!(!dir.join("Series Extras").join("cover.jpg").exists());
+
+        fs::write(series.join("Series v005.cbz"), b"v5").expect("write volume");
+        assert!(already_organized(&series, &options)
+            .expect("check series")
+            .is_none());
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
-    for component in Path::new(entry_name).components() {
-        if let Component::Normal(part) = component {
-            let part_str = part.to_string_lossy();
-            if part_str == "__MACOSX" || is_hidden_or_macos_junk(&part_str) {
-                return false;
+    #[test]
+    fn incremental_plan_tops_up_the_last_batch_and_continues_numbering() {
+        let dir = scratch_dir("incremental");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for (folder, volumes) in [("Series 1", 1..=4), ("Series 2", 5..=6)] {
+            fs::create_dir_all(dir.join(folder)).expect("create batch dir");
+            for n in volumes {
+                fs::write(dir.join(folder).join(format!("Series v{n:03}.cbz")), [n])
+                    .expect("write volume");
             }
         }
-    }
+        for n in 7..=9u8 {
+            fs::write(series.join(format!("Series v{n}.cbz")), [n]).expect("write volume");
+        }
+        let cover = series.join("cover.jpg");
+        save_jpeg(
+            &DynamicImage::ImageRgb8(RgbImage::from_pixel(60, 90, Rgb([90, 120, 200]))),
+            &cover,
+        )
+        .expect("write series cover");
 
-    true
-}
+        let options = PlanOptions {
+            batch_size: 4,
+            incremental: true,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, Some(&cover), &options).expect("build plan");
+        let summary: Vec<(usize, String, usize, usize, usize)> = plan
+            .iter()
+            .map(|b| {
+                (
+                    b.batch_index,
+                    file_name_text(&b.batch_dir),
+                    b.volume_start,
+                    b.moves.len(),
+                    b.existing_volumes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, "Series 2".to_string(), 7, 2, 2),
+                (3, "Series 3".to_string(), 9, 1, 0),
+            ]
+        );
+        let text = format_plan(&series, &plan, Some(&cover), &options);
+        assert!(text.contains("continuing after 6 volumes"), "{text}");
+        assert!(text.contains("[KEEP] 2 volumes"), "{text}");
+
+        execute(
+            &plan,
+            Some(&cover),
+            &ExecuteOptions::default(),
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("execute plan");
+        assert_eq!(scan_volumes(&dir.join("Series 2")).expect("scan").len(), 4);
+        assert!(dir.join("Series 2").join("cover.jpg").is_file());
+        assert!(dir.join("Series 3").join("cover.jpg").is_file());
+        assert!(!dir.join("Series 1").join("cover.jpg").exists());
 
-fn first_image_entry_in_zip(volume_file: &Path) -> Result<Option<String>> {
-    let file = fs::File::open(volume_file)
-        .with_context(|| format!("failed to open archive: {}", volume_file.display()))?;
-    let mut archive = ZipArchive::new(file)
-        .with_context(|| format!("failed to read archive: {}", volume_file.display()))?;
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-    let mut entries = Vec::new();
-    for idx in 0..archive.len() {
-        let entry = archive.by_index(idx)?;
-        if entry.is_dir() {
-            continue;
+    #[test]
+    fn execute_summary_lists_every_path_it_changed() {
+        let dir = scratch_dir("execute_summary");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for n in 1..=2u8 {
+            fs::write(series.join(format!("Series v{n}.cbz")), [n]).expect("write volume");
         }
-        let name = entry.name().to_string();
-        if zip_entry_is_image(&name) {
-            entries.push(name);
+        let cover = series.join("cover.jpg");
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(60, 90, Rgb([90, 120, 200])));
+        save_jpeg(&image, &cover).expect("write series cover");
+        let first = dir.join("Series 1");
+        fs::create_dir_all(&first).expect("create batch dir");
+        save_jpeg(&image, &first.join("cover.jpg")).expect("write earlier cover");
+
+        let plan = build_plan(
+            &series,
+            Some(&cover),
+            &PlanOptions {
+                batch_size: 1,
+                ..PlanOptions::default()
+            },
+        )
+        .expect("build plan");
+        let execute_options = ExecuteOptions {
+            metadata: Some(BatchMetadata {
+                series_title: "Series".to_string(),
+                cover_source: None,
+            }),
+            ..ExecuteOptions::default()
+        };
+        let summary = execute(
+            &plan,
+            Some(&cover),
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("execute plan");
+
+        let second = dir.join("Series 2");
+        assert_eq!(
+            summary.moved,
+            vec![
+                (series.join("Series v1.cbz"), first.join("Series v001.cbz")),
+                (series.join("Series v2.cbz"), second.join("Series v002.cbz")),
+            ]
+        );
+        assert_eq!(summary.created_dirs, vec![second.clone()]);
+        assert_eq!(summary.covers_archived.len(), 1);
+        assert!(summary.covers_archived[0].is_file());
+        assert!(summary.covers_written.contains(&first.join("cover.jpg")));
+        assert!(summary.covers_written.contains(&second.join("cover.jpg")));
+        assert!(summary
+            .covers_written
+            .contains(&second.join("cover_old.jpg")));
+        assert_eq!(
+            summary.metadata_written,
+            vec![
+                first.join(METADATA_FILE_NAME),
+                second.join(METADATA_FILE_NAME)
+            ]
+        );
+        assert_eq!(summary.affected_dirs(), vec![series, first, second]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn continue_on_error_finishes_the_plan_and_reports_failed_moves() {
+        let dir = scratch_dir("continue_on_error");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for n in 1..=3u8 {
+            fs::write(series.join(format!("Series v{n}.cbz")), [n]).expect("write volume");
         }
+        let options = PlanOptions {
+            batch_size: 2,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("build plan");
+        // The volume disappears between planning and processing, so its move fails.
+        fs::remove_file(series.join("Series v1.cbz")).expect("remove volume");
+
+        let err = execute(
+            &plan,
+            None,
+            &ExecuteOptions::default(),
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect_err("fail fast by default");
+        assert!(
+            format!("{err:#}").contains("failed to move file"),
+            "{err:#}"
+        );
+        assert!(series.join("Series v2.cbz").is_file());
+
+        let mut tags = Vec::new();
+        let summary = execute(
+            &plan,
+            None,
+            &ExecuteOptions {
+                continue_on_error: true,
+                ..ExecuteOptions::default()
+            },
+            &AtomicBool::new(false),
+            &mut |event| tags.extend(event.tag()),
+        )
+        .expect("execute plan");
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].src, series.join("Series v1.cbz"));
+        assert_eq!(summary.moved.len(), 2);
+        assert_eq!(tags.iter().filter(|tag| **tag == "[FAILED]").count(), 1);
+        assert!(dir.join("Series 2").join("Series v003.cbz").is_file());
+        assert_eq!((summary.batches_completed, summary.total_batches), (1, 2));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn mangadex_results_are_scored_by_match_tier_and_threshold() {
+        let item = |id: &str, main: &str, alt: &str| {
+            json!({
+                "id": id,
+                "attributes": {
+                    "title": { "en": main },
+                    "altTitles": [{ "ja-ro": alt }],
+                },
+            })
+        };
+        let title_l = "one piece";
+        let title_n = normalize_title(title_l);
+        let score = |value: &Value| {
+            let hit = mangadex_item_titles(value);
+            score_titles(&hit.main, &hit.alts, title_l, &title_n)
+        };
+        assert_eq!(score(&item("a", "One-Piece!", "x")), 6);
+        assert_eq!(score(&item("b", "Wan Pisu", "ONE PIECE")), 5);
+        assert_eq!(score(&item("c", "One Piece Party", "x")), 2);
+        assert_eq!(score(&item("d", "Wan Pisu", "one piece: strong world")), 1);
+        assert_eq!(score(&item("e", "Naruto", "Naruto Shippuden")), 0);
+
+        let items = vec![
+            item("contains", "One Piece Party", "x"),
+            item("exact", "One Piece", "x"),
+            item("exact-too", "one piece", "x"),
+        ];
+        let ranked: Vec<(&Value, u8)> = rank_mangadex_items(&items, "One Piece", 0);
+        let ids: Vec<&Value> = ranked.iter().map(|(item, _)| &item["id"]).collect();
+        assert_eq!(ids, vec!["exact", "exact-too", "contains"]);
+        assert_eq!(ranked[0].1, MANGADEX_MAX_SCORE);
+
+        let unrelated = vec![item("e", "Naruto", "x"), item("c", "One Piece Party", "x")];
+        assert_eq!(
+            rank_mangadex_items(&unrelated, "One Piece", 0)[0].0["id"],
+            "c"
+        );
+        assert_eq!(rank_mangadex_items(&unrelated, "One Piece", 1).len(), 1);
+        assert!(rank_mangadex_items(&unrelated, "One Piece", 5).is_empty());
+        assert!(rank_mangadex_items(&[], "One Piece", 0).is_empty());
+    }
+
+    #[test]
+    fn mangadex_search_filters_are_only_sent_when_set() {
+        let defaults = mangadex_search_params("Berserk", 5, &CoverOptions::default());
+        assert_eq!(
+            defaults,
+            vec![("title", "Berserk".to_string()), ("limit", "5".to_string())]
+        );
+
+        let options = CoverOptions {
+            content_ratings: vec![ContentRating::Safe, ContentRating::Suggestive],
+            languages: vec!["EN".to_string()],
+            original_languages: vec!["ja".to_string()],
+            ..CoverOptions::default()
+        };
+        let filtered = mangadex_search_params("Berserk", 5, &options);
+        assert_eq!(
+            &filtered[2..],
+            &[
+                ("contentRating[]", "safe".to_string()),
+                ("contentRating[]", "suggestive".to_string()),
+                ("availableTranslatedLanguage[]", "en".to_string()),
+                ("originalLanguage[]", "ja".to_string()),
+            ]
+        );
+
+        let config = Config::from_value(json!({
+            "cover_content_ratings": ["safe", "Erotica"],
+            "cover_languages": ["en"],
+        }))
+        .expect("valid config");
+        assert_eq!(
+            config.cover_content_ratings,
+            Some(vec![ContentRating::Safe, ContentRating::Erotica])
+        );
+        let err = Config::from_value(json!({ "cover_content_ratings": ["spicy"] }))
+            .expect_err("unknown rating");
+        assert!(
+            err.to_string().contains("invalid content rating"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn tied_mangadex_results_rank_the_same_whatever_order_they_arrive_in() {
+        let item =
+            |id: &str, main: &str| json!({ "id": id, "attributes": { "title": { "en": main } } });
+        let pick = |items: &[Value]| -> Vec<String> {
+            rank_mangadex_items(items, "Berserk", 0)
+                .iter()
+                .map(|(item, _)| item["id"].as_str().unwrap_or_default().to_string())
+                .collect()
+        };
+
+        // Both contain the search; the shorter title is the closer match.
+        let long = item("a-long", "Berserk of Gluttony Side Stories");
+        let short = item("z-short", "Berserk Prototype");
+        assert_eq!(pick(&[long.clone(), short.clone()]), ["z-short", "a-long"]);
+        assert_eq!(pick(&[short, long]), ["z-short", "a-long"]);
+
+        // Same score and length: the lower id wins.
+        let first = item("0d1c", "Berserk (Colored)");
+        let second = item("9f2e", "Berserk [Colored]");
+        assert_eq!(pick(&[second.clone(), first.clone()]), ["0d1c", "9f2e"]);
+        assert_eq!(pick(&[first, second]), ["0d1c", "9f2e"]);
     }
 
-    if entries.is_empty() {
-        return Ok(None);
-    }
+    #[test]
+    fn manual_cover_must_decode_and_is_copied_in_as_jpeg() {
+        let dir = scratch_dir("manual_cover");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        let picked = dir.join("better.png");
+        fs::write(&picked, png_bytes(6, 9)).expect("write picked cover");
+        let broken = dir.join("broken.jpg");
+        fs::write(&broken, b"not an image").expect("write broken cover");
+        let local = series.join("poster.png");
+        fs::write(&local, png_bytes(6, 9)).expect("write local cover");
+
+        let err = manual_cover_decision(&series, &broken).expect_err("undecodable cover");
+        assert!(
+            format!("{err:#}").contains("not a readable image"),
+            "{err:#}"
+        );
+        assert!(matches!(
+            manual_cover_decision(&series, &local).expect("decide local cover"),
+            CoverDecision::Existing(path) if path == local
+        ));
+
+        let decision = manual_cover_decision(&series, &picked).expect("decide picked cover");
+        assert_eq!(
+            decision.planned_path(),
+            Some(series.join("cover.jpg").as_path())
+        );
+        assert!(!series.join("cover.jpg").exists());
+        let written = apply_cover_decision(&decision, &mut |_| {}).expect("apply decision");
+        let bytes = fs::read(written.expect("cover written")).expect("read cover");
+        assert_eq!(
+            image::guess_format(&bytes).expect("known format"),
+            ImageFormat::Jpeg
+        );
 
-    natural_sort_strings(&mut entries);
-    Ok(entries.into_iter().next())
-}
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-fn find_first_volume_cover_inner(series_dir: &Path) -> Result<Option<VolumeCoverResult>> {
-    let volumes = scan_volumes(series_dir)?;
-    if volumes.is_empty() {
-        return Ok(None);
+    #[test]
+    fn interrupted_cover_write_leaves_the_old_file_in_place() {
+        let dir = scratch_dir("atomic_write");
+        let cover = dir.join("cover.jpg");
+        fs::write(&cover, b"old cover").expect("write old cover");
+
+        let err = write_atomically(&cover, |out| {
+            out.write_all(b"half a new co")?;
+            bail!("killed mid-write")
+        })
+        .expect_err("write should fail");
+        assert!(err.to_string().contains("killed mid-write"));
+        assert_eq!(fs::read(&cover).expect("read cover"), b"old cover");
+        assert!(!dir.join("cover.jpg.tmp").exists());
+
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(60, 90, Rgb([90, 120, 200])));
+        save_jpeg(&image, &cover).expect("save cover");
+        assert_eq!(
+            image::guess_format(&fs::read(&cover).expect("read cover")).ok(),
+            Some(ImageFormat::Jpeg)
+        );
+        assert!(!dir.join("cover.jpg.tmp").exists());
     }
 
-    let first_volume = volumes[0].clone();
-    if !has_known_ext(&first_volume, &[".cbz", ".zip"]) {
-        let ext = first_volume
-            .extension()
-            .map(|e| format!(".{}", e.to_string_lossy().to_ascii_lowercase()))
-            .unwrap_or_else(|| "(none)".to_string());
-        bail!(
-            "first volume is {} (local extraction currently supports .cbz/.zip only)",
-            ext
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_covers_are_archived_without_touching_the_shared_store() {
+        use std::os::unix::fs::symlink;
+
+        let dir = scratch_dir("symlinked_covers");
+        let store = dir.join("store");
+        let batch = dir.join("batch");
+        fs::create_dir_all(&store).expect("create store");
+        fs::create_dir_all(&batch).expect("create batch");
+        fs::write(store.join("shared.jpg"), b"shared cover").expect("write shared cover");
+        symlink(store.join("shared.jpg"), batch.join("cover.jpg")).expect("link cover");
+
+        let archived = archive_existing_cover_jpg(&batch)
+            .expect("archive")
+            .expect("archived path");
+        assert_eq!(archived, batch.join("cover_old.jpg"));
+        assert!(!fs::symlink_metadata(&archived)
+            .expect("archived metadata")
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read(&archived).expect("read archive"), b"shared cover");
+        assert!(fs::symlink_metadata(batch.join("cover.jpg")).is_err());
+        assert_eq!(
+            fs::read(store.join("shared.jpg")).expect("read store"),
+            b"shared cover"
         );
+
+        // A dangling cover_old.jpg link is skipped rather than copied through.
+        let other = dir.join("other");
+        fs::create_dir_all(&other).expect("create other batch");
+        symlink(store.join("missing.jpg"), other.join("cover_old.jpg")).expect("link cover_old");
+        let series_cover = dir.join("series.jpg");
+        fs::write(&series_cover, b"series cover").expect("write series cover");
+        let base = ensure_cover_old(&other, &series_cover).expect("ensure cover_old");
+        assert_eq!(base, other.join("cover_old_2.jpg"));
+        assert!(!store.join("missing.jpg").exists());
     }
 
-    let first_image = first_image_entry_in_zip(&first_volume)?.ok_or_else(|| {
-        anyhow!(
-            "no image files found in first volume archive: {}",
-            file_name_text(&first_volume)
-        )
-    })?;
+    #[test]
+    fn unbatch_moves_volumes_back_and_removes_the_batch_folders() {
+        let dir = scratch_dir("unbatch");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::write(series.join("Series v002.cbz"), b"loose").expect("write loose volume");
+        for (folder, volume) in [
+            ("Series 1", "Series v001.cbz"),
+            ("Series 1", "Series v002.cbz"),
+            ("Series 2", "Series v003.cbz"),
+            ("Series 2", "notes.txt"),
+        ] {
+            fs::create_dir_all(dir.join(folder)).expect("create batch dir");
+            fs::write(dir.join(folder).join(volume), volume.as_bytes()).expect("write file");
+        }
+        for generated in [
+            "cover.jpg",
+            "cover_old.jpg",
+            "cover_old_2.jpg",
+            METADATA_FILE_NAME,
+        ] {
+            fs::write(dir.join("Series 1").join(generated), b"x").expect("write generated");
+        }
 
-    Ok(Some(VolumeCoverResult {
-        volume_file: first_volume,
-        image_entry: first_image,
-        output_file: series_dir.join("cover.jpg"),
-    }))
-}
+        let options = PlanOptions::default();
+        let planned: Vec<(String, String)> = plan_unbatch(&series, &options)
+            .expect("plan")
+            .iter()
+            .map(|m| (file_name_text(&m.src), m.dst_name.clone()))
+            .collect();
+        assert_eq!(
+            planned,
+            vec![
+                ("Series v001.cbz".to_string(), "Series v001.cbz".to_string()),
+                (
+                    "Series v002.cbz".to_string(),
+                    "Series v002 (2).cbz".to_string()
+                ),
+                ("Series v003.cbz".to_string(), "Series v003.cbz".to_string()),
+            ]
+        );
+        assert!(dir.join("Series 1").join("Series v001.cbz").exists());
 
-pub fn find_first_volume_cover(series_dir: &Path) -> (Option<VolumeCoverResult>, Option<String>) {
-    match find_first_volume_cover_inner(series_dir) {
-        Ok(result) => (result, None),
-        Err(err) => (None, Some(err.to_string())),
+        let mut warnings = Vec::new();
+        let moved = unbatch(&series, &options, &mut |event| {
+            if let LogEvent::Warn(message) = event {
+                warnings.push(message);
+            }
+        })
+        .expect("unbatch");
+        assert_eq!(moved.len(), 3);
+        assert_eq!(
+            fs::read(series.join("Series v002 (2).cbz")).expect("read moved"),
+            b"Series v002.cbz"
+        );
+        assert_eq!(
+            fs::read(series.join("Series v002.cbz")).expect("read"),
+            b"loose"
+        );
+        assert!(!dir.join("Series 1").exists());
+        assert!(dir.join("Series 2").join("notes.txt").exists());
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
     }
-}
 
-fn save_jpeg(image: &DynamicImage, out_path: &Path) -> Result<()> {
-    if let Some(parent) = out_path.parent() {
-        ensure_dir(parent)?;
+    #[test]
+    fn other_users_home_directories_are_rejected_rather_than_taken_literally() {
+        let err = resolve_series_dir("~someone/Manga/Series").expect_err("~user is rejected");
+        assert!(
+            err.to_string().contains("~someone paths are not supported"),
+            "{err}"
+        );
+        assert_eq!(
+            expand_tilde("~someone/Manga"),
+            PathBuf::from("~someone/Manga")
+        );
+
+        let err = resolve_series_dir("~/manga_cleaner_missing_folder").expect_err("missing");
+        assert!(err.to_string().contains("failed to resolve path"), "{err}");
     }
 
-    let rgb = image.to_rgb8();
-    let rendered = DynamicImage::ImageRgb8(rgb);
+    #[test]
+    fn tilde_expands_from_userprofile_when_home_is_unset() {
+        let windows_env = |var: &str| (var == "USERPROFILE").then(|| OsString::from("C:/Users/me"));
+        let home = home_dir_from(windows_env);
+        assert_eq!(home, Some(PathBuf::from("C:/Users/me")));
+        assert_eq!(
+            expand_tilde_in("~/Manga", home.clone()),
+            Path::new("C:/Users/me").join("Manga")
+        );
+        assert_eq!(
+            expand_tilde_in("~", home.clone()),
+            PathBuf::from("C:/Users/me")
+        );
 
-    let mut out = fs::File::create(out_path)
-        .with_context(|| format!("failed to create image file: {}", out_path.display()))?;
-    let mut encoder = JpegEncoder::new_with_quality(&mut out, 95);
-    encoder
-        .encode_image(&rendered)
-        .with_context(|| format!("failed to encode JPEG: {}", out_path.display()))?;
-    Ok(())
-}
+        let both = |var: &str| match var {
+            "HOME" => Some(OsString::from("/home/me")),
+            "USERPROFILE" => Some(OsString::from("C:/Users/me")),
+            _ => None,
+        };
+        assert_eq!(home_dir_from(both), Some(PathBuf::from("/home/me")));
+        let empty_home = |var: &str| match var {
+            "HOME" => Some(OsString::new()),
+            "USERPROFILE" => Some(OsString::from("C:/Users/me")),
+            _ => None,
+        };
+        assert_eq!(home_dir_from(empty_home), home);
+        assert_eq!(home_dir_from(|_| None), None);
+        assert_eq!(expand_tilde_in("~/Manga", None), PathBuf::from("~/Manga"));
 
-pub fn write_volume_cover(result: &VolumeCoverResult) -> Result<PathBuf> {
-    if let Some(parent) = result.output_file.parent() {
-        ensure_dir(parent)?;
+        // The real environment goes through the same lookup on every platform.
+        if let Some(real_home) = home_dir() {
+            assert_eq!(expand_tilde("~/Manga"), real_home.join("Manga"));
+        }
     }
 
-    let file = fs::File::open(&result.volume_file)
-        .with_context(|| format!("failed to open archive: {}", result.volume_file.display()))?;
-    let mut archive = ZipArchive::new(file)
-        .with_context(|| format!("failed to read archive: {}", result.volume_file.display()))?;
-    let mut entry = archive
-        .by_name(&result.image_entry)
-        .with_context(|| format!("missing image entry in archive: {}", result.image_entry))?;
-
-    let mut bytes = Vec::new();
-    entry
-        .read_to_end(&mut bytes)
-        .context("failed to read image from archive")?;
-
-    let image = image::load_from_memory(&bytes).context("failed to decode image from archive")?;
-    save_jpeg(&image, &result.output_file)?;
-    Ok(result.output_file.clone())
-}
+    #[test]
+    fn download_progress_is_reported_per_tenth_and_at_the_end() {
+        let mut throttle = ProgressThrottle::default();
+        let reported: Vec<u64> = (1..=40)
+            .map(|chunk| chunk * 25)
+            .filter(|&received| throttle.due(received, Some(1000)))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![25, 100, 200, 300, 400, 500, 600, 700, 800, 900, 1000]
+        );
 
-pub fn ensure_cover_jpg(series_dir: &Path, selected_cover: &Path) -> Result<PathBuf> {
-    let cover_jpg = series_dir.join("cover.jpg");
-    let selected_resolved = selected_cover
-        .canonicalize()
-        .unwrap_or_else(|_| selected_cover.to_path_buf());
-    let cover_resolved = cover_jpg
-        .canonicalize()
-        .unwrap_or_else(|_| cover_jpg.clone());
+        let mut throttle = ProgressThrottle::default();
+        let unknown: Vec<u64> = (1..=40)
+            .map(|chunk| chunk * 16 * 1024)
+            .filter(|&received| throttle.due(received, None))
+            .collect();
+        assert_eq!(unknown, vec![16 * 1024, 256 * 1024, 512 * 1024]);
 
-    if selected_resolved == cover_resolved {
-        return Ok(cover_jpg);
+        let event = LogEvent::CoverDownloadProgress {
+            received: 300 * 1024,
+            total: Some(1200 * 1024),
+        };
+        assert_eq!(
+            event.to_string(),
+            "[COVER] Downloading series cover: 300 of 1200 KB (25%)"
+        );
     }
 
-    let image = ImageReader::open(selected_cover)
-        .with_context(|| format!("failed to open image: {}", selected_cover.display()))?
-        .decode()
-        .context("failed to decode selected cover image")?;
+    #[test]
+    #[cfg(feature = "network")]
+    fn cancelled_download_stops_mid_stream_and_removes_the_partial_file() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let url = format!("http://{}/cover.jpg", listener.local_addr().expect("addr"));
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("accept");
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: 1048576\r\n\r\n",
+            );
+            // Keep sending until the client hangs up.
+            let chunk = [7u8; 16 * 1024];
+            while stream.write_all(&chunk).is_ok() {}
+        });
 
-    save_jpeg(&image, &cover_jpg)?;
-    Ok(cover_jpg)
-}
+        let dir = scratch_dir("cancelled_download");
+        let output = dir.join("cover.jpg");
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut chunks = 0;
+        let result = with_cancel(Arc::clone(&cancel), || {
+            download_file(&url, &output, 5, &mut |_, total| {
+                assert_eq!(total, Some(1_048_576));
+                chunks += 1;
+                cancel.store(true, Ordering::Relaxed);
+            })
+        });
+        let err = result.expect_err("download is cancelled");
+        assert!(err.to_string().contains("cancelled"), "{err:#}");
+        assert_eq!(chunks, 1);
+        assert!(!output.exists());
+        assert!(!dir.join("cover.jpg.tmp").exists());
+        server.join().expect("server thread");
+
+        // Outside `with_cancel` requests run as usual.
+        assert!(check_cancelled().is_ok());
+    }
 
-pub fn open_image(path: &Path) -> Result<()> {
-    #[cfg(target_os = "macos")]
-    let mut command = {
-        let mut cmd = Command::new("open");
-        cmd.arg(path);
-        cmd
-    };
+    #[test]
+    fn anilist_and_kitsu_pick_their_best_title_match_not_the_first() {
+        let anilist = json!({
+            "data": { "Page": { "media": [
+                {
+                    "id": 2,
+                    "title": { "english": "Berserk: The Prototype", "romaji": "Berserk Prototype" },
+                    "synonyms": [],
+                    "coverImage": { "extraLarge": "https://anilist/spin-off.jpg" },
+                },
+                {
+                    "id": 1,
+                    "title": { "english": null, "romaji": "Berserk", "native": "ベルセルク" },
+                    "synonyms": ["Berserk Manga"],
+                    "coverImage": { "large": "https://anilist/berserk.jpg" },
+                },
+            ] } }
+        });
+        let result = anilist_best_cover(&anilist, "Berserk").expect("anilist match");
+        assert_eq!(result.url, "https://anilist/berserk.jpg");
+        assert_eq!(result.match_score, Some(MANGADEX_MAX_SCORE));
+
+        let kitsu = json!({
+            "data": [
+                {
+                    "id": "10",
+                    "attributes": {
+                        "canonicalTitle": "Shingeki no Kyojin: Before the Fall",
+                        "coverImage": { "original": "https://kitsu/spin-off.jpg" },
+                    },
+                },
+                {
+                    "id": "11",
+                    "attributes": {
+                        "canonicalTitle": "Shingeki no Kyojin",
+                        "titles": { "en": "Attack on Titan" },
+                        "abbreviatedTitles": ["SnK"],
+                        "coverImage": { "tiny": "https://kitsu/aot.jpg" },
+                    },
+                },
+                {
+                    "id": "12",
+                    "attributes": {
+                        "canonicalTitle": "Attack on Titan",
+                        "coverImage": null,
+                    },
+                },
+            ]
+        });
+        // The exact main-title hit has no cover, so the alternative-title match wins.
+        let result = kitsu_best_cover(&kitsu, "Attack on Titan").expect("kitsu match");
+        assert_eq!(result.url, "https://kitsu/aot.jpg");
+        assert_eq!(result.match_score, Some(5));
+        assert!(kitsu_best_cover(&json!({ "data": [] }), "Attack on Titan").is_none());
+    }
 
-    #[cfg(target_os = "windows")]
-    let mut command = {
-        let mut cmd = Command::new("cmd");
-        cmd.arg("/C").arg("start").arg("").arg(path);
-        cmd
-    };
+    #[test]
+    #[cfg(feature = "network")]
+    fn downloads_that_are_not_images_fall_through_to_the_next_provider() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let base = format!("http://{}", listener.local_addr().expect("addr"));
+        let server = thread::spawn(move || {
+            for _ in 0..3 {
+                let (mut stream, _) = listener.accept().expect("accept");
+                let mut request = [0u8; 1024];
+                let read = stream.read(&mut request).expect("read request");
+                let request = String::from_utf8_lossy(&request[..read]).into_owned();
+                let (content_type, body) = if request.starts_with("GET /html ") {
+                    (
+                        "text/html; charset=utf-8",
+                        b"<html>Not found</html>".to_vec(),
+                    )
+                } else if request.starts_with("GET /mislabeled ") {
+                    ("image/jpeg", b"<html>Rate limited</html>".to_vec())
+                } else {
+                    ("image/png", png_bytes(4, 6))
+                };
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&body);
+            }
+        });
 
-    #[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
-    let mut command = {
-        let mut cmd = Command::new("xdg-open");
-        cmd.arg(path);
-        cmd
-    };
+        let hit = |source: &str, path: &str| CoverResult {
+            source: source.to_string(),
+            url: format!("{base}/{path}"),
+            note: None,
+            match_score: None,
+        };
+        let dir = scratch_dir("non_image_download");
+        let output = dir.join("cover.jpg");
+        let decision = CoverDecision::Download {
+            result: hit("mangadex", "html"),
+            fallbacks: vec![hit("anilist", "mislabeled"), hit("kitsu", "cover.png")],
+            output: output.clone(),
+        };
+        let mut events = Vec::new();
+        let written = apply_cover_decision(&decision, &mut |event| events.push(event.to_string()))
+            .expect("apply decision");
+        server.join().expect("server thread");
+
+        assert_eq!(written.as_deref(), Some(output.as_path()));
+        let cover = decode_cover_image(&fs::read(&output).expect("read cover")).expect("decode");
+        assert_eq!(cover.width(), 4);
+        assert!(!dir.join("cover.jpg.tmp").exists());
+        let warnings: Vec<&String> = events.iter().filter(|e| e.contains("Failed")).collect();
+        assert_eq!(warnings.len(), 2, "{events:?}");
+        assert!(warnings[0].contains("returned text/html"), "{events:?}");
+        assert!(
+            warnings[1].contains("did not return a readable image"),
+            "{events:?}"
+        );
+        assert!(events
+            .iter()
+            .any(|e| e.contains("Trying the kitsu cover instead.")));
+    }
 
-    let status = command
-        .status()
-        .with_context(|| format!("failed to launch image viewer for {}", path.display()))?;
+    #[test]
+    fn http_settings_keep_the_defaults_unless_overridden() {
+        let defaults = HttpSettings::with_overrides(None, None, None);
+        assert_eq!(defaults, HttpSettings::default());
+        assert_eq!(defaults.user_agent, USER_AGENT);
+        assert_eq!(defaults.referer.as_deref(), Some(DEFAULT_REFERER));
+
+        let config = Config::from_value(json!({
+            "timeout": 90,
+            "user_agent": "my-library-tool/2.0 (me@example.com)",
+            "referer": "",
+        }))
+        .expect("valid config");
+        let settings = HttpSettings::with_overrides(
+            config.timeout,
+            config.user_agent.as_deref(),
+            config.referer.as_deref(),
+        );
+        assert_eq!(settings.timeout, Some(Duration::from_secs(90)));
+        assert_eq!(settings.user_agent, "my-library-tool/2.0 (me@example.com)");
+        assert_eq!(settings.referer, None);
 
-    if !status.success() {
-        bail!("image viewer exited with status: {status}");
+        let err = Config::from_value(json!({ "timeout": 0 })).expect_err("zero timeout");
+        assert!(err.to_string().contains("`timeout`"), "{err:#}");
     }
 
-    Ok(())
-}
+    #[test]
+    #[cfg(feature = "network")]
+    fn unreachable_proxies_are_reported_as_proxy_failures() {
+        use std::net::TcpListener;
+
+        // Bind and drop a listener to get a local port nothing is listening on.
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port");
+        let proxy = format!("http://{closed}");
+        let client = Client::builder()
+            .proxy(Proxy::all(&proxy).expect("proxy url"))
+            .timeout(Duration::from_secs(5))
+            .build()
+            .expect("client");
+        let url = "http://covers.example/cover.jpg";
+        let err = client.get(url).send().expect_err("proxy is down");
+
+        let failure = request_failure(err, url, Some(&proxy));
+        assert!(
+            failure
+                .to_string()
+                .starts_with(&format!("could not connect through proxy {proxy}")),
+            "{failure:#}"
+        );
 
-pub fn choose_series_cover(series_dir: &Path) -> Result<Option<PathBuf>> {
-    for name in COVER_CANDIDATES {
-        let candidate = series_dir.join(name);
-        if candidate.is_file() {
-            return Ok(Some(candidate));
-        }
+        let settings = HttpSettings {
+            proxy: Some(proxy.clone()),
+            ..HttpSettings::default()
+        };
+        assert_eq!(active_proxy(&settings, url), Some(proxy));
+        let direct = HttpSettings {
+            env_proxy: false,
+            ..HttpSettings::default()
+        };
+        assert_eq!(active_proxy(&direct, url), None);
     }
 
-    let mut images = Vec::new();
-    for entry in fs::read_dir(series_dir)
-        .with_context(|| format!("failed to read directory: {}", series_dir.display()))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-        if !path.is_file() {
-            continue;
-        }
-        let name = file_name_text(&path);
-        if is_hidden_or_macos_junk(&name) {
-            continue;
-        }
-        if has_known_ext(&path, IMAGE_EXTS) {
-            images.push(path);
+    #[test]
+    fn windows_reserved_and_illegal_names_are_sanitized() {
+        assert_eq!(sanitize_file_name("Re:Zero v001.cbz"), "Re_Zero v001.cbz");
+        assert_eq!(
+            sanitize_file_name("What? <Why> v002.cbz"),
+            "What_ _Why_ v002.cbz"
+        );
+        assert_eq!(
+            sanitize_file_name("Dr. Stone Vol....cbz"),
+            "Dr. Stone Vol.cbz"
+        );
+        assert_eq!(sanitize_file_name("Extras. "), "Extras");
+        assert_eq!(sanitize_file_name("CON.cbz"), "CON_.cbz");
+        assert_eq!(sanitize_file_name("con"), "con_");
+        assert_eq!(sanitize_file_name("Lpt1.tar.cbz"), "Lpt1_.tar.cbz");
+        assert_eq!(sanitize_file_name("Aux v001.cbz"), "Aux v001.cbz");
+        assert_eq!(sanitize_file_name("COM10.cbz"), "COM10.cbz");
+        assert_eq!(sanitize_file_name("..."), "_");
+
+        // Windows itself can't create the unsanitized names.
+        #[cfg(unix)]
+        {
+            let root = scratch_dir("sanitize_names");
+            let series = root.join("NUL");
+            fs::create_dir_all(&series).expect("series dir");
+            fs::write(series.join("NUL.cbz"), b"one").expect("cbz");
+            fs::write(series.join("Q&A: v1.cbr"), b"two").expect("cbr");
+
+            let names = |sanitize_names| {
+                let options = PlanOptions {
+                    sanitize_names,
+                    ..PlanOptions::default()
+                };
+                let plan = build_plan(&series, None, &options).expect("plan");
+                plan[0]
+                    .moves
+                    .iter()
+                    .map(|m| m.dst_name.clone())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(names(SanitizeNames::Cbz), vec!["Q&A: v001.cbr", "NUL_.cbz"]);
+            assert_eq!(names(SanitizeNames::All), vec!["Q&A_ v001.cbr", "NUL_.cbz"]);
+            assert_eq!(names(SanitizeNames::Off), vec!["Q&A: v001.cbr", "NUL.cbz"]);
         }
     }
 
-    if images.is_empty() {
-        return Ok(None);
+    #[test]
+    fn extensions_are_lowercased_only_when_asked_and_stems_keep_their_case() {
+        assert_eq!(
+            lowercase_extension("One Piece v001.CBZ"),
+            "One Piece v001.cbz"
+        );
+        assert_eq!(
+            lowercase_extension("Dr. STONE v002.Cbr"),
+            "Dr. STONE v002.cbr"
+        );
+        assert_eq!(lowercase_extension("README"), "README");
+        assert_eq!(lowercase_extension(".CBZ"), ".CBZ");
+
+        let root = scratch_dir("lowercase_ext");
+        let series = root.join("Series");
+        fs::create_dir_all(&series).expect("series dir");
+        fs::write(series.join("Series v01.CBZ"), b"one").expect("cbz");
+        fs::write(series.join("Series v02.Cb7"), b"two").expect("cb7");
+        fs::write(series.join("Series v03.cbr"), b"three").expect("cbr");
+
+        let names = |lowercase_extensions| {
+            let options = PlanOptions {
+                lowercase_extensions,
+                ..PlanOptions::default()
+            };
+            let plan = build_plan(&series, None, &options).expect("plan");
+            plan[0]
+                .moves
+                .iter()
+                .map(|m| m.dst_name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(true),
+            vec!["Series v001.cbz", "Series v002.cb7", "Series v003.cbr"]
+        );
+        assert_eq!(
+            names(false),
+            vec!["Series v001.CBZ", "Series v002.Cb7", "Series v003.cbr"]
+        );
     }
 
-    natural_sort_paths(&mut images);
-    Ok(images.into_iter().next())
-}
+    #[test]
+    fn run_report_counts_moves_renames_and_skipped_duplicates() {
+        let root = scratch_dir("run_report");
+        let series = root.join("Series");
+        fs::create_dir_all(&series).expect("series dir");
+        fs::write(series.join("Series v01.cbz"), b"one").expect("v01");
+        fs::write(series.join("Series v1 (Digital).cbz"), b"one").expect("dup");
+        fs::write(series.join("Series v002.cbz"), b"two").expect("v002");
+        fs::write(series.join("Series v03.cbz"), b"three").expect("v03");
+
+        let options = PlanOptions {
+            batch_size: 2,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("plan");
+        let summary = execute(
+            &plan,
+            None,
+            &ExecuteOptions::default(),
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("execute");
+
+        let report = summary.report(&plan);
+        assert_eq!(
+            report,
+            RunReport {
+                batches: 2,
+                new_folders: 2,
+                placed: 3,
+                renamed: 2,
+                copied: false,
+                covers_written: 0,
+                covers_archived: 0,
+                covers_pruned: 0,
+                skipped: 1,
+                failed: 0,
+            }
+        );
+        assert_eq!(
+            report.to_string(),
+            "3 files moved (2 renamed), 0 covers written, 0 cover_old archived across 2 batches (2 new); 1 skipped"
+        );
 
-pub fn ensure_series_cover(
-    series_dir: &Path,
-    title: &str,
-    log: &mut dyn FnMut(String),
-) -> Result<Option<PathBuf>> {
-    let (first_vol_cover, mut first_vol_err) = find_first_volume_cover(series_dir);
+        let single = RunReport {
+            batches: 1,
+            placed: 1,
+            copied: true,
+            covers_written: 1,
+            covers_archived: 1,
+            failed: 2,
+            ..RunReport::default()
+        };
+        assert_eq!(
+            single.to_string(),
+            "1 file copied, 1 cover written, 1 cover_old archived across 1 batch; 2 failed"
+        );
+        let pruned = RunReport {
+            covers_pruned: 3,
+            ..single
+        };
+        assert!(pruned
+            .to_string()
+            .contains("1 batch; 3 old covers pruned; 2 failed"));
+    }
 
-    if let Some(cover) = first_vol_cover {
-        match write_volume_cover(&cover) {
-            Ok(out) => {
-                log(format!(
-                    "[COVER] Extracted series cover from first volume: {} (source={}:{})",
-                    out.display(),
-                    file_name_text(&cover.volume_file),
-                    cover.image_entry
-                ));
-                return Ok(Some(out));
-            }
-            Err(err) => {
-                first_vol_err = Some(err.to_string());
-            }
+    #[test]
+    fn ignore_patterns_leave_matching_volumes_out_and_the_plan_lists_them() {
+        let root = scratch_dir("ignore_patterns");
+        let series = root.join("Title");
+        fs::create_dir_all(&series).expect("series dir");
+        for name in [
+            "Title v01.cbz",
+            "Title v02.cbz",
+            "Title ARTBOOK.cbz",
+            "Title Omake.zip",
+        ] {
+            fs::write(series.join(name), name).expect("volume");
         }
-    }
 
-    if let Some(existing) = choose_series_cover(series_dir)? {
-        return Ok(Some(existing));
+        let ignore: Vec<IgnorePattern> = ["*artbook*", "* omake.zip"]
+            .iter()
+            .map(|glob| glob.parse().expect("valid glob"))
+            .collect();
+        let options = PlanOptions {
+            ignore,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("plan");
+        let moved: Vec<&str> = plan[0].moves.iter().map(|m| m.dst_name.as_str()).collect();
+        assert_eq!(moved, vec!["Title v001.cbz", "Title v002.cbz"]);
+        let ignored: Vec<String> = plan[0].ignored.iter().map(|p| file_name_text(p)).collect();
+        assert_eq!(ignored, vec!["Title ARTBOOK.cbz", "Title Omake.zip"]);
+
+        let text = format_plan(&series, &plan, None, &options);
+        assert!(text.contains("[PLAN] Ignored: 2"), "{text}");
+        assert!(text.contains("       - Title Omake.zip\n"), "{text}");
+
+        let single: IgnorePattern = "Title v0?.cbz".parse().expect("glob");
+        assert!(single.matches("title V01.CBZ"));
+        assert!(!single.matches("Title v010.cbz"));
+        assert!("extras/*.cbz".parse::<IgnorePattern>().is_err());
+
+        let config = Config::from_value(json!({ "ignore": ["*Artbook*"] })).expect("config");
+        assert_eq!(config.ignore.expect("ignore")[0].to_string(), "*Artbook*");
     }
 
-    let out_file = series_dir.join("cover.jpg");
-    let (remote_cover, mut last_err) = find_remote_cover(title);
-    if let Some(result) = remote_cover {
-        match download_file(&result.url, &out_file, 30) {
-            Ok(_) => {
-                log(format!(
-                    "[COVER] Downloaded series cover: {} (source={})",
-                    out_file.display(),
-                    result.source
-                ));
-                return Ok(Some(out_file));
-            }
-            Err(err) => {
-                last_err = Some(err.to_string());
-            }
+    #[test]
+    fn extension_sets_replace_the_default_volume_and_image_extensions() {
+        let root = scratch_dir("extension_sets");
+        let series = root.join("Title");
+        fs::create_dir_all(&series).expect("series dir");
+        for name in ["Title v01.cbz", "Title v02.CBT", "Title v03.zip"] {
+            fs::write(series.join(name), name).expect("volume");
         }
-    }
+        fs::write(series.join("front.tiff"), "tiff").expect("image");
 
-    if let Some(err) = first_vol_err {
-        log(format!(
-            "[WARN] Failed to extract first-volume cover. Last error: {err}"
-        ));
-    }
+        let volume_exts: ExtensionSet = "cbz, .CBT".parse().expect("extensions");
+        assert_eq!(volume_exts.to_string(), ".cbz,.cbt");
+        let options = PlanOptions {
+            volume_exts,
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("plan");
+        let moved: Vec<&str> = plan[0].moves.iter().map(|m| m.dst_name.as_str()).collect();
+        assert_eq!(moved, vec!["Title v001.cbz", "Title v002.CBT"]);
+
+        assert_eq!(choose_series_cover(&series).expect("default scan"), None);
+        let images = ExtensionSet::new(&["tiff"]).expect("extensions");
+        assert_eq!(
+            choose_series_cover_with(&series, &images).expect("tiff scan"),
+            Some(series.join("front.tiff"))
+        );
 
-    if let Some(err) = last_err {
-        log(format!(
-            "[WARN] Failed to download series cover. Last error: {err}"
-        ));
-    } else {
-        log("[WARN] Failed to download series cover (no results).".to_string());
+        assert!("cbz,".parse::<ExtensionSet>().is_err());
+        let err = Config::from_value(json!({ "volume_extensions": [] })).expect_err("empty");
+        assert!(err.to_string().contains("`volume_extensions`"), "{err}");
+        let config =
+            Config::from_value(json!({ "image_extensions": ["JPG", "tiff"] })).expect("config");
+        assert_eq!(
+            config.image_extensions.expect("images").to_string(),
+            ".jpg,.tiff"
+        );
     }
 
-    Ok(None)
-}
+    #[test]
+    fn errors_are_classified_through_added_context() {
+        let root = scratch_dir("error_classes");
+        let missing = root.join("missing");
+        let err = resolve_series_dir(&missing.to_string_lossy()).expect_err("missing folder");
+        assert!(matches!(err, Error::PathResolution(_)), "{err:?}");
+
+        let series = root.join("Title");
+        fs::create_dir_all(&series).expect("series dir");
+        let err = build_plan(&series, None, &PlanOptions::default()).expect_err("no volumes");
+        assert!(matches!(err, Error::NoVolumes(_)), "{err:?}");
+
+        // A typed error keeps its class, and its message, under context added later.
+        let wrapped = Error::from(anyhow!(err).context("planning Title"));
+        assert!(matches!(wrapped, Error::NoVolumes(_)), "{wrapped:?}");
+        let text = format!("{wrapped:#}");
+        assert!(
+            text.starts_with("planning Title: No volume files found"),
+            "{text}"
+        );
 
-fn pick_font() -> Result<FontArc> {
-    let candidates = [
-        "/System/Library/Fonts/Supplemental/Arial Black.ttf",
-        "/System/Library/Fonts/Supplemental/Arial Bold.ttf",
-        "/System/Library/Fonts/Supplemental/Impact.ttf",
-        "/System/Library/Fonts/Supplemental/Helvetica Bold.ttf",
-        "/Library/Fonts/Arial Black.ttf",
-        "/Library/Fonts/Arial Bold.ttf",
-        "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
-    ];
+        fs::write(series.join("Title v01.cbz"), "not a zip").expect("volume");
+        let err = Error::from(find_volume_cover(&series, 0, 0).expect_err("broken archive"));
+        assert!(matches!(err, Error::Archive(_)), "{err:?}");
 
-    for candidate in candidates {
-        let path = Path::new(candidate);
-        if !path.exists() {
-            continue;
-        }
+        let err = Error::from(anyhow!(io::Error::other("disk full")).context("writing cover"));
+        assert!(matches!(err, Error::Io(_)), "{err:?}");
+        let err = Error::from(anyhow!("cancelled"));
+        assert!(matches!(err, Error::Other(_)), "{err:?}");
+    }
 
-        let bytes = fs::read(path)
-            .with_context(|| format!("failed to read font file: {}", path.display()))?;
-        if let Ok(font) = FontArc::try_from_vec(bytes) {
-            return Ok(font);
+    #[test]
+    fn log_levels_put_warnings_below_routine_lines_and_http_above() {
+        let warn = LogEvent::Warn("careful".to_string());
+        let plan_line = LogEvent::Info("[PLAN] Batches: 1".to_string());
+        let http = LogEvent::Http {
+            url: "https://api.mangadex.org/manga".to_string(),
+            attempt: 2,
+            status: Some(429),
+            elapsed: Duration::from_millis(120),
+        };
+        assert_eq!(warn.level(), LogLevel::Quiet);
+        assert_eq!(plan_line.level(), LogLevel::Normal);
+        assert_eq!(http.level(), LogLevel::Verbose);
+
+        let shown = |level: LogLevel| {
+            [&warn, &plan_line, &http]
+                .iter()
+                .filter(|event| event.level() <= level)
+                .count()
+        };
+        assert_eq!(
+            (
+                shown(LogLevel::Quiet),
+                shown(LogLevel::Normal),
+                shown(LogLevel::Verbose)
+            ),
+            (1, 2, 3)
+        );
+        assert_eq!(
+            http.to_string(),
+            "[HTTP] GET https://api.mangadex.org/manga -> 429 in 120 ms (attempt 2)"
+        );
+    }
+
+    #[test]
+    fn phase_timings_are_logged_and_summed_per_phase() {
+        let root = scratch_dir("phase_timings");
+        let series = root.join("Title");
+        fs::create_dir_all(&series).expect("series dir");
+        for name in ["Title v01.cbz", "Title v02.cbz"] {
+            fs::write(series.join(name), name).expect("volume");
         }
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("plan");
+
+        let mut timings = PhaseTimings::default();
+        let mut timing_events = 0;
+        execute(
+            &plan,
+            None,
+            &ExecuteOptions::default(),
+            &AtomicBool::new(false),
+            &mut |event| {
+                if matches!(event, LogEvent::Timing { .. }) {
+                    assert_eq!(event.level(), LogLevel::Verbose);
+                    timing_events += 1;
+                }
+                timings.record(&event);
+            },
+        )
+        .expect("execute");
+        // Both moves are reported as a single total.
+        assert_eq!(timing_events, 1);
+        assert!(timings.get(Phase::Moves).is_some());
+        assert_eq!(timings.get(Phase::FontFit), None);
+
+        let mut summed = PhaseTimings::default();
+        summed.add(Phase::RemoteLookup, Duration::from_millis(1200));
+        summed.add(Phase::Moves, Duration::from_millis(200));
+        summed.add(Phase::Moves, Duration::from_millis(110));
+        assert_eq!(summed.to_string(), "remote lookup 1.2 s, moves 310 ms");
     }
 
-    bail!("unable to find a usable font for cover rendering")
-}
+    #[test]
+    fn sessions_round_trip_and_can_be_forgotten() {
+        let dir = scratch_dir("session");
+        let path = dir.join("manga_cleaner").join(SESSION_FILE_NAME);
+        assert_eq!(
+            Session::load(&path).expect("missing file"),
+            Session::default()
+        );
 
-fn fit_font_size(font: &FontArc, text: &str, w: u32, h: u32, margin_frac: f32) -> u32 {
-    let max_w = ((w as f32) * (1.0 - 2.0 * margin_frac)).max(1.0) as u32;
-    let max_h = ((h as f32) * (1.0 - 2.0 * margin_frac)).max(1.0) as u32;
+        let session = Session {
+            series_dir: Some(dir.join("Library")),
+            library: true,
+            batch_size: Some(12),
+            cover_text_color: Some(CoverTextColor::Auto.to_string()),
+        };
+        session.save(&path).expect("save session");
+        assert_eq!(Session::load(&path).expect("load session"), session);
 
-    let mut lo: u32 = 10;
-    let mut hi: u32 = w.max(h).saturating_mul(5).max(10);
-    let mut best = lo;
+        Session::forget(&path).expect("forget session");
+        assert!(!path.exists());
+        Session::forget(&path).expect("forgetting twice is fine");
 
-    while lo <= hi {
-        let mid = (lo + hi) / 2;
-        let scale = PxScale::from(mid as f32);
-        let (tw, th) = text_size(scale, font, text);
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
 
-        if tw <= max_w && th <= max_h {
-            best = mid;
-            lo = mid.saturating_add(1);
-        } else {
-            if mid == 0 {
-                break;
-            }
-            hi = mid.saturating_sub(1);
+    #[test]
+    fn plans_that_retitle_most_volumes_are_flagged() {
+        let root = scratch_dir("rename_warning");
+        let series = root.join("Series");
+        fs::create_dir_all(&series).expect("series dir");
+        for name in [
+            "Series v01 (Digital).cbz",
+            "Series v02 (Digital).cbz",
+            "Series v003.cbz",
+        ] {
+            fs::write(series.join(name), name).expect("volume");
         }
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("plan");
+        assert_eq!(rename_count(&plan), 2);
+        // Dropping tags doesn't change the title, so it is never flagged.
+        assert!(rename_warning(&plan, 0.0).is_none());
+
+        let options = PlanOptions {
+            clean_rules: CleanRules {
+                replace: vec![(
+                    Regex::new(r"^Series (v0[12])").expect("regex"),
+                    "Other $1".to_string(),
+                )],
+                ..CleanRules::default()
+            },
+            ..PlanOptions::default()
+        };
+        let plan = build_plan(&series, None, &options).expect("plan");
+        assert!(rename_warning(&plan, DEFAULT_RENAME_WARN_RATIO).is_none());
+        let warning = rename_warning(&plan, 0.5).expect("two of three retitled");
+        assert!(warning.starts_with("2 of 3 volumes (66%)"), "{warning}");
+        assert!(rename_warning(&plan, 1.0).is_none());
+        assert!(Config::from_value(serde_json::json!({ "rename_warn_ratio": 1.5 })).is_err());
     }
 
-    best
-}
+    #[test]
+    fn existing_batch_covers_are_archived_overwritten_or_kept() {
+        let dir = scratch_dir("existing_cover");
+        let series_cover = dir.join("series.jpg");
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(60, 90, Rgb([90, 120, 200])));
+        save_jpeg(&image, &series_cover).expect("save series cover");
+        let batch = dir.join("Series 1");
+        let cover_jpg = batch.join("cover.jpg");
+        let font = pick_font(None).expect("cover font");
+        let style = CoverStyle::default();
+        let render = |number: usize, existing: ExistingCover| {
+            render_batch_cover(&batch, number, &series_cover, &style, existing, &font, None)
+                .expect("render cover")
+        };
+        let old_covers = || {
+            fs::read_dir(&batch)
+                .expect("list batch")
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with("cover_old"))
+                .count()
+        };
 
-fn alpha_bbox(image: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
-    let (w, h) = image.dimensions();
-    let mut min_x = w;
-    let mut min_y = h;
-    let mut max_x = 0;
-    let mut max_y = 0;
-    let mut found = false;
-
-    for (x, y, px) in image.enumerate_pixels() {
-        if px.0[3] == 0 {
-            continue;
-        }
-        found = true;
-        if x < min_x {
-            min_x = x;
-        }
-        if y < min_y {
-            min_y = y;
-        }
-        if x > max_x {
-            max_x = x;
-        }
-        if y > max_y {
-            max_y = y;
-        }
-    }
+        assert!(!render(1, ExistingCover::Archive).kept);
+        let again = render(1, ExistingCover::Archive);
+        assert!(again.kept && again.archived.is_none());
+        assert_eq!(old_covers(), 1, "a re-run leaves only cover_old.jpg");
 
-    if !found {
-        return None;
-    }
+        let renumbered = render(2, ExistingCover::Archive);
+        assert_eq!(renumbered.archived, Some(batch.join("cover_old_2.jpg")));
 
-    Some((min_x, min_y, max_x, max_y))
-}
+        let numbered_two = fs::read(&cover_jpg).expect("read cover");
+        assert!(!render(3, ExistingCover::Overwrite).kept);
+        assert_eq!(old_covers(), 2);
+        assert_ne!(fs::read(&cover_jpg).expect("read cover"), numbered_two);
 
-fn draw_dead_center_text(
-    base_image: &DynamicImage,
-    text: &str,
-    opacity: u8,
-    scale: f32,
-) -> Result<DynamicImage> {
-    let mut rgba = base_image.to_rgba8();
-    let (w, h) = rgba.dimensions();
+        let numbered_three = fs::read(&cover_jpg).expect("read cover");
+        assert!(render(4, ExistingCover::Skip).kept);
+        assert_eq!(fs::read(&cover_jpg).expect("read cover"), numbered_three);
+        assert!("Overwrite".parse::<ExistingCover>().is_ok());
 
-    let font = pick_font()?;
-    let max_size = fit_font_size(&font, text, w, h, 0.06);
-    let font_size = ((max_size as f32) * scale).max(10.0);
-    let px_scale = PxScale::from(font_size);
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-    // Probe-and-correct placement on a full-size transparent canvas until the rendered bbox center
-    // lands on the image center. This mirrors Pillow's anchor-centered behavior.
-    let mut x = (w as f32 / 2.0).round() as i32;
-    let mut y = (h as f32 / 2.0).round() as i32;
-    let cx = w as f32 / 2.0;
-    let cy = h as f32 / 2.0;
+    #[test]
+    fn pruning_keeps_the_newest_old_covers_and_the_base() {
+        let batch = scratch_dir("prune_cover_old");
+        for (name, age) in [
+            ("cover_old.jpg", 50),
+            ("cover_old_2.jpg", 40),
+            ("cover_old_3.jpg", 10),
+            ("cover_old_4.jpg", 30),
+            ("cover_old_notes.jpg", 60),
+            ("cover.jpg", 0),
+        ] {
+            let path = batch.join(name);
+            fs::write(&path, name).expect("write cover");
+            let modified = SystemTime::now() - Duration::from_secs(age);
+            filetime::set_file_mtime(&path, filetime::FileTime::from_system_time(modified))
+                .expect("set mtime");
+        }
 
-    for _ in 0..4 {
-        let mut probe = RgbaImage::from_pixel(w, h, Rgba([0, 0, 0, 0]));
-        draw_text_mut(
-            &mut probe,
-            Rgba([0, 0, 0, 255]),
-            x,
-            y,
-            px_scale,
-            &font,
-            text,
+        let removed = prune_cover_old(&batch, 2, None).expect("prune");
+        assert_eq!(removed, vec![batch.join("cover_old_2.jpg")]);
+        let removed = prune_cover_old(&batch, 0, None).expect("prune all");
+        assert_eq!(
+            removed,
+            vec![batch.join("cover_old_3.jpg"), batch.join("cover_old_4.jpg")]
         );
+        for kept in ["cover_old.jpg", "cover_old_notes.jpg", "cover.jpg"] {
+            assert!(batch.join(kept).is_file(), "{kept} is kept");
+        }
 
-        let Some((min_x, min_y, max_x, max_y)) = alpha_bbox(&probe) else {
-            break;
+        let _ = fs::remove_dir_all(&batch);
+    }
+
+    #[test]
+    fn rollback_restores_a_cover_archived_by_a_pruning_run() {
+        let dir = scratch_dir("prune_rollback");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::write(series.join("Series v1.cbz"), b"one").expect("write v1");
+        let series_cover = dir.join("series.jpg");
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(60, 90, Rgb([90, 120, 200])));
+        save_jpeg(&image, &series_cover).expect("save series cover");
+        let batch = dir.join("Series 1");
+        save_jpeg(&image, &batch.join("cover_old.jpg")).expect("save base cover");
+        fs::write(batch.join("cover_old_2.jpg"), b"older").expect("write old cover");
+        fs::write(batch.join("cover.jpg"), b"hand-made").expect("write cover");
+
+        let journal = dir.join("journal.json");
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+        let execute_options = ExecuteOptions {
+            journal: Some(journal.clone()),
+            prune_covers: Some(0),
+            ..ExecuteOptions::default()
         };
+        let summary = execute(
+            &plan,
+            Some(&series_cover),
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("execute plan");
+        assert_eq!(summary.covers_archived, vec![batch.join("cover_old_3.jpg")]);
+        assert_eq!(summary.covers_pruned, vec![batch.join("cover_old_2.jpg")]);
+
+        rollback(&journal, &mut |_| {}).expect("rollback");
+        assert_eq!(
+            fs::read(batch.join("cover.jpg")).expect("restored cover"),
+            b"hand-made"
+        );
+        assert!(!batch.join("cover_old_3.jpg").exists());
 
-        let bcx = (min_x as f32 + max_x as f32) / 2.0;
-        let bcy = (min_y as f32 + max_y as f32) / 2.0;
-        let dx = (cx - bcx).round() as i32;
-        let dy = (cy - bcy).round() as i32;
+        let _ = fs::remove_dir_all(dir);
+    }
 
-        if dx == 0 && dy == 0 {
-            break;
+    #[test]
+    fn checksum_manifests_verify_until_a_volume_changes() {
+        let dir = scratch_dir("checksums");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for name in ["Series v1.cbz", "Series v2.cbz", "Series v3.cbz"] {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
         }
-        x += dx;
-        y += dy;
-    }
 
-    draw_text_mut(
-        &mut rgba,
-        Rgba([0, 0, 0, opacity]),
-        x,
-        y,
-        px_scale,
-        &font,
-        text,
-    );
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+        let execute_options = ExecuteOptions {
+            checksums: true,
+            ..ExecuteOptions::default()
+        };
+        let summary = execute(
+            &plan,
+            None,
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("execute plan");
 
-    let rgb = DynamicImage::ImageRgba8(rgba).to_rgb8();
-    Ok(DynamicImage::ImageRgb8(rgb))
-}
+        let batch_dir = dir.join("Series 1");
+        assert_eq!(
+            summary.checksums_written,
+            vec![batch_dir.join(CHECKSUMS_FILE_NAME)]
+        );
+        let manifest =
+            fs::read_to_string(batch_dir.join(CHECKSUMS_FILE_NAME)).expect("read manifest");
+        assert!(manifest.ends_with("  Series v003.cbz\n"), "{manifest}");
+        let checks = verify_manifest(&batch_dir).expect("verify manifest");
+        assert_eq!(checks.len(), 3);
+        assert!(checks.iter().all(|(_, matches)| *matches), "{checks:?}");
+
+        fs::write(batch_dir.join("Series v001.cbz"), b"corrupted").expect("corrupt volume");
+        fs::remove_file(batch_dir.join("Series v002.cbz")).expect("remove volume");
+        let checks = verify_manifest(&batch_dir).expect("reverify manifest");
+        assert_eq!(
+            checks,
+            vec![
+                (batch_dir.join("Series v001.cbz"), false),
+                (batch_dir.join("Series v002.cbz"), false),
+                (batch_dir.join("Series v003.cbz"), true),
+            ]
+        );
 
-pub fn ensure_cover_old(batch_dir: &Path, series_cover: &Path) -> Result<PathBuf> {
-    let primary = batch_dir.join("cover_old.jpg");
-    if primary.exists() {
-        return Ok(primary);
+        let _ = fs::remove_dir_all(dir);
     }
 
-    let target = unique_cover_old_path(batch_dir);
-    fs::copy(series_cover, &target).with_context(|| {
-        format!(
-            "failed to copy series cover from {} to {}",
-            series_cover.display(),
-            target.display()
-        )
-    })?;
-    Ok(target)
-}
+    #[test]
+    fn cover_decisions_describe_the_step_without_writing_it() {
+        let dir = scratch_dir("describe_cover");
+        write_zip(
+            &dir.join("Series v01.cbz"),
+            &[("001.png", &png_bytes(8, 12))],
+        );
+        let options = SeriesCoverOptions {
+            providers: vec![stub("network", Err("must not be called"))],
+            cache: None,
+            ..SeriesCoverOptions::default()
+        };
 
-pub fn archive_existing_cover_jpg(batch_dir: &Path) -> Result<Option<PathBuf>> {
-    let cover = batch_dir.join("cover.jpg");
-    if !cover.exists() {
-        return Ok(None);
+        let decision =
+            decide_series_cover(&dir, "Series", &options, &mut |_| {}).expect("decide cover");
+        assert_eq!(
+            decision.describe(),
+            "extract 001.png from Series v01.cbz to cover.jpg"
+        );
+        let entries: Vec<PathBuf> = fs::read_dir(&dir)
+            .expect("read series dir")
+            .map(|entry| entry.expect("dir entry").path())
+            .collect();
+        assert_eq!(entries, vec![dir.join("Series v01.cbz")]);
+        assert_eq!(CoverDecision::None.describe(), "none; batches get no cover");
+
+        let _ = fs::remove_dir_all(dir);
     }
 
-    let destination = unique_cover_old_path(batch_dir);
-    fs::rename(&cover, &destination).with_context(|| {
-        format!(
-            "failed to archive cover from {} to {}",
-            cover.display(),
-            destination.display()
+    #[test]
+    fn log_events_serialize_to_flat_json_objects() {
+        let event = LogEvent::Move {
+            index: 3,
+            total: 40,
+            batch: 1,
+            from: PathBuf::from("Series/Series v3.cbz"),
+            to: PathBuf::from("Series 1/Series v003.cbz"),
+            copied: false,
+        };
+        assert_eq!(
+            event.to_json(),
+            json!({
+                "type": "move",
+                "index": 3,
+                "total": 40,
+                "batch": 1,
+                "from": "Series/Series v3.cbz",
+                "to": "Series 1/Series v003.cbz",
+                "copied": false,
+                "message": "(3/40) Series v3.cbz -> Series v003.cbz",
+            })
+        );
+        let timing = LogEvent::Timing {
+            phase: Phase::Moves,
+            elapsed: Duration::from_millis(1500),
+        };
+        assert_eq!(timing.to_json()["elapsed_ms"], 1500);
+        assert_eq!(
+            LogEvent::Warn("careful".to_string()).to_json()["type"],
+            "warn"
+        );
+    }
+
+    #[test]
+    fn names_taken_after_planning_are_not_overwritten() {
+        let dir = scratch_dir("claimed_destination");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        for name in ["Series v1.cbz", "Series v2.cbz"] {
+            fs::write(series.join(name), name.as_bytes()).expect("write volume");
+        }
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+
+        // Another run puts its own file at the first planned name before this one executes.
+        let batch_dir = dir.join("Series 1");
+        fs::create_dir_all(&batch_dir).expect("create batch dir");
+        fs::write(batch_dir.join("Series v001.cbz"), b"other run").expect("write rival");
+        let mut warnings = Vec::new();
+        let summary = execute(
+            &plan,
+            None,
+            &ExecuteOptions::default(),
+            &AtomicBool::new(false),
+            &mut |event| {
+                if let LogEvent::Warn(message) = event {
+                    warnings.push(message);
+                }
+            },
         )
-    })?;
+        .expect("execute plan");
 
-    Ok(Some(destination))
-}
+        assert_eq!(
+            fs::read(batch_dir.join("Series v001.cbz")).expect("read rival"),
+            b"other run"
+        );
+        assert_eq!(
+            fs::read(batch_dir.join("Series v001 (2).cbz")).expect("read moved volume"),
+            b"Series v1.cbz"
+        );
+        assert_eq!(
+            summary.moved[0],
+            (
+                series.join("Series v1.cbz"),
+                batch_dir.join("Series v001 (2).cbz")
+            )
+        );
+        assert!(
+            warnings.iter().any(|w| w.contains("taken by another run")),
+            "{warnings:?}"
+        );
 
-pub fn write_numbered_cover(batch_dir: &Path, number: usize, series_cover: &Path) -> Result<()> {
-    ensure_dir(batch_dir)?;
-    archive_existing_cover_jpg(batch_dir)?;
-    let base_cover = ensure_cover_old(batch_dir, series_cover)?;
+        let mut reserved = HashSet::from(["Series v002.cbz".to_string()]);
+        assert_eq!(
+            unique_path_reserved(&batch_dir, "Series v002.cbz", &mut reserved),
+            batch_dir.join("Series v002 (2).cbz")
+        );
+        assert!(reserved.contains("Series v002 (2).cbz"));
 
-    let image = ImageReader::open(&base_cover)
-        .with_context(|| format!("failed to open base cover image: {}", base_cover.display()))?
-        .decode()
-        .context("failed to decode base cover image")?;
+        let _ = fs::remove_dir_all(dir);
+    }
 
-    let rendered = draw_dead_center_text(&image, &number.to_string(), 255, 0.90)?;
-    save_jpeg(&rendered, &batch_dir.join("cover.jpg"))?;
-    Ok(())
-}
+    #[test]
+    fn identical_destinations_are_checked_again_and_restored_on_rollback() {
+        let dir = scratch_dir("identical_replace");
+        let series = dir.join("Series");
+        let batch_dir = dir.join("Series 1");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::create_dir_all(&batch_dir).expect("create batch dir");
+        fs::write(series.join("Series v1.cbz"), b"one").expect("write v1");
+        fs::write(series.join("Series v2.cbz"), b"two").expect("write v2");
+        fs::write(batch_dir.join("Series v001.cbz"), b"one").expect("write identical");
+        fs::write(batch_dir.join("Series v002.cbz"), b"two").expect("write identical");
+
+        let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+        assert!(plan[0]
+            .moves
+            .iter()
+            .all(|mv| mv.destination == DestinationState::Identical));
+        // Another run changes one of them after planning.
+        fs::write(batch_dir.join("Series v002.cbz"), b"changed").expect("change v002");
+
+        let journal = journal_path(&series);
+        let execute_options = ExecuteOptions {
+            journal: Some(journal.clone()),
+            ..ExecuteOptions::default()
+        };
+        execute(
+            &plan,
+            None,
+            &execute_options,
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+        .expect("execute plan");
+        assert!(!series.join("Series v1.cbz").exists());
+        assert_eq!(
+            fs::read(batch_dir.join("Series v002.cbz")).expect("read v002"),
+            b"changed"
+        );
+        assert_eq!(
+            fs::read(batch_dir.join("Series v002 (2).cbz")).expect("read placed v002"),
+            b"two"
+        );
 
-pub fn execute(
-    plan: &[BatchPlan],
-    series_cover: Option<&Path>,
-    log: &mut dyn FnMut(String),
-) -> Result<()> {
-    for batch in plan {
-        ensure_dir(&batch.batch_dir)?;
+        rollback(&journal, &mut |_| {}).expect("rollback");
+        assert_eq!(
+            fs::read(series.join("Series v1.cbz")).expect("restored v1"),
+            b"one"
+        );
+        assert_eq!(
+            fs::read(series.join("Series v2.cbz")).expect("restored v2"),
+            b"two"
+        );
+        assert_eq!(
+            fs::read(batch_dir.join("Series v001.cbz")).expect("kept v001"),
+            b"one"
+        );
+        assert!(!batch_dir.join("Series v002 (2).cbz").exists());
 
-        log(String::new());
-        log("-".repeat(98));
-        log(format!(
-            "[DO] Batch {}: {}",
-            batch.batch_index,
-            file_name_text(&batch.batch_dir)
-        ));
-        log("-".repeat(98));
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
 
-        for (i, mv) in batch.moves.iter().enumerate() {
-            log(format!(
-                "[MOVE] ({}/{}) {} -> {}",
-                i + 1,
-                batch.moves.len(),
-                file_name_text(&mv.src),
-                mv.dst_name
-            ));
-            move_file(&mv.src, &mv.dst)?;
-        }
+    #[test]
+    fn rolling_back_a_rerun_restores_the_earlier_manifest() {
+        let dir = scratch_dir("checksums_rollback");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::write(series.join("Series v1.cbz"), b"one").expect("write v1");
+        let run = |journal: &Path| {
+            let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+            let execute_options = ExecuteOptions {
+                journal: Some(journal.to_path_buf()),
+                checksums: true,
+                ..ExecuteOptions::default()
+            };
+            execute(
+                &plan,
+                None,
+                &execute_options,
+                &AtomicBool::new(false),
+                &mut |_| {},
+            )
+            .expect("execute plan");
+        };
 
-        if let Some(cover) = series_cover {
-            log(format!(
-                "[COVER] Rendering cover.jpg (batch number {})",
-                batch.batch_index
-            ));
-            write_numbered_cover(&batch.batch_dir, batch.batch_index, cover)?;
-        }
+        run(&dir.join("first.json"));
+        let manifest_path = dir.join("Series 1").join(CHECKSUMS_FILE_NAME);
+        let first = fs::read_to_string(&manifest_path).expect("read manifest");
+        fs::write(series.join("Series v2.cbz"), b"two").expect("write v2");
+        run(&dir.join("second.json"));
+        let second = fs::read_to_string(&manifest_path).expect("reread manifest");
+        assert!(second.contains("  Series v002.cbz\n"), "{second}");
+
+        rollback(&dir.join("second.json"), &mut |_| {}).expect("rollback");
+        assert_eq!(
+            fs::read_to_string(&manifest_path).expect("restored manifest"),
+            first
+        );
+        assert!(series.join("Series v2.cbz").is_file());
+
+        let _ = fs::remove_dir_all(dir);
     }
 
-    log("[COMPLETE] Done.".to_string());
-    Ok(())
-}
+    #[test]
+    fn rolling_back_a_rerun_restores_the_earlier_metadata() {
+        let dir = scratch_dir("metadata_rollback");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::write(series.join("Series v1.cbz"), b"one").expect("write v1");
+        let run = |journal: &Path| {
+            let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+            let execute_options = ExecuteOptions {
+                journal: Some(journal.to_path_buf()),
+                metadata: Some(BatchMetadata {
+                    series_title: "Series".to_string(),
+                    cover_source: None,
+                }),
+                ..ExecuteOptions::default()
+            };
+            execute(
+                &plan,
+                None,
+                &execute_options,
+                &AtomicBool::new(false),
+                &mut |_| {},
+            )
+            .expect("execute plan");
+        };
 
-pub fn run_action(
-    action: UiAction,
-    series_dir: &Path,
-    log: &mut dyn FnMut(String),
-) -> Result<ActionOutput> {
-    if !series_dir.is_dir() {
-        bail!("Not a directory: {}", series_dir.display());
+        run(&dir.join("first.json"));
+        let metadata_path = dir.join("Series 1").join(METADATA_FILE_NAME);
+        let first = fs::read_to_string(&metadata_path).expect("read metadata");
+        fs::write(series.join("Series v2.5.cbz"), b"two").expect("write v2.5");
+        run(&dir.join("second.json"));
+        let second: Value =
+            serde_json::from_str(&fs::read_to_string(&metadata_path).expect("reread metadata"))
+                .expect("parse metadata");
+        assert_eq!(second["start_volume"], 1);
+        assert_eq!(second["end_volume"], 2.5);
+
+        rollback(&dir.join("second.json"), &mut |_| {}).expect("rollback");
+        assert_eq!(
+            fs::read_to_string(&metadata_path).expect("restored metadata"),
+            first
+        );
+
+        let _ = fs::remove_dir_all(dir);
     }
 
-    match action {
-        UiAction::ShowCover => {
-            let series_cover = ensure_series_cover(series_dir, &file_name_text(series_dir), log)?;
-            let Some(series_cover) = series_cover else {
-                bail!("[COVER-CHECK] No cover found from local files or remote providers.");
+    #[test]
+    fn journals_of_earlier_runs_are_archived_and_rolled_back_in_turn() {
+        let dir = scratch_dir("journal_archive");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        fs::write(series.join("Series v1.cbz"), b"one").expect("write v1");
+        let journal = journal_path(&series);
+        let run = || {
+            let plan = build_plan(&series, None, &PlanOptions::default()).expect("build plan");
+            let execute_options = ExecuteOptions {
+                journal: Some(journal.clone()),
+                ..ExecuteOptions::default()
             };
+            execute(
+                &plan,
+                None,
+                &execute_options,
+                &AtomicBool::new(false),
+                &mut |_| {},
+            )
+            .expect("execute plan");
+        };
 
-            let cover_jpg = ensure_cover_jpg(series_dir, &series_cover)?;
-            log(format!("{}", cover_jpg.display()));
-            Ok(ActionOutput {
-                action,
-                cover_path: Some(cover_jpg),
-            })
-        }
-        UiAction::Preview => {
-            let series_cover = ensure_series_cover(series_dir, &file_name_text(series_dir), log)?;
-            let plan = build_plan(series_dir, series_cover.as_deref())?;
-            let plan_text = format_plan(series_dir, &plan, series_cover.as_deref());
-            for line in plan_text.lines() {
-                log(line.to_string());
-            }
-            log("[DRY-RUN] Plan printed only. No changes were made.".to_string());
-            Ok(ActionOutput {
-                action,
-                cover_path: None,
+        run();
+        fs::write(series.join("Series v2.cbz"), b"two").expect("write v2");
+        run();
+        assert_eq!(
+            journal_archives(&journal),
+            vec![journal_archive_path(&journal, 1)]
+        );
+
+        rollback(&journal, &mut |_| {}).expect("roll back the second run");
+        assert!(series.join("Series v2.cbz").is_file());
+        assert!(!series.join("Series v1.cbz").exists());
+        assert!(journal.is_file(), "the first run's journal is back");
+        assert!(journal_archives(&journal).is_empty());
+
+        rollback(&journal, &mut |_| {}).expect("roll back the first run");
+        assert!(series.join("Series v1.cbz").is_file());
+        assert!(!dir.join("Series 1").exists());
+        assert!(!journal.exists());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn a_missing_middle_journal_archive_keeps_the_rest_in_order() {
+        let dir = scratch_dir("journal_archive_gap");
+        let journal = dir.join(JOURNAL_FILE_NAME);
+        let write_run = |name: &str| {
+            let mut run = Journal::create(&journal).expect("create journal");
+            run.record(JournalEntry::WriteFile {
+                path: dir.join(name),
             })
+            .expect("record");
+            fs::write(dir.join(name), name).expect("write file");
+        };
+
+        for name in ["first.txt", "second.txt", "third.txt"] {
+            write_run(name);
         }
-        UiAction::Process => {
-            let series_cover = ensure_series_cover(series_dir, &file_name_text(series_dir), log)?;
-            let plan = build_plan(series_dir, series_cover.as_deref())?;
-            let plan_text = format_plan(series_dir, &plan, series_cover.as_deref());
-            for line in plan_text.lines() {
-                log(line.to_string());
-            }
-            execute(&plan, series_cover.as_deref(), log)?;
-            Ok(ActionOutput {
-                action,
-                cover_path: None,
-            })
+        fs::remove_file(journal_archive_path(&journal, 1)).expect("delete the oldest archive");
+        fs::remove_file(dir.join("first.txt")).expect("delete its file");
+        write_run("fourth.txt");
+        // Archive 1 is gone; the third run's journal goes after archive 2, not into the gap.
+        assert_eq!(
+            journal_archives(&journal),
+            vec![
+                journal_archive_path(&journal, 2),
+                journal_archive_path(&journal, 3)
+            ]
+        );
+
+        for name in ["fourth.txt", "third.txt", "second.txt"] {
+            rollback(&journal, &mut |_| {}).expect("roll back");
+            assert!(!dir.join(name).exists(), "{name} is rolled back");
         }
+        assert!(!journal.exists());
+        assert!(journal_archives(&journal).is_empty());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
     }
-}
 
-pub fn prompt_confirm(prompt: &str) -> Result<bool> {
-    print!("{prompt}");
-    io::stdout().flush().context("failed to flush stdout")?;
+    #[test]
+    fn rollback_undoes_what_it_can_and_keeps_the_failures() {
+        let dir = scratch_dir("rollback_failures");
+        let written = dir.join("notes.txt");
+        let blocked = dir.join("not a file");
+        fs::write(&written, b"notes").expect("write notes");
+        fs::create_dir_all(blocked.join("inside")).expect("create folder");
+        let journal = dir.join("journal.json");
+        let mut recorder = Journal::create(&journal).expect("create journal");
+        for path in [&written, &blocked] {
+            recorder
+                .record(JournalEntry::WriteFile { path: path.clone() })
+                .expect("record");
+        }
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .context("failed to read user input")?;
+        let err = rollback(&journal, &mut |_| {}).expect_err("one entry can't be undone");
+        assert!(
+            err.to_string().contains("could not undo 1 changes"),
+            "{err}"
+        );
+        assert!(!written.exists(), "older entries are still undone");
+        assert_eq!(
+            Journal::load(&journal).expect("remaining journal"),
+            vec![JournalEntry::WriteFile { path: blocked }]
+        );
 
-    let answer = input.trim().to_ascii_lowercase();
-    Ok(answer == "y" || answer == "yes")
-}
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use image::{DynamicImage, Rgb, RgbImage};
+    #[test]
+    fn zip_image_entries_are_filtered_and_naturally_sorted() {
+        let dir = scratch_dir("zip_entries");
+        let archive = dir.join("Series v01.cbz");
+        let png = png_bytes(4, 6);
+        write_zip(
+            &archive,
+            &[
+                ("__MACOSX/._p1.png", b"junk".as_slice()),
+                ("p10.png", &png),
+                ("notes.txt", b"text".as_slice()),
+                ("p2.png", &png),
+                (".hidden.png", &png),
+            ],
+        );
 
-    fn bbox_for_mask(mask: impl Iterator<Item = (u32, u32)>) -> Option<(u32, u32, u32, u32)> {
-        let mut found = false;
-        let mut min_x = u32::MAX;
-        let mut min_y = u32::MAX;
-        let mut max_x = 0;
-        let mut max_y = 0;
+        let entries = archive_image_entries(&archive).expect("list entries");
+        assert_eq!(entries, vec!["p2.png".to_string(), "p10.png".to_string()]);
+        let bytes = read_archive_entry(&archive, "p2.png").expect("read entry");
+        assert_eq!(bytes, png);
 
-        for (x, y) in mask {
-            found = true;
-            if x < min_x {
-                min_x = x;
-            }
-            if y < min_y {
-                min_y = y;
-            }
-            if x > max_x {
-                max_x = x;
-            }
-            if y > max_y {
-                max_y = y;
-            }
-        }
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-        if !found {
-            return None;
-        }
-        Some((min_x, min_y, max_x, max_y))
+    #[test]
+    fn cbt_first_volume_cover_is_extracted() {
+        let dir = scratch_dir("cbt_cover");
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        let volume = series.join("Series v01.cbt");
+        let mut builder = tar::Builder::new(fs::File::create(&volume).expect("create cbt"));
+        let mut append = |name: &str, bytes: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, bytes)
+                .expect("append entry");
+        };
+        append("__MACOSX/._page1.png", b"junk");
+        append("page10.png", &png_bytes(4, 6));
+        append("page2.png", &png_bytes(8, 12));
+        builder.finish().expect("finish cbt");
+        drop(builder);
+
+        assert_eq!(scan_volumes(&series).expect("scan"), vec![volume.clone()]);
+        check_archive(&volume).expect("cbt passes the archive check");
+        let (cover, err) = find_first_volume_cover(&series);
+        assert!(err.is_none(), "unexpected error: {err:?}");
+        let cover = cover.expect("cover from cbt");
+        assert_eq!(cover.image_entry, "page2.png");
+
+        let out = write_volume_cover(&cover).expect("write cover");
+        let decoded = ImageReader::open(&out)
+            .expect("open cover")
+            .decode()
+            .expect("decode cover");
+        assert_eq!((decoded.width(), decoded.height()), (8, 12));
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
-    fn center_of_bbox(b: (u32, u32, u32, u32)) -> (f32, f32) {
-        let (x0, y0, x1, y1) = b;
-        ((x0 + x1) as f32 / 2.0, (y0 + y1) as f32 / 2.0)
+    #[test]
+    fn cb7_first_volume_cover_is_extracted() {
+        let dir = scratch_dir("cb7_cover");
+        let pages = dir.join("pages");
+        fs::create_dir_all(&pages).expect("create pages dir");
+        fs::write(pages.join("page10.png"), png_bytes(4, 6)).expect("write page");
+        fs::write(pages.join("page2.png"), png_bytes(8, 12)).expect("write page");
+
+        let series = dir.join("Series");
+        fs::create_dir_all(&series).expect("create series dir");
+        sevenz_rust::compress_to_path(&pages, series.join("Series v01.cb7")).expect("write cb7");
+
+        let (cover, err) = find_first_volume_cover(&series);
+        assert!(err.is_none(), "unexpected error: {err:?}");
+        let cover = cover.expect("cover from cb7");
+        assert!(cover.image_entry.ends_with("page2.png"));
+
+        let out = write_volume_cover(&cover).expect("write cover");
+        let decoded = ImageReader::open(&out)
+            .expect("open cover")
+            .decode()
+            .expect("decode cover");
+        assert_eq!((decoded.width(), decoded.height()), (8, 12));
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
     #[test]
@@ -1412,7 +11596,8 @@ mod tests {
         let w = 1000;
         let h = 1500;
         let base = DynamicImage::ImageRgb8(RgbImage::from_pixel(w, h, Rgb([255, 255, 255])));
-        let rendered = draw_dead_center_text(&base, "12", 255, 0.90).expect("rendered text");
+        let (_, rendered) =
+            render_numbered_cover("white_canvas", &base, 12, &CoverStyle::default());
         let rgb = rendered.to_rgb8();
 
         let bbox = bbox_for_mask(rgb.enumerate_pixels().filter_map(|(x, y, p)| {
@@ -1447,7 +11632,8 @@ mod tests {
             .expect("open example cover")
             .decode()
             .expect("decode example cover");
-        let rendered = draw_dead_center_text(&base, "2", 255, 0.90).expect("rendered text");
+        let (base, rendered) =
+            render_numbered_cover("example_cover", &base, 2, &CoverStyle::default());
 
         let src = base.to_rgb8();
         let out = rendered.to_rgb8();
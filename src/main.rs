@@ -1,25 +1,35 @@
 use std::{
+    fs,
     path::{Path, PathBuf},
-    sync::mpsc::{self, Receiver},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
     thread,
     time::Duration,
 };
 
 use clap::Parser;
 use iced::{
-    executor,
+    event, executor,
+    keyboard::{self, key::Named, Key, Modifiers},
     theme::{self, Theme},
     time,
     widget::{
-        button, column, container, horizontal_rule, horizontal_space, image, progress_bar, row,
-        scrollable, text,
+        button, checkbox, column, container, horizontal_rule, horizontal_space, image, pick_list,
+        progress_bar, row, scrollable, text, text_input,
     },
-    Alignment, Application, Background, Border, Color, Command, Element, Font, Length, Settings,
-    Shadow, Size, Subscription, Vector,
+    window, Alignment, Application, Background, Border, Color, Command, Element, Font, Length,
+    Settings, Shadow, Size, Subscription, Vector,
 };
 use manga_cleaner::{
-    build_plan, ensure_cover_jpg, ensure_series_cover, execute, resolve_series_dir, BatchPlan,
-    FILES_PER_FOLDER,
+    analyze, apply_cover_decision, download_cover_thumbnail, ensure_cover_jpg, execute,
+    format_plan, is_offline, library_series_dirs, mangadex_cover_candidates, manual_cover_decision,
+    plan_unbatch, preview_series_cover, rename_warning, resolve_series_dir, unbatch, with_cancel,
+    BatchPlan, Config, CoverDecision, CoverOptions, CoverResult, CoverTextColor, Error,
+    ExecuteOptions, ExecuteSummary, FileMove, LogEvent, LogLevel, PlanOptions, RunReport, Session,
+    DEFAULT_RENAME_WARN_RATIO, FILES_PER_FOLDER,
 };
 use rfd::FileDialog;
 
@@ -72,22 +82,73 @@ enum ActivityTone {
 struct ActivityItem {
     tone: ActivityTone,
     message: String,
+    /// The tagged line the CLI would print for a log event, kept for exported logs.
+    raw: Option<String>,
+}
+
+impl ActivityItem {
+    /// The item as it appears in an exported log: a tone prefix, then the raw line when it
+    /// says more than the message shown.
+    fn export_line(&self) -> String {
+        let prefix = match self.tone {
+            ActivityTone::Info => "[INFO]",
+            ActivityTone::Success => "[OK]",
+            ActivityTone::Warning => "[WARN]",
+            ActivityTone::Error => "[ERROR]",
+        };
+        match &self.raw {
+            Some(raw) if raw.trim() != self.message => {
+                format!("{prefix} {}\n    {}", self.message, raw.trim())
+            }
+            _ => format!("{prefix} {}", self.message),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 struct AnalysisSnapshot {
     resolved_dir: PathBuf,
+    /// Nothing is written until processing starts; this is where the cover will come from.
+    cover: CoverDecision,
     cover_path: Option<PathBuf>,
+    cover_preview: Option<iced::widget::image::Handle>,
+    /// Other remote covers the user can switch to before processing.
+    cover_choices: Vec<CoverChoice>,
+    /// The entry of `cover_choices` that replaced the resolved cover, if any.
+    selected_choice: Option<usize>,
     plan: Vec<BatchPlan>,
+    /// What the plan was built with, so its text matches the CLI's `--dry-run`.
+    plan_options: Box<PlanOptions>,
     volume_count: usize,
     rename_count: usize,
 }
 
+#[derive(Debug, Clone)]
+struct CoverChoice {
+    title: String,
+    result: CoverResult,
+    thumbnail: Option<iced::widget::image::Handle>,
+}
+
+const COVER_CHOICES: usize = 5;
+
 impl AnalysisSnapshot {
     fn batch_count(&self) -> usize {
         self.plan.len()
     }
 
+    /// The plan exactly as `process_manga_rs --dry-run` prints it.
+    fn plan_text(&self) -> String {
+        format_plan(
+            &self.resolved_dir,
+            &self.plan,
+            self.cover_path.as_deref(),
+            &self.plan_options,
+        )
+        .trim_matches('\n')
+        .to_string()
+    }
+
     fn cover_batch_count(&self) -> usize {
         self.plan
             .iter()
@@ -96,16 +157,264 @@ impl AnalysisSnapshot {
     }
 }
 
+/// The series found in a library folder, checked and processed one at a time.
+#[derive(Debug, Clone)]
+struct SeriesQueue {
+    /// The library folder's name, or a description of where the series came from.
+    name: String,
+    series: Vec<QueuedSeries>,
+    /// The series whose cover and plan the workspace shows.
+    viewing: Option<usize>,
+    /// Series in the current processing run, and how many of them have finished.
+    run_total: usize,
+    run_done: usize,
+}
+
+impl SeriesQueue {
+    /// Ticked series whose checks passed and that haven't been processed yet.
+    fn ready(&self) -> impl Iterator<Item = (usize, &QueuedSeries)> {
+        self.series
+            .iter()
+            .enumerate()
+            .filter(|(_, series)| series.selected && matches!(series.status, QueueStatus::Ready))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct QueuedSeries {
+    dir: PathBuf,
+    /// Unticked series are left out when the queue is processed.
+    selected: bool,
+    analysis: Option<AnalysisSnapshot>,
+    status: QueueStatus,
+}
+
+#[derive(Debug, Clone)]
+enum QueueStatus {
+    Waiting,
+    Analyzing,
+    Ready,
+    /// The checks failed, so the series can't be processed.
+    Unusable(String),
+    Processing,
+    Processed(RunReport),
+    Failed(String),
+}
+
+impl QueueStatus {
+    fn label(&self) -> (&'static str, StageState) {
+        match self {
+            QueueStatus::Waiting => ("Waiting", StageState::Pending),
+            QueueStatus::Analyzing => ("Checking", StageState::Running),
+            QueueStatus::Ready => ("Ready", StageState::Complete),
+            QueueStatus::Unusable(_) => ("Can't process", StageState::Error),
+            QueueStatus::Processing => ("Processing", StageState::Running),
+            QueueStatus::Processed(_) => ("Done", StageState::Complete),
+            QueueStatus::Failed(_) => ("Failed", StageState::Error),
+        }
+    }
+}
+
+/// Everything a worker thread needs to analyze one series folder.
+#[derive(Debug, Clone)]
+struct AnalysisJob {
+    config: Config,
+    batch_size: usize,
+    title_override: Option<String>,
+    /// Look up other MangaDex covers to switch to when the cover comes from a remote search.
+    offer_choices: bool,
+}
+
+impl AnalysisJob {
+    fn run(
+        self,
+        raw_path: &str,
+        cancel: Arc<AtomicBool>,
+        send: &dyn Fn(WorkerEvent),
+    ) -> Result<AnalysisSnapshot, String> {
+        let AnalysisJob {
+            config,
+            batch_size,
+            title_override,
+            offer_choices,
+        } = self;
+        let resolved = resolve_series_dir(raw_path).map_err(|err| failure_message(&err))?;
+
+        send(WorkerEvent::Activity(format!(
+            "Analyzing source folder: {}",
+            resolved.display()
+        )));
+
+        let mut log = |event: LogEvent| send(WorkerEvent::Log(event));
+
+        // Cancelled when the checks are restarted.
+        with_cancel(cancel, || -> Result<AnalysisSnapshot, String> {
+            let mut options = config.analyze_options().map_err(|err| format!("{err:#}"))?;
+            options.plan.batch_size = batch_size;
+            options.cover.title_override = title_override.clone();
+            let remote_options = config.cover_search_options();
+            let used_plan_options = Box::new(options.plan.clone());
+            let search_title = title_override.unwrap_or_else(|| leaf_name(&resolved));
+            let analysis =
+                analyze(&resolved, &options, &mut log).map_err(|err| failure_message(&err))?;
+
+            // Remote covers are fetched into a temp file for display; the series folder is
+            // only written once processing starts.
+            let cover_preview = match analysis
+                .cover
+                .preview_bytes()
+                .map_err(|err| err.to_string())?
+            {
+                Some(bytes) => Some(iced::widget::image::Handle::from_memory(bytes)),
+                None => preview_series_cover(&analysis.cover, &mut log)
+                    .map_err(|err| failure_message(&err))?
+                    .map(iced::widget::image::Handle::from_path),
+            };
+
+            // Only offer alternatives when the cover would come from a remote search anyway.
+            let searched = matches!(
+                analysis.cover,
+                CoverDecision::Download { .. } | CoverDecision::None
+            );
+            let cover_choices = if offer_choices && searched && !is_offline() {
+                cover_choices(&resolved, &search_title, &remote_options, &mut log)
+            } else {
+                Vec::new()
+            };
+
+            Ok(AnalysisSnapshot {
+                cover_path: analysis.cover.planned_path().map(Path::to_path_buf),
+                cover: analysis.cover,
+                cover_preview,
+                cover_choices,
+                selected_choice: None,
+                resolved_dir: resolved,
+                plan: analysis.plan,
+                plan_options: used_plan_options,
+                volume_count: analysis.volume_count,
+                rename_count: analysis.rename_count,
+            })
+        })
+    }
+}
+
+/// Everything a worker thread needs to apply one series' approved plan.
+#[derive(Debug)]
+struct ProcessJob {
+    plan: Vec<BatchPlan>,
+    cover: CoverDecision,
+    series_dir: PathBuf,
+    keep_cover_format: bool,
+    execute_options: ExecuteOptions,
+}
+
+impl ProcessJob {
+    fn run(
+        self,
+        cancel: &Arc<AtomicBool>,
+        send: &dyn Fn(WorkerEvent),
+    ) -> Result<ExecuteSummary, String> {
+        let mut log = |event: LogEvent| {
+            if let LogEvent::Move {
+                index,
+                total,
+                batch,
+                to,
+                ..
+            } = &event
+            {
+                send(WorkerEvent::ProcessProgress {
+                    completed_moves: *index,
+                    total_moves: *total,
+                    label: format!("Batch {batch}: {} ({index}/{total})", leaf_name(to)),
+                });
+            }
+            send(WorkerEvent::Log(event));
+        };
+
+        (|| -> Result<ExecuteSummary, Error> {
+            // Cancel also stops a cover download in progress.
+            let applied = with_cancel(Arc::clone(cancel), || {
+                apply_cover_decision(&self.cover, &mut log)
+            });
+            let series_cover = match applied? {
+                Some(selected) if self.keep_cover_format => Some(selected),
+                Some(selected) => Some(ensure_cover_jpg(&self.series_dir, &selected)?),
+                None => None,
+            };
+            execute(
+                &self.plan,
+                series_cover.as_deref(),
+                &self.execute_options,
+                cancel,
+                &mut log,
+            )
+        })()
+        .map_err(|err| failure_message(&err))
+    }
+}
+
 #[derive(Debug)]
 enum WorkerEvent {
     Activity(String),
+    Log(LogEvent),
     AnalysisComplete(Result<AnalysisSnapshot, String>),
     ProcessProgress {
-        completed_batches: usize,
-        total_batches: usize,
+        completed_moves: usize,
+        total_moves: usize,
         label: String,
     },
-    ProcessComplete(Result<(), String>),
+    ProcessComplete(Result<ExecuteSummary, String>),
+    UnbatchComplete(Result<usize, String>),
+    /// An event of the `index`-th series of the queue.
+    Queued {
+        index: usize,
+        event: Box<WorkerEvent>,
+    },
+    /// The queue worker has been through every series (or was cancelled).
+    QueueComplete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextColorChoice {
+    Black,
+    White,
+    Auto,
+}
+
+impl TextColorChoice {
+    const ALL: [TextColorChoice; 3] = [
+        TextColorChoice::Black,
+        TextColorChoice::White,
+        TextColorChoice::Auto,
+    ];
+
+    fn cover_text_color(self) -> CoverTextColor {
+        match self {
+            TextColorChoice::Black => CoverTextColor::Rgb([0, 0, 0]),
+            TextColorChoice::White => CoverTextColor::Rgb([255, 255, 255]),
+            TextColorChoice::Auto => CoverTextColor::Auto,
+        }
+    }
+
+    fn from_cover_text_color(color: CoverTextColor) -> Option<Self> {
+        match color {
+            CoverTextColor::Rgb([0, 0, 0]) => Some(TextColorChoice::Black),
+            CoverTextColor::Rgb([255, 255, 255]) => Some(TextColorChoice::White),
+            CoverTextColor::Auto => Some(TextColorChoice::Auto),
+            CoverTextColor::Rgb(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TextColorChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TextColorChoice::Black => "Black",
+            TextColorChoice::White => "White",
+            TextColorChoice::Auto => "Auto (contrast)",
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -216,15 +525,37 @@ impl iced::widget::button::StyleSheet for NativeButton {
 #[derive(Debug, Clone)]
 enum Message {
     BrowseFolder,
+    BrowseLibrary,
+    QueueToggled(usize, bool),
+    ViewQueued(usize),
     RefreshAnalysis,
     RequestProcess,
     CancelProcessConfirmation,
     ConfirmProcess,
+    CancelProcess,
+    BatchSizeChanged(String),
+    BatchSizeSubmitted,
+    TitleOverrideChanged(String),
+    TitleOverrideSubmitted,
+    TextColorSelected(TextColorChoice),
+    SelectCover(usize),
+    ChooseCover,
+    RequestUnbatch,
+    CancelUnbatch,
+    ConfirmUnbatch,
+    FileDropped(PathBuf),
+    ForgetSession,
+    ExportLog,
+    TogglePlanText,
     Tick,
 }
 
 struct MangaCleanerApp {
     series_dir_input: String,
+    batch_size_input: String,
+    title_override_input: String,
+    batch_size: usize,
+    text_color: TextColorChoice,
     status_text: String,
     analysis_stage: StageState,
     plan_stage: StageState,
@@ -232,13 +563,43 @@ struct MangaCleanerApp {
     analysis_running: bool,
     processing_running: bool,
     show_confirm_sheet: bool,
+    /// Show the plan as the CLI's text instead of the file tree.
+    show_plan_text: bool,
+    /// Planned moves shown for confirmation before flattening the batch folders.
+    unbatch_preview: Option<(PathBuf, Vec<FileMove>)>,
     process_progress: f32,
     process_label: String,
+    /// What the last run changed, shown once processing finishes.
+    run_report: Option<RunReport>,
     analysis: Option<AnalysisSnapshot>,
     cover_path: Option<PathBuf>,
     cover_handle: Option<iced::widget::image::Handle>,
     activity: Vec<ActivityItem>,
     worker_rx: Option<Receiver<WorkerEvent>>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    /// Set while working through a library folder instead of a single series.
+    queue: Option<SeriesQueue>,
+    /// Paths dropped onto the window, opened together on the next tick.
+    dropped: Vec<PathBuf>,
+    /// Restored on the next launch; see `remember`.
+    session: Session,
+    config: Config,
+}
+
+/// Maps a key press to the message of the button it stands in for. `update` checks the same
+/// conditions the buttons do, so a shortcut for a disabled action does nothing.
+fn shortcut(key: Key, modifiers: Modifiers) -> Option<Message> {
+    match key.as_ref() {
+        Key::Named(Named::Escape) => Some(Message::CancelProcessConfirmation),
+        Key::Named(Named::Enter) if modifiers.command() => Some(Message::RequestProcess),
+        Key::Character(c) if modifiers.command() && c.eq_ignore_ascii_case("o") => {
+            Some(Message::BrowseFolder)
+        }
+        Key::Character(c) if modifiers.command() && c.eq_ignore_ascii_case("r") => {
+            Some(Message::RefreshAnalysis)
+        }
+        _ => None,
+    }
 }
 
 impl MangaCleanerApp {
@@ -246,11 +607,38 @@ impl MangaCleanerApp {
         self.analysis_running || self.processing_running
     }
 
+    /// Checks may be restarted while they run; the old analysis is cancelled.
     fn can_refresh(&self) -> bool {
-        !self.is_busy() && !self.series_dir_input.trim().is_empty()
+        !self.processing_running && !self.series_dir_input.trim().is_empty()
+    }
+
+    /// Why the plans about to be processed deserve a second look at the cleaning rules.
+    fn rename_warnings(&self) -> Vec<String> {
+        let ratio = self
+            .config
+            .rename_warn_ratio
+            .unwrap_or(DEFAULT_RENAME_WARN_RATIO);
+        if let Some(queue) = &self.queue {
+            return queue
+                .ready()
+                .filter_map(|(_, series)| {
+                    let snapshot = series.analysis.as_ref()?;
+                    let warning = rename_warning(&snapshot.plan, ratio)?;
+                    Some(format!("{}: {warning}", leaf_name(&series.dir)))
+                })
+                .collect();
+        }
+        self.analysis
+            .as_ref()
+            .and_then(|snapshot| rename_warning(&snapshot.plan, ratio))
+            .into_iter()
+            .collect()
     }
 
     fn can_process(&self) -> bool {
+        if let Some(queue) = &self.queue {
+            return !self.is_busy() && queue.ready().next().is_some();
+        }
         !self.is_busy()
             && self.analysis.is_some()
             && self.analysis_stage == StageState::Complete
@@ -259,7 +647,11 @@ impl MangaCleanerApp {
     }
 
     fn append_activity(&mut self, tone: ActivityTone, message: impl AsRef<str>) {
-        let text = message.as_ref().trim();
+        self.push_activity(tone, message.as_ref(), None);
+    }
+
+    fn push_activity(&mut self, tone: ActivityTone, message: &str, raw: Option<String>) {
+        let text = message.trim();
         if text.is_empty() {
             return;
         }
@@ -267,6 +659,7 @@ impl MangaCleanerApp {
         self.activity.push(ActivityItem {
             tone,
             message: text.to_string(),
+            raw,
         });
 
         let max_items = 180;
@@ -277,28 +670,238 @@ impl MangaCleanerApp {
     }
 
     fn reset_for_new_analysis(&mut self) {
+        if self.analysis_running {
+            // Its lookups stop at the next request; its events go to a dropped channel.
+            if let Some(cancel) = self.cancel_flag.take() {
+                cancel.store(true, Ordering::Relaxed);
+            }
+            self.worker_rx = None;
+            self.analysis_running = false;
+        }
         self.analysis = None;
-        self.set_cover_path(None);
+        self.set_cover_preview(None, None);
         self.process_progress = 0.0;
         self.process_label = "Waiting for analysis".to_string();
+        self.run_report = None;
         self.show_confirm_sheet = false;
+        self.unbatch_preview = None;
         self.process_stage = StageState::Pending;
         self.analysis_stage = StageState::Pending;
         self.plan_stage = StageState::Pending;
+        if let Some(queue) = &mut self.queue {
+            for series in &mut queue.series {
+                // A series left out because its checks failed gets another chance.
+                if matches!(series.status, QueueStatus::Unusable(_)) {
+                    series.selected = true;
+                }
+                series.analysis = None;
+                series.status = QueueStatus::Waiting;
+            }
+            queue.viewing = None;
+        }
     }
 
-    fn set_cover_path(&mut self, path: Option<PathBuf>) {
-        self.cover_path = path.clone();
-        self.cover_handle = path.map(iced::widget::image::Handle::from_path);
+    fn set_cover_preview(
+        &mut self,
+        path: Option<PathBuf>,
+        handle: Option<iced::widget::image::Handle>,
+    ) {
+        self.cover_path = path;
+        self.cover_handle = handle;
+    }
+
+    /// Replaces the analyzed cover with one the user chose; nothing is written until
+    /// processing starts.
+    fn use_cover(
+        &mut self,
+        decision: CoverDecision,
+        preview: Option<iced::widget::image::Handle>,
+        selected_choice: Option<usize>,
+    ) {
+        let Some(snapshot) = self.analysis.as_mut() else {
+            return;
+        };
+        snapshot.cover_path = decision.planned_path().map(Path::to_path_buf);
+        snapshot.cover = decision;
+        snapshot.cover_preview = preview.clone();
+        snapshot.selected_choice = selected_choice;
+        // The plan may have been built without any cover.
+        for batch in &mut snapshot.plan {
+            batch.will_make_cover = true;
+        }
+        let cover_path = snapshot.cover_path.clone();
+        self.set_cover_preview(cover_path, preview);
+        if let Some(queue) = &mut self.queue {
+            if let Some(series) = queue.viewing.and_then(|index| queue.series.get_mut(index)) {
+                series.analysis = self.analysis.clone();
+            }
+        }
     }
 
     fn set_series_folder(&mut self, raw_path: impl AsRef<str>) {
         self.series_dir_input = raw_path.as_ref().to_string();
+        self.queue = None;
+        let dir = PathBuf::from(self.series_dir_input.trim());
+        self.remember(|session| {
+            session.series_dir = Some(dir);
+            session.library = false;
+        });
+        self.activity.clear();
+        self.reset_for_new_analysis();
+        self.start_analysis();
+    }
+
+    /// Queues every subfolder of `parent` as a series and checks them, leaving out hidden ones
+    /// and the batch folders of an earlier run, the same way `--bulk` does.
+    fn set_library_folder(&mut self, parent: PathBuf) {
+        let dirs = match library_series_dirs(&parent, &self.template_options()) {
+            Ok(dirs) => dirs,
+            Err(err) => {
+                self.status_text = format!("Could not read {}: {err:#}", parent.display());
+                return;
+            }
+        };
+        if dirs.is_empty() {
+            self.status_text = format!("No series folders in {}.", leaf_name(&parent));
+            self.append_activity(
+                ActivityTone::Warning,
+                format!(
+                    "{} has no subfolders to process as series.",
+                    parent.display()
+                ),
+            );
+            return;
+        }
+        self.series_dir_input = parent.display().to_string();
+        self.set_queue(leaf_name(&parent), dirs);
+        self.remember(|session| {
+            session.series_dir = Some(parent);
+            session.library = true;
+        });
+    }
+
+    /// Updates the session and writes it out so the next launch starts where this one left off.
+    fn remember(&mut self, update: impl FnOnce(&mut Session)) {
+        update(&mut self.session);
+        let Some(path) = Session::default_path() else {
+            return;
+        };
+        if let Err(err) = self.session.save(&path) {
+            self.append_activity(
+                ActivityTone::Warning,
+                format!("Could not save the session: {err:#}"),
+            );
+        }
+    }
+
+    fn set_queue(&mut self, name: String, dirs: Vec<PathBuf>) {
         self.activity.clear();
         self.reset_for_new_analysis();
+        self.queue = Some(SeriesQueue {
+            name,
+            series: dirs
+                .into_iter()
+                .map(|dir| QueuedSeries {
+                    dir,
+                    selected: true,
+                    analysis: None,
+                    status: QueueStatus::Waiting,
+                })
+                .collect(),
+            viewing: None,
+            run_total: 0,
+            run_done: 0,
+        });
         self.start_analysis();
     }
 
+    /// Adds series to the queue and checks the new ones; folders already queued are skipped.
+    fn enqueue(&mut self, dirs: Vec<PathBuf>) {
+        let Some(queue) = self.queue.as_mut() else {
+            return;
+        };
+        let mut added = 0;
+        for dir in dirs {
+            if queue.series.iter().any(|series| series.dir == dir) {
+                continue;
+            }
+            queue.series.push(QueuedSeries {
+                dir,
+                selected: true,
+                analysis: None,
+                status: QueueStatus::Waiting,
+            });
+            added += 1;
+        }
+        if added > 0 {
+            self.append_activity(
+                ActivityTone::Info,
+                format!("Added {added} series to the queue."),
+            );
+            self.start_analysis();
+        }
+    }
+
+    /// Opens the folders dropped onto the window since the last tick. One folder becomes the
+    /// series folder and several become a queue; while a queue is open they join it.
+    fn open_dropped(&mut self) {
+        if self.dropped.is_empty() {
+            return;
+        }
+        let (dirs, others): (Vec<PathBuf>, Vec<PathBuf>) = std::mem::take(&mut self.dropped)
+            .into_iter()
+            .partition(|path| path.is_dir());
+
+        if !dirs.is_empty() && self.is_busy() {
+            self.append_activity(
+                ActivityTone::Warning,
+                "Wait for the current run to finish before dropping more folders.",
+            );
+        } else if self.queue.is_some() {
+            self.enqueue(dirs);
+        } else if let [dir] = dirs.as_slice() {
+            self.set_series_folder(dir.display().to_string());
+        } else if !dirs.is_empty() {
+            // Shown in the path well; a queue never analyzes the input itself.
+            self.series_dir_input = format!("{} dropped folders", dirs.len());
+            self.set_queue("Dropped folders".to_string(), dirs);
+        }
+        // After the above, which may clear the activity log.
+        for path in others {
+            self.append_activity(
+                ActivityTone::Warning,
+                format!(
+                    "Ignored {}: drop a series folder (or several), not a file.",
+                    path.display()
+                ),
+            );
+        }
+    }
+
+    /// Shows the `index`-th queued series' cover, plan, and (once processed) run summary.
+    fn view_queued(&mut self, index: usize) {
+        let Some(queue) = self.queue.as_mut() else {
+            return;
+        };
+        let Some(series) = queue.series.get(index) else {
+            return;
+        };
+        queue.viewing = Some(index);
+        let snapshot = series.analysis.clone();
+        self.run_report = match series.status {
+            QueueStatus::Processed(report) => Some(report),
+            _ => None,
+        };
+        match &snapshot {
+            Some(snapshot) => {
+                let (path, preview) = (snapshot.cover_path.clone(), snapshot.cover_preview.clone());
+                self.set_cover_preview(path, preview);
+            }
+            None => self.set_cover_preview(None, None),
+        }
+        self.analysis = snapshot;
+    }
+
     fn start_analysis(&mut self) {
         if self.is_busy() {
             return;
@@ -322,75 +925,78 @@ impl MangaCleanerApp {
             "Running automatic checks and building a processing plan.",
         );
 
+        let mut job = self.analysis_job();
         let (tx, rx) = mpsc::channel();
         self.worker_rx = Some(rx);
-
-        thread::spawn(move || {
-            let resolved = match resolve_series_dir(&raw_path) {
-                Ok(path) => path,
-                Err(err) => {
-                    let _ = tx.send(WorkerEvent::AnalysisComplete(Err(err.to_string())));
-                    return;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel_flag = Some(Arc::clone(&cancel));
+
+        if let Some(queue) = &self.queue {
+            // The search title and cover alternatives are picked per series, so not here.
+            job.title_override = None;
+            job.offer_choices = false;
+            // Series added to a checked queue are the only ones waiting.
+            let pending: Vec<(usize, PathBuf)> = queue
+                .series
+                .iter()
+                .enumerate()
+                .filter(|(_, series)| matches!(series.status, QueueStatus::Waiting))
+                .map(|(index, series)| (index, series.dir.clone()))
+                .collect();
+            thread::spawn(move || {
+                for (index, dir) in pending {
+                    if cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let send = |event| {
+                        let _ = tx.send(WorkerEvent::Queued {
+                            index,
+                            event: Box::new(event),
+                        });
+                    };
+                    let result =
+                        job.clone()
+                            .run(&dir.to_string_lossy(), Arc::clone(&cancel), &send);
+                    send(WorkerEvent::AnalysisComplete(result));
                 }
-            };
-
-            let _ = tx.send(WorkerEvent::Activity(format!(
-                "Analyzing source folder: {}",
-                resolved.display()
-            )));
-
-            let series_title = leaf_name(&resolved);
-            let mut log = |line: String| {
-                let _ = tx.send(WorkerEvent::Activity(line));
-            };
-
-            let result = (|| -> Result<AnalysisSnapshot, String> {
-                let series_cover = ensure_series_cover(&resolved, &series_title, &mut log)
-                    .map_err(|err| err.to_string())?;
-
-                let cover_path = if let Some(ref selected_cover) = series_cover {
-                    Some(
-                        ensure_cover_jpg(&resolved, selected_cover)
-                            .map_err(|err| err.to_string())?,
-                    )
-                } else {
-                    None
-                };
-
-                let plan = build_plan(&resolved, series_cover.as_deref())
-                    .map_err(|err| err.to_string())?;
-                let volume_count: usize = plan.iter().map(|batch| batch.moves.len()).sum();
-                let rename_count = plan
-                    .iter()
-                    .flat_map(|batch| batch.moves.iter())
-                    .filter(|mv| leaf_name(&mv.src) != mv.dst_name)
-                    .count();
-
-                Ok(AnalysisSnapshot {
-                    resolved_dir: resolved,
-                    cover_path,
-                    plan,
-                    volume_count,
-                    rename_count,
-                })
-            })();
+                let _ = tx.send(WorkerEvent::QueueComplete);
+            });
+            return;
+        }
 
+        thread::spawn(move || {
+            let result = job.run(&raw_path, cancel, &|event| {
+                let _ = tx.send(event);
+            });
             let _ = tx.send(WorkerEvent::AnalysisComplete(result));
         });
     }
 
+    /// The options the checks run with, taken from the config file and the controls.
+    fn analysis_job(&self) -> AnalysisJob {
+        AnalysisJob {
+            config: self.config.clone(),
+            batch_size: self.batch_size,
+            title_override: Some(self.title_override_input.trim().to_string())
+                .filter(|title| !title.is_empty()),
+            offer_choices: true,
+        }
+    }
+
     fn start_process(&mut self) {
         if !self.can_process() {
             return;
         }
+        if self.queue.is_some() {
+            self.start_queue_process();
+            return;
+        }
 
         let Some(snapshot) = self.analysis.clone() else {
             return;
         };
 
-        let plan = snapshot.plan.clone();
-        let series_cover = snapshot.cover_path.clone();
-        let total_batches = plan.len().max(1);
+        let job = self.process_job(&snapshot);
 
         self.processing_running = true;
         self.analysis_running = false;
@@ -398,7 +1004,7 @@ impl MangaCleanerApp {
         self.process_stage = StageState::Running;
         self.status_text = "Applying file changes...".to_string();
         self.process_progress = 0.0;
-        self.process_label = format!("Starting {} batches", plan.len());
+        self.process_label = format!("Starting {} batches", job.plan.len());
         self.append_activity(
             ActivityTone::Info,
             "Confirmation received. Applying the approved batch plan.",
@@ -406,32 +1012,162 @@ impl MangaCleanerApp {
 
         let (tx, rx) = mpsc::channel();
         self.worker_rx = Some(rx);
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel_flag = Some(Arc::clone(&cancel));
 
         thread::spawn(move || {
-            let mut log = |line: String| {
-                if let Some((batch_index, batch_name)) = parse_batch_start(&line) {
-                    let completed = batch_index.saturating_sub(1);
-                    let _ = tx.send(WorkerEvent::ProcessProgress {
-                        completed_batches: completed,
-                        total_batches,
-                        label: format!("Processing {batch_name}"),
-                    });
-                }
+            let result = job.run(&cancel, &|event| {
+                let _ = tx.send(event);
+            });
+            let _ = tx.send(WorkerEvent::ProcessComplete(result));
+        });
+    }
 
-                if line.starts_with("[COMPLETE]") {
-                    let _ = tx.send(WorkerEvent::ProcessProgress {
-                        completed_batches: total_batches,
-                        total_batches,
-                        label: "Finalizing".to_string(),
+    /// Processes the ticked, ready series of the queue one after the other. A failure is
+    /// recorded on its series and the next one starts; cancelling stops the whole queue.
+    fn start_queue_process(&mut self) {
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        let jobs: Vec<(usize, ProcessJob)> = queue
+            .ready()
+            .filter_map(|(index, series)| {
+                let snapshot = series.analysis.as_ref()?;
+                Some((index, self.process_job(snapshot)))
+            })
+            .collect();
+        let Some(queue) = self.queue.as_mut() else {
+            return;
+        };
+        for (index, _) in &jobs {
+            queue.series[*index].status = QueueStatus::Waiting;
+        }
+        queue.run_total = jobs.len();
+        queue.run_done = 0;
+
+        self.processing_running = true;
+        self.analysis_running = false;
+        self.show_confirm_sheet = false;
+        self.run_report = None;
+        self.process_stage = StageState::Running;
+        self.status_text = "Processing the queue...".to_string();
+        self.process_progress = 0.0;
+        self.process_label = format!("Starting {} series", jobs.len());
+        self.append_activity(
+            ActivityTone::Info,
+            format!(
+                "Confirmation received. Processing {} series one at a time.",
+                jobs.len()
+            ),
+        );
+
+        let (tx, rx) = mpsc::channel();
+        self.worker_rx = Some(rx);
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel_flag = Some(Arc::clone(&cancel));
+
+        thread::spawn(move || {
+            for (index, job) in jobs {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                let send = |event| {
+                    let _ = tx.send(WorkerEvent::Queued {
+                        index,
+                        event: Box::new(event),
                     });
+                };
+                send(WorkerEvent::Activity(
+                    "Applying the approved plan.".to_string(),
+                ));
+                let result = job.run(&cancel, &send);
+                send(WorkerEvent::ProcessComplete(result));
+            }
+            let _ = tx.send(WorkerEvent::QueueComplete);
+        });
+    }
+
+    fn process_job(&self, snapshot: &AnalysisSnapshot) -> ProcessJob {
+        let keep_cover_format = self.config.keep_cover_format.unwrap_or(false);
+        let mut execute_options = self
+            .config
+            .execute_options(&snapshot.resolved_dir, &snapshot.cover);
+        execute_options.cover_style.text_color = self.text_color.cover_text_color();
+
+        ProcessJob {
+            plan: snapshot.plan.clone(),
+            cover: snapshot.cover.clone(),
+            series_dir: snapshot.resolved_dir.clone(),
+            keep_cover_format,
+            execute_options,
+        }
+    }
+
+    /// Plan options for the steps that only need to recognize batch folders.
+    fn template_options(&self) -> PlanOptions {
+        PlanOptions {
+            folder_template: self.config.folder_template.clone().unwrap_or_default(),
+            ..PlanOptions::default()
+        }
+    }
+
+    /// A dry run of the flattening: lists what would move back into the series folder and asks
+    /// for confirmation.
+    fn preview_unbatch(&mut self) {
+        let planned = resolve_series_dir(self.series_dir_input.trim()).and_then(|series_dir| {
+            plan_unbatch(&series_dir, &self.template_options()).map(|moves| (series_dir, moves))
+        });
+        match planned {
+            Ok((series_dir, moves)) if moves.is_empty() => {
+                self.status_text = format!(
+                    "No batch folders with volumes next to {}.",
+                    leaf_name(&series_dir)
+                );
+            }
+            Ok((series_dir, moves)) => {
+                for planned in &moves {
+                    let folder = planned.src.parent().map(leaf_name).unwrap_or_default();
+                    self.append_activity(
+                        ActivityTone::Info,
+                        format!(
+                            "Would move {folder}/{} -> {}",
+                            leaf_name(&planned.src),
+                            planned.dst_name
+                        ),
+                    );
                 }
+                self.show_confirm_sheet = false;
+                self.unbatch_preview = Some((series_dir, moves));
+            }
+            Err(err) => {
+                self.status_text = format!("Could not plan flattening: {err:#}");
+                self.append_activity(
+                    ActivityTone::Error,
+                    format!("Could not plan flattening: {err:#}"),
+                );
+            }
+        }
+    }
 
-                let _ = tx.send(WorkerEvent::Activity(line));
-            };
+    fn start_unbatch(&mut self) {
+        let Some((series_dir, _)) = self.unbatch_preview.take() else {
+            return;
+        };
+        let options = self.template_options();
 
-            let result =
-                execute(&plan, series_cover.as_deref(), &mut log).map_err(|err| err.to_string());
-            let _ = tx.send(WorkerEvent::ProcessComplete(result));
+        self.processing_running = true;
+        self.status_text = "Moving volumes back into the series folder...".to_string();
+        let (tx, rx) = mpsc::channel();
+        self.worker_rx = Some(rx);
+
+        thread::spawn(move || {
+            let mut log = |event: LogEvent| {
+                let _ = tx.send(WorkerEvent::Log(event));
+            };
+            let result = unbatch(&series_dir, &options, &mut log)
+                .map(|moved| moved.len())
+                .map_err(|err| failure_message(&err));
+            let _ = tx.send(WorkerEvent::UnbatchComplete(result));
         });
     }
 
@@ -445,17 +1181,22 @@ impl MangaCleanerApp {
         while let Ok(event) = rx.try_recv() {
             match event {
                 WorkerEvent::Activity(line) => {
-                    if let Some(message) = humanize_activity_line(&line) {
-                        self.append_activity(activity_tone(&line), message);
+                    self.append_activity(ActivityTone::Info, line);
+                }
+                WorkerEvent::Log(event) => {
+                    if let Some((tone, message)) = log_event_activity(&event) {
+                        self.push_activity(tone, &message, Some(raw_log_line(&event)));
                     }
                 }
                 WorkerEvent::AnalysisComplete(result) => {
                     finished = true;
                     self.analysis_running = false;
+                    self.cancel_flag = None;
 
                     match result {
                         Ok(snapshot) => {
                             let cover_path = snapshot.cover_path.clone();
+                            let cover_preview = snapshot.cover_preview.clone();
                             self.status_text =
                                 "Plan ready. Review and confirm processing.".to_string();
                             self.analysis_stage = StageState::Complete;
@@ -474,7 +1215,15 @@ impl MangaCleanerApp {
                                 ),
                             );
 
-                            if let Some(path) = cover_path.clone() {
+                            if let CoverDecision::Download { result, .. } = &snapshot.cover {
+                                self.append_activity(
+                                    ActivityTone::Success,
+                                    format!(
+                                        "Cover found on {}; it will be downloaded when processing starts.",
+                                        result.source
+                                    ),
+                                );
+                            } else if let Some(path) = cover_path.clone() {
                                 self.append_activity(
                                     ActivityTone::Success,
                                     format!("Cover preview ready from {}", path.display()),
@@ -487,7 +1236,10 @@ impl MangaCleanerApp {
                             }
 
                             self.analysis = Some(snapshot);
-                            self.set_cover_path(cover_path);
+                            self.set_cover_preview(cover_path, cover_preview);
+                            for warning in self.rename_warnings() {
+                                self.append_activity(ActivityTone::Warning, warning);
+                            }
                         }
                         Err(err) => {
                             self.status_text = format!("Could not build plan: {err}");
@@ -495,7 +1247,7 @@ impl MangaCleanerApp {
                             self.plan_stage = StageState::Error;
                             self.process_stage = StageState::Pending;
                             self.analysis = None;
-                            self.set_cover_path(None);
+                            self.set_cover_preview(None, None);
                             self.append_activity(
                                 ActivityTone::Error,
                                 format!("Automatic checks failed: {err}"),
@@ -504,14 +1256,14 @@ impl MangaCleanerApp {
                     }
                 }
                 WorkerEvent::ProcessProgress {
-                    completed_batches,
-                    total_batches,
+                    completed_moves,
+                    total_moves,
                     label,
                 } => {
-                    let pct = if total_batches == 0 {
+                    let pct = if total_moves == 0 {
                         0.0
                     } else {
-                        completed_batches as f32 / total_batches as f32
+                        completed_moves as f32 / total_moves as f32
                     };
                     self.process_progress = pct.clamp(0.0, 1.0);
                     self.process_label = label;
@@ -519,16 +1271,59 @@ impl MangaCleanerApp {
                 WorkerEvent::ProcessComplete(result) => {
                     finished = true;
                     self.processing_running = false;
+                    self.cancel_flag = None;
+                    if let (Ok(summary), Some(snapshot)) = (&result, &self.analysis) {
+                        self.run_report = Some(summary.report(&snapshot.plan));
+                    }
 
                     match result {
-                        Ok(()) => {
+                        Ok(summary) if summary.cancelled => {
+                            // Files were moved, so the old plan can't be re-run as-is.
+                            self.process_stage = StageState::Error;
+                            self.process_label = format!(
+                                "Cancelled after {} of {} batches",
+                                summary.batches_completed, summary.total_batches
+                            );
+                            self.status_text = "Processing cancelled.".to_string();
+                            self.append_activity(
+                                ActivityTone::Warning,
+                                format!(
+                                    "Processing cancelled after {} of {} batches. Completed moves are journaled and can be rolled back.",
+                                    summary.batches_completed, summary.total_batches
+                                ),
+                            );
+                        }
+                        Ok(summary) if !summary.failed.is_empty() => {
+                            self.process_stage = StageState::Error;
+                            self.process_progress = 1.0;
+                            self.process_label =
+                                format!("{} volumes could not be moved", summary.failed.len());
+                            self.status_text = "Processing finished with errors.".to_string();
+                            self.append_activity(
+                                ActivityTone::Warning,
+                                format!(
+                                    "Processing finished, but {} volumes were left in place: {}",
+                                    summary.failed.len(),
+                                    summary
+                                        .failed
+                                        .iter()
+                                        .map(|failed| leaf_name(&failed.src))
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ),
+                            );
+                        }
+                        Ok(summary) => {
                             self.process_stage = StageState::Complete;
                             self.process_progress = 1.0;
                             self.process_label = "All batches complete".to_string();
                             self.status_text = "Processing finished.".to_string();
                             self.append_activity(
                                 ActivityTone::Success,
-                                "Processing finished. Files and covers were updated.",
+                                format!(
+                                    "Processing finished: {} folders updated.",
+                                    summary.affected_dirs().len()
+                                ),
                             );
                         }
                         Err(err) => {
@@ -541,6 +1336,37 @@ impl MangaCleanerApp {
                         }
                     }
                 }
+                WorkerEvent::Queued { index, event } => self.handle_queued_event(index, *event),
+                WorkerEvent::QueueComplete => {
+                    finished = true;
+                    self.finish_queue();
+                }
+                WorkerEvent::UnbatchComplete(result) => {
+                    finished = true;
+                    self.processing_running = false;
+
+                    match result {
+                        Ok(moved) => {
+                            self.append_activity(
+                                ActivityTone::Success,
+                                format!(
+                                    "{moved} volumes moved back into the series folder. Running checks again."
+                                ),
+                            );
+                            // Installs the analysis worker's channel; `finished` stops the
+                            // old one from being put back below.
+                            self.reset_for_new_analysis();
+                            self.start_analysis();
+                        }
+                        Err(err) => {
+                            self.status_text = format!("Flattening failed: {err}");
+                            self.append_activity(
+                                ActivityTone::Error,
+                                format!("Flattening failed: {err}"),
+                            );
+                        }
+                    }
+                }
             }
         }
 
@@ -549,6 +1375,175 @@ impl MangaCleanerApp {
         }
     }
 
+    fn handle_queued_event(&mut self, index: usize, event: WorkerEvent) {
+        let processing = self.processing_running;
+        let Some(queue) = self.queue.as_mut() else {
+            return;
+        };
+        let (run_done, run_total) = (queue.run_done, queue.run_total);
+        let Some(series) = queue.series.get_mut(index) else {
+            return;
+        };
+        let name = leaf_name(&series.dir);
+        if matches!(series.status, QueueStatus::Waiting) {
+            series.status = if processing {
+                QueueStatus::Processing
+            } else {
+                QueueStatus::Analyzing
+            };
+        }
+
+        let mut activity = None;
+        let mut raw = None;
+        let mut view = false;
+        match event {
+            WorkerEvent::Activity(line) => {
+                activity = Some((ActivityTone::Info, format!("{name}: {line}")));
+            }
+            WorkerEvent::Log(event) => {
+                activity = log_event_activity(&event)
+                    .map(|(tone, message)| (tone, format!("{name}: {message}")));
+                raw = Some(format!("{name}: {}", raw_log_line(&event)));
+            }
+            WorkerEvent::AnalysisComplete(Ok(snapshot)) => {
+                activity = Some((
+                    ActivityTone::Success,
+                    format!(
+                        "{name}: {} volumes organized into {} batches.",
+                        snapshot.volume_count,
+                        snapshot.batch_count()
+                    ),
+                ));
+                series.analysis = Some(snapshot);
+                series.status = QueueStatus::Ready;
+                view = queue.viewing.is_none();
+            }
+            WorkerEvent::AnalysisComplete(Err(err)) => {
+                activity = Some((
+                    ActivityTone::Warning,
+                    format!("{name}: left out of the queue. {err}"),
+                ));
+                series.selected = false;
+                series.status = QueueStatus::Unusable(err);
+            }
+            WorkerEvent::ProcessProgress {
+                completed_moves,
+                total_moves,
+                label,
+            } => {
+                let fraction = if total_moves == 0 {
+                    0.0
+                } else {
+                    completed_moves as f32 / total_moves as f32
+                };
+                self.process_progress =
+                    ((run_done as f32 + fraction) / run_total.max(1) as f32).clamp(0.0, 1.0);
+                self.process_label = format!("{name} ({}/{run_total}): {label}", run_done + 1);
+            }
+            WorkerEvent::ProcessComplete(result) => {
+                queue.run_done += 1;
+                self.process_progress = queue.run_done as f32 / run_total.max(1) as f32;
+                let report = match (&result, &series.analysis) {
+                    (Ok(summary), Some(snapshot)) => Some(summary.report(&snapshot.plan)),
+                    _ => None,
+                };
+                series.status = match result {
+                    Ok(summary) if summary.cancelled => QueueStatus::Failed(
+                        "Cancelled; completed moves can be rolled back.".to_string(),
+                    ),
+                    Ok(summary) if !summary.failed.is_empty() => QueueStatus::Failed(format!(
+                        "{} volumes could not be moved",
+                        summary.failed.len()
+                    )),
+                    Ok(_) => QueueStatus::Processed(report.unwrap_or_default()),
+                    Err(err) => QueueStatus::Failed(err),
+                };
+                activity = Some(match &series.status {
+                    QueueStatus::Processed(report) => {
+                        (ActivityTone::Success, format!("{name}: {report}"))
+                    }
+                    QueueStatus::Failed(err) => (ActivityTone::Error, format!("{name}: {err}")),
+                    _ => (ActivityTone::Info, format!("{name}: finished.")),
+                });
+                view = queue.viewing == Some(index);
+            }
+            _ => {}
+        }
+
+        if let Some((tone, message)) = activity {
+            self.push_activity(tone, &message, raw);
+        }
+        if view {
+            self.view_queued(index);
+        }
+    }
+
+    fn finish_queue(&mut self) {
+        let processing = self.processing_running;
+        self.analysis_running = false;
+        self.processing_running = false;
+        self.cancel_flag = None;
+        let Some(queue) = self.queue.as_mut() else {
+            return;
+        };
+        // Series the worker never reached, because it was cancelled, keep their old state.
+        for series in &mut queue.series {
+            if matches!(
+                series.status,
+                QueueStatus::Waiting | QueueStatus::Analyzing | QueueStatus::Processing
+            ) {
+                series.status = if series.analysis.is_some() {
+                    QueueStatus::Ready
+                } else {
+                    QueueStatus::Waiting
+                };
+            }
+        }
+        let total = queue.series.len();
+
+        if processing {
+            let processed = queue
+                .series
+                .iter()
+                .filter(|series| matches!(series.status, QueueStatus::Processed(_)))
+                .count();
+            let run_total = queue.run_total;
+            self.process_stage = if processed == run_total {
+                StageState::Complete
+            } else {
+                StageState::Error
+            };
+            self.process_label = format!("{processed} of {run_total} series processed");
+            self.status_text = if processed == run_total {
+                "Queue finished.".to_string()
+            } else {
+                "Queue finished with errors.".to_string()
+            };
+            let tone = if processed == run_total {
+                ActivityTone::Success
+            } else {
+                ActivityTone::Warning
+            };
+            self.append_activity(
+                tone,
+                format!("Queue finished: {processed} of {run_total} series processed."),
+            );
+        } else {
+            let ready = queue.ready().count();
+            let state = if ready > 0 {
+                StageState::Complete
+            } else {
+                StageState::Error
+            };
+            self.analysis_stage = state;
+            self.plan_stage = state;
+            self.process_label = format!("{ready} series ready");
+            self.status_text = format!(
+                "{ready} of {total} series ready. Untick any to leave out, then process the queue."
+            );
+        }
+    }
+
     fn status_chip_palette(&self) -> (Color, Color, Color, &'static str) {
         if self.analysis_stage == StageState::Error || self.process_stage == StageState::Error {
             (
@@ -651,6 +1646,145 @@ impl MangaCleanerApp {
         }
     }
 
+    fn render_cover_choices(&self) -> Element<'_, Message> {
+        let Some(snapshot) = self
+            .analysis
+            .as_ref()
+            .filter(|snapshot| !snapshot.cover_choices.is_empty())
+        else {
+            return column![].into();
+        };
+
+        let mut choices = row![].spacing(8);
+        for (index, choice) in snapshot.cover_choices.iter().enumerate() {
+            let preview: Element<'_, Message> = match &choice.thumbnail {
+                Some(handle) => image(handle.clone())
+                    .content_fit(iced::ContentFit::Contain)
+                    .width(Length::Fixed(48.0))
+                    .height(Length::Fixed(68.0))
+                    .into(),
+                None => text(ICON_COVER)
+                    .font(FONT_SYMBOLS)
+                    .size(20)
+                    .style(theme::Text::Color(Color::from_rgb8(117, 131, 152)))
+                    .into(),
+            };
+            let tone = if snapshot.selected_choice == Some(index) {
+                ButtonTone::Accent
+            } else {
+                ButtonTone::Secondary
+            };
+            let mut choice_button = button(preview)
+                .padding(4)
+                .style(theme::Button::custom(NativeButton::new(tone)));
+            if !self.is_busy() && self.process_stage != StageState::Complete {
+                choice_button = choice_button.on_press(Message::SelectCover(index));
+            }
+            choices = choices.push(choice_button);
+        }
+
+        column![
+            text("Other MangaDex matches")
+                .font(FONT_TEXT)
+                .size(12)
+                .style(theme::Text::Color(Color::from_rgb8(74, 88, 109))),
+            scrollable(choices).direction(scrollable::Direction::Horizontal(
+                scrollable::Properties::default()
+            )),
+        ]
+        .spacing(6)
+        .into()
+    }
+
+    fn render_queue(&self) -> Element<'_, Message> {
+        let Some(queue) = &self.queue else {
+            return column![].into();
+        };
+
+        let mut rows = column![].spacing(6);
+        for (index, series) in queue.series.iter().enumerate() {
+            let (label, state) = series.status.label();
+            let (_, bg, border, color, _) = stage_palette(state);
+            let toggle = (!self.is_busy() && matches!(series.status, QueueStatus::Ready))
+                .then_some(move |selected| Message::QueueToggled(index, selected));
+            let detail = match (&series.status, &series.analysis) {
+                (QueueStatus::Unusable(err) | QueueStatus::Failed(err), _) => err.clone(),
+                (QueueStatus::Processed(report), _) => report.to_string(),
+                (_, Some(snapshot)) => format!(
+                    "{} volumes, {} batches",
+                    snapshot.volume_count,
+                    snapshot.batch_count()
+                ),
+                _ => String::new(),
+            };
+            let viewing = queue.viewing == Some(index);
+            let mut view_button = button(
+                text(if viewing { "Showing" } else { "Show" })
+                    .font(FONT_TEXT)
+                    .size(12)
+                    .style(theme::Text::Color(if viewing {
+                        Color::WHITE
+                    } else {
+                        Color::from_rgb8(70, 84, 104)
+                    })),
+            )
+            .padding([4, 10])
+            .style(theme::Button::custom(NativeButton::new(if viewing {
+                ButtonTone::Accent
+            } else {
+                ButtonTone::Ghost
+            })));
+            if series.analysis.is_some() {
+                view_button = view_button.on_press(Message::ViewQueued(index));
+            }
+
+            rows = rows.push(
+                row![
+                    checkbox(leaf_name(&series.dir), series.selected)
+                        .on_toggle_maybe(toggle)
+                        .font(FONT_TEXT)
+                        .text_size(13)
+                        .width(Length::FillPortion(2)),
+                    text(detail)
+                        .font(FONT_TEXT)
+                        .size(12)
+                        .style(theme::Text::Color(Color::from_rgb8(97, 111, 131)))
+                        .width(Length::FillPortion(3)),
+                    chip(label.to_string(), bg, border, color),
+                    view_button,
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        let selected = queue.ready().count();
+        container(
+            column![
+                row![
+                    text(format!("Library Queue: {}", queue.name))
+                        .font(FONT_DISPLAY)
+                        .size(15)
+                        .style(theme::Text::Color(Color::from_rgb8(37, 52, 70))),
+                    horizontal_space(),
+                    text(format!(
+                        "{selected} of {} series selected",
+                        queue.series.len()
+                    ))
+                    .font(FONT_TEXT)
+                    .size(12)
+                    .style(theme::Text::Color(Color::from_rgb8(97, 111, 131))),
+                ]
+                .align_items(Alignment::Center),
+                scrollable(rows).height(Length::Fixed(150.0)),
+            ]
+            .spacing(10),
+        )
+        .padding([14, 16])
+        .style(card_surface)
+        .into()
+    }
+
     fn render_plan_tree(&self) -> Element<'_, Message> {
         let Some(snapshot) = &self.analysis else {
             return container(
@@ -671,6 +1805,20 @@ impl MangaCleanerApp {
             .into();
         };
 
+        let view_toggle = button(
+            text(if self.show_plan_text {
+                "Show Tree"
+            } else {
+                "Show Text"
+            })
+            .font(FONT_TEXT)
+            .size(12)
+            .style(theme::Text::Color(Color::from_rgb8(53, 69, 89))),
+        )
+        .padding([5, 10])
+        .style(theme::Button::custom(NativeButton::new(ButtonTone::Ghost)))
+        .on_press(Message::TogglePlanText);
+
         let mut batches = column![
             row![
                 text("Planned File Tree")
@@ -678,6 +1826,7 @@ impl MangaCleanerApp {
                     .size(18)
                     .style(theme::Text::Color(Color::from_rgb8(33, 46, 62))),
                 horizontal_space(),
+                view_toggle,
                 chip(
                     format!("{} batches", snapshot.batch_count()),
                     Color::from_rgba8(36, 128, 197, 0.14),
@@ -685,6 +1834,7 @@ impl MangaCleanerApp {
                     Color::from_rgb8(23, 87, 132),
                 ),
             ]
+            .spacing(8)
             .align_items(Alignment::Center),
             text("Preview of destination folders and move/rename operations.")
                 .font(FONT_TEXT)
@@ -694,6 +1844,20 @@ impl MangaCleanerApp {
         ]
         .spacing(10);
 
+        if self.show_plan_text {
+            batches = batches.push(
+                text(snapshot.plan_text())
+                    .font(Font::MONOSPACE)
+                    .size(12)
+                    .style(theme::Text::Color(Color::from_rgb8(44, 57, 74))),
+            );
+            return container(scrollable(batches).height(Length::Fill))
+                .padding([14, 15])
+                .height(Length::Fill)
+                .style(card_surface)
+                .into();
+        }
+
         for batch in &snapshot.plan {
             let mut rows = column![].spacing(8);
             for mv in &batch.moves {
@@ -708,7 +1872,7 @@ impl MangaCleanerApp {
                     )
                 } else {
                     chip(
-                        "Move".to_string(),
+                        if batch.copy { "Copy" } else { "Move" }.to_string(),
                         Color::from_rgba8(104, 123, 150, 0.14),
                         Color::from_rgba8(104, 123, 150, 0.30),
                         Color::from_rgb8(66, 81, 100),
@@ -717,19 +1881,83 @@ impl MangaCleanerApp {
 
                 rows = rows.push(
                     row![
-                        action_chip,
-                        text(src_name)
-                            .font(FONT_TEXT)
-                            .size(13)
-                            .style(theme::Text::Color(Color::from_rgb8(44, 57, 74))),
-                        text(ICON_ARROW)
-                            .font(FONT_SYMBOLS)
-                            .size(12)
-                            .style(theme::Text::Color(Color::from_rgb8(109, 122, 141))),
-                        text(&mv.dst_name)
-                            .font(FONT_TEXT)
-                            .size(13)
-                            .style(theme::Text::Color(Color::from_rgb8(25, 37, 52))),
+                        action_chip,
+                        text(&src_name)
+                            .font(FONT_TEXT)
+                            .size(13)
+                            .style(theme::Text::Color(Color::from_rgb8(44, 57, 74))),
+                        text(ICON_ARROW)
+                            .font(FONT_SYMBOLS)
+                            .size(12)
+                            .style(theme::Text::Color(Color::from_rgb8(109, 122, 141))),
+                        text(&mv.dst_name)
+                            .font(FONT_TEXT)
+                            .size(13)
+                            .style(theme::Text::Color(Color::from_rgb8(25, 37, 52))),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                );
+
+                if let Some(problem) = &mv.problem {
+                    rows = rows.push(
+                        row![
+                            chip(
+                                "Broken".to_string(),
+                                Color::from_rgba8(196, 64, 64, 0.14),
+                                Color::from_rgba8(196, 64, 64, 0.34),
+                                Color::from_rgb8(150, 36, 36),
+                            ),
+                            text(format!("{src_name} could not be read: {problem}"))
+                                .font(FONT_TEXT)
+                                .size(12)
+                                .style(theme::Text::Color(Color::from_rgb8(54, 73, 93))),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    );
+                }
+            }
+
+            for dup in &batch.duplicates {
+                rows = rows.push(
+                    row![
+                        chip(
+                            "Skip".to_string(),
+                            Color::from_rgba8(201, 135, 38, 0.14),
+                            Color::from_rgba8(201, 135, 38, 0.34),
+                            Color::from_rgb8(140, 88, 14),
+                        ),
+                        text(format!(
+                            "{} is identical to {}; left in place.",
+                            leaf_name(&dup.path),
+                            leaf_name(&dup.original)
+                        ))
+                        .font(FONT_TEXT)
+                        .size(12)
+                        .style(theme::Text::Color(Color::from_rgb8(54, 73, 93))),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                );
+            }
+
+            for path in &batch.ignored {
+                rows = rows.push(
+                    row![
+                        chip(
+                            "Skip".to_string(),
+                            Color::from_rgba8(201, 135, 38, 0.14),
+                            Color::from_rgba8(201, 135, 38, 0.34),
+                            Color::from_rgb8(140, 88, 14),
+                        ),
+                        text(format!(
+                            "{} matches an ignore pattern; left in place.",
+                            leaf_name(path)
+                        ))
+                        .font(FONT_TEXT)
+                        .size(12)
+                        .style(theme::Text::Color(Color::from_rgb8(54, 73, 93))),
                     ]
                     .spacing(8)
                     .align_items(Alignment::Center),
@@ -755,7 +1983,7 @@ impl MangaCleanerApp {
                 );
             }
 
-            let start = (batch.batch_index - 1) * FILES_PER_FOLDER + 1;
+            let start = batch.volume_start;
             let end = start + batch.moves.len().saturating_sub(1);
 
             let batch_card = container(
@@ -807,8 +2035,43 @@ impl Application for MangaCleanerApp {
     type Flags = AppFlags;
 
     fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let (config, config_error) = match Config::load_default() {
+            Ok(config) => (config, None),
+            Err(err) => (Config::default(), Some(format!("{err:#}"))),
+        };
+        config.apply_network_settings();
+        let (session, session_error) =
+            match Session::default_path().map(|path| Session::load(&path)) {
+                Some(Ok(session)) => (session, None),
+                Some(Err(err)) => (Session::default(), Some(format!("{err:#}"))),
+                None => (Session::default(), None),
+            };
+        // The last session's choices win over the config file's defaults.
+        let batch_size = session
+            .batch_size
+            .filter(|&size| size > 0)
+            .or(config.batch_size)
+            .unwrap_or(FILES_PER_FOLDER);
+        let config_color = config.cover_text_color;
+        let text_color = session
+            .cover_text_color
+            .as_deref()
+            .and_then(|color| color.parse::<CoverTextColor>().ok())
+            .and_then(TextColorChoice::from_cover_text_color)
+            .or_else(|| config_color.and_then(TextColorChoice::from_cover_text_color))
+            .unwrap_or(TextColorChoice::Black);
+        let restore = session
+            .series_dir
+            .clone()
+            .filter(|_| flags.initial_series_dir.is_empty());
+        let restore_library = session.library;
+
         let mut app = Self {
             series_dir_input: flags.initial_series_dir,
+            batch_size_input: batch_size.to_string(),
+            title_override_input: String::new(),
+            batch_size,
+            text_color,
             status_text: "Choose a folder to start.".to_string(),
             analysis_stage: StageState::Pending,
             plan_stage: StageState::Pending,
@@ -816,23 +2079,67 @@ impl Application for MangaCleanerApp {
             analysis_running: false,
             processing_running: false,
             show_confirm_sheet: false,
+            show_plan_text: false,
+            unbatch_preview: None,
             process_progress: 0.0,
             process_label: "Waiting for analysis".to_string(),
+            run_report: None,
             analysis: None,
             cover_path: None,
             cover_handle: None,
             activity: Vec::new(),
             worker_rx: None,
+            cancel_flag: None,
+            queue: None,
+            dropped: Vec::new(),
+            session,
+            config,
         };
 
+        if let Some(err) = config_error {
+            app.append_activity(
+                ActivityTone::Warning,
+                format!("Ignoring config file: {err}"),
+            );
+        }
+        if let Some(color @ CoverTextColor::Rgb(_)) = config_color {
+            if TextColorChoice::from_cover_text_color(color).is_none() {
+                app.append_activity(
+                    ActivityTone::Warning,
+                    format!("Config cover_text_color {color} isn't offered here; using Black."),
+                );
+            }
+        }
+
         app.append_activity(
             ActivityTone::Info,
             "Select a series folder. Checks and planning run automatically.",
         );
 
+        if let Some(err) = session_error {
+            app.append_activity(
+                ActivityTone::Warning,
+                format!("Ignoring the saved session: {err}"),
+            );
+        }
+
         if !app.series_dir_input.trim().is_empty() {
             let initial = app.series_dir_input.clone();
             app.set_series_folder(initial);
+        } else if let Some(dir) = restore {
+            if !dir.is_dir() {
+                app.append_activity(
+                    ActivityTone::Info,
+                    format!(
+                        "The last folder, {}, is no longer there. Choose a folder to begin.",
+                        dir.display()
+                    ),
+                );
+            } else if restore_library {
+                app.set_library_folder(dir);
+            } else {
+                app.set_series_folder(dir.display().to_string());
+            }
         }
 
         (app, Command::none())
@@ -856,7 +2163,16 @@ impl Application for MangaCleanerApp {
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        time::every(Duration::from_millis(120)).map(|_| Message::Tick)
+        Subscription::batch([
+            time::every(Duration::from_millis(120)).map(|_| Message::Tick),
+            event::listen_with(|event, _status| match event {
+                iced::Event::Window(_, window::Event::FileDropped(path)) => {
+                    Some(Message::FileDropped(path))
+                }
+                _ => None,
+            }),
+            keyboard::on_key_press(shortcut),
+        ])
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
@@ -870,6 +2186,31 @@ impl Application for MangaCleanerApp {
                     self.set_series_folder(folder.display().to_string());
                 }
             }
+            Message::BrowseLibrary => {
+                if self.is_busy() {
+                    return Command::none();
+                }
+
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    self.set_library_folder(folder);
+                }
+            }
+            Message::QueueToggled(index, selected) => {
+                if self.is_busy() {
+                    return Command::none();
+                }
+                if let Some(series) = self
+                    .queue
+                    .as_mut()
+                    .and_then(|queue| queue.series.get_mut(index))
+                    .filter(|series| matches!(series.status, QueueStatus::Ready))
+                {
+                    series.selected = selected;
+                }
+            }
+            Message::ViewQueued(index) => {
+                self.view_queued(index);
+            }
             Message::RefreshAnalysis => {
                 if self.can_refresh() {
                     self.activity.clear();
@@ -889,8 +2230,181 @@ impl Application for MangaCleanerApp {
                 self.show_confirm_sheet = false;
                 self.start_process();
             }
+            Message::CancelProcess => {
+                if let Some(cancel) = &self.cancel_flag {
+                    cancel.store(true, Ordering::Relaxed);
+                    self.status_text = "Cancelling after the current file...".to_string();
+                }
+            }
+            Message::BatchSizeChanged(value) => {
+                self.batch_size_input = value;
+            }
+            Message::BatchSizeSubmitted => {
+                if self.is_busy() {
+                    return Command::none();
+                }
+
+                match self.batch_size_input.trim().parse::<usize>() {
+                    Ok(size) if size > 0 => {
+                        self.batch_size_input = size.to_string();
+                        if size != self.batch_size {
+                            self.batch_size = size;
+                            self.remember(|session| session.batch_size = Some(size));
+                            if self.can_refresh() {
+                                self.reset_for_new_analysis();
+                                self.start_analysis();
+                            }
+                        }
+                    }
+                    _ => {
+                        self.status_text =
+                            "Batch size must be a whole number of at least 1.".to_string();
+                        self.batch_size_input = self.batch_size.to_string();
+                    }
+                }
+            }
+            Message::TitleOverrideChanged(value) => {
+                self.title_override_input = value;
+            }
+            Message::TitleOverrideSubmitted => {
+                if self.can_refresh() {
+                    self.reset_for_new_analysis();
+                    self.start_analysis();
+                }
+            }
+            Message::TextColorSelected(choice) => {
+                if !self.processing_running {
+                    self.text_color = choice;
+                    let color = choice.cover_text_color().to_string();
+                    self.remember(|session| session.cover_text_color = Some(color));
+                }
+            }
+            Message::SelectCover(index) => {
+                if self.is_busy() || self.process_stage == StageState::Complete {
+                    return Command::none();
+                }
+                let Some(snapshot) = &self.analysis else {
+                    return Command::none();
+                };
+                let Some(choice) = snapshot.cover_choices.get(index).cloned() else {
+                    return Command::none();
+                };
+                let decision = CoverDecision::Download {
+                    result: choice.result,
+                    fallbacks: Vec::new(),
+                    output: snapshot.resolved_dir.join("cover.jpg"),
+                };
+                self.use_cover(decision, choice.thumbnail, Some(index));
+                self.append_activity(
+                    ActivityTone::Info,
+                    format!(
+                        "Using the MangaDex cover for {}; it will be downloaded when processing starts.",
+                        choice.title
+                    ),
+                );
+            }
+            Message::ChooseCover => {
+                if self.is_busy() || self.process_stage == StageState::Complete {
+                    return Command::none();
+                }
+                let Some(series_dir) = self.analysis.as_ref().map(|s| s.resolved_dir.clone())
+                else {
+                    return Command::none();
+                };
+                let Some(picked) = FileDialog::new()
+                    .add_filter("Images", &["jpg", "jpeg", "png", "webp", "bmp", "gif"])
+                    .set_directory(&series_dir)
+                    .pick_file()
+                else {
+                    return Command::none();
+                };
+
+                match manual_cover_decision(&series_dir, &picked) {
+                    Ok(decision) => {
+                        let preview = iced::widget::image::Handle::from_path(&picked);
+                        self.use_cover(decision, Some(preview), None);
+                        self.append_activity(
+                            ActivityTone::Info,
+                            format!(
+                                "Using {} as the series cover; it will be copied in when processing starts.",
+                                picked.display()
+                            ),
+                        );
+                    }
+                    Err(err) => {
+                        self.status_text = format!("Could not use that cover: {err:#}");
+                        self.append_activity(
+                            ActivityTone::Error,
+                            format!("Could not use that cover: {err:#}"),
+                        );
+                    }
+                }
+            }
+            Message::RequestUnbatch => {
+                if self.can_refresh() && !self.is_busy() {
+                    self.preview_unbatch();
+                }
+            }
+            Message::CancelUnbatch => {
+                self.unbatch_preview = None;
+            }
+            Message::ConfirmUnbatch => {
+                if !self.is_busy() {
+                    self.start_unbatch();
+                }
+            }
+            Message::ForgetSession => {
+                self.session = Session::default();
+                let forgotten =
+                    Session::default_path().map_or(Ok(()), |path| Session::forget(&path));
+                match forgotten {
+                    Ok(()) => self.append_activity(
+                        ActivityTone::Info,
+                        "Forgot the last folder and options; the next launch starts blank.",
+                    ),
+                    Err(err) => self.append_activity(
+                        ActivityTone::Error,
+                        format!("Could not forget the session: {err:#}"),
+                    ),
+                }
+            }
+            Message::TogglePlanText => {
+                self.show_plan_text = !self.show_plan_text;
+            }
+            Message::ExportLog => {
+                if self.activity.is_empty() {
+                    return Command::none();
+                }
+                if let Some(path) = FileDialog::new()
+                    .set_file_name("manga_cleaner activity.log")
+                    .save_file()
+                {
+                    let mut log = self
+                        .activity
+                        .iter()
+                        .map(ActivityItem::export_line)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    log.push('\n');
+                    match fs::write(&path, log) {
+                        Ok(()) => self.append_activity(
+                            ActivityTone::Info,
+                            format!("Saved the activity log to {}.", path.display()),
+                        ),
+                        Err(err) => self.append_activity(
+                            ActivityTone::Error,
+                            format!("Could not save the activity log: {err}"),
+                        ),
+                    }
+                }
+            }
+            Message::FileDropped(path) => {
+                // A multi-file drop arrives as one event per path.
+                self.dropped.push(path);
+            }
             Message::Tick => {
                 self.drain_worker_events();
+                self.open_dropped();
             }
         }
 
@@ -963,6 +2477,21 @@ impl Application for MangaCleanerApp {
             browse_button = browse_button.on_press(Message::BrowseFolder);
         }
 
+        let mut library_button = button(
+            text("Choose Library")
+                .font(FONT_TEXT)
+                .size(14)
+                .style(theme::Text::Color(Color::from_rgb8(40, 57, 77))),
+        )
+        .padding([11, 14])
+        .style(theme::Button::custom(NativeButton::new(
+            ButtonTone::Secondary,
+        )));
+
+        if !self.is_busy() {
+            library_button = library_button.on_press(Message::BrowseLibrary);
+        }
+
         let mut refresh_button = button(
             row![
                 text(ICON_REFRESH)
@@ -986,6 +2515,74 @@ impl Application for MangaCleanerApp {
             refresh_button = refresh_button.on_press(Message::RefreshAnalysis);
         }
 
+        let mut unbatch_button = button(
+            text("Flatten Batches")
+                .font(FONT_TEXT)
+                .size(14)
+                .style(theme::Text::Color(Color::from_rgb8(53, 69, 89))),
+        )
+        .padding([11, 14])
+        .style(theme::Button::custom(NativeButton::new(ButtonTone::Ghost)));
+
+        if self.can_refresh() && !self.is_busy() && self.queue.is_none() {
+            unbatch_button = unbatch_button.on_press(Message::RequestUnbatch);
+        }
+
+        let forget_button = button(
+            text("Forget")
+                .font(FONT_TEXT)
+                .size(14)
+                .style(theme::Text::Color(Color::from_rgb8(53, 69, 89))),
+        )
+        .padding([11, 14])
+        .style(theme::Button::custom(NativeButton::new(ButtonTone::Ghost)))
+        .on_press(Message::ForgetSession);
+
+        let mut batch_size_field = text_input("20", &self.batch_size_input)
+            .font(FONT_TEXT)
+            .size(14)
+            .padding([10, 10])
+            .width(Length::Fixed(64.0));
+
+        if !self.is_busy() {
+            batch_size_field = batch_size_field
+                .on_input(Message::BatchSizeChanged)
+                .on_submit(Message::BatchSizeSubmitted);
+        }
+
+        let mut title_field = text_input("Defaults to the folder name", &self.title_override_input)
+            .font(FONT_TEXT)
+            .size(13)
+            .padding([8, 10])
+            .width(Length::Fill);
+
+        // Each queued series is searched under its own folder name.
+        if !self.is_busy() && self.queue.is_none() {
+            title_field = title_field
+                .on_input(Message::TitleOverrideChanged)
+                .on_submit(Message::TitleOverrideSubmitted);
+        }
+
+        let title_control = row![
+            text("Cover search title")
+                .font(FONT_TEXT)
+                .size(13)
+                .style(theme::Text::Color(Color::from_rgb8(70, 84, 104))),
+            title_field,
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center);
+
+        let batch_size_control = row![
+            text("Batch size")
+                .font(FONT_TEXT)
+                .size(13)
+                .style(theme::Text::Color(Color::from_rgb8(70, 84, 104))),
+            batch_size_field,
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center);
+
         let source_card = container(
             column![
                 row![
@@ -1001,7 +2598,16 @@ impl Application for MangaCleanerApp {
                     ]
                     .spacing(4),
                     horizontal_space(),
-                    row![browse_button, refresh_button].spacing(10),
+                    row![
+                        batch_size_control,
+                        browse_button,
+                        library_button,
+                        refresh_button,
+                        unbatch_button,
+                        forget_button
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
                 ]
                 .align_items(Alignment::Center),
                 container(
@@ -1017,13 +2623,81 @@ impl Application for MangaCleanerApp {
                 .padding([12, 14])
                 .width(Length::Fill)
                 .style(path_well_surface),
+                title_control,
             ]
             .spacing(12),
         )
         .padding([16, 18])
         .style(card_surface);
 
-        let analysis_detail = if self.analysis_running {
+        let source_card: Element<'_, Message> = match &self.unbatch_preview {
+            Some((series_dir, moves)) => {
+                let batch_count = moves
+                    .iter()
+                    .filter_map(|planned| planned.src.parent())
+                    .collect::<std::collections::BTreeSet<_>>()
+                    .len();
+                let cancel_btn = button(
+                    text("Not yet")
+                        .font(FONT_TEXT)
+                        .size(14)
+                        .style(theme::Text::Color(Color::from_rgb8(53, 69, 89))),
+                )
+                .padding([10, 14])
+                .style(theme::Button::custom(NativeButton::new(ButtonTone::Ghost)))
+                .on_press(Message::CancelUnbatch);
+
+                let confirm_btn = button(
+                    text("Confirm and Flatten")
+                        .font(FONT_DISPLAY)
+                        .size(14)
+                        .style(theme::Text::Color(Color::WHITE)),
+                )
+                .padding([10, 14])
+                .style(theme::Button::custom(NativeButton::new(ButtonTone::Danger)))
+                .on_press(Message::ConfirmUnbatch);
+
+                column![
+                    source_card,
+                    container(
+                        column![
+                            text("Flatten Batch Folders")
+                                .font(FONT_DISPLAY)
+                                .size(14)
+                                .style(theme::Text::Color(Color::from_rgb8(118, 34, 39))),
+                            text(format!(
+                                "This will move {} volume files from {batch_count} batch folders back into {} and delete their generated covers. The moves are listed in Activity.",
+                                moves.len(),
+                                leaf_name(series_dir)
+                            ))
+                            .font(FONT_TEXT)
+                            .size(12)
+                            .style(theme::Text::Color(Color::from_rgb8(113, 50, 54))),
+                            row![cancel_btn, confirm_btn]
+                                .spacing(9)
+                                .align_items(Alignment::Center),
+                        ]
+                        .spacing(10),
+                    )
+                    .padding([12, 13])
+                    .style(confirm_surface),
+                ]
+                .spacing(8)
+                .into()
+            }
+            None => source_card.into(),
+        };
+
+        let analysis_detail = if let Some(queue) = &self.queue {
+            let checked = queue
+                .series
+                .iter()
+                .filter(|series| {
+                    !matches!(series.status, QueueStatus::Waiting | QueueStatus::Analyzing)
+                })
+                .count();
+            format!("{checked} of {} series checked", queue.series.len())
+        } else if self.analysis_running {
             "Scanning files and cover sources".to_string()
         } else if let Some(snapshot) = &self.analysis {
             format!("{} files validated", snapshot.volume_count)
@@ -1044,6 +2718,19 @@ impl Application for MangaCleanerApp {
         .padding([14, 16])
         .style(card_surface);
 
+        let mut choose_cover_button = button(
+            text("Choose Cover")
+                .font(FONT_TEXT)
+                .size(12)
+                .style(theme::Text::Color(Color::from_rgb8(70, 84, 104))),
+        )
+        .padding([5, 10])
+        .style(theme::Button::custom(NativeButton::new(ButtonTone::Ghost)));
+        if self.analysis.is_some() && !self.is_busy() && self.process_stage != StageState::Complete
+        {
+            choose_cover_button = choose_cover_button.on_press(Message::ChooseCover);
+        }
+
         let cover_card = container(
             column![
                 row![
@@ -1052,6 +2739,7 @@ impl Application for MangaCleanerApp {
                         .size(16)
                         .style(theme::Text::Color(Color::from_rgb8(31, 45, 62))),
                     horizontal_space(),
+                    choose_cover_button,
                     if self.cover_path.is_some() {
                         chip(
                             "Ready".to_string(),
@@ -1068,6 +2756,7 @@ impl Application for MangaCleanerApp {
                         )
                     }
                 ]
+                .spacing(8)
                 .align_items(Alignment::Center),
                 container(self.render_cover_preview())
                     .height(Length::FillPortion(4))
@@ -1081,6 +2770,7 @@ impl Application for MangaCleanerApp {
                 .font(FONT_TEXT)
                 .size(12)
                 .style(theme::Text::Color(Color::from_rgb8(101, 116, 136))),
+                self.render_cover_choices(),
             ]
             .spacing(10),
         )
@@ -1113,12 +2803,33 @@ impl Application for MangaCleanerApp {
 
         let process_label = if self.processing_running {
             "Processing..."
+        } else if self.queue.is_some() {
+            "Process Queue"
         } else if self.process_stage == StageState::Complete {
             "Completed"
         } else {
             "Process Files"
         };
 
+        let text_color_picker = pick_list(
+            &TextColorChoice::ALL[..],
+            Some(self.text_color),
+            Message::TextColorSelected,
+        )
+        .font(FONT_TEXT)
+        .text_size(12)
+        .padding([6, 10]);
+
+        let cover_color_row = row![
+            text("Cover number color")
+                .font(FONT_TEXT)
+                .size(12)
+                .style(theme::Text::Color(Color::from_rgb8(96, 111, 131))),
+            horizontal_space(),
+            text_color_picker,
+        ]
+        .align_items(Alignment::Center);
+
         let mut process_button = button(
             row![
                 text(ICON_PROCESS)
@@ -1141,6 +2852,19 @@ impl Application for MangaCleanerApp {
             process_button = process_button.on_press(Message::RequestProcess);
         }
 
+        let mut cancel_button = button(
+            text("Cancel")
+                .font(FONT_TEXT)
+                .size(14)
+                .style(theme::Text::Color(Color::from_rgb8(53, 69, 89))),
+        )
+        .padding([12, 14])
+        .style(theme::Button::custom(NativeButton::new(ButtonTone::Ghost)));
+
+        if self.processing_running {
+            cancel_button = cancel_button.on_press(Message::CancelProcess);
+        }
+
         let mut summary_column = column![
             row![
                 text("Execution")
@@ -1178,12 +2902,54 @@ impl Application for MangaCleanerApp {
                 .font(FONT_TEXT)
                 .size(12)
                 .style(theme::Text::Color(Color::from_rgb8(94, 108, 128))),
-            process_button,
+            cover_color_row,
+            row![process_button, cancel_button]
+                .spacing(9)
+                .align_items(Alignment::Center),
         ]
         .spacing(10);
 
+        if let Some(report) = self.run_report.filter(|_| !self.processing_running) {
+            let mut report_column = column![
+                text("Run Summary")
+                    .font(FONT_DISPLAY)
+                    .size(14)
+                    .style(theme::Text::Color(Color::from_rgb8(23, 110, 79))),
+                text(report.to_string())
+                    .font(FONT_TEXT)
+                    .size(12)
+                    .style(theme::Text::Color(Color::from_rgb8(36, 86, 66))),
+            ]
+            .spacing(8);
+            if report.failed > 0 {
+                report_column = report_column.push(
+                    text(format!(
+                        "{} volumes were left in place; Activity lists them.",
+                        report.failed
+                    ))
+                    .font(FONT_TEXT)
+                    .size(12)
+                    .style(theme::Text::Color(Color::from_rgb8(122, 80, 14))),
+                );
+            }
+            summary_column = summary_column.push(
+                container(report_column)
+                    .padding([12, 13])
+                    .width(Length::Fill)
+                    .style(report_surface),
+            );
+        }
+
         if self.show_confirm_sheet {
-            let destructive_summary = if let Some(snapshot) = &self.analysis {
+            let destructive_summary = if let Some(queue) = &self.queue {
+                let (series, volumes) = queue.ready().fold((0, 0), |(series, volumes), (_, s)| {
+                    let count = s.analysis.as_ref().map_or(0, |a| a.volume_count);
+                    (series + 1, volumes + count)
+                });
+                format!(
+                    "This will process {series} series one after the other, moving {volumes} volume files into batch folders and writing batch covers where available."
+                )
+            } else if let Some(snapshot) = &self.analysis {
                 format!(
                     "This will move {} volume files into {} destination folders and write batch covers where available.",
                     snapshot.volume_count,
@@ -1193,6 +2959,16 @@ impl Application for MangaCleanerApp {
                 "This will apply the prepared file and cover changes.".to_string()
             };
 
+            let mut warnings = column![].spacing(6);
+            for warning in self.rename_warnings() {
+                warnings = warnings.push(
+                    text(warning)
+                        .font(FONT_TEXT)
+                        .size(12)
+                        .style(theme::Text::Color(Color::from_rgb8(140, 88, 14))),
+                );
+            }
+
             let cancel_btn = button(
                 text("Not yet")
                     .font(FONT_TEXT)
@@ -1224,6 +3000,7 @@ impl Application for MangaCleanerApp {
                             .font(FONT_TEXT)
                             .size(12)
                             .style(theme::Text::Color(Color::from_rgb8(113, 50, 54))),
+                        warnings,
                         row![cancel_btn, confirm_btn]
                             .spacing(9)
                             .align_items(Alignment::Center),
@@ -1253,6 +3030,18 @@ impl Application for MangaCleanerApp {
             .spacing(12)
             .height(Length::FillPortion(3));
 
+        let mut export_button = button(
+            text("Export Log")
+                .font(FONT_TEXT)
+                .size(12)
+                .style(theme::Text::Color(Color::from_rgb8(53, 69, 89))),
+        )
+        .padding([5, 10])
+        .style(theme::Button::custom(NativeButton::new(ButtonTone::Ghost)));
+        if !self.activity.is_empty() {
+            export_button = export_button.on_press(Message::ExportLog);
+        }
+
         let mut activity_list = column![
             row![
                 text(ICON_ACTIVITY)
@@ -1263,6 +3052,8 @@ impl Application for MangaCleanerApp {
                     .font(FONT_DISPLAY)
                     .size(16)
                     .style(theme::Text::Color(Color::from_rgb8(33, 47, 63))),
+                horizontal_space(),
+                export_button,
             ]
             .spacing(8)
             .align_items(Alignment::Center),
@@ -1315,7 +3106,13 @@ impl Application for MangaCleanerApp {
             .height(Length::FillPortion(1))
             .style(card_surface);
 
-        let content = column![topbar, source_card, flow_card, workspace_row, activity_card]
+        let mut content = column![topbar, source_card, flow_card];
+        if self.queue.is_some() {
+            content = content.push(self.render_queue());
+        }
+        let content = content
+            .push(workspace_row)
+            .push(activity_card)
             .spacing(12)
             .padding(top_content_padding())
             .width(Length::Fill)
@@ -1435,60 +3232,88 @@ fn stage_palette(state: StageState) -> (&'static str, Color, Color, Color, &'sta
     }
 }
 
-fn humanize_activity_line(line: &str) -> Option<String> {
-    let trimmed = line.trim();
-    if trimmed.is_empty() {
-        return None;
-    }
-
-    if trimmed.chars().all(|ch| ch == '-' || ch == '=') {
-        return None;
-    }
-
-    if let Some(rest) = trimmed.strip_prefix("[COVER] ") {
-        return Some(rest.to_string());
-    }
-    if let Some(rest) = trimmed.strip_prefix("[WARN] ") {
-        return Some(rest.to_string());
-    }
-    if let Some(rest) = trimmed.strip_prefix("[ERROR] ") {
-        return Some(rest.to_string());
-    }
-    if let Some(rest) = trimmed.strip_prefix("[DO] Batch ") {
-        return Some(format!("Processing {rest}"));
-    }
-    if let Some(rest) = trimmed.strip_prefix("[MOVE] ") {
-        return Some(rest.to_string());
-    }
-    if let Some(rest) = trimmed.strip_prefix("[COMPLETE] ") {
-        return Some(rest.to_string());
-    }
+/// The error with every cause, plus what to do about it when the failure class suggests
+/// something.
+fn failure_message(err: &Error) -> String {
+    let hint = match err {
+        Error::PathResolution(_) => "Check the folder path, or choose the folder again.",
+        Error::NoVolumes(_) => {
+            "Choose the folder that holds the .cbz, .cbr, .cb7, .cbt, or .zip volumes."
+        }
+        Error::Archive(_) => {
+            "A volume may be damaged; replace it, or leave it out with an `ignore` pattern."
+        }
+        Error::Network(_) => "Check the connection or proxy settings, or choose a cover by hand.",
+        Error::Font(_) => "Set `font` in the config file to a TTF or OTF font.",
+        Error::CoverNotFound(_) => "Choose a cover image by hand.",
+        Error::Io(_) => "Check that the folders are writable and the disk has room.",
+        Error::Other(_) => return format!("{err:#}"),
+    };
+    let text = format!("{err:#}");
+    format!("{}. {hint}", text.trim_end_matches('.'))
+}
 
-    if trimmed.starts_with("[PLAN]") {
-        return None;
+/// The event as the CLI prints it, with its `[TAG]`, on one line.
+fn raw_log_line(event: &LogEvent) -> String {
+    match event.tag() {
+        Some(tag) => format!("{tag} {}", event.message()),
+        None => event.message(),
     }
-
-    Some(trimmed.to_string())
 }
 
-fn activity_tone(line: &str) -> ActivityTone {
-    let lower = line.to_ascii_lowercase();
-    if line.contains("[ERROR]") || lower.contains("failed") {
-        ActivityTone::Error
-    } else if line.contains("[WARN]") {
-        ActivityTone::Warning
-    } else if line.contains("[COMPLETE]") || line.contains("[COVER]") {
-        ActivityTone::Success
-    } else {
-        ActivityTone::Info
+fn log_event_activity(event: &LogEvent) -> Option<(ActivityTone, String)> {
+    if event.level() > LogLevel::Normal {
+        return None;
     }
+    let tone = match event {
+        LogEvent::MoveFailed { .. } => ActivityTone::Error,
+        LogEvent::Warn(_) | LogEvent::Cancelled { .. } => ActivityTone::Warning,
+        LogEvent::CoverExtracted { .. }
+        | LogEvent::CoverDownloaded { .. }
+        | LogEvent::CoverCopied { .. }
+        | LogEvent::CoverRendered { .. }
+        | LogEvent::CoverKept { .. }
+        | LogEvent::Complete
+        | LogEvent::RollbackComplete => ActivityTone::Success,
+        LogEvent::Info(line) if line.trim().is_empty() => return None,
+        _ => ActivityTone::Info,
+    };
+    let message = match event {
+        LogEvent::BatchStarted { .. } => format!("Processing {}", event.message()),
+        _ => event.message(),
+    };
+    Some((tone, message))
 }
 
-fn parse_batch_start(line: &str) -> Option<(usize, String)> {
-    let rest = line.strip_prefix("[DO] Batch ")?;
-    let (index_raw, name_raw) = rest.split_once(':')?;
-    let batch_index = index_raw.trim().parse::<usize>().ok()?;
-    Some((batch_index, name_raw.trim().to_string()))
+/// MangaDex's best matches for `title` with downloaded thumbnails. Failures only cost the
+/// choices, so they are logged rather than failing the analysis.
+fn cover_choices(
+    series_dir: &Path,
+    title: &str,
+    options: &CoverOptions,
+    log: &mut dyn FnMut(LogEvent),
+) -> Vec<CoverChoice> {
+    let candidates = match mangadex_cover_candidates(title, options, COVER_CHOICES) {
+        Ok(candidates) => candidates,
+        Err(err) => {
+            log(LogEvent::Warn(format!(
+                "Could not load alternative covers: {err:#}"
+            )));
+            return Vec::new();
+        }
+    };
+    let series = leaf_name(series_dir);
+    candidates
+        .into_iter()
+        .enumerate()
+        .map(|(i, candidate)| CoverChoice {
+            thumbnail: download_cover_thumbnail(&candidate, &format!("{series} choice {}", i + 1))
+                .ok()
+                .map(iced::widget::image::Handle::from_path),
+            title: candidate.title,
+            result: candidate.result,
+        })
+        .collect()
 }
 
 fn leaf_name(path: &Path) -> String {
@@ -1597,6 +3422,19 @@ fn confirm_surface(_theme: &Theme) -> iced::widget::container::Appearance {
     }
 }
 
+fn report_surface(_theme: &Theme) -> iced::widget::container::Appearance {
+    iced::widget::container::Appearance {
+        text_color: None,
+        background: Some(Background::Color(Color::from_rgba8(228, 245, 237, 0.90))),
+        border: Border {
+            color: Color::from_rgba8(52, 158, 116, 0.36),
+            width: 1.0,
+            radius: 12.0.into(),
+        },
+        shadow: Shadow::default(),
+    }
+}
+
 fn plan_batch_surface(_theme: &Theme) -> iced::widget::container::Appearance {
     iced::widget::container::Appearance {
         text_color: None,
@@ -1631,6 +3469,7 @@ fn mix(a: Color, b: Color, t: f32) -> Color {
 fn main() -> iced::Result {
     let args = UiArgs::parse();
 
+    #[cfg_attr(not(target_os = "macos"), allow(unused_mut))]
     let mut window_settings = iced::window::Settings {
         size: Size::new(1280.0, 860.0),
         min_size: Some(Size::new(1080.0, 760.0)),
@@ -0,0 +1,225 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("manga_cleaner_cli_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("scratch dir");
+    dir
+}
+
+/// Runs the CLI with a config directory of its own, so a user config can't interfere.
+fn run_cli(config_home: &Path, args: &[&str]) -> i32 {
+    run_cli_output(config_home, args).0
+}
+
+fn run_cli_output(config_home: &Path, args: &[&str]) -> (i32, String) {
+    let (code, stdout, _) = run_cli_streams(config_home, args);
+    (code, stdout)
+}
+
+fn run_cli_streams(config_home: &Path, args: &[&str]) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_process_manga_rs"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_home)
+        .env("APPDATA", config_home)
+        .output()
+        .expect("run process_manga_rs");
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    (output.status.code().expect("exit status"), stdout, stderr)
+}
+
+#[test]
+fn failures_exit_with_the_documented_status() {
+    let root = scratch_dir("exit_codes");
+    let series = root.join("Empty Series");
+    fs::create_dir_all(&series).expect("series dir");
+    let series = series.to_string_lossy().into_owned();
+    let missing = root.join("missing").to_string_lossy().into_owned();
+
+    let flags = ["--offline", "--no-cover", "--dry-run"];
+    assert_eq!(
+        run_cli(&root, &[&[missing.as_str()], &flags[..]].concat()),
+        3,
+        "nonexistent folder"
+    );
+    assert_eq!(
+        run_cli(&root, &[&[series.as_str()], &flags[..]].concat()),
+        4,
+        "folder without volumes"
+    );
+
+    assert_eq!(
+        run_cli(&root, &[&series, "--show-cover", "--dry-run"]),
+        2,
+        "conflicting flags"
+    );
+    assert_eq!(
+        run_cli(&root, &[&series, "--json", "--offline", "--no-cover"]),
+        2,
+        "--json without --yes or --dry-run"
+    );
+}
+
+#[test]
+fn bulk_mode_processes_every_series_and_reports_the_rest() {
+    let root = scratch_dir("bulk");
+    let library = root.join("library");
+    for name in ["Alpha", "Beta", "Gamma"] {
+        fs::create_dir_all(library.join(name)).expect("series dir");
+    }
+    fs::write(library.join("Alpha/Alpha v01.cbz"), b"").expect("volume");
+    fs::write(library.join("Gamma/Gamma v01.cbz"), b"").expect("volume");
+    let library = library.to_string_lossy().into_owned();
+
+    let (code, stdout) = run_cli_output(
+        &root,
+        &["--bulk", &library, "--offline", "--no-cover", "--dry-run"],
+    );
+    assert_eq!(code, 0, "{stdout}");
+    assert!(stdout.contains("[BULK] (3/3) Gamma"), "{stdout}");
+    assert!(
+        stdout.contains("2 of 3 series processed, 1 skipped (no volumes), 0 failed"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("[SKIP] Beta"), "{stdout}");
+}
+
+#[test]
+fn subcommands_match_the_top_level_flags() {
+    let root = scratch_dir("subcommands");
+    let series = root.join("Series");
+    fs::create_dir_all(&series).expect("series dir");
+    fs::write(series.join("Series v01.cbz"), b"").expect("volume");
+    let series = series.to_string_lossy().into_owned();
+
+    let (code, stdout) = run_cli_output(
+        &root,
+        &[
+            "--offline",
+            "plan",
+            &series,
+            "--no-cover",
+            "--batch-size",
+            "5",
+        ],
+    );
+    assert_eq!(code, 0, "{stdout}");
+    assert!(stdout.contains("[PLAN] Batch size: 5"), "{stdout}");
+    assert!(stdout.contains("[DRY-RUN] Plan printed only."), "{stdout}");
+    assert!(!root.join("Series 1").exists(), "plan changed files");
+
+    assert_eq!(
+        run_cli(&root, &["plan", &series, "--show-cover"]),
+        2,
+        "mode flag of another subcommand"
+    );
+    assert_eq!(
+        run_cli(&root, &["--dry-run", "rollback", &series]),
+        2,
+        "top-level mode flag with a subcommand"
+    );
+    assert_eq!(
+        run_cli(&root, &["rollback", &series]),
+        3,
+        "rollback without a journal"
+    );
+}
+
+#[test]
+fn completions_cover_the_subcommands_and_flags() {
+    let root = scratch_dir("completions");
+    for shell in ["bash", "zsh", "fish"] {
+        let (code, stdout) = run_cli_output(&root, &["completions", shell]);
+        assert_eq!(code, 0, "{shell}");
+        assert!(stdout.contains("process_manga_rs"), "{shell}: {stdout}");
+        assert!(stdout.contains("batch-size"), "{shell}: {stdout}");
+        assert!(stdout.contains("rollback"), "{shell}: {stdout}");
+    }
+    assert_eq!(run_cli(&root, &["completions", "tcsh"]), 2, "unknown shell");
+}
+
+#[test]
+fn a_second_bulk_run_leaves_earlier_batch_folders_alone() {
+    let root = scratch_dir("bulk_twice");
+    let library = root.join("library");
+    for name in ["Alpha", "Beta"] {
+        fs::create_dir_all(library.join(name)).expect("series dir");
+        fs::write(library.join(format!("{name}/{name} v01.cbz")), b"").expect("volume");
+    }
+    // A sequel named like a batch folder is still a series of its own.
+    fs::create_dir_all(library.join("Alpha 2")).expect("sequel dir");
+    fs::write(library.join("Alpha 2/Alpha 2 v01.cbz"), b"").expect("volume");
+    let library_arg = library.to_string_lossy().into_owned();
+
+    let flags = ["--offline", "--no-cover"];
+    let (code, stdout) = run_cli_output(
+        &root,
+        &[&["--bulk", &library_arg, "--yes"], &flags[..]].concat(),
+    );
+    assert_eq!(code, 0, "{stdout}");
+    assert!(library.join("Alpha 1/Alpha v001.cbz").is_file(), "{stdout}");
+
+    let (code, stdout) = run_cli_output(
+        &root,
+        &[&["--bulk", &library_arg, "--dry-run"], &flags[..]].concat(),
+    );
+    assert_eq!(code, 0, "{stdout}");
+    assert!(stdout.contains("[BULK] (3/3)"), "{stdout}");
+    assert!(!stdout.contains("Alpha 1 1"), "{stdout}");
+    assert!(!stdout.contains("] Alpha 1\n"), "{stdout}");
+    assert!(!library.join("Alpha 1/Alpha 1 1").exists());
+}
+
+#[test]
+fn ndjson_progress_keeps_every_stderr_line_json() {
+    let root = scratch_dir("ndjson_stderr");
+    let series = root.join("Empty Series");
+    fs::create_dir_all(&series).expect("series dir");
+    let series = series.to_string_lossy().into_owned();
+    let missing = root.join("missing").to_string_lossy().into_owned();
+
+    for args in [
+        vec![missing.as_str(), "--dry-run"],
+        vec![series.as_str(), "--dry-run"],
+        vec![series.as_str(), "--preview-clean"],
+        vec!["--bulk", missing.as_str(), "--dry-run"],
+    ] {
+        let args = [
+            &args[..],
+            &["--offline", "--no-cover", "--progress", "ndjson"],
+        ]
+        .concat();
+        let (code, _, stderr) = run_cli_streams(&root, &args);
+        assert_ne!(code, 0, "{args:?}");
+        assert!(!stderr.trim().is_empty(), "{args:?}");
+        for line in stderr.lines() {
+            let value: serde_json::Value = serde_json::from_str(line)
+                .unwrap_or_else(|err| panic!("{args:?}: {line:?} is not JSON: {err}"));
+            assert!(value["type"].is_string(), "{line}");
+            assert!(value["message"].is_string(), "{line}");
+        }
+    }
+}
+
+#[test]
+fn warnings_go_to_stderr_and_the_plan_to_stdout() {
+    let root = scratch_dir("warn_stderr");
+    let series = root.join("Delta");
+    fs::create_dir_all(&series).expect("series dir");
+    fs::write(series.join("Delta v01.cbz"), b"").expect("volume");
+    let series = series.to_string_lossy().into_owned();
+
+    let (code, stdout, stderr) = run_cli_streams(&root, &[&series, "--offline", "--dry-run"]);
+    assert_eq!(code, 0, "{stdout}{stderr}");
+    assert!(stdout.contains("[PLAN] Volumes found: 1"), "{stdout}");
+    assert!(!stdout.contains("[WARN]"), "{stdout}");
+    assert!(
+        stderr.contains("[WARN] Failed to extract volume cover"),
+        "{stderr}"
+    );
+}